@@ -1,4 +1,4 @@
-use crates_io_cdn_logs::{cloudfront, fastly};
+use crates_io_cdn_logs::{caddy, cloudfront, combined, fastly};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use std::io::Cursor;
 
@@ -23,6 +23,22 @@ fn criterion_benchmark(c: &mut Criterion) {
         b.to_async(&rt)
             .iter(|| fastly::count_downloads(black_box(Cursor::new(bytes))));
     });
+
+    let bytes = include_bytes!("../test_data/combined/basic.log");
+    c.bench_function("combined", |b| {
+        // Insert a call to `to_async` to convert the bencher to async mode.
+        // The timing loops are the same as with the normal bencher.
+        b.to_async(&rt)
+            .iter(|| combined::count_downloads(black_box(Cursor::new(bytes))));
+    });
+
+    let bytes = include_bytes!("../test_data/caddy/basic.log");
+    c.bench_function("caddy", |b| {
+        // Insert a call to `to_async` to convert the bencher to async mode.
+        // The timing loops are the same as with the normal bencher.
+        b.to_async(&rt)
+            .iter(|| caddy::count_downloads(black_box(Cursor::new(bytes))));
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -1,9 +1,11 @@
 use anyhow::Context;
 use clap::Parser;
-use crates_io_cdn_logs::{count_downloads, Decompressor};
+use crates_io_cdn_logs::{
+    count_downloads, count_downloads_as, Decompressor, DownloadsMap, LogFormat,
+};
 use std::path::PathBuf;
 use tokio::fs::File;
-use tokio::io::BufReader;
+use tokio::io::{AsyncBufRead, BufReader};
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::{fmt, EnvFilter};
 
@@ -11,6 +13,11 @@ use tracing_subscriber::{fmt, EnvFilter};
 struct Options {
     /// The path to the CDN log file to parse
     path: PathBuf,
+
+    /// The format of the log file (`cloudfront`, `fastly`, `combined` or
+    /// `caddy`). The format is detected automatically if not specified.
+    #[arg(long)]
+    format: Option<LogFormat>,
 }
 
 #[tokio::main]
@@ -35,9 +42,9 @@ async fn main() -> anyhow::Result<()> {
         "gz" | "zst" => {
             let decompressor = Decompressor::from_extension(reader, Some(extension))?;
             let reader = BufReader::new(decompressor);
-            count_downloads(reader).await?
+            count(reader, options.format).await?
         }
-        _ => count_downloads(reader).await?,
+        _ => count(reader, options.format).await?,
     };
     println!("{downloads:?}");
     println!();
@@ -53,6 +60,16 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn count<R>(reader: R, format: Option<LogFormat>) -> anyhow::Result<DownloadsMap>
+where
+    R: AsyncBufRead + Unpin,
+{
+    match format {
+        Some(format) => count_downloads_as(reader, format).await,
+        None => count_downloads(reader).await,
+    }
+}
+
 fn init_tracing() {
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::INFO.into())
@@ -0,0 +1,238 @@
+//! # Caddy access log parsing
+//!
+//! see <https://caddyserver.com/docs/caddyfile/directives/log#format-modules>.
+
+use crate::paths::parse_path;
+use crate::{ClientInfo, DownloadsMap};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::borrow::Cow;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tracing::{debug_span, instrument, warn};
+
+#[instrument(level = "debug", skip(reader))]
+pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Result<DownloadsMap> {
+    let mut downloads = DownloadsMap::new();
+
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        let span = debug_span!("process_line");
+        let _guard = span.enter();
+
+        let json = match parse_json(&line) {
+            Ok(json) => json,
+            Err(error) => {
+                warn!("Failed to parse JSON: {error}");
+                continue;
+            }
+        };
+
+        if json.request.method != "GET" {
+            // Ignore non-GET requests.
+            continue;
+        }
+
+        if json.status != 200 {
+            // Ignore non-200 responses.
+            continue;
+        }
+
+        let Some(date_time) = json.ts.date_time() else {
+            warn!("Failed to parse timestamp");
+            continue;
+        };
+
+        let uri = decode_uri(&json.request.uri);
+
+        let Some((name, version)) = parse_path(&uri) else {
+            continue;
+        };
+
+        let date = date_time.date_naive();
+
+        if let Some(user_agent) = json.request.headers.user_agent.first() {
+            let client = ClientInfo::from_user_agent(user_agent);
+            downloads.add_client(name.clone(), date, client);
+        }
+
+        downloads.add(name, version, date);
+    }
+
+    Ok(downloads)
+}
+
+#[instrument(level = "debug", skip(json))]
+fn parse_json(json: &str) -> Result<LogLine<'_>, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+/// Deal with paths like `/crates/tikv-jemalloc-sys/tikv-jemalloc-sys-0.5.4%2B5.3.0-patched.crate`.
+///
+/// Caddy logs the request URI as it was received, so a single round of
+/// percent-decoding is sufficient here.
+#[instrument(level = "debug", skip(uri))]
+fn decode_uri(uri: &str) -> Cow<'_, str> {
+    percent_encoding::percent_decode_str(uri).decode_utf8_lossy()
+}
+
+/// This struct corresponds to the subset of fields of a Caddy access log
+/// line that are relevant for counting downloads.
+///
+/// Caddy logs a lot more information (headers, TLS details, durations, …),
+/// but these fields are ignored during deserialization.
+#[derive(Debug, Deserialize)]
+struct LogLine<'a> {
+    ts: Timestamp,
+    #[serde(borrow)]
+    request: Request<'a>,
+    status: u16,
+}
+
+#[derive(Debug, Deserialize)]
+struct Request<'a> {
+    #[serde(borrow)]
+    method: Cow<'a, str>,
+    #[serde(borrow)]
+    uri: Cow<'a, str>,
+    #[serde(borrow, default)]
+    headers: Headers<'a>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Headers<'a> {
+    #[serde(borrow, default, rename = "User-Agent")]
+    user_agent: Vec<Cow<'a, str>>,
+}
+
+/// Caddy logs timestamps as fractional Unix timestamps by default, but the
+/// `time_format` option of the JSON encoder can be used to switch them to
+/// RFC 3339 strings.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Unix(f64),
+    Rfc3339(DateTime<Utc>),
+}
+
+impl Timestamp {
+    fn date_time(&self) -> Option<DateTime<Utc>> {
+        match *self {
+            Timestamp::Unix(ts) => {
+                let secs = ts.floor();
+                let nanos = ((ts - secs) * 1e9) as u32;
+                DateTime::from_timestamp(secs as i64, nanos)
+            }
+            Timestamp::Rfc3339(date_time) => Some(date_time),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use claims::{assert_ok, assert_some};
+    use insta::assert_debug_snapshot;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_parse() {
+        let input = r#"{"level":"info","ts":1705449200.4605572,"logger":"http.log.access","msg":"handled request","request":{"remote_ip":"203.0.113.7","proto":"HTTP/1.1","method":"GET","host":"static.example.com","uri":"/crates/strsim/strsim-0.10.0.crate","headers":{"User-Agent":["cargo"]}},"bytes_read":0,"user_id":"","duration":0.000912,"size":11355,"status":200,"resp_headers":{"Server":["Caddy"]}}"#;
+        let output = assert_ok!(parse_json(input));
+        assert_debug_snapshot!(output, @r#"
+        LogLine {
+            ts: Unix(
+                1705449200.4605572,
+            ),
+            request: Request {
+                method: "GET",
+                uri: "/crates/strsim/strsim-0.10.0.crate",
+                headers: Headers {
+                    user_agent: [
+                        "cargo",
+                    ],
+                },
+            },
+            status: 200,
+        }
+        "#);
+
+        let date_time = assert_some!(output.ts.date_time());
+        assert_eq!(date_time.date_naive().to_string(), "2024-01-16");
+        assert!(matches!(output.request.uri, Cow::Borrowed(_)));
+
+        let input =
+            r#"{"ts":"2024-01-17T00:05:13.2Z","request":{"method":"GET","uri":"/"},"status":200}"#;
+        let output = assert_ok!(parse_json(input));
+        let date_time = assert_some!(output.ts.date_time());
+        assert_eq!(date_time.to_string(), "2024-01-17 00:05:13.200 UTC");
+    }
+
+    #[tokio::test]
+    async fn test_basic() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/caddy/basic.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads, @r"
+        DownloadsMap {
+            2024-01-16  strsim@0.10.0 .. 1
+            2024-01-16  tikv-jemalloc-sys@0.5.2+5.3.0-patched .. 1
+            2024-01-16  winnow@0.5.4 .. 1
+            2024-01-17  cc@1.0.73 .. 1
+            2024-01-17  lazy_static@1.4.0 .. 1
+            2024-01-17  libc@0.2.126 .. 1
+            2024-01-17  windows_x86_64_gnu@0.48.0 .. 2
+        }
+        ");
+    }
+
+    #[tokio::test]
+    async fn test_clients() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/caddy/basic.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(format_clients(&downloads), @r#"
+        [
+            "2024-01-16  strsim  1.75 .. 1",
+            "2024-01-16  tikv-jemalloc-sys  1.75 .. 1",
+            "2024-01-16  winnow  1.75 .. 1",
+            "2024-01-17  cc  1.75 .. 1",
+            "2024-01-17  lazy_static  1.75 .. 1",
+            "2024-01-17  libc  1.75 .. 1",
+            "2024-01-17  windows_x86_64_gnu  1.75 .. 2",
+        ]
+        "#);
+    }
+
+    #[tokio::test]
+    async fn test_unrelated_traffic() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/caddy/unrelated-traffic.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads, @r"
+        DownloadsMap {
+            2024-01-16  strsim@0.10.0 .. 2
+        }
+        ");
+    }
+
+    #[tokio::test]
+    async fn test_recoverable_errors() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/caddy/recoverable-errors.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads, @r"
+        DownloadsMap {
+            2024-01-16  strsim@0.10.0 .. 1
+        }
+        ");
+    }
+}
@@ -4,7 +4,7 @@
 //! and <https://www.w3.org/TR/WD-logfile.html>.
 
 use crate::paths::parse_path;
-use crate::DownloadsMap;
+use crate::{ClientInfo, DownloadsMap};
 use chrono::NaiveDate;
 use std::borrow::Cow;
 use tokio::io::{AsyncBufRead, AsyncBufReadExt};
@@ -18,6 +18,7 @@ const FIELD_DATE: &str = "date";
 const FIELD_METHOD: &str = "cs-method";
 const FIELD_PATH: &str = "cs-uri-stem";
 const FIELD_STATUS: &str = "sc-status";
+const FIELD_USER_AGENT: &str = "cs(User-Agent)";
 
 #[instrument(level = "debug", skip(reader))]
 pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Result<DownloadsMap> {
@@ -26,6 +27,7 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
     let mut method_index = None;
     let mut path_index = None;
     let mut status_index = None;
+    let mut user_agent_index = None;
 
     let mut downloads = DownloadsMap::new();
 
@@ -47,6 +49,7 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
             method_index = fields.iter().position(|f| f == &FIELD_METHOD);
             path_index = fields.iter().position(|f| f == &FIELD_PATH);
             status_index = fields.iter().position(|f| f == &FIELD_STATUS);
+            user_agent_index = fields.iter().position(|f| f == &FIELD_USER_AGENT);
 
             continue;
         }
@@ -99,6 +102,14 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
             }
         };
 
+        // The `User-Agent` field is optional, since it is only used for
+        // the per-client download statistics.
+        let user_agent = user_agent_index.and_then(|i| values.get(i));
+        if let Some(user_agent) = user_agent {
+            let client = ClientInfo::from_user_agent(&decode_user_agent(user_agent));
+            downloads.add_client(name.clone(), date, client);
+        }
+
         downloads.add(name, version, date);
     }
 
@@ -110,6 +121,13 @@ fn decode_path(path: &str) -> Cow<'_, str> {
     percent_encoding::percent_decode_str(path).decode_utf8_lossy()
 }
 
+/// CloudFront percent-encodes the `User-Agent` field, e.g.
+/// `cargo%201.74.0%20(ecb9851af%202023-10-18)`.
+#[instrument(level = "debug", skip(user_agent))]
+fn decode_user_agent(user_agent: &str) -> Cow<'_, str> {
+    percent_encoding::percent_decode_str(user_agent).decode_utf8_lossy()
+}
+
 fn get_value<'a>(values: &'a [&'a str], index: Option<usize>, field_name: &'static str) -> &'a str {
     index
         .and_then(|i| values.get(i))
@@ -176,6 +194,38 @@ mod tests {
         ");
     }
 
+    #[tokio::test]
+    async fn test_clients() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/cloudfront/basic.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(format_clients(&downloads), @r#"
+        [
+            "2024-01-16  bindgen  1.74 .. 1",
+            "2024-01-16  cumulus-primitives-core  1.74 .. 1",
+            "2024-01-16  derive_more  1.74 .. 1",
+            "2024-01-16  hash-db  1.74 .. 1",
+            "2024-01-16  hyper-rustls  1.74 .. 1",
+            "2024-01-16  jsonrpsee-server  1.74 .. 1",
+            "2024-01-16  peeking_take_while  1.74 .. 1",
+            "2024-01-16  quick-error  1.74 .. 2",
+            "2024-01-16  tracing-core  1.74 .. 1",
+            "2024-01-17  flatbuffers  1.71 .. 1",
+            "2024-01-17  jemallocator  1.71 .. 1",
+            "2024-01-17  leveldb-sys  1.71 .. 1",
+            "2024-01-17  num_cpus  1.71 .. 1",
+            "2024-01-17  paste  1.71 .. 1",
+            "2024-01-17  quick-error  1.74 .. 1",
+            "2024-01-17  rand  1.71 .. 1",
+            "2024-01-17  serde_derive  1.71 .. 1",
+            "2024-01-17  smallvec  1.71 .. 1",
+            "2024-01-17  tar  1.71 .. 1",
+        ]
+        "#);
+    }
+
     #[tokio::test]
     async fn test_unrelated_traffic() {
         let _guard = enable_tracing_output();
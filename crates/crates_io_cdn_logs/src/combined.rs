@@ -0,0 +1,250 @@
+//! # Combined access log parsing
+//!
+//! This module parses the "combined" log format that is used by default by
+//! nginx and is commonly configured for Apache and other reverse proxies.
+//!
+//! see <https://httpd.apache.org/docs/current/logs.html#combined>.
+
+use crate::paths::parse_path;
+use crate::{ClientInfo, DownloadsMap};
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use std::borrow::Cow;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tracing::{debug_span, instrument, warn};
+
+/// The format of the `[10/Oct/2000:13:55:36 -0700]` timestamp field.
+const DATE_TIME_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+#[instrument(level = "debug", skip(reader))]
+pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Result<DownloadsMap> {
+    let mut downloads = DownloadsMap::new();
+
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        let span = debug_span!("process_line");
+        let _guard = span.enter();
+
+        let Some(line) = parse_line(&line) else {
+            warn!("Failed to parse log line");
+            continue;
+        };
+
+        if line.method != "GET" {
+            // Ignore non-GET requests.
+            continue;
+        }
+
+        if line.status != "200" {
+            // Ignore non-200 responses.
+            continue;
+        }
+
+        let date = match parse_date(line.date_time) {
+            Ok(date) => date,
+            Err(error) => {
+                warn!("Failed to parse date: {error}");
+                continue;
+            }
+        };
+
+        let path = decode_path(line.path);
+
+        let Some((name, version)) = parse_path(&path) else {
+            continue;
+        };
+
+        if let Some(user_agent) = line.user_agent {
+            let client = ClientInfo::from_user_agent(user_agent);
+            downloads.add_client(name.clone(), date, client);
+        }
+
+        downloads.add(name, version, date);
+    }
+
+    Ok(downloads)
+}
+
+/// The fields of a combined log line that are relevant for counting
+/// downloads.
+#[derive(Debug, PartialEq, Eq)]
+struct LogLine<'a> {
+    date_time: &'a str,
+    method: &'a str,
+    path: &'a str,
+    status: &'a str,
+    user_agent: Option<&'a str>,
+}
+
+/// Extracts the relevant fields from a log line like:
+///
+/// ```text
+/// 127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /crates/foo/foo-1.0.0.crate HTTP/1.1" 200 2326 "-" "cargo"
+/// ```
+///
+/// Similar to the other parsers in this crate, this is using simple string
+/// searches instead of a regex for performance reasons.
+#[instrument(level = "debug", skip(line))]
+fn parse_line(line: &str) -> Option<LogLine<'_>> {
+    let (_, rest) = line.split_once(" [")?;
+    let (date_time, rest) = rest.split_once("] \"")?;
+    let (request, rest) = rest.split_once("\" ")?;
+
+    let mut request = request.split(' ');
+    let method = request.next()?;
+    let path = request.next()?;
+
+    let status = rest.split(' ').next()?;
+
+    // The `User-Agent` is the last field of the combined format, but it is
+    // missing in the "common" log format.
+    let user_agent = rest
+        .strip_suffix('"')
+        .and_then(|rest| rest.rsplit_once('"'))
+        .map(|(_, user_agent)| user_agent)
+        .filter(|user_agent| *user_agent != "-");
+
+    Some(LogLine {
+        date_time,
+        method,
+        path,
+        status,
+        user_agent,
+    })
+}
+
+/// Parses the date from a timestamp like `10/Oct/2000:13:55:36 -0700`.
+///
+/// The date is returned in UTC to be consistent with the other log formats.
+#[instrument(level = "debug")]
+fn parse_date(date_time: &str) -> chrono::ParseResult<NaiveDate> {
+    let date_time = DateTime::<FixedOffset>::parse_from_str(date_time, DATE_TIME_FORMAT)?;
+    Ok(date_time.naive_utc().date())
+}
+
+/// Deal with paths like `/crates/tikv-jemalloc-sys/tikv-jemalloc-sys-0.5.4%2B5.3.0-patched.crate`.
+///
+/// nginx and Apache log the request line as it was received, so a single
+/// round of percent-decoding is sufficient here.
+#[instrument(level = "debug", skip(path))]
+fn decode_path(path: &str) -> Cow<'_, str> {
+    percent_encoding::percent_decode_str(path).decode_utf8_lossy()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use claims::{assert_none, assert_ok, assert_some};
+    use insta::assert_debug_snapshot;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_parse_line() {
+        let line = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /crates/foo/foo-1.0.0.crate HTTP/1.1" 200 2326 "-" "cargo""#;
+        assert_debug_snapshot!(assert_some!(parse_line(line)), @r#"
+        LogLine {
+            date_time: "10/Oct/2000:13:55:36 -0700",
+            method: "GET",
+            path: "/crates/foo/foo-1.0.0.crate",
+            status: "200",
+            user_agent: Some(
+                "cargo",
+            ),
+        }
+        "#);
+
+        // Common log format without referer and user agent
+        let line = r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET /crates/foo/foo-1.0.0.crate HTTP/1.1" 200 2326"#;
+        let line = assert_some!(parse_line(line));
+        assert_none!(line.user_agent);
+
+        assert_none!(parse_line(""));
+        assert_none!(parse_line("127.0.0.1 - - [10/Oct/2000:13:55:36 -0700]"));
+        assert_none!(parse_line(
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "-" 400 0"#
+        ));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let date = assert_ok!(parse_date("10/Oct/2000:13:55:36 -0700"));
+        assert_eq!(date.to_string(), "2000-10-10");
+
+        // The date is converted to UTC
+        let date = assert_ok!(parse_date("11/Oct/2000:00:55:36 +0100"));
+        assert_eq!(date.to_string(), "2000-10-10");
+    }
+
+    #[tokio::test]
+    async fn test_basic() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/combined/basic.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads, @r"
+        DownloadsMap {
+            2024-01-16  cc@1.0.73 .. 1
+            2024-01-16  strsim@0.10.0 .. 1
+            2024-01-16  tikv-jemalloc-sys@0.5.2+5.3.0-patched .. 1
+            2024-01-16  winnow@0.5.4 .. 1
+            2024-01-17  lazy_static@1.4.0 .. 1
+            2024-01-17  libc@0.2.126 .. 1
+            2024-01-17  windows_x86_64_gnu@0.48.0 .. 2
+        }
+        ");
+    }
+
+    #[tokio::test]
+    async fn test_clients() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/combined/basic.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(format_clients(&downloads), @r#"
+        [
+            "2024-01-16  cc  1.75 .. 1",
+            "2024-01-16  strsim  1.75 .. 1",
+            "2024-01-16  tikv-jemalloc-sys  1.75 .. 1",
+            "2024-01-16  winnow  1.74 .. 1",
+            "2024-01-17  lazy_static  1.73 .. 1",
+            "2024-01-17  libc  1.75 .. 1",
+            "2024-01-17  windows_x86_64_gnu  1.73 .. 1",
+            "2024-01-17  windows_x86_64_gnu  1.75 .. 1",
+        ]
+        "#);
+    }
+
+    #[tokio::test]
+    async fn test_unrelated_traffic() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!(
+            "../test_data/combined/unrelated-traffic.log"
+        ));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads, @r"
+        DownloadsMap {
+            2024-01-16  strsim@0.10.0 .. 2
+        }
+        ");
+    }
+
+    #[tokio::test]
+    async fn test_recoverable_errors() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!(
+            "../test_data/combined/recoverable-errors.log"
+        ));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads, @r"
+        DownloadsMap {
+            2024-01-16  strsim@0.10.0 .. 1
+        }
+        ");
+    }
+}
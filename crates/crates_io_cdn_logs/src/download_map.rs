@@ -1,3 +1,4 @@
+use crate::user_agent::ClientInfo;
 use chrono::NaiveDate;
 use derive_more::Deref;
 use semver::Version;
@@ -5,31 +6,51 @@ use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 
 #[derive(Clone, Default, Deref)]
-pub struct DownloadsMap(HashMap<(String, Version, NaiveDate), u64>);
+pub struct DownloadsMap {
+    #[deref]
+    downloads: HashMap<(String, Version, NaiveDate), u64>,
+    /// Download counts per crate, date and client, for log formats that
+    /// include the `User-Agent` header of the requests.
+    clients: HashMap<(String, NaiveDate, ClientInfo), u64>,
+}
 
 impl DownloadsMap {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
     }
 
     /// Increments the download count for the given crate version on the given date.
     pub fn add(&mut self, name: String, version: Version, date: NaiveDate) {
-        *self.0.entry((name, version, date)).or_default() += 1;
+        *self.downloads.entry((name, version, date)).or_default() += 1;
+    }
+
+    /// Increments the download count for the given crate and client on the
+    /// given date.
+    pub fn add_client(&mut self, name: String, date: NaiveDate, client: ClientInfo) {
+        *self.clients.entry((name, date, client)).or_default() += 1;
+    }
+
+    /// Returns the download counts per crate, date and client.
+    pub fn clients(&self) -> &HashMap<(String, NaiveDate, ClientInfo), u64> {
+        &self.clients
     }
 
     /// Returns a [HashSet] of all crate names in the map.
     pub fn unique_crates(&self) -> HashSet<&str> {
-        self.0.keys().map(|(krate, _, _)| krate.as_str()).collect()
+        self.downloads
+            .keys()
+            .map(|(krate, _, _)| krate.as_str())
+            .collect()
     }
 
     /// Returns the total number of downloads across all crates and versions.
     pub fn sum_downloads(&self) -> u64 {
-        self.0.values().sum()
+        self.downloads.values().sum()
     }
 
     /// Converts the map into a vector of `(crate, version, date, downloads)` tuples.
     pub fn into_vec(self) -> Vec<(String, Version, NaiveDate, u64)> {
-        self.0
+        self.downloads
             .into_iter()
             .map(|((name, version, date), downloads)| (name, version, date, downloads))
             .collect()
@@ -39,7 +60,7 @@ impl DownloadsMap {
 impl Debug for DownloadsMap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut downloads = self
-            .0
+            .downloads
             .iter()
             .map(|((krate, version, date), downloads)| (date, krate, version, downloads))
             .collect::<Vec<_>>();
@@ -106,4 +127,56 @@ mod tests {
         }
         ");
     }
+
+    #[test]
+    fn test_clients() {
+        let mut downloads = DownloadsMap::new();
+        let date = "2023-12-25".parse::<NaiveDate>().unwrap();
+
+        let client = ClientInfo::from_user_agent("cargo 1.74.0 (ecb9851af 2023-10-18)");
+        downloads.add_client("xmas".to_string(), date, client.clone());
+        downloads.add_client("xmas".to_string(), date, client.clone());
+        downloads.add_client("foo".to_string(), date, client);
+
+        let client = ClientInfo::from_user_agent("cargo 1.75.0 (1d8b05cdd 2023-11-20)");
+        downloads.add_client("xmas".to_string(), date, client);
+
+        let mut clients = downloads
+            .clients()
+            .iter()
+            .map(|((name, _, client), downloads)| {
+                (name.as_str(), client.cargo_version.as_deref(), *downloads)
+            })
+            .collect::<Vec<_>>();
+        clients.sort();
+
+        assert_debug_snapshot!(clients, @r#"
+        [
+            (
+                "foo",
+                Some(
+                    "1.74",
+                ),
+                1,
+            ),
+            (
+                "xmas",
+                Some(
+                    "1.74",
+                ),
+                2,
+            ),
+            (
+                "xmas",
+                Some(
+                    "1.75",
+                ),
+                1,
+            ),
+        ]
+        "#);
+
+        // The per-client counts are not included in the regular counts
+        assert!(downloads.is_empty());
+    }
 }
@@ -37,6 +37,12 @@ impl LogLine<'_> {
             LogLine::V1(line) => line.status,
         }
     }
+
+    pub fn user_agent(&self) -> Option<&str> {
+        match self {
+            LogLine::V1(line) => line.user_agent.as_deref(),
+        }
+    }
 }
 
 /// This struct corresponds to the `"version": "1"` variant of the [LogLine] enum.
@@ -52,6 +58,8 @@ impl LogLine<'_> {
 ///   crates.io codebase.
 /// - The `method` and `url` fields are using `Cow` to avoid
 ///   unnecessary allocations.
+/// - The `user_agent` field contains the `User-Agent` header of the request.
+///   It is optional, since older log lines don't include it.
 #[derive(Debug, Deserialize)]
 pub struct LogLineV1<'a> {
     pub date_time: DateTime<Utc>,
@@ -60,6 +68,8 @@ pub struct LogLineV1<'a> {
     #[serde(borrow)]
     pub url: Cow<'a, str>,
     pub status: u16,
+    #[serde(borrow, default)]
+    pub user_agent: Option<Cow<'a, str>>,
 }
 
 #[cfg(test)]
@@ -79,6 +89,7 @@ mod tests {
                 method: "GET",
                 url: "https://static.staging.crates.io/?1705420437",
                 status: 403,
+                user_agent: None,
             },
         )
         "#);
@@ -90,6 +101,7 @@ mod tests {
         assert_eq!(output.method(), "GET");
         assert_eq!(output.url(), "https://static.staging.crates.io/?1705420437");
         assert_eq!(output.status(), 403);
+        assert_eq!(output.user_agent(), None);
 
         match output {
             LogLine::V1(l) => {
@@ -99,6 +111,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_user_agent() {
+        let input = r#"{"bytes":null,"date_time":"2024-01-16T16:03:04.44007323Z","ip":"45.79.107.220","method":"GET","status":200,"url":"https://static.crates.io/crates/strsim/strsim-0.10.0.crate","user_agent":"cargo 1.75.0 (1d8b05cdd 2023-11-20)","version":"1"}"#;
+        let output = assert_ok!(serde_json::from_str::<LogLine<'_>>(input));
+        assert_eq!(
+            output.user_agent(),
+            Some("cargo 1.75.0 (1d8b05cdd 2023-11-20)")
+        );
+    }
+
     #[allow(clippy::ptr_arg)]
     fn is_borrowed(s: &Cow<'_, str>) -> bool {
         match s {
@@ -5,7 +5,7 @@
 mod json;
 
 use crate::paths::parse_path;
-use crate::DownloadsMap;
+use crate::{ClientInfo, DownloadsMap};
 use std::borrow::Cow;
 use tokio::io::{AsyncBufRead, AsyncBufReadExt};
 use tracing::{debug_span, instrument, warn};
@@ -54,6 +54,11 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
 
         let date = json.date_time().date_naive();
 
+        if let Some(user_agent) = json.user_agent() {
+            let client = ClientInfo::from_user_agent(user_agent);
+            downloads.add_client(name.clone(), date, client);
+        }
+
         downloads.add(name, version, date);
     }
 
@@ -124,6 +129,23 @@ mod tests {
         ");
     }
 
+    #[tokio::test]
+    async fn test_clients() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../../test_data/fastly/user-agent.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(format_clients(&downloads), @r#"
+        [
+            "2024-01-16  strsim  1.75 .. 1",
+            "2024-01-16  tinyvec  1.74 .. 1",
+            "2024-01-16  tinyvec  1.75 .. 1",
+            "2024-01-17  tinyvec  - .. 1",
+        ]
+        "#);
+    }
+
     #[tokio::test]
     async fn test_percent_encoding() {
         let _guard = enable_tracing_output();
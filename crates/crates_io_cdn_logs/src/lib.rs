@@ -1,46 +1,128 @@
 #![doc = include_str!("../README.md")]
 
+pub mod caddy;
 pub mod cloudfront;
+pub mod combined;
 mod compression;
 mod download_map;
 pub mod fastly;
 mod paths;
 #[cfg(test)]
 mod test_utils;
+mod user_agent;
 
 pub use crate::compression::Decompressor;
 pub use crate::download_map::DownloadsMap;
+pub use crate::user_agent::ClientInfo;
+use std::fmt;
 use std::io::Cursor;
+use std::str::FromStr;
 use tokio::io::{AsyncBufRead, AsyncReadExt};
 use tracing::instrument;
 
+/// The log file formats that are supported by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// AWS CloudFront standard logs.
+    CloudFront,
+    /// Fastly syslog lines with a JSON payload.
+    Fastly,
+    /// The "combined" access log format of nginx and Apache.
+    Combined,
+    /// The JSON access logs of Caddy.
+    Caddy,
+}
+
+impl LogFormat {
+    /// Tries to determine the log file format from the first byte of the
+    /// file.
+    ///
+    /// Combined log files can only be detected if they start with an IPv4
+    /// address. For other files the format has to be configured explicitly.
+    pub fn detect(first_byte: u8) -> Option<Self> {
+        match first_byte {
+            // CloudFront log files start with a `#Version` header.
+            b'#' => Some(Self::CloudFront),
+            // Fastly log lines start with a `<123>` field.
+            b'<' => Some(Self::Fastly),
+            // Caddy log lines are JSON objects.
+            b'{' => Some(Self::Caddy),
+            // Combined log lines start with the IP address of the client.
+            byte if byte.is_ascii_digit() => Some(Self::Combined),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CloudFront => f.write_str("cloudfront"),
+            Self::Fastly => f.write_str("fastly"),
+            Self::Combined => f.write_str("combined"),
+            Self::Caddy => f.write_str("caddy"),
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = UnknownLogFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cloudfront" => Ok(Self::CloudFront),
+            "fastly" => Ok(Self::Fastly),
+            "combined" | "nginx" | "apache" => Ok(Self::Combined),
+            "caddy" => Ok(Self::Caddy),
+            _ => Err(UnknownLogFormat(s.to_string())),
+        }
+    }
+}
+
+/// The error that is returned when parsing an unknown [LogFormat] name.
+#[derive(Debug)]
+pub struct UnknownLogFormat(String);
+
+impl fmt::Display for UnknownLogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown log format: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownLogFormat {}
+
+/// Counts the downloads in the given log file, automatically detecting the
+/// log file format.
 #[instrument(skip_all)]
 pub async fn count_downloads<R>(mut reader: R) -> anyhow::Result<DownloadsMap>
 where
     R: AsyncBufRead + Unpin,
 {
     // Read the first byte to determine the file format.
-    match reader.read_u8().await? {
-        // CloudFront log files start with a `#Version` header.
-        b'#' => {
-            // We can't use `AsyncSeek` here because `async-compression` does
-            // not support it, but we can use `Cursor` to prepend the `#` back
-            // onto the reader.
-            let reader = Cursor::new(b"#").chain(reader);
-            cloudfront::count_downloads(reader).await
-        }
-        // Fastly log lines start with a `<123>` field.
-        b'<' => {
-            // We can't use `AsyncSeek` here because `async-compression` does
-            // not support it, but we can use `Cursor` to prepend the `<` back
-            // onto the reader.
-            let reader = Cursor::new(b"<").chain(reader);
-            fastly::count_downloads(reader).await
-        }
-        // Anything else is rejected.
-        byte => {
-            anyhow::bail!("Failed to determine log file format. Unrecognized first byte: {byte:?}.")
-        }
+    let byte = reader.read_u8().await?;
+    let Some(format) = LogFormat::detect(byte) else {
+        anyhow::bail!("Failed to determine log file format. Unrecognized first byte: {byte:?}.")
+    };
+
+    // We can't use `AsyncSeek` here because `async-compression` does not
+    // support it, but we can use `Cursor` to prepend the byte back onto the
+    // reader.
+    let reader = Cursor::new([byte]).chain(reader);
+    count_downloads_as(reader, format).await
+}
+
+/// Counts the downloads in the given log file, using the given log file
+/// format.
+#[instrument(skip(reader))]
+pub async fn count_downloads_as<R>(reader: R, format: LogFormat) -> anyhow::Result<DownloadsMap>
+where
+    R: AsyncBufRead + Unpin,
+{
+    match format {
+        LogFormat::CloudFront => cloudfront::count_downloads(reader).await,
+        LogFormat::Fastly => fastly::count_downloads(reader).await,
+        LogFormat::Combined => combined::count_downloads(reader).await,
+        LogFormat::Caddy => caddy::count_downloads(reader).await,
     }
 }
 
@@ -195,6 +277,63 @@ mod tests {
         ");
     }
 
+    #[tokio::test]
+    async fn test_combined() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/combined/basic.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+        assert_eq!(downloads.sum_downloads(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_caddy() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/caddy/basic.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+        assert_eq!(downloads.sum_downloads(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_explicit_format() {
+        let _guard = enable_tracing_output();
+
+        // IPv6 addresses can't be detected automatically
+        let line = br#"::1 - - [16/Jan/2024:23:55:12 +0000] "GET /crates/foo/foo-1.0.0.crate HTTP/1.1" 200 1 "-" "cargo""#;
+
+        let mut cursor = Cursor::new(line);
+        let error = assert_err!(count_downloads(&mut cursor).await);
+        assert_snapshot!(error, @"Failed to determine log file format. Unrecognized first byte: 58.");
+
+        let mut cursor = Cursor::new(line);
+        let downloads = assert_ok!(count_downloads_as(&mut cursor, LogFormat::Combined).await);
+        assert_debug_snapshot!(downloads, @r"
+        DownloadsMap {
+            2024-01-16  foo@1.0.0 .. 1
+        }
+        ");
+    }
+
+    #[test]
+    fn test_log_format_from_str() {
+        for format in [
+            LogFormat::CloudFront,
+            LogFormat::Fastly,
+            LogFormat::Combined,
+            LogFormat::Caddy,
+        ] {
+            assert_eq!(assert_ok!(format.to_string().parse::<LogFormat>()), format);
+        }
+
+        assert_eq!(
+            assert_ok!("nginx".parse::<LogFormat>()),
+            LogFormat::Combined
+        );
+        let error = assert_err!("foo".parse::<LogFormat>());
+        assert_snapshot!(error, @r#"Unknown log format: "foo""#);
+    }
+
     #[tokio::test]
     async fn test_unknown() {
         let _guard = enable_tracing_output();
@@ -1,3 +1,4 @@
+use crate::DownloadsMap;
 use tracing::dispatcher::DefaultGuard;
 use tracing::subscriber;
 use tracing_subscriber::fmt;
@@ -9,3 +10,19 @@ use tracing_subscriber::fmt;
 pub fn enable_tracing_output() -> DefaultGuard {
     subscriber::set_default(fmt().compact().with_test_writer().finish())
 }
+
+/// Formats the per-client download counts of a [DownloadsMap] as a sorted
+/// list of strings for use in snapshot tests.
+pub fn format_clients(downloads: &DownloadsMap) -> Vec<String> {
+    let mut clients = downloads
+        .clients()
+        .iter()
+        .map(|((name, date, client), downloads)| {
+            let cargo_version = client.cargo_version.as_deref().unwrap_or("-");
+            format!("{date}  {name}  {cargo_version} .. {downloads}")
+        })
+        .collect::<Vec<_>>();
+
+    clients.sort();
+    clients
+}
@@ -0,0 +1,81 @@
+use tracing::instrument;
+
+/// Information about the client that downloaded a crate, derived from the
+/// `User-Agent` header of the request.
+///
+/// cargo only sends its own version in its default `User-Agent`, so the
+/// platform or whether the download was made from CI can't be derived for
+/// the vast majority of downloads, and are not included.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientInfo {
+    /// The `major.minor` version of cargo, which matches the version of the
+    /// Rust toolchain, or `None` if the download was not made by cargo.
+    pub cargo_version: Option<String>,
+}
+
+impl ClientInfo {
+    /// Parses a `User-Agent` header like `cargo 1.74.0 (ecb9851af 2023-10-18)`.
+    #[instrument(level = "debug")]
+    pub fn from_user_agent(user_agent: &str) -> Self {
+        Self {
+            cargo_version: parse_cargo_version(user_agent),
+        }
+    }
+}
+
+/// Returns the `major.minor` part of the version in a `cargo 1.74.0 (…)` or
+/// `cargo/1.74.0` user agent.
+fn parse_cargo_version(user_agent: &str) -> Option<String> {
+    let rest = user_agent
+        .strip_prefix("cargo ")
+        .or_else(|| user_agent.strip_prefix("cargo/"))?;
+
+    let version = rest.split(' ').next()?;
+    let mut parts = version.splitn(3, '.');
+    let major = parts.next()?;
+    let minor = parts.next()?;
+
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !is_number(major) || !is_number(minor) {
+        return None;
+    }
+
+    Some(format!("{major}.{minor}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    fn parse(user_agent: &str) -> String {
+        let client = ClientInfo::from_user_agent(user_agent);
+        client.cargo_version.unwrap_or_else(|| "-".into())
+    }
+
+    #[test]
+    fn test_from_user_agent() {
+        let user_agents = [
+            "cargo 1.74.0 (ecb9851af 2023-10-18)",
+            "cargo 1.77.0-nightly (7bb7b5395 2024-01-20)",
+            "cargo/1.56.1",
+            "cargo foo",
+            "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0",
+            "curl/8.4.0",
+            "",
+        ];
+
+        let results = user_agents.map(parse);
+        assert_debug_snapshot!(results, @r#"
+        [
+            "1.74",
+            "1.77",
+            "1.56",
+            "-",
+            "-",
+            "-",
+            "-",
+        ]
+        "#);
+    }
+}
@@ -12,6 +12,9 @@ pub use self::follow::Follow;
 pub use self::keyword::{CrateKeyword, Keyword};
 pub use self::krate::{Crate, CrateName, NewCrate, RecentCrateDownloads};
 pub use self::owner::{CrateOwner, Owner, OwnerKind};
+pub use self::storage_integrity_issue::{
+    NewStorageIntegrityIssue, StorageIntegrityIssue, StorageIssueKind,
+};
 pub use self::team::{NewTeam, Team};
 pub use self::token::ApiToken;
 pub use self::user::{NewUser, User};
@@ -31,6 +34,7 @@ mod follow;
 mod keyword;
 pub mod krate;
 mod owner;
+mod storage_integrity_issue;
 pub mod team;
 pub mod token;
 pub mod user;
@@ -0,0 +1,89 @@
+use crate::schema::storage_integrity_issues;
+use bon::Builder;
+use chrono::{DateTime, Utc};
+use crates_io_diesel_helpers::pg_enum;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+pg_enum! {
+    pub enum StorageIssueKind {
+        MissingCrateFile = 0,
+        ChecksumMismatch = 1,
+        MissingReadme = 2,
+    }
+}
+
+impl From<StorageIssueKind> for &'static str {
+    fn from(kind: StorageIssueKind) -> Self {
+        match kind {
+            StorageIssueKind::MissingCrateFile => "missing crate file",
+            StorageIssueKind::ChecksumMismatch => "checksum mismatch",
+            StorageIssueKind::MissingReadme => "missing readme",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable)]
+#[diesel(
+    table_name = storage_integrity_issues,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(version_id, kind)
+)]
+pub struct StorageIntegrityIssue {
+    pub version_id: i32,
+    pub kind: StorageIssueKind,
+    pub path: String,
+    pub message: Option<String>,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl StorageIntegrityIssue {
+    /// Deletes all previously recorded issues for the given versions.
+    ///
+    /// This is used to resolve issues that were fixed since the last audit.
+    pub async fn delete_for_versions(
+        conn: &mut AsyncPgConnection,
+        version_ids: &[i32],
+    ) -> QueryResult<usize> {
+        let query = storage_integrity_issues::table
+            .filter(storage_integrity_issues::version_id.eq_any(version_ids));
+
+        diesel::delete(query).execute(conn).await
+    }
+}
+
+/// Struct used to `INSERT` a new `storage_integrity_issues` record into the database.
+#[derive(Insertable, Debug, Clone, Builder)]
+#[diesel(table_name = storage_integrity_issues, check_for_backend(diesel::pg::Pg))]
+pub struct NewStorageIntegrityIssue {
+    version_id: i32,
+    kind: StorageIssueKind,
+    #[builder(into)]
+    path: String,
+    #[builder(into)]
+    message: Option<String>,
+}
+
+impl NewStorageIntegrityIssue {
+    /// Inserts the issue into the database, or refreshes the existing record
+    /// if the same issue has already been detected before.
+    pub async fn insert(&self, conn: &mut AsyncPgConnection) -> QueryResult<()> {
+        diesel::insert_into(storage_integrity_issues::table)
+            .values(self)
+            .on_conflict((
+                storage_integrity_issues::version_id,
+                storage_integrity_issues::kind,
+            ))
+            .do_update()
+            .set((
+                storage_integrity_issues::path.eq(excluded(storage_integrity_issues::path)),
+                storage_integrity_issues::message.eq(excluded(storage_integrity_issues::message)),
+                storage_integrity_issues::detected_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+}
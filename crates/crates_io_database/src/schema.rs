@@ -79,6 +79,26 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Dependencies between background jobs. A job is not run until all of its parent jobs have succeeded and their rows have been deleted.
+    background_job_dependencies (job_id, parent_id) {
+        /// The job that is waiting for its parent job.
+        job_id -> Int8,
+        /// The parent job that has to succeed before the job is run.
+        parent_id -> Int8,
+    }
+}
+
+diesel::table! {
+    /// The state of the cron schedules of the background job runner.
+    background_job_schedules (job_type) {
+        /// The job type that is enqueued by this schedule.
+        job_type -> Text,
+        /// The most recent tick of the schedule that has been handled.
+        last_tick -> Timestamptz,
+    }
+}
+
 diesel::table! {
     /// Representation of the `background_jobs` table.
     ///
@@ -126,6 +146,14 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         priority -> Int2,
+        /// The queue that the job was enqueued in.
+        queue -> Text,
+        /// The error message of the last failed run, if any.
+        last_error -> Nullable<Text>,
+        /// The earliest time at which the job may be run.
+        run_at -> Timestamptz,
+        /// The `sentry-trace` header of the trace in which the job was enqueued, if any. The job is run as part of the same trace.
+        trace_context -> Nullable<Text>,
     }
 }
 
@@ -176,6 +204,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Number of downloads per crate, day and client, as derived from the `User-Agent` header of the requests in the CDN logs.
+    crate_client_downloads (crate_id, date, cargo_version) {
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// The date on which the downloads happened.
+        date -> Date,
+        /// The `major.minor` version of cargo that downloaded the crate, or an empty string if the download was not made by cargo.
+        cargo_version -> Varchar,
+        /// The number of downloads for this combination of crate, date and client.
+        downloads -> Int8,
+    }
+}
+
 diesel::table! {
     /// Number of downloads per crate. This was extracted from the `crates` table for performance reasons.
     crate_downloads (crate_id) {
@@ -397,6 +439,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Registry-wide statistics per day, maintained by the `update_downloads` background job.
+    daily_registry_stats (date) {
+        /// The date that these statistics are for.
+        date -> Date,
+        /// The total number of downloads across all crates on this date.
+        downloads -> Int8,
+        /// The number of crates that were created on this date.
+        new_crates -> Int4,
+        /// The number of versions that were published on this date.
+        new_versions -> Int4,
+    }
+}
+
 diesel::table! {
     /// A mapping from crates to the versions that the frontend will display by default.
     default_versions (crate_id) {
@@ -541,6 +597,32 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Background jobs that have exhausted their retries.
+    failed_background_jobs (id) {
+        /// The ID that the job had in the `background_jobs` table.
+        id -> Int8,
+        /// The type of the job.
+        job_type -> Text,
+        /// The serialized job data.
+        data -> Jsonb,
+        /// The number of times the job was retried before it was given up on.
+        retries -> Int4,
+        /// The priority of the job.
+        priority -> Int2,
+        /// The time at which the job was originally enqueued.
+        created_at -> Timestamptz,
+        /// The time at which the job was given up on.
+        failed_at -> Timestamptz,
+        /// The error message of the last failed run.
+        error -> Text,
+        /// The backtrace of the last failed run, if one was captured.
+        backtrace -> Nullable<Text>,
+        /// The queue that the job was enqueued in.
+        queue -> Text,
+    }
+}
+
 diesel::table! {
     /// Representation of the `follows` table.
     ///
@@ -607,6 +689,26 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// The alerts of the monitor that are currently triggered. Used by alert sinks that can't deduplicate alerts themselves.
+    monitor_alerts (key) {
+        /// The key of the check that triggered the alert.
+        key -> Text,
+        /// The time at which the alert was first triggered.
+        triggered_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    /// Background job queues that are paused. The job runner does not pick up jobs from paused queues.
+    paused_background_job_queues (queue) {
+        /// The name of the paused queue.
+        queue -> Text,
+        /// The time at which the queue was paused.
+        paused_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     /// List of all processed CDN log files, used to avoid processing the same file multiple times.
     processed_log_files (path) {
@@ -649,6 +751,62 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Representation of the `publish_rate_override_actions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_rate_override_actions (id) {
+        /// The `id` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Nullable<Int4>,
+        /// The `action` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        action -> Int4,
+        /// The `burst` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        burst -> Int4,
+        /// The `expires_at` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        expires_at -> Nullable<Timestamptz>,
+        /// The `admin_id` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        admin_id -> Nullable<Int4>,
+        /// The `reason` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reason -> Text,
+        /// The `created_at` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     /// Representation of the `publish_rate_overrides` table.
     ///
@@ -733,6 +891,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Missing or corrupted files in the storage backend, as detected by the `audit_storage` background job.
+    storage_integrity_issues (version_id, kind) {
+        /// Reference to the version in the `versions` table.
+        version_id -> Int4,
+        /// Kind of the detected issue (0 = missing crate file, 1 = checksum mismatch, 2 = missing readme)
+        kind -> Int4,
+        /// Path of the affected file inside the storage bucket
+        path -> Varchar,
+        /// Optional details about the issue, e.g. the checksum that was found instead of the expected one
+        message -> Nullable<Varchar>,
+        /// Date and time when the issue was last detected
+        detected_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     /// Representation of the `teams` table.
     ///
@@ -1049,6 +1223,7 @@ diesel::table! {
 }
 
 diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(crate_client_downloads -> crates (crate_id));
 diesel::joinable!(crate_downloads -> crates (crate_id));
 diesel::joinable!(crate_owner_invitations -> crates (crate_id));
 diesel::joinable!(crate_owners -> crates (crate_id));
@@ -1070,6 +1245,7 @@ diesel::joinable!(publish_limit_buckets -> users (user_id));
 diesel::joinable!(publish_rate_overrides -> users (user_id));
 diesel::joinable!(readme_renderings -> versions (version_id));
 diesel::joinable!(recent_crate_downloads -> crates (crate_id));
+diesel::joinable!(storage_integrity_issues -> versions (version_id));
 diesel::joinable!(version_downloads -> versions (version_id));
 diesel::joinable!(version_owner_actions -> api_tokens (api_token_id));
 diesel::joinable!(version_owner_actions -> users (user_id));
@@ -1080,27 +1256,36 @@ diesel::joinable!(versions_published_by -> versions (version_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     api_tokens,
+    background_job_dependencies,
+    background_job_schedules,
     background_jobs,
     categories,
+    crate_client_downloads,
     crate_downloads,
     crate_owner_invitations,
     crate_owners,
     crates,
     crates_categories,
     crates_keywords,
+    daily_registry_stats,
     default_versions,
     deleted_crates,
     dependencies,
     emails,
+    failed_background_jobs,
     follows,
     keywords,
     metadata,
+    monitor_alerts,
+    paused_background_job_queues,
     processed_log_files,
     publish_limit_buckets,
+    publish_rate_override_actions,
     publish_rate_overrides,
     readme_renderings,
     recent_crate_downloads,
     reserved_crate_names,
+    storage_integrity_issues,
     teams,
     users,
     version_downloads,
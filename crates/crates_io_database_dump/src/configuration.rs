@@ -15,7 +15,8 @@ pub enum ColumnVisibility {
 /// and should list all tables the current tables refers to with foreign key
 /// constraints on public columns. The `filter` field is a valid SQL expression
 /// used in a `WHERE` clause to filter the rows of the table. The `columns`
-/// field maps column names to their respective visibilities.
+/// field maps column names to their respective visibilities. The `indexes`
+/// field lists the column sets that are indexed in the SQLite export.
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct TableConfig {
     #[serde(default)]
@@ -24,6 +25,8 @@ pub struct TableConfig {
     pub columns: BTreeMap<String, ColumnVisibility>,
     #[serde(default)]
     pub column_defaults: BTreeMap<String, String>,
+    #[serde(default)]
+    pub indexes: Vec<Vec<String>>,
 }
 
 /// Maps table names to the respective configurations. Used to load `dump_db.toml`.
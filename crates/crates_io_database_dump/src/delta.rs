@@ -0,0 +1,729 @@
+use crate::configuration::{ColumnVisibility, TableConfig, VisibilityConfig};
+use crate::parquet_export::{ColumnTypes, CopyCsvReader, CopyCsvWriter, COLUMN_TYPES_PATH};
+use crate::CancellationFlag;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Read, Write};
+use std::path::Path;
+use tracing::debug;
+
+/// Separates the values of multi-column primary keys in [`TableFingerprints`].
+const KEY_SEPARATOR: char = '\u{1f}';
+
+/// The header of a fingerprints file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    /// The timestamp of the dump the fingerprints belong to.
+    timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TableHeader {
+    table: String,
+    /// The CSV header of the table. If the columns change between two dumps,
+    /// the table is included in full in the delta.
+    columns: Vec<String>,
+}
+
+/// A line of a fingerprints file, after the [`Header`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Line {
+    Table(TableHeader),
+    /// The primary key values of a row and a hash of the complete row.
+    Row(String, u64),
+}
+
+#[derive(Debug)]
+struct TableFingerprints {
+    columns: Vec<String>,
+    /// Maps the primary key values of each row to a hash of the complete
+    /// row. Empty for tables without a public primary key.
+    rows: HashMap<String, u64>,
+}
+
+/// Reads the fingerprints of all rows of a database dump, as written by
+/// [`FingerprintsWriter`].
+///
+/// The fingerprints are stored next to the dumps, so that the next dump can
+/// compute the delta to this one without having to download the full
+/// previous dump. They are stored as gzip-compressed JSON lines, with the
+/// tables in alphabetical order, so that only the fingerprints of a single
+/// table have to be kept in memory at a time.
+pub struct FingerprintsReader<R: Read> {
+    /// The timestamp of the dump these fingerprints belong to.
+    pub timestamp: DateTime<Utc>,
+    lines: Lines<BufReader<GzDecoder<R>>>,
+    next_table: Option<TableHeader>,
+}
+
+impl<R: Read> FingerprintsReader<R> {
+    pub fn new(reader: R) -> anyhow::Result<Self> {
+        let mut lines = BufReader::new(GzDecoder::new(reader)).lines();
+        let header = lines.next().context("Missing fingerprints header")??;
+        let Header { timestamp } = serde_json::from_str(&header)?;
+
+        let mut reader = Self {
+            timestamp,
+            lines,
+            next_table: None,
+        };
+        reader.read_rows(false)?;
+        Ok(reader)
+    }
+
+    /// Reads the fingerprints of the given table, skipping all tables before
+    /// it. The tables have to be requested in alphabetical order.
+    fn table(&mut self, name: &str) -> anyhow::Result<Option<TableFingerprints>> {
+        loop {
+            let Some(header) = &self.next_table else {
+                return Ok(None);
+            };
+
+            match header.table.as_str().cmp(name) {
+                Ordering::Less => {
+                    self.read_rows(false)?;
+                }
+                Ordering::Equal => {
+                    let columns = self.next_table.take().unwrap().columns;
+                    let rows = self.read_rows(true)?;
+                    return Ok(Some(TableFingerprints { columns, rows }));
+                }
+                Ordering::Greater => return Ok(None),
+            }
+        }
+    }
+
+    /// Reads the rows of the current table, until the header of the next
+    /// table is found.
+    fn read_rows(&mut self, keep: bool) -> anyhow::Result<HashMap<String, u64>> {
+        let mut rows = HashMap::new();
+        self.next_table = None;
+        for line in self.lines.by_ref() {
+            match serde_json::from_str(&line?)? {
+                Line::Row(key, hash) if keep => {
+                    rows.insert(key, hash);
+                }
+                Line::Row(..) => {}
+                Line::Table(header) => {
+                    self.next_table = Some(header);
+                    break;
+                }
+            }
+        }
+        Ok(rows)
+    }
+}
+
+/// Writes the fingerprints of a database dump, see [`FingerprintsReader`].
+pub struct FingerprintsWriter<W: Write> {
+    encoder: BufWriter<GzEncoder<W>>,
+}
+
+impl<W: Write> FingerprintsWriter<W> {
+    pub fn new(writer: W, timestamp: DateTime<Utc>) -> anyhow::Result<Self> {
+        let encoder = GzEncoder::new(writer, flate2::Compression::default());
+        let mut writer = Self {
+            encoder: BufWriter::new(encoder),
+        };
+        writer.write_line(&Header { timestamp })?;
+        Ok(writer)
+    }
+
+    fn write_line(&mut self, line: &impl Serialize) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.encoder, line)?;
+        self.encoder.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<W> {
+        let encoder = self
+            .encoder
+            .into_inner()
+            .map_err(|error| error.into_error())?;
+        Ok(encoder.finish()?)
+    }
+}
+
+/// Describes a chain of deltas, starting at a full base snapshot.
+///
+/// Applying all deltas in order to the base snapshot results in the most
+/// recent dump.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeltaManifest {
+    pub base: BaseSnapshot,
+    pub deltas: Vec<Delta>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BaseSnapshot {
+    pub timestamp: DateTime<Utc>,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Delta {
+    /// The timestamp of the dump this delta has to be applied to.
+    pub from: DateTime<Utc>,
+    /// The timestamp of the dump this delta results in.
+    pub to: DateTime<Utc>,
+    pub path: String,
+}
+
+impl DeltaManifest {
+    pub fn new(base: BaseSnapshot) -> Self {
+        let deltas = vec![];
+        Self { base, deltas }
+    }
+
+    /// Returns the paths of the base snapshot and of all deltas.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        let deltas = self.deltas.iter().map(|delta| delta.path.as_str());
+        std::iter::once(self.base.path.as_str()).chain(deltas)
+    }
+
+    /// Returns the timestamp of the most recent dump in the chain.
+    pub fn head(&self) -> DateTime<Utc> {
+        let last_delta = self.deltas.last();
+        last_delta.map_or(self.base.timestamp, |delta| delta.to)
+    }
+}
+
+/// Compute the fingerprints of the CSV files in the `data` folder of the
+/// export directory, and write them to `fingerprints`.
+///
+/// If the fingerprints of the previous dump are given, the inserted, updated
+/// and deleted rows of each table are additionally written to the `delta`
+/// folder, together with an `import.sql` script that applies them in
+/// dependency order. Rows are matched by their primary key.
+pub fn create_delta<R: Read, W: Write>(
+    export_dir: &Path,
+    timestamp: DateTime<Utc>,
+    mut previous: Option<FingerprintsReader<R>>,
+    fingerprints: W,
+    cancellation: &CancellationFlag,
+) -> anyhow::Result<W> {
+    let delta_dir = export_dir.join("delta");
+    if let Some(previous) = &previous {
+        debug!("Filling delta folder…");
+        fs::create_dir_all(delta_dir.join("data"))
+            .context("Failed to create `delta/data` directory")?;
+
+        add_metadata(&delta_dir, previous.timestamp, timestamp)
+            .context("Failed to write delta metadata.json file")?;
+    }
+
+    let column_types = ColumnTypes::load(&export_dir.join(COLUMN_TYPES_PATH))?;
+    let visibility_config = VisibilityConfig::get();
+
+    let mut fingerprints = FingerprintsWriter::new(fingerprints, timestamp)?;
+    let mut template_tables = BTreeMap::new();
+
+    // The tables are processed in alphabetical order, which is the order
+    // of the fingerprints files.
+    for (table, config) in &visibility_config.0 {
+        cancellation.check()?;
+
+        let table = table.as_str();
+        let csv_path = export_dir.join("data").join(table).with_extension("csv");
+        if !csv_path.exists() {
+            continue;
+        }
+
+        let primary_key = public_primary_key(&column_types, table, config);
+
+        let delta = TableDelta {
+            table,
+            primary_key,
+            csv_path: &csv_path,
+            delta_dir: previous.is_some().then_some(delta_dir.as_path()),
+        };
+
+        let previous_table = match &mut previous {
+            Some(previous) => previous.table(table)?,
+            None => None,
+        };
+        let (columns, full) = delta
+            .write(previous_table, &mut fingerprints)
+            .with_context(|| format!("Failed to compute delta for {table}.csv"))?;
+
+        if previous.is_some() {
+            let primary_key = primary_key.unwrap_or_default();
+            let context = template_context(table, config, primary_key, &columns, full);
+            template_tables.insert(table, context);
+        }
+    }
+
+    if previous.is_some() {
+        let template_tables = visibility_config
+            .topological_sort()
+            .into_iter()
+            .filter_map(|table| template_tables.remove(table))
+            .collect();
+
+        gen_import_script(&delta_dir.join("import.sql"), template_tables)
+            .context("Failed to generate delta import script")?;
+    }
+
+    fingerprints.finish()
+}
+
+/// Create a tarball of the `delta` folder of the export directory.
+pub fn create_delta_archive(
+    export_dir: &Path,
+    tarball_prefix: &Path,
+) -> anyhow::Result<tempfile::NamedTempFile> {
+    debug!("Creating delta tarball file…");
+    let tar_tempfile = tempfile::NamedTempFile::new()?;
+    let encoder =
+        flate2::write::GzEncoder::new(tar_tempfile.as_file(), flate2::Compression::default());
+    let mut tar = tar::Builder::new(encoder);
+
+    tar.append_dir_all(tarball_prefix, export_dir.join("delta"))?;
+    tar.into_inner()?.finish()?;
+
+    Ok(tar_tempfile)
+}
+
+fn add_metadata(delta_dir: &Path, from: DateTime<Utc>, to: DateTime<Utc>) -> anyhow::Result<()> {
+    #[derive(Serialize)]
+    struct Metadata {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    }
+
+    let path = delta_dir.join("metadata.json");
+    debug!(?path, "Writing metadata.json file…");
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(file, &Metadata { from, to })?;
+    Ok(())
+}
+
+/// Returns the primary key columns of the given table, if all of them are
+/// public.
+fn public_primary_key<'a>(
+    column_types: &'a ColumnTypes,
+    table: &str,
+    config: &TableConfig,
+) -> Option<&'a [String]> {
+    let primary_key = column_types.primary_keys.get(table)?;
+    let is_public = |column: &String| {
+        let visibility = config.columns.get(column);
+        visibility == Some(&ColumnVisibility::Public)
+    };
+
+    primary_key.iter().all(is_public).then_some(primary_key)
+}
+
+struct TableDelta<'a> {
+    table: &'a str,
+    primary_key: Option<&'a [String]>,
+    csv_path: &'a Path,
+    /// Only set if the delta to a previous dump should be written.
+    delta_dir: Option<&'a Path>,
+}
+
+impl TableDelta<'_> {
+    /// Compute the fingerprints of the table and, if requested, write the
+    /// delta files for it.
+    ///
+    /// Returns the columns of the table and whether the table is included in
+    /// full.
+    fn write<W: Write>(
+        &self,
+        previous: Option<TableFingerprints>,
+        fingerprints: &mut FingerprintsWriter<W>,
+    ) -> anyhow::Result<(Vec<String>, bool)> {
+        let mut reader = CopyCsvReader::from_path(self.csv_path)?;
+        let columns = reader.headers().to_vec();
+
+        let key_indices = self.primary_key.and_then(|primary_key| {
+            let position = |column| columns.iter().position(|header| header == column);
+            primary_key.iter().map(position).collect::<Option<Vec<_>>>()
+        });
+
+        // Tables without a public primary key, tables that were not part of
+        // the previous dump and tables with changed columns are included in
+        // full.
+        let mut previous = previous.filter(|previous| previous.columns == columns);
+        let full = key_indices.is_none() || previous.is_none();
+
+        if let Some(delta_dir) = self.delta_dir.filter(|_| full) {
+            let path = delta_dir
+                .join("data")
+                .join(self.table)
+                .with_extension("csv");
+            fs::copy(self.csv_path, path)?;
+        }
+
+        let mut writers = match (self.delta_dir, &key_indices) {
+            (Some(delta_dir), Some(key_indices)) if !full => {
+                let path = |kind| {
+                    let file_name = format!("{}.{kind}.csv", self.table);
+                    delta_dir.join("data").join(file_name)
+                };
+
+                // `NULL` and empty strings have to be written differently,
+                // since the `\copy` of the import script would otherwise
+                // import empty strings as `NULL`.
+                let mut inserted = CopyCsvWriter::from_path(&path("inserted"))?;
+                let mut updated = CopyCsvWriter::from_path(&path("updated"))?;
+                let mut deleted = CopyCsvWriter::from_path(&path("deleted"))?;
+
+                let headers = columns.iter().map(|column| Some(column.as_str()));
+                inserted.write_record(headers.clone())?;
+                updated.write_record(headers)?;
+                let key_headers = key_indices
+                    .iter()
+                    .map(|&index| Some(columns[index].as_str()));
+                deleted.write_record(key_headers)?;
+
+                Some((inserted, updated, deleted))
+            }
+            _ => None,
+        };
+
+        fingerprints.write_line(&Line::Table(TableHeader {
+            table: self.table.to_string(),
+            columns: columns.clone(),
+        }))?;
+
+        let mut record = Vec::new();
+        while reader.read_record(&mut record)? {
+            let Some(key_indices) = &key_indices else {
+                continue;
+            };
+
+            let key = row_key(&record, key_indices);
+            let hash = row_hash(&record);
+
+            // The rows that are left in the previous fingerprints afterwards
+            // have been deleted.
+            if let (Some((inserted, updated, _)), Some(previous)) = (&mut writers, &mut previous) {
+                let values = record.iter().map(Option::as_deref);
+                match previous.rows.remove(&key) {
+                    None => inserted.write_record(values)?,
+                    Some(previous_hash) if previous_hash != hash => updated.write_record(values)?,
+                    Some(_) => {}
+                }
+            }
+
+            fingerprints.write_line(&Line::Row(key, hash))?;
+        }
+
+        if let (Some((inserted, updated, mut deleted)), Some(previous)) = (writers, previous) {
+            for key in previous.rows.keys() {
+                deleted.write_record(key.split(KEY_SEPARATOR).map(Some))?;
+            }
+
+            inserted.into_inner()?;
+            updated.into_inner()?;
+            deleted.into_inner()?;
+        }
+
+        Ok((columns, full))
+    }
+}
+
+fn row_key(record: &[Option<String>], key_indices: &[usize]) -> String {
+    let mut key = String::new();
+    for (i, &index) in key_indices.iter().enumerate() {
+        if i > 0 {
+            key.push(KEY_SEPARATOR);
+        }
+        // Primary key columns can't be `NULL`.
+        key.push_str(record[index].as_deref().unwrap_or_default());
+    }
+    key
+}
+
+/// Hash the values of a CSV row with the 64-bit FNV-1a hash function.
+///
+/// Unlike the hasher of the standard library, the result is guaranteed to be
+/// stable across releases, which is required since the fingerprints are
+/// persisted between dumps.
+fn row_hash(record: &[Option<String>]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    /// Hashed in place of `NULL` values, so that they differ from empty
+    /// strings. `0xff` can't be part of a UTF-8 string.
+    const NULL: &[u8] = &[0xff];
+
+    let mut hash = OFFSET_BASIS;
+    for field in record {
+        let bytes = field.as_deref().map_or(NULL, str::as_bytes);
+        // Terminate each field, so that e.g. `ab,c` and `a,bc` differ.
+        for byte in bytes.iter().copied().chain([KEY_SEPARATOR as u8]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+/// Subset of the configuration data to be passed on to the import template.
+#[derive(Debug, Serialize)]
+struct TableContext<'a> {
+    name: &'a str,
+    full: bool,
+    columns: String,
+    key_columns: String,
+    key_condition: String,
+    assignments: String,
+    column_defaults: Vec<ColumnDefault<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ColumnDefault<'a> {
+    column: &'a str,
+    value: &'a str,
+}
+
+fn template_context<'a>(
+    name: &'a str,
+    config: &'a TableConfig,
+    primary_key: &[String],
+    columns: &[String],
+    full: bool,
+) -> TableContext<'a> {
+    let quote = |columns: &[String]| {
+        let columns = columns.iter().map(|column| format!("\"{column}\""));
+        columns.collect::<Vec<_>>().join(", ")
+    };
+
+    let assignments = columns
+        .iter()
+        .map(|column| format!("\"{column}\" = d.\"{column}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let columns = quote(columns);
+    let key_columns = quote(primary_key);
+
+    let key_condition = primary_key
+        .iter()
+        .map(|column| format!("t.\"{column}\" = d.\"{column}\""))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+
+    let column_defaults = config
+        .column_defaults
+        .iter()
+        .map(|(column, value)| ColumnDefault { column, value })
+        .collect();
+
+    TableContext {
+        name,
+        full,
+        columns,
+        key_columns,
+        key_condition,
+        assignments,
+        column_defaults,
+    }
+}
+
+fn gen_import_script(path: &Path, tables: Vec<TableContext<'_>>) -> anyhow::Result<()> {
+    use minijinja::{context, Environment};
+
+    let mut env = Environment::new();
+    env.add_template(
+        "dump-import-delta.sql",
+        include_str!("dump-import-delta.sql.j2"),
+    )
+    .context("Failed to load dump-import-delta.sql.j2 template")?;
+
+    debug!("Rendering dump-import-delta.sql file…");
+    let import_sql = env
+        .get_template("dump-import-delta.sql")
+        .unwrap()
+        .render(context! { tables })
+        .context("Failed to render dump-import-delta.sql file")?;
+
+    debug!(?path, "Writing delta import.sql file…");
+    fs::write(path, import_sql).context("Failed to write dump-import-delta.sql file")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parquet_export::write_test_column_types;
+    use insta::assert_snapshot;
+
+    fn write_csv_files(export_dir: &Path, crates: &str, crates_categories: &str) {
+        let data_dir = export_dir.join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::write(data_dir.join("crates.csv"), crates).unwrap();
+        fs::write(data_dir.join("crates_categories.csv"), crates_categories).unwrap();
+        fs::write(data_dir.join("metadata.csv"), "total_downloads\n42\n").unwrap();
+        write_test_column_types(export_dir);
+    }
+
+    #[test]
+    fn test_create_delta() {
+        let first_dir = tempfile::tempdir().unwrap();
+        write_csv_files(
+            first_dir.path(),
+            "id,name,updated_at\n1,foo,2017-01-06\n2,bar,2017-01-06\n3,baz,2017-01-06\n",
+            "category_id,crate_id\n1,1\n2,1\n",
+        );
+
+        let first_timestamp = "2017-01-06T12:00:00Z".parse().unwrap();
+        let cancellation = CancellationFlag::default();
+        let fingerprints = create_delta::<&[u8], _>(
+            first_dir.path(),
+            first_timestamp,
+            None,
+            vec![],
+            &cancellation,
+        )
+        .unwrap();
+        assert!(!first_dir.path().join("delta").exists());
+
+        let fingerprints = FingerprintsReader::new(fingerprints.as_slice()).unwrap();
+        assert_eq!(fingerprints.timestamp, first_timestamp);
+
+        let second_dir = tempfile::tempdir().unwrap();
+        write_csv_files(
+            second_dir.path(),
+            "id,name,updated_at\n1,foo,2017-01-06\n2,bar,2017-01-07\n4,qux,2017-01-07\n",
+            "category_id,crate_id\n2,1\n3,4\n",
+        );
+
+        let second_timestamp = "2017-01-07T12:00:00Z".parse().unwrap();
+        create_delta(
+            second_dir.path(),
+            second_timestamp,
+            Some(fingerprints),
+            vec![],
+            &cancellation,
+        )
+        .unwrap();
+
+        let read = |name: &str| {
+            let path = second_dir.path().join("delta").join(name);
+            fs::read_to_string(path).unwrap()
+        };
+
+        assert_snapshot!(read("data/crates.inserted.csv"), @r#"
+        "id","name","updated_at"
+        "4","qux","2017-01-07"
+        "#);
+        assert_snapshot!(read("data/crates.updated.csv"), @r#"
+        "id","name","updated_at"
+        "2","bar","2017-01-07"
+        "#);
+        assert_snapshot!(read("data/crates.deleted.csv"), @r#"
+        "id"
+        "3"
+        "#);
+        assert_snapshot!(read("data/crates_categories.inserted.csv"), @r#"
+        "category_id","crate_id"
+        "3","4"
+        "#);
+        assert_snapshot!(read("data/crates_categories.deleted.csv"), @r#"
+        "crate_id","category_id"
+        "1","1"
+        "#);
+
+        assert_snapshot!(read("metadata.json"), @r#"
+        {
+          "from": "2017-01-06T12:00:00Z",
+          "to": "2017-01-07T12:00:00Z"
+        }
+        "#);
+
+        assert_snapshot!("import.sql", read("import.sql"));
+    }
+
+    #[test]
+    fn test_create_delta_null_and_empty() {
+        let first_dir = tempfile::tempdir().unwrap();
+        write_csv_files(
+            first_dir.path(),
+            "id,name,description\n1,foo,\n2,bar,\"\"\n3,baz,\n",
+            "category_id,crate_id\n",
+        );
+
+        let cancellation = CancellationFlag::default();
+        let fingerprints = create_delta::<&[u8], _>(
+            first_dir.path(),
+            "2017-01-06T12:00:00Z".parse().unwrap(),
+            None,
+            vec![],
+            &cancellation,
+        )
+        .unwrap();
+        let fingerprints = FingerprintsReader::new(fingerprints.as_slice()).unwrap();
+
+        // `NULL` changes to an empty string and vice versa, and new rows with
+        // both are inserted.
+        let second_dir = tempfile::tempdir().unwrap();
+        write_csv_files(
+            second_dir.path(),
+            "id,name,description\n1,foo,\"\"\n2,bar,\n3,baz,\n4,qux,\n5,quux,\"\"\n",
+            "category_id,crate_id\n",
+        );
+
+        create_delta(
+            second_dir.path(),
+            "2017-01-07T12:00:00Z".parse().unwrap(),
+            Some(fingerprints),
+            vec![],
+            &cancellation,
+        )
+        .unwrap();
+
+        let read = |name: &str| {
+            let path = second_dir.path().join("delta").join(name);
+            fs::read_to_string(path).unwrap()
+        };
+
+        assert_snapshot!(read("data/crates.inserted.csv"), @r#"
+        "id","name","description"
+        "4","qux",
+        "5","quux",""
+        "#);
+        assert_snapshot!(read("data/crates.updated.csv"), @r#"
+        "id","name","description"
+        "1","foo",""
+        "2","bar",
+        "#);
+    }
+
+    #[test]
+    fn test_delta_manifest_head() {
+        let base = BaseSnapshot {
+            timestamp: "2017-01-06T12:00:00Z".parse().unwrap(),
+            path: "db-dump/delta/base-2017-01-06-120000.tar.gz".to_string(),
+        };
+
+        let mut manifest = DeltaManifest::new(base);
+        assert_eq!(manifest.head().to_string(), "2017-01-06 12:00:00 UTC");
+
+        manifest.deltas.push(Delta {
+            from: manifest.head(),
+            to: "2017-01-07T12:00:00Z".parse().unwrap(),
+            path: "db-dump/delta/2017-01-07-120000.tar.gz".to_string(),
+        });
+        assert_eq!(manifest.head().to_string(), "2017-01-07 12:00:00 UTC");
+
+        let paths = manifest.paths().collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "db-dump/delta/base-2017-01-06-120000.tar.gz",
+                "db-dump/delta/2017-01-07-120000.tar.gz"
+            ]
+        );
+    }
+}
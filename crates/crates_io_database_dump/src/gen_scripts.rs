@@ -1,4 +1,5 @@
 use crate::configuration::{ColumnVisibility, TableConfig, VisibilityConfig};
+use crate::parquet_export::{ColumnTypes, COLUMN_TYPES_PATH};
 use anyhow::Context;
 use serde::Serialize;
 use std::{fs::File, path::Path};
@@ -61,6 +62,7 @@ impl TableConfig {
 #[derive(Debug, Serialize)]
 struct TemplateContext<'a> {
     tables: Vec<HandlebarsTableContext<'a>>,
+    column_types_command: String,
 }
 
 impl VisibilityConfig {
@@ -70,7 +72,11 @@ impl VisibilityConfig {
             .into_iter()
             .filter_map(|table| self.0[table].template_context(table))
             .collect();
-        TemplateContext { tables }
+        let column_types_command = ColumnTypes::export_command(COLUMN_TYPES_PATH);
+        TemplateContext {
+            tables,
+            column_types_command,
+        }
     }
 
     fn gen_psql_scripts<W>(&self, mut export_writer: W, mut import_writer: W) -> anyhow::Result<()>
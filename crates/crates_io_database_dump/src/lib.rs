@@ -1,18 +1,53 @@
 #![doc = include_str!("../README.md")]
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, ensure, Context};
 use serde::Serialize;
 use std::fs;
 use std::fs::File;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::debug;
 use zip::write::SimpleFileOptions;
 
 mod configuration;
+mod delta;
 mod gen_scripts;
+mod parquet_export;
+mod sqlite;
 
 pub use configuration::VisibilityConfig;
+pub use delta::{
+    create_delta, create_delta_archive, BaseSnapshot, Delta, DeltaManifest, FingerprintsReader,
+    FingerprintsWriter,
+};
 pub use gen_scripts::gen_scripts;
+pub use parquet_export::{ColumnType, ColumnTypes, ScalarType, COLUMN_TYPES_PATH};
+pub use sqlite::create_sqlite;
+
+/// A flag that can be used to stop a database dump that is running on
+/// another thread.
+///
+/// The export functions check the flag before each table and return an
+/// error once it has been set. Running `psql` processes are killed.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationFlag(Arc<AtomicBool>);
+
+impl CancellationFlag {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        ensure!(!self.is_cancelled(), "Database dump was cancelled");
+        Ok(())
+    }
+}
 
 /// Manage the export directory.
 ///
@@ -38,7 +73,11 @@ impl DumpDirectory {
         self.tempdir.path()
     }
 
-    pub fn populate(&self, database_url: &str) -> anyhow::Result<()> {
+    pub fn populate(
+        &self,
+        database_url: &str,
+        cancellation: &CancellationFlag,
+    ) -> anyhow::Result<()> {
         self.add_readme()
             .context("Failed to write README.md file")?;
 
@@ -48,7 +87,7 @@ impl DumpDirectory {
         self.dump_schema(database_url)
             .context("Failed to generate schema.sql file")?;
 
-        self.dump_db(database_url)
+        self.dump_db(database_url, cancellation)
             .context("Failed to create database dump")
     }
 
@@ -107,7 +146,11 @@ impl DumpDirectory {
         Ok(())
     }
 
-    pub fn dump_db(&self, database_url: &str) -> anyhow::Result<()> {
+    pub fn dump_db(
+        &self,
+        database_url: &str,
+        cancellation: &CancellationFlag,
+    ) -> anyhow::Result<()> {
         debug!("Generating export.sql and import.sql files…");
         let export_script = self.path().join("export.sql");
         let import_script = self.path().join("import.sql");
@@ -117,16 +160,71 @@ impl DumpDirectory {
         debug!("Filling data folder…");
         fs::create_dir(self.path().join("data")).context("Failed to create `data` directory")?;
 
-        run_psql(&export_script, database_url)
+        let column_types_path = self.path().join(COLUMN_TYPES_PATH);
+        fs::create_dir_all(column_types_path.parent().unwrap())
+            .context("Failed to create column types directory")?;
+
+        run_psql_until_cancelled(&export_script, database_url, cancellation)
+    }
+
+    /// Convert the CSV files in the `data` folder into Parquet files in the
+    /// `parquet` folder, using the column types that were exported together
+    /// with them.
+    ///
+    /// Since the CSV files only contain the public columns and rows, the
+    /// visibility rules of `dump-db.toml` apply to the Parquet files too.
+    ///
+    /// Returns the paths of the Parquet files and a copy of the
+    /// `metadata.json` file.
+    pub fn dump_parquet(&self, cancellation: &CancellationFlag) -> anyhow::Result<Vec<PathBuf>> {
+        debug!("Filling parquet folder…");
+        let parquet_dir = self.path().join("parquet");
+        fs::create_dir(&parquet_dir).context("Failed to create `parquet` directory")?;
+
+        let metadata_path = parquet_dir.join("metadata.json");
+        fs::copy(self.path().join("metadata.json"), &metadata_path)
+            .context("Failed to copy metadata.json file")?;
+
+        let mut paths = vec![metadata_path];
+
+        let column_types = ColumnTypes::load(&self.path().join(COLUMN_TYPES_PATH))?;
+        let visibility_config = VisibilityConfig::get();
+        for table in visibility_config.topological_sort() {
+            cancellation.check()?;
+
+            let csv_path = self.path().join("data").join(table).with_extension("csv");
+            if csv_path.exists() {
+                let parquet_path = parquet_dir.join(table).with_extension("parquet");
+                column_types
+                    .csv_to_parquet(table, &csv_path, &parquet_path)
+                    .with_context(|| format!("Failed to convert {table}.csv to Parquet"))?;
+
+                paths.push(parquet_path);
+            }
+        }
+
+        Ok(paths)
     }
 }
 
 pub fn run_psql(script: &Path, database_url: &str) -> anyhow::Result<()> {
+    run_psql_until_cancelled(script, database_url, &CancellationFlag::default())
+}
+
+/// Run a psql script, and kill the `psql` process once the cancellation
+/// flag is set.
+fn run_psql_until_cancelled(
+    script: &Path,
+    database_url: &str,
+    cancellation: &CancellationFlag,
+) -> anyhow::Result<()> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
     debug!(?script, "Running psql script…");
     let psql_script =
         File::open(script).with_context(|| format!("Failed to open {}", script.display()))?;
 
-    let psql = std::process::Command::new("psql")
+    let mut psql = std::process::Command::new("psql")
         .arg("--no-psqlrc")
         .arg(database_url)
         .current_dir(script.parent().unwrap())
@@ -136,15 +234,39 @@ pub fn run_psql(script: &Path, database_url: &str) -> anyhow::Result<()> {
         .spawn()
         .context("Failed to run psql command")?;
 
-    let output = psql
-        .wait_with_output()
-        .context("Failed to wait for psql command to exit")?;
+    // Read stderr on a separate thread, so that psql doesn't block on a
+    // full pipe while it is polled below.
+    let mut stderr = psql.stderr.take().unwrap();
+    let stderr = std::thread::spawn(move || {
+        let mut buffer = String::new();
+        std::io::Read::read_to_string(&mut stderr, &mut buffer).map(|_| buffer)
+    });
+
+    let status = loop {
+        if let Some(status) = psql.try_wait().context("Failed to wait for psql command")? {
+            break status;
+        }
+
+        if cancellation.is_cancelled() {
+            debug!("Killing psql command…");
+            psql.kill().context("Failed to kill psql command")?;
+            psql.wait()
+                .context("Failed to wait for psql command to exit")?;
+            cancellation.check()?;
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let stderr = stderr
+        .join()
+        .map_err(|_| anyhow!("Failed to read psql output"))?
+        .context("Failed to read psql output")?;
 
-    let stderr = String::from_utf8_lossy(&output.stderr);
     if stderr.contains("ERROR") {
         return Err(anyhow!("Error while executing psql: {stderr}"));
     }
-    if !output.status.success() {
+    if !status.success() {
         return Err(anyhow!("psql did not finish successfully."));
     }
     Ok(())
@@ -155,7 +277,11 @@ pub struct Archives {
     pub zip: tempfile::NamedTempFile,
 }
 
-pub fn create_archives(export_dir: &Path, tarball_prefix: &Path) -> anyhow::Result<Archives> {
+pub fn create_archives(
+    export_dir: &Path,
+    tarball_prefix: &Path,
+    cancellation: &CancellationFlag,
+) -> anyhow::Result<Archives> {
     debug!("Creating tarball file…");
     let tar_tempfile = tempfile::NamedTempFile::new()?;
     let encoder =
@@ -205,6 +331,8 @@ pub fn create_archives(export_dir: &Path, tarball_prefix: &Path) -> anyhow::Resu
     zip.add_directory("data", SimpleFileOptions::default())?;
 
     for table in sorted_tables {
+        cancellation.check()?;
+
         let csv_path = export_dir.join("data").join(table).with_extension("csv");
         if csv_path.exists() {
             let name = tarball_prefix
@@ -253,7 +381,12 @@ mod tests {
         fs::write(p.join("data").join("crate_owners.csv"), "").unwrap();
         fs::write(p.join("data").join("users.csv"), "").unwrap();
 
-        let archives = create_archives(p, &PathBuf::from("0000-00-00")).unwrap();
+        let archives = create_archives(
+            p,
+            &PathBuf::from("0000-00-00"),
+            &CancellationFlag::default(),
+        )
+        .unwrap();
         let gz = GzDecoder::new(File::open(archives.tar.path()).unwrap());
         let mut tar = Archive::new(gz);
 
@@ -289,6 +422,23 @@ mod tests {
         "#);
     }
 
+    #[test]
+    fn cancelled_dumps_are_stopped() {
+        let db = TestDatabase::new();
+
+        let cancellation = CancellationFlag::default();
+        cancellation.cancel();
+
+        let directory = DumpDirectory::create().unwrap();
+        let error = directory.populate(db.url(), &cancellation).unwrap_err();
+        assert_snapshot!(format!("{error:#}"), @"Failed to create database dump: Database dump was cancelled");
+
+        let tarball_prefix = PathBuf::from("0000-00-00");
+        let result = create_archives(directory.path(), &tarball_prefix, &cancellation);
+        let error = result.err().unwrap();
+        assert_snapshot!(format!("{error:#}"), @"Database dump was cancelled");
+    }
+
     #[test]
     fn dump_db_and_reimport_dump() {
         let db_one = TestDatabase::new();
@@ -296,7 +446,9 @@ mod tests {
         // TODO prefill database with some data
 
         let directory = DumpDirectory::create().unwrap();
-        directory.populate(db_one.url()).unwrap();
+        directory
+            .populate(db_one.url(), &CancellationFlag::default())
+            .unwrap();
 
         let db_two = TestDatabase::empty();
 
@@ -309,12 +461,126 @@ mod tests {
         // TODO: Consistency checks on the re-imported data?
     }
 
+    #[test]
+    fn apply_delta_to_base_dump() {
+        use diesel::RunQueryDsl;
+
+        let db_one = TestDatabase::new();
+        let mut conn = db_one.connect();
+
+        diesel::sql_query("INSERT INTO keywords (keyword) VALUES ('foo'), ('bar'), ('baz')")
+            .execute(&mut conn)
+            .unwrap();
+
+        let cancellation = CancellationFlag::default();
+        let base = DumpDirectory::create().unwrap();
+        base.populate(db_one.url(), &cancellation).unwrap();
+        let fingerprints =
+            create_delta::<&[u8], _>(base.path(), base.timestamp, None, vec![], &cancellation)
+                .unwrap();
+
+        diesel::sql_query("DELETE FROM keywords WHERE keyword = 'bar'")
+            .execute(&mut conn)
+            .unwrap();
+        diesel::sql_query("UPDATE keywords SET crates_cnt = 5 WHERE keyword = 'foo'")
+            .execute(&mut conn)
+            .unwrap();
+        diesel::sql_query("INSERT INTO keywords (keyword) VALUES ('qux')")
+            .execute(&mut conn)
+            .unwrap();
+        // Empty strings must not be imported as `NULL` into `NOT NULL` columns.
+        diesel::sql_query(
+            "INSERT INTO categories (category, slug, description) VALUES ('Foo', 'foo', '')",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        let full = DumpDirectory::create().unwrap();
+        full.populate(db_one.url(), &cancellation).unwrap();
+        let previous = FingerprintsReader::new(fingerprints.as_slice()).unwrap();
+        create_delta(
+            full.path(),
+            full.timestamp,
+            Some(previous),
+            vec![],
+            &cancellation,
+        )
+        .unwrap();
+
+        // Import the base dump into a new database and apply the delta to it.
+        let db_two = TestDatabase::empty();
+        run_psql(&base.path().join("schema.sql"), db_two.url()).unwrap();
+        run_psql(&base.path().join("import.sql"), db_two.url()).unwrap();
+        run_psql(&full.path().join("delta/import.sql"), db_two.url()).unwrap();
+
+        // Dumping the result has to reproduce the full dump.
+        let applied = DumpDirectory::create().unwrap();
+        applied.populate(db_two.url(), &cancellation).unwrap();
+
+        let mut tables = 0;
+        for entry in fs::read_dir(full.path().join("data")).unwrap() {
+            let path = entry.unwrap().path();
+            let file_name = path.file_name().unwrap();
+            let expected = fs::read_to_string(&path).unwrap();
+            let actual = fs::read_to_string(applied.path().join("data").join(file_name)).unwrap();
+            assert_eq!(actual, expected, "{file_name:?} differs");
+            tables += 1;
+        }
+        assert!(tables > 0);
+
+        let keywords = fs::read_to_string(applied.path().join("data/keywords.csv")).unwrap();
+        assert!(keywords.contains("qux"));
+        assert!(!keywords.contains("bar"));
+
+        let categories = fs::read_to_string(applied.path().join("data/categories.csv")).unwrap();
+        assert!(categories.contains("Foo"));
+    }
+
+    #[test]
+    fn column_types_are_exported_with_the_data() {
+        let db = TestDatabase::new();
+
+        let directory = DumpDirectory::create().unwrap();
+        directory
+            .populate(db.url(), &CancellationFlag::default())
+            .unwrap();
+
+        let path = directory.path().join(COLUMN_TYPES_PATH);
+        let column_types = ColumnTypes::load(&path).unwrap();
+
+        let column_type = column_types.column_type("versions", "bin_names");
+        assert_eq!(column_type.scalar, ScalarType::Text);
+        assert!(column_type.array);
+        assert!(column_type.nullable);
+
+        let column_type = column_types.column_type("crates", "created_at");
+        assert_eq!(column_type.scalar, ScalarType::Timestamptz);
+        assert!(!column_type.nullable);
+
+        let primary_key = |table| column_types.primary_keys.get(table).unwrap();
+        assert_eq!(primary_key("crates"), &["id"]);
+        assert_eq!(primary_key("version_downloads"), &["version_id", "date"]);
+
+        // The column types are not part of the archives.
+        let tarball_prefix = PathBuf::from("0000-00-00");
+        let cancellation = CancellationFlag::default();
+        let archives = create_archives(directory.path(), &tarball_prefix, &cancellation).unwrap();
+        let gz = GzDecoder::new(File::open(archives.tar.path()).unwrap());
+        let mut tar = Archive::new(gz);
+        for entry in tar.entries().unwrap() {
+            let path = entry.unwrap().path().unwrap().display().to_string();
+            assert!(!path.contains("types"), "{path}");
+        }
+    }
+
     #[test]
     fn test_sql_scripts() {
         let db = TestDatabase::new();
 
         let directory = DumpDirectory::create().unwrap();
-        directory.populate(db.url()).unwrap();
+        directory
+            .populate(db.url(), &CancellationFlag::default())
+            .unwrap();
 
         insta::glob!(directory.path(), "{import,export}.sql", |path| {
             let content = fs::read_to_string(path).unwrap();
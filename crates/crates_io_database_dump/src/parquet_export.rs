@@ -0,0 +1,819 @@
+use anyhow::{anyhow, bail, Context};
+use arrow_array::builder::{
+    BooleanBuilder, Date32Builder, Int16Builder, Int32Builder, Int64Builder, ListBuilder,
+    StringBuilder, TimestampMicrosecondBuilder,
+};
+use arrow_array::types::Date32Type;
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Number of CSV rows that are buffered before they are written to the
+/// Parquet file as a single record batch.
+const BATCH_SIZE: usize = 64 * 1024;
+
+/// The scalar types that have a native representation in the Parquet files.
+///
+/// All other SQL types (`Text`, `Varchar`, `Jsonb`, `Ltree`, …) are written
+/// as strings containing their PostgreSQL text representation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScalarType {
+    Bool,
+    Int2,
+    Int4,
+    Int8,
+    Date,
+    Timestamp,
+    Timestamptz,
+    Text,
+}
+
+/// The type of a single database column, as exported from the database.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColumnType {
+    pub scalar: ScalarType,
+    /// Arrays are written as lists of strings, regardless of their element
+    /// type.
+    pub array: bool,
+    pub nullable: bool,
+}
+
+impl ColumnType {
+    /// Used for columns that are missing from the exported column types.
+    const UNKNOWN: Self = Self {
+        scalar: ScalarType::Text,
+        array: false,
+        nullable: true,
+    };
+
+    /// Convert a PostgreSQL type name from `information_schema.columns`,
+    /// like `int4` or `_text`, whose leading underscore marks an array type.
+    fn from_udt_name(udt_name: &str, nullable: bool) -> Self {
+        let (udt_name, array) = udt_name
+            .strip_prefix('_')
+            .map_or((udt_name, false), |element| (element, true));
+
+        let scalar = match udt_name {
+            "bool" => ScalarType::Bool,
+            "int2" => ScalarType::Int2,
+            "int4" => ScalarType::Int4,
+            "int8" => ScalarType::Int8,
+            "date" => ScalarType::Date,
+            "timestamp" => ScalarType::Timestamp,
+            "timestamptz" => ScalarType::Timestamptz,
+            _ => ScalarType::Text,
+        };
+
+        Self {
+            scalar,
+            array,
+            nullable,
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        if self.array {
+            return DataType::List(Arc::new(Field::new_list_field(DataType::Utf8, true)));
+        }
+
+        match self.scalar {
+            ScalarType::Bool => DataType::Boolean,
+            ScalarType::Int2 => DataType::Int16,
+            ScalarType::Int4 => DataType::Int32,
+            ScalarType::Int8 => DataType::Int64,
+            ScalarType::Date => DataType::Date32,
+            ScalarType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+            ScalarType::Timestamptz => {
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+            }
+            ScalarType::Text => DataType::Utf8,
+        }
+    }
+}
+
+/// The path of the column types file within the export directory, which is
+/// written by the export script and loaded by [ColumnTypes::load].
+pub const COLUMN_TYPES_PATH: &str = "types/columns.csv";
+
+/// Selects the columns of all tables, with their types and their positions
+/// within the primary key of their table.
+const COLUMN_TYPES_QUERY: &str = "\
+SELECT c.table_name, c.column_name, c.udt_name, c.is_nullable, k.ordinal_position AS primary_key_position \
+FROM information_schema.columns c \
+LEFT JOIN information_schema.table_constraints t \
+ON t.table_schema = c.table_schema AND t.table_name = c.table_name AND t.constraint_type = 'PRIMARY KEY' \
+LEFT JOIN information_schema.key_column_usage k \
+ON k.constraint_schema = t.constraint_schema AND k.constraint_name = t.constraint_name AND k.column_name = c.column_name \
+WHERE c.table_schema = 'public' \
+ORDER BY c.table_name, c.ordinal_position";
+
+/// Maps table names to their column names and types, and to their primary
+/// key columns.
+///
+/// The types are taken from `information_schema.columns` at export time, so
+/// that they always match the exported data.
+#[derive(Clone, Debug, Default)]
+pub struct ColumnTypes {
+    pub tables: BTreeMap<String, BTreeMap<String, ColumnType>>,
+    pub primary_keys: BTreeMap<String, Vec<String>>,
+}
+
+impl ColumnTypes {
+    /// Returns the `psql` command that exports the column types of the
+    /// database to the given CSV file.
+    pub fn export_command(path: &str) -> String {
+        format!("\\copy ({COLUMN_TYPES_QUERY}) TO '{path}' WITH CSV HEADER")
+    }
+
+    /// Load the column types from a CSV file that was written by the
+    /// [export command](Self::export_command).
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        struct Row {
+            table_name: String,
+            column_name: String,
+            udt_name: String,
+            is_nullable: String,
+            primary_key_position: Option<u32>,
+        }
+
+        let mut reader = csv::Reader::from_path(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        let mut tables: BTreeMap<String, BTreeMap<_, _>> = BTreeMap::new();
+        let mut primary_keys: BTreeMap<String, Vec<_>> = BTreeMap::new();
+        for row in reader.deserialize() {
+            let row: Row = row.with_context(|| format!("Failed to read {}", path.display()))?;
+
+            let nullable = row.is_nullable == "YES";
+            let column_type = ColumnType::from_udt_name(&row.udt_name, nullable);
+            let columns = tables.entry(row.table_name.clone()).or_default();
+            columns.insert(row.column_name.clone(), column_type);
+
+            if let Some(position) = row.primary_key_position {
+                let primary_key = primary_keys.entry(row.table_name).or_default();
+                primary_key.push((position, row.column_name));
+            }
+        }
+
+        let primary_keys = primary_keys
+            .into_iter()
+            .map(|(table, mut columns)| {
+                columns.sort();
+                let columns = columns.into_iter().map(|(_, column)| column).collect();
+                (table, columns)
+            })
+            .collect();
+
+        Ok(Self {
+            tables,
+            primary_keys,
+        })
+    }
+
+    /// Returns the type of the given column, or a nullable string type if
+    /// the column is missing from the column types.
+    pub fn column_type(&self, table: &str, column: &str) -> ColumnType {
+        let columns = self.tables.get(table);
+        let column_type = columns.and_then(|columns| columns.get(column));
+        column_type.copied().unwrap_or(ColumnType::UNKNOWN)
+    }
+
+    /// Convert a CSV file that was exported from the given table via `psql`
+    /// into a Parquet file.
+    ///
+    /// The CSV file must contain a header row. Unquoted empty values are
+    /// written as `NULL`, while quoted empty values are empty strings, like
+    /// in `COPY … FROM`. Columns that are missing from the column types are
+    /// written as nullable strings.
+    pub fn csv_to_parquet(
+        &self,
+        table: &str,
+        csv_path: &Path,
+        parquet_path: &Path,
+    ) -> anyhow::Result<()> {
+        debug!(?csv_path, ?parquet_path, "Converting CSV file to Parquet…");
+
+        if !self.tables.contains_key(table) {
+            bail!("Unknown table: {table}");
+        }
+
+        let mut reader = CopyCsvReader::from_path(csv_path)?;
+
+        let headers = reader.headers().to_vec();
+        let column_types = headers
+            .iter()
+            .map(|name| self.column_type(table, name))
+            .collect::<Vec<_>>();
+
+        let fields = headers
+            .iter()
+            .zip(&column_types)
+            .map(|(name, column_type)| {
+                Field::new(name, column_type.data_type(), column_type.nullable)
+            })
+            .collect::<Vec<_>>();
+
+        let schema = Arc::new(Schema::new(fields));
+
+        let file = File::create(parquet_path)
+            .with_context(|| format!("Failed to create {}", parquet_path.display()))?;
+
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+
+        let mut builders = column_types
+            .iter()
+            .map(ColumnBuilder::new)
+            .collect::<Vec<_>>();
+
+        let mut num_rows = 0;
+        let mut record = Vec::new();
+        while reader.read_record(&mut record)? {
+            for ((builder, value), name) in builders.iter_mut().zip(&record).zip(&headers) {
+                builder
+                    .append(value.as_deref())
+                    .with_context(|| format!("Failed to convert {table}.{name} value {value:?}"))?;
+            }
+
+            num_rows += 1;
+            if num_rows == BATCH_SIZE {
+                writer.write(&finish_batch(&schema, &mut builders)?)?;
+                num_rows = 0;
+            }
+        }
+
+        if num_rows > 0 {
+            writer.write(&finish_batch(&schema, &mut builders)?)?;
+        }
+
+        writer.close()?;
+
+        Ok(())
+    }
+}
+
+fn finish_batch(
+    schema: &Arc<Schema>,
+    builders: &mut [ColumnBuilder],
+) -> anyhow::Result<RecordBatch> {
+    let columns = builders.iter_mut().map(ColumnBuilder::finish).collect();
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+/// Accumulates the values of a single column until the next record batch is
+/// written.
+struct ColumnBuilder {
+    nullable: bool,
+    values: ValuesBuilder,
+}
+
+enum ValuesBuilder {
+    Bool(BooleanBuilder),
+    Int2(Int16Builder),
+    Int4(Int32Builder),
+    Int8(Int64Builder),
+    Date(Date32Builder),
+    Timestamp(TimestampMicrosecondBuilder),
+    Text(StringBuilder),
+    Array(ListBuilder<StringBuilder>),
+}
+
+impl ColumnBuilder {
+    fn new(column_type: &ColumnType) -> Self {
+        let values = match column_type.scalar {
+            _ if column_type.array => ValuesBuilder::Array(ListBuilder::new(StringBuilder::new())),
+            ScalarType::Bool => ValuesBuilder::Bool(BooleanBuilder::new()),
+            ScalarType::Int2 => ValuesBuilder::Int2(Int16Builder::new()),
+            ScalarType::Int4 => ValuesBuilder::Int4(Int32Builder::new()),
+            ScalarType::Int8 => ValuesBuilder::Int8(Int64Builder::new()),
+            ScalarType::Date => ValuesBuilder::Date(Date32Builder::new()),
+            ScalarType::Timestamp => ValuesBuilder::Timestamp(TimestampMicrosecondBuilder::new()),
+            ScalarType::Timestamptz => {
+                let builder = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+                ValuesBuilder::Timestamp(builder)
+            }
+            ScalarType::Text => ValuesBuilder::Text(StringBuilder::new()),
+        };
+
+        Self {
+            nullable: column_type.nullable,
+            values,
+        }
+    }
+
+    /// Append a single value in the `psql` CSV output format, or `None` for
+    /// `NULL`.
+    fn append(&mut self, value: Option<&str>) -> anyhow::Result<()> {
+        let Some(value) = value else {
+            if !self.nullable {
+                bail!("Unexpected NULL value");
+            }
+
+            match &mut self.values {
+                ValuesBuilder::Bool(builder) => builder.append_null(),
+                ValuesBuilder::Int2(builder) => builder.append_null(),
+                ValuesBuilder::Int4(builder) => builder.append_null(),
+                ValuesBuilder::Int8(builder) => builder.append_null(),
+                ValuesBuilder::Date(builder) => builder.append_null(),
+                ValuesBuilder::Timestamp(builder) => builder.append_null(),
+                ValuesBuilder::Text(builder) => builder.append_null(),
+                ValuesBuilder::Array(builder) => builder.append_null(),
+            }
+            return Ok(());
+        };
+
+        match &mut self.values {
+            ValuesBuilder::Bool(builder) => builder.append_value(parse_bool(value)?),
+            ValuesBuilder::Int2(builder) => builder.append_value(value.parse()?),
+            ValuesBuilder::Int4(builder) => builder.append_value(value.parse()?),
+            ValuesBuilder::Int8(builder) => builder.append_value(value.parse()?),
+            ValuesBuilder::Date(builder) => {
+                let date = NaiveDate::parse_from_str(value, "%F")?;
+                builder.append_value(Date32Type::from_naive_date(date));
+            }
+            ValuesBuilder::Timestamp(builder) => {
+                builder.append_value(parse_timestamp_micros(value)?);
+            }
+            ValuesBuilder::Text(builder) => builder.append_value(value),
+            ValuesBuilder::Array(builder) => {
+                for element in parse_array(value)? {
+                    builder.values().append_option(element);
+                }
+                builder.append(true);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match &mut self.values {
+            ValuesBuilder::Bool(builder) => Arc::new(builder.finish()),
+            ValuesBuilder::Int2(builder) => Arc::new(builder.finish()),
+            ValuesBuilder::Int4(builder) => Arc::new(builder.finish()),
+            ValuesBuilder::Int8(builder) => Arc::new(builder.finish()),
+            ValuesBuilder::Date(builder) => Arc::new(builder.finish()),
+            ValuesBuilder::Timestamp(builder) => Arc::new(builder.finish()),
+            ValuesBuilder::Text(builder) => Arc::new(builder.finish()),
+            ValuesBuilder::Array(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+/// Reads CSV files in the format of `COPY … TO … WITH CSV HEADER`.
+///
+/// Unlike `csv::Reader`, this keeps the difference between unquoted empty
+/// values, which `COPY` uses for `NULL`, and quoted empty strings.
+pub(crate) struct CopyCsvReader<R> {
+    reader: R,
+    headers: Vec<String>,
+    line: String,
+}
+
+impl CopyCsvReader<BufReader<File>> {
+    pub(crate) fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: BufRead> CopyCsvReader<R> {
+    pub(crate) fn new(reader: R) -> anyhow::Result<Self> {
+        let mut reader = Self {
+            reader,
+            headers: Vec::new(),
+            line: String::new(),
+        };
+
+        let mut headers = Vec::new();
+        if reader.read_record(&mut headers)? {
+            reader.headers = headers.into_iter().map(Option::unwrap_or_default).collect();
+        }
+
+        Ok(reader)
+    }
+
+    pub(crate) fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Read the next record, with `None` for `NULL` values.
+    ///
+    /// Returns `false` at the end of the file.
+    pub(crate) fn read_record(&mut self, record: &mut Vec<Option<String>>) -> anyhow::Result<bool> {
+        record.clear();
+        self.line.clear();
+
+        // Quoted values may contain line breaks, so the record continues
+        // until all quotes are closed. Escaped quotes (`""`) don't change
+        // whether the number of quotes is odd.
+        loop {
+            if self.reader.read_line(&mut self.line)? == 0 {
+                if self.line.is_empty() {
+                    return Ok(false);
+                }
+                break;
+            }
+
+            if self.line.matches('"').count() % 2 == 0 {
+                break;
+            }
+        }
+
+        let line = self.line.strip_suffix('\n').unwrap_or(&self.line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        let mut chars = line.chars().peekable();
+        loop {
+            if chars.next_if_eq(&'"').is_some() {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.next_if_eq(&'"').is_some() => value.push('"'),
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => bail!("Unterminated quoted value"),
+                    }
+                }
+                record.push(Some(value));
+            } else {
+                let mut value = String::new();
+                while let Some(c) = chars.next_if(|c| *c != ',') {
+                    value.push(c);
+                }
+                record.push((!value.is_empty()).then_some(value));
+            }
+
+            match chars.next() {
+                Some(',') => continue,
+                Some(c) => bail!("Unexpected character {c:?} after quoted value"),
+                None => break,
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Writes CSV files that can be read with `COPY … FROM … WITH CSV HEADER`.
+///
+/// `NULL` values are written as unquoted empty values and all other values
+/// are quoted, so that empty strings are not imported as `NULL`.
+pub(crate) struct CopyCsvWriter<W: Write> {
+    writer: BufWriter<W>,
+}
+
+impl CopyCsvWriter<File> {
+    pub(crate) fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+
+        Ok(Self::new(file))
+    }
+}
+
+impl<W: Write> CopyCsvWriter<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        let writer = BufWriter::new(writer);
+        Self { writer }
+    }
+
+    pub(crate) fn write_record<'a, I>(&mut self, record: I) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = Option<&'a str>>,
+    {
+        for (i, value) in record.into_iter().enumerate() {
+            if i > 0 {
+                self.writer.write_all(b",")?;
+            }
+            if let Some(value) = value {
+                let value = value.replace('"', "\"\"");
+                write!(self.writer, "\"{value}\"")?;
+            }
+        }
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub(crate) fn into_inner(self) -> anyhow::Result<W> {
+        let writer = self.writer.into_inner();
+        writer.map_err(|error| error.into_error().into())
+    }
+}
+
+pub(crate) fn parse_bool(value: &str) -> anyhow::Result<bool> {
+    match value {
+        "t" | "true" => Ok(true),
+        "f" | "false" => Ok(false),
+        _ => Err(anyhow!("Invalid boolean")),
+    }
+}
+
+/// Parse a `timestamp` or `timestamptz` value into microseconds since the
+/// Unix epoch. Values with a UTC offset are converted to UTC.
+pub(crate) fn parse_timestamp_micros(value: &str) -> anyhow::Result<i64> {
+    if let Ok(timestamp) = DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z") {
+        return Ok(timestamp.timestamp_micros());
+    }
+
+    let timestamp = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")?;
+    Ok(timestamp.and_utc().timestamp_micros())
+}
+
+/// Parse a one-dimensional PostgreSQL array literal like `{a,"b c",NULL}`.
+pub(crate) fn parse_array(value: &str) -> anyhow::Result<Vec<Option<String>>> {
+    let inner = value
+        .strip_prefix('{')
+        .and_then(|value| value.strip_suffix('}'))
+        .ok_or_else(|| anyhow!("Invalid array literal"))?;
+
+    let mut elements = Vec::new();
+    if inner.is_empty() {
+        return Ok(elements);
+    }
+
+    let mut chars = inner.chars().peekable();
+    loop {
+        if chars.next_if_eq(&'"').is_some() {
+            let mut element = String::new();
+            loop {
+                match chars.next() {
+                    Some('\\') => element.push(chars.next().context("Unterminated escape")?),
+                    Some('"') => break,
+                    Some(c) => element.push(c),
+                    None => bail!("Unterminated quoted array element"),
+                }
+            }
+            elements.push(Some(element));
+        } else {
+            let mut element = String::new();
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                element.push(c);
+            }
+            elements.push((element != "NULL").then_some(element));
+        }
+
+        match chars.next() {
+            Some(',') => continue,
+            Some(c) => bail!("Unexpected character {c:?} in array literal"),
+            None => break,
+        }
+    }
+
+    Ok(elements)
+}
+
+/// Write the column types of the tables that are used in the tests to the
+/// given export directory, in the format of the column types export.
+#[cfg(test)]
+pub(crate) fn write_test_column_types(export_dir: &Path) {
+    let path = export_dir.join(COLUMN_TYPES_PATH);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(
+        path,
+        "table_name,column_name,udt_name,is_nullable,primary_key_position\n\
+         crates,id,int4,NO,1\n\
+         crates,name,varchar,NO,\n\
+         crates,updated_at,timestamptz,NO,\n\
+         crates,created_at,timestamptz,NO,\n\
+         crates,description,varchar,YES,\n\
+         crates_categories,crate_id,int4,NO,1\n\
+         crates_categories,category_id,int4,NO,2\n\
+         metadata,total_downloads,int8,NO,1\n\
+         versions,id,int4,NO,1\n\
+         versions,crate_id,int4,NO,\n\
+         versions,num,varchar,NO,\n\
+         versions,created_at,timestamptz,NO,\n\
+         versions,yanked,bool,NO,\n\
+         versions,license,varchar,YES,\n\
+         versions,keywords,_text,NO,\n",
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Int32Type, TimestampMicrosecondType};
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn test_column_type_from_udt_name() {
+        let column_type = ColumnType::from_udt_name("int4", false);
+        assert_eq!(column_type.scalar, ScalarType::Int4);
+        assert!(!column_type.array);
+        assert!(!column_type.nullable);
+
+        let column_type = ColumnType::from_udt_name("_text", true);
+        assert_eq!(column_type.scalar, ScalarType::Text);
+        assert!(column_type.array);
+        assert!(column_type.nullable);
+
+        let column_type = ColumnType::from_udt_name("timestamptz", true);
+        assert_eq!(column_type.scalar, ScalarType::Timestamptz);
+        assert!(!column_type.array);
+        assert!(column_type.nullable);
+
+        let column_type = ColumnType::from_udt_name("ltree", false);
+        assert_eq!(column_type.scalar, ScalarType::Text);
+    }
+
+    #[test]
+    fn test_load_column_types() {
+        let tempdir = tempfile::tempdir().unwrap();
+        write_test_column_types(tempdir.path());
+
+        let path = tempdir.path().join(COLUMN_TYPES_PATH);
+        let column_types = ColumnTypes::load(&path).unwrap();
+
+        let column_type = column_types.column_type("versions", "license");
+        assert_eq!(column_type.scalar, ScalarType::Text);
+        assert!(column_type.nullable);
+
+        let column_type = column_types.column_type("versions", "keywords");
+        assert!(column_type.array);
+        assert!(!column_type.nullable);
+
+        let column_type = column_types.column_type("versions", "unknown");
+        assert_eq!(column_type, ColumnType::UNKNOWN);
+
+        let primary_key = |table| column_types.primary_keys.get(table).unwrap();
+        assert_eq!(primary_key("crates"), &["id"]);
+        assert_eq!(
+            primary_key("crates_categories"),
+            &["crate_id", "category_id"]
+        );
+    }
+
+    #[test]
+    fn test_copy_csv_reader() {
+        let csv = "id,name,description\n\
+                   1,foo,\n\
+                   2,\"\",\"multiple\n\"\"lines\"\"\"\n";
+
+        let mut reader = CopyCsvReader::new(csv.as_bytes()).unwrap();
+        assert_eq!(reader.headers(), ["id", "name", "description"]);
+
+        let mut record = Vec::new();
+        assert!(reader.read_record(&mut record).unwrap());
+        assert_eq!(record, [Some("1".into()), Some("foo".into()), None]);
+
+        assert!(reader.read_record(&mut record).unwrap());
+        let expected = [
+            Some("2".into()),
+            Some("".into()),
+            Some("multiple\n\"lines\"".into()),
+        ];
+        assert_eq!(record, expected);
+
+        assert!(!reader.read_record(&mut record).unwrap());
+
+        let mut reader = CopyCsvReader::new("id\n\"1".as_bytes()).unwrap();
+        assert!(reader.read_record(&mut record).is_err());
+    }
+
+    #[test]
+    fn test_copy_csv_writer() {
+        let mut writer = CopyCsvWriter::new(vec![]);
+        let headers = ["id", "name", "description"];
+        writer.write_record(headers.map(Some)).unwrap();
+        writer
+            .write_record([Some("1"), Some(""), None, Some("with \"quotes\"")])
+            .unwrap();
+        let csv = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "\"id\",\"name\",\"description\"\n\"1\",\"\",,\"with \"\"quotes\"\"\"\n"
+        );
+
+        let mut reader = CopyCsvReader::new(csv.as_bytes()).unwrap();
+        assert_eq!(reader.headers(), headers);
+
+        let mut record = Vec::new();
+        assert!(reader.read_record(&mut record).unwrap());
+        let expected = [
+            Some("1".into()),
+            Some("".into()),
+            None,
+            Some("with \"quotes\"".into()),
+        ];
+        assert_eq!(record, expected);
+    }
+
+    #[test]
+    fn test_parse_array() {
+        assert_eq!(parse_array("{}").unwrap(), vec![]);
+        assert_eq!(
+            parse_array(r#"{foo,"bar baz",NULL,"with \"quotes\""}"#).unwrap(),
+            vec![
+                Some("foo".to_string()),
+                Some("bar baz".to_string()),
+                None,
+                Some("with \"quotes\"".to_string()),
+            ]
+        );
+        assert!(parse_array("foo").is_err());
+        assert!(parse_array(r#"{"foo}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_timestamp_micros() {
+        let micros = parse_timestamp_micros("2017-01-06 14:23:11.123456+00").unwrap();
+        assert_eq!(micros, 1483712591123456);
+
+        let micros = parse_timestamp_micros("2017-01-06 15:23:11.123456+01").unwrap();
+        assert_eq!(micros, 1483712591123456);
+
+        let micros = parse_timestamp_micros("2017-01-06 14:23:11").unwrap();
+        assert_eq!(micros, 1483712591000000);
+    }
+
+    #[test]
+    fn test_csv_to_parquet() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let csv_path = tempdir.path().join("versions.csv");
+        let parquet_path = tempdir.path().join("versions.parquet");
+
+        std::fs::write(
+            &csv_path,
+            "id,yanked,created_at,license,keywords\n\
+             1,f,2017-01-06 14:23:11.123456+00,MIT,{}\n\
+             2,t,2017-01-07 14:23:11+00,,\"{cli,\"\"web server\"\"}\"\n\
+             3,f,2017-01-08 14:23:11+00,\"\",{}\n",
+        )
+        .unwrap();
+
+        write_test_column_types(tempdir.path());
+        let column_types_path = tempdir.path().join(COLUMN_TYPES_PATH);
+        ColumnTypes::load(&column_types_path)
+            .unwrap()
+            .csv_to_parquet("versions", &csv_path, &parquet_path)
+            .unwrap();
+
+        let file = File::open(&parquet_path).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+
+        let schema = reader.schema().clone();
+        let field = |name| schema.field_with_name(name).unwrap();
+        assert_eq!(field("id").data_type(), &DataType::Int32);
+        assert_eq!(field("yanked").data_type(), &DataType::Boolean);
+        assert_eq!(
+            field("created_at").data_type(),
+            &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        );
+        assert_eq!(field("license").data_type(), &DataType::Utf8);
+        assert!(field("license").is_nullable());
+        assert!(matches!(field("keywords").data_type(), DataType::List(_)));
+
+        let batches = reader
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 3);
+
+        let ids = batch.column(0).as_primitive::<Int32Type>();
+        assert_eq!(ids.values(), &[1, 2, 3]);
+
+        let yanked = batch.column(1).as_boolean();
+        assert!(!yanked.value(0));
+        assert!(yanked.value(1));
+
+        let created_at = batch.column(2).as_primitive::<TimestampMicrosecondType>();
+        assert_eq!(created_at.value(0), 1483712591123456);
+
+        let licenses = batch.column(3).as_string::<i32>();
+        assert_eq!(licenses.value(0), "MIT");
+        assert!(licenses.is_null(1));
+        assert!(!licenses.is_null(2));
+        assert_eq!(licenses.value(2), "");
+
+        let keywords = batch.column(4).as_list::<i32>();
+        assert_eq!(keywords.value(0).len(), 0);
+        let keywords = keywords.value(1);
+        let keywords = keywords.as_string::<i32>();
+        assert_eq!(keywords.value(0), "cli");
+        assert_eq!(keywords.value(1), "web server");
+    }
+}
@@ -0,0 +1,338 @@
+use crate::configuration::{TableConfig, VisibilityConfig};
+use crate::parquet_export::{
+    parse_array, parse_bool, parse_timestamp_micros, ColumnType, ColumnTypes, CopyCsvReader,
+    ScalarType, COLUMN_TYPES_PATH,
+};
+use crate::CancellationFlag;
+use anyhow::{anyhow, bail, Context};
+use chrono::DateTime;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::fs::File;
+use std::path::Path;
+use tracing::debug;
+
+/// Create a gzip-compressed SQLite database from the CSV files in the `data`
+/// folder of the export directory.
+///
+/// Since the CSV files only contain the public columns and rows, the
+/// visibility rules of `dump-db.toml` apply to the SQLite database too.
+pub fn create_sqlite(
+    export_dir: &Path,
+    cancellation: &CancellationFlag,
+) -> anyhow::Result<tempfile::NamedTempFile> {
+    let sqlite_tempfile = tempfile::NamedTempFile::new()?;
+    write_sqlite(export_dir, sqlite_tempfile.path(), cancellation)?;
+
+    debug!("Compressing SQLite database…");
+    let gz_tempfile = tempfile::NamedTempFile::new()?;
+    let mut encoder =
+        flate2::write::GzEncoder::new(gz_tempfile.as_file(), flate2::Compression::default());
+    std::io::copy(&mut File::open(sqlite_tempfile.path())?, &mut encoder)?;
+    encoder.finish()?;
+
+    Ok(gz_tempfile)
+}
+
+fn write_sqlite(
+    export_dir: &Path,
+    sqlite_path: &Path,
+    cancellation: &CancellationFlag,
+) -> anyhow::Result<()> {
+    debug!(?sqlite_path, "Creating SQLite database…");
+    let mut conn = Connection::open(sqlite_path)?;
+
+    // The database is written once and only read afterwards, so there is no
+    // need for a rollback journal.
+    conn.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")?;
+
+    let column_types = ColumnTypes::load(&export_dir.join(COLUMN_TYPES_PATH))?;
+    let visibility_config = VisibilityConfig::get();
+    for table in visibility_config.topological_sort() {
+        cancellation.check()?;
+
+        let csv_path = export_dir.join("data").join(table).with_extension("csv");
+        if csv_path.exists() {
+            let config = &visibility_config.0[table];
+            import_table(&mut conn, &column_types, table, config, &csv_path)
+                .with_context(|| format!("Failed to import {table}.csv into SQLite"))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a table for the given CSV file, fill it with the CSV rows and
+/// create the indexes declared in `dump-db.toml`.
+fn import_table(
+    conn: &mut Connection,
+    column_types: &ColumnTypes,
+    table: &str,
+    config: &TableConfig,
+    csv_path: &Path,
+) -> anyhow::Result<()> {
+    debug!(?csv_path, "Importing CSV file into SQLite…");
+    let mut reader = CopyCsvReader::from_path(csv_path)?;
+
+    let headers = reader.headers().to_vec();
+    let types = headers
+        .iter()
+        .map(|column| column_types.column_type(table, column))
+        .collect::<Vec<_>>();
+
+    let column_definitions = headers
+        .iter()
+        .zip(&types)
+        .map(|(column, column_type)| {
+            let sqlite_type = sqlite_type(column_type);
+            let constraint = if column_type.nullable {
+                ""
+            } else {
+                " NOT NULL"
+            };
+            format!("\"{column}\" {sqlite_type}{constraint}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let column_names = headers
+        .iter()
+        .map(|column| format!("\"{column}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let placeholders = vec!["?"; headers.len()].join(", ");
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        &format!("CREATE TABLE \"{table}\" ({column_definitions})"),
+        [],
+    )?;
+
+    {
+        let insert = format!("INSERT INTO \"{table}\" ({column_names}) VALUES ({placeholders})");
+        let mut statement = tx.prepare(&insert)?;
+
+        let mut record = Vec::new();
+        let mut values = Vec::with_capacity(headers.len());
+        while reader.read_record(&mut record)? {
+            values.clear();
+            for ((value, column_type), column) in record.iter().zip(&types).zip(&headers) {
+                let value = sqlite_value(value.as_deref(), column_type).with_context(|| {
+                    format!("Failed to convert {table}.{column} value {value:?}")
+                })?;
+                values.push(value);
+            }
+
+            statement.execute(params_from_iter(&values))?;
+        }
+    }
+
+    for columns in &config.indexes {
+        let index_name = format!("{table}_{}_idx", columns.join("_"));
+        let column_names = columns
+            .iter()
+            .map(|column| format!("\"{column}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        debug!(%index_name, "Creating SQLite index…");
+        let sql = format!("CREATE INDEX \"{index_name}\" ON \"{table}\" ({column_names})");
+        tx.execute(&sql, [])?;
+    }
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn sqlite_type(column_type: &ColumnType) -> &'static str {
+    match column_type.scalar {
+        _ if column_type.array => "TEXT",
+        ScalarType::Bool | ScalarType::Int2 | ScalarType::Int4 | ScalarType::Int8 => "INTEGER",
+        ScalarType::Date | ScalarType::Timestamp | ScalarType::Timestamptz | ScalarType::Text => {
+            "TEXT"
+        }
+    }
+}
+
+/// Convert a single value in the `psql` CSV output format, or `None` for
+/// `NULL`, to a SQLite value.
+///
+/// Booleans are stored as `0` and `1`, timestamps as UTC in the
+/// `YYYY-MM-DD HH:MM:SS.SSSSSS` format that the SQLite date and time functions
+/// understand, and arrays as JSON arrays.
+fn sqlite_value(value: Option<&str>, column_type: &ColumnType) -> anyhow::Result<Value> {
+    let Some(value) = value else {
+        if !column_type.nullable {
+            bail!("Unexpected NULL value");
+        }
+        return Ok(Value::Null);
+    };
+
+    if column_type.array {
+        let elements = parse_array(value)?;
+        return Ok(Value::Text(serde_json::to_string(&elements)?));
+    }
+
+    let value = match column_type.scalar {
+        ScalarType::Bool => Value::Integer(i64::from(parse_bool(value)?)),
+        ScalarType::Int2 | ScalarType::Int4 | ScalarType::Int8 => Value::Integer(value.parse()?),
+        ScalarType::Timestamp | ScalarType::Timestamptz => {
+            let micros = parse_timestamp_micros(value)?;
+            let timestamp = DateTime::from_timestamp_micros(micros)
+                .ok_or_else(|| anyhow!("Timestamp out of range"))?;
+            Value::Text(timestamp.format("%F %T%.6f").to_string())
+        }
+        ScalarType::Date | ScalarType::Text => Value::Text(value.to_string()),
+    };
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::ColumnVisibility;
+    use crate::parquet_export::write_test_column_types;
+    use std::fs;
+
+    #[test]
+    fn test_write_sqlite() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let export_dir = tempdir.path();
+
+        fs::create_dir(export_dir.join("data")).unwrap();
+        fs::write(
+            export_dir.join("data").join("crates.csv"),
+            "id,name,description,created_at\n\
+             1,foo,,2017-01-06 14:23:11.123456+00\n\
+             2,bar,\"A \"\"bar\"\" crate\",2017-01-07 15:23:11+01\n\
+             3,baz,\"\",2017-01-08 14:23:11+00\n",
+        )
+        .unwrap();
+        fs::write(
+            export_dir.join("data").join("versions.csv"),
+            "id,crate_id,num,yanked,keywords\n\
+             1,1,1.0.0,f,{}\n\
+             2,1,1.0.1,t,\"{cli,\"\"web server\"\"}\"\n",
+        )
+        .unwrap();
+
+        write_test_column_types(export_dir);
+
+        let sqlite_path = export_dir.join("db-dump.sqlite");
+        write_sqlite(export_dir, &sqlite_path, &CancellationFlag::default()).unwrap();
+
+        let conn = Connection::open(&sqlite_path).unwrap();
+
+        let crates = conn
+            .prepare("SELECT id, name, description, created_at FROM crates ORDER BY id")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        insta::assert_debug_snapshot!(crates, @r#"
+        [
+            (
+                1,
+                "foo",
+                None,
+                "2017-01-06 14:23:11.123456",
+            ),
+            (
+                2,
+                "bar",
+                Some(
+                    "A \"bar\" crate",
+                ),
+                "2017-01-07 14:23:11.000000",
+            ),
+            (
+                3,
+                "baz",
+                Some(
+                    "",
+                ),
+                "2017-01-08 14:23:11.000000",
+            ),
+        ]
+        "#);
+
+        let versions = conn
+            .prepare("SELECT num, yanked, keywords FROM versions WHERE crate_id = 1 ORDER BY id")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, bool>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        insta::assert_debug_snapshot!(versions, @r#"
+        [
+            (
+                "1.0.0",
+                false,
+                "[]",
+            ),
+            (
+                "1.0.1",
+                true,
+                "[\"cli\",\"web server\"]",
+            ),
+        ]
+        "#);
+
+        let indexes = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'index' ORDER BY name")
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        insta::assert_debug_snapshot!(indexes, @r#"
+        [
+            "crates_id_idx",
+            "crates_name_idx",
+            "versions_crate_id_num_idx",
+            "versions_id_idx",
+        ]
+        "#);
+    }
+
+    /// Test whether all indexed columns of the visibility configuration are
+    /// public, since private columns are not part of the export.
+    #[test]
+    fn check_index_columns() {
+        let mut errors = vec![];
+        for (table, config) in VisibilityConfig::get().0 {
+            for column in config.indexes.iter().flatten() {
+                let visibility = config.columns.get(column);
+                if visibility != Some(&ColumnVisibility::Public) {
+                    errors.push(format!("Indexed column {table}.{column} is not public."));
+                }
+            }
+        }
+
+        assert!(
+            errors.is_empty(),
+            "The SQLite indexes do not match the visibility configuration:\n{}",
+            errors.join("\n"),
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Functions to inspect and control the background job queues, e.g. from
+//! an admin CLI or API.
+
+use crate::listener::notification_channel;
+use crate::schema::{
+    background_job_dependencies, background_jobs, failed_background_jobs,
+    paused_background_job_queues,
+};
+use crate::storage;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::dsl::{exists, now};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::{delete, insert_into, select, update};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+
+/// A job in the `background_jobs` table.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = background_jobs, check_for_backend(Pg))]
+pub struct JobInfo {
+    pub id: i64,
+    pub job_type: String,
+    pub queue: String,
+    pub data: serde_json::Value,
+    pub retries: i32,
+    pub last_retry: NaiveDateTime,
+    pub created_at: DateTime<Utc>,
+    pub priority: i16,
+    /// The earliest time at which the job may be run.
+    pub run_at: DateTime<Utc>,
+    /// The error of the most recent failed run, if any.
+    pub last_error: Option<String>,
+}
+
+impl JobInfo {
+    /// Returns the position of the job in the order of [list_jobs].
+    pub fn cursor(&self) -> JobCursor {
+        JobCursor {
+            priority: self.priority,
+            id: self.id,
+        }
+    }
+}
+
+/// The position of a job in the order in which [list_jobs] returns the jobs,
+/// which can be used to continue listing the jobs after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobCursor {
+    pub priority: i16,
+    pub id: i64,
+}
+
+/// The status of a job, e.g. of the last job of a workflow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    /// The job is waiting for its parent jobs to succeed.
+    Waiting { parents: Vec<i64> },
+    /// The job is enqueued, and is either waiting for a worker or running.
+    Enqueued,
+    /// The job has failed, either by itself or because one of its parent
+    /// jobs has failed.
+    Failed { error: String },
+    /// The job is no longer enqueued, i.e. it has succeeded or was cancelled.
+    Finished,
+}
+
+/// A job could not be changed
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum JobControlError {
+    /// The job does not exist, e.g. because it has already finished
+    #[error("Job not found")]
+    NotFound,
+
+    /// The job is locked by a worker that is currently running it
+    #[error("Job is currently running")]
+    Running,
+
+    /// The queue is not one of the queues that are configured
+    #[error("Unknown queue")]
+    UnknownQueue,
+
+    /// An error occurred accessing the database
+    #[error(transparent)]
+    DatabaseError(#[from] diesel::result::Error),
+}
+
+/// Lists up to `limit` enqueued jobs, optionally only of the given job type
+/// and queue, in the order in which the workers will pick them up.
+///
+/// If `after` is given, only the jobs after that position are listed, e.g.
+/// the [cursor](JobInfo::cursor) of the last job of the previous page.
+pub async fn list_jobs(
+    conn: &mut AsyncPgConnection,
+    job_type: Option<&str>,
+    queue: Option<&str>,
+    after: Option<JobCursor>,
+    limit: i64,
+) -> QueryResult<Vec<JobInfo>> {
+    let mut query = background_jobs::table
+        .select(JobInfo::as_select())
+        .order((background_jobs::priority.desc(), background_jobs::id))
+        .limit(limit)
+        .into_boxed();
+
+    if let Some(JobCursor { priority, id }) = after {
+        query = query.filter(
+            background_jobs::priority
+                .lt(priority)
+                .or(background_jobs::priority
+                    .eq(priority)
+                    .and(background_jobs::id.gt(id))),
+        );
+    }
+
+    if let Some(job_type) = job_type {
+        query = query.filter(background_jobs::job_type.eq(job_type));
+    }
+
+    if let Some(queue) = queue {
+        query = query.filter(background_jobs::queue.eq(queue));
+    }
+
+    query.load(conn).await
+}
+
+/// Finds an enqueued job by its ID.
+pub async fn find_job(conn: &mut AsyncPgConnection, id: i64) -> QueryResult<Option<JobInfo>> {
+    background_jobs::table
+        .find(id)
+        .select(JobInfo::as_select())
+        .first(conn)
+        .await
+        .optional()
+}
+
+/// Returns the status of a job.
+pub async fn job_status(conn: &mut AsyncPgConnection, id: i64) -> QueryResult<JobStatus> {
+    // The job is looked up in the `background_jobs` table first, since it is
+    // moved to the `failed_background_jobs` table when it fails.
+    let query = background_jobs::table.find(id);
+    if select(exists(query)).get_result::<bool>(conn).await? {
+        let parents = background_job_dependencies::table
+            .select(background_job_dependencies::parent_id)
+            .filter(background_job_dependencies::job_id.eq(id))
+            .order(background_job_dependencies::parent_id)
+            .load::<i64>(conn)
+            .await?;
+
+        return Ok(if parents.is_empty() {
+            JobStatus::Enqueued
+        } else {
+            JobStatus::Waiting { parents }
+        });
+    }
+
+    let error = failed_background_jobs::table
+        .find(id)
+        .select(failed_background_jobs::error)
+        .first::<String>(conn)
+        .await
+        .optional()?;
+
+    Ok(match error {
+        Some(error) => JobStatus::Failed { error },
+        None => JobStatus::Finished,
+    })
+}
+
+/// Makes a job that is delayed or waiting for its next retry available to
+/// the workers immediately.
+pub async fn retry_now(conn: &mut AsyncPgConnection, id: i64) -> Result<(), JobControlError> {
+    conn.transaction(|conn| {
+        async move {
+            lock_job(conn, id).await?;
+
+            let queue = update(background_jobs::table.find(id))
+                .set((
+                    background_jobs::last_retry.eq(DateTime::UNIX_EPOCH.naive_utc()),
+                    background_jobs::run_at.eq(now),
+                ))
+                .returning(background_jobs::queue)
+                .get_result::<String>(conn)
+                .await?;
+
+            diesel::sql_query("SELECT pg_notify($1, '')")
+                .bind::<Text, _>(notification_channel(&queue))
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Deletes a job that is not currently running, together with all jobs that
+/// depend on it.
+pub async fn cancel_job(conn: &mut AsyncPgConnection, id: i64) -> Result<(), JobControlError> {
+    conn.transaction(|conn| {
+        async move {
+            lock_job(conn, id).await?;
+
+            let mut ids = storage::dependent_jobs(conn, id).await?;
+            ids.push(id);
+
+            delete(background_jobs::table.filter(background_jobs::id.eq_any(ids)))
+                .execute(conn)
+                .await?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Locks the row of a job for the rest of the transaction, unless it is
+/// already locked by a worker that is currently running the job.
+async fn lock_job(conn: &mut AsyncPgConnection, id: i64) -> Result<(), JobControlError> {
+    let locked = background_jobs::table
+        .find(id)
+        .select(background_jobs::id)
+        .for_update()
+        .skip_locked()
+        .first::<i64>(conn)
+        .await
+        .optional()?;
+
+    if locked.is_some() {
+        return Ok(());
+    }
+
+    let query = background_jobs::table.find(id);
+    if select(exists(query)).get_result(conn).await? {
+        Err(JobControlError::Running)
+    } else {
+        Err(JobControlError::NotFound)
+    }
+}
+
+/// Pauses a queue, so that its workers don't pick up any new jobs until the
+/// queue is resumed. Jobs that are already running are not affected.
+///
+/// Since a typo would otherwise silently pause nothing, queues that are not
+/// one of the given configured queues are rejected.
+pub async fn pause_queue(
+    conn: &mut AsyncPgConnection,
+    queue: &str,
+    configured_queues: &[&str],
+) -> Result<(), JobControlError> {
+    if !configured_queues.contains(&queue) {
+        return Err(JobControlError::UnknownQueue);
+    }
+
+    insert_into(paused_background_job_queues::table)
+        .values(paused_background_job_queues::queue.eq(queue))
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Resumes a paused queue. Returns `false` if the queue was not paused.
+pub async fn resume_queue(conn: &mut AsyncPgConnection, queue: &str) -> QueryResult<bool> {
+    let deleted = delete(paused_background_job_queues::table.find(queue))
+        .execute(conn)
+        .await?;
+    Ok(deleted > 0)
+}
+
+/// Lists the paused queues, and when they were paused.
+pub async fn paused_queues(
+    conn: &mut AsyncPgConnection,
+) -> QueryResult<Vec<(String, DateTime<Utc>)>> {
+    paused_background_job_queues::table
+        .select((
+            paused_background_job_queues::queue,
+            paused_background_job_queues::paused_at,
+        ))
+        .order(paused_background_job_queues::queue)
+        .load(conn)
+        .await
+}
@@ -1,15 +1,19 @@
 use crate::errors::EnqueueError;
-use crate::schema::background_jobs;
-use diesel::dsl::{exists, not};
-use diesel::sql_types::{Int2, Jsonb, Text};
-use diesel::{ExpressionMethods, IntoSql, OptionalExtension, QueryDsl};
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use crate::schema::{background_job_dependencies, background_jobs, failed_background_jobs};
+use crate::util::current_trace_context;
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::dsl::{exists, not, now};
+use diesel::sql_types::{Int2, Jsonb, Nullable, Text, Timestamptz};
+use diesel::{define_sql_function, ExpressionMethods, IntoSql, OptionalExtension, QueryDsl};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 use futures_util::future::BoxFuture;
 use futures_util::FutureExt;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json::Value;
 use std::future::Future;
+use std::time::Duration;
 use tracing::instrument;
 
 pub const DEFAULT_QUEUE: &str = "default";
@@ -28,12 +32,36 @@ pub trait BackgroundJob: Serialize + DeserializeOwned + Send + Sync + 'static {
     /// Whether the job should be deduplicated.
     ///
     /// If true, the job will not be enqueued if there is already an unstarted
-    /// job with the same data.
+    /// job with the same data, which is run at the same time or earlier and
+    /// is not waiting for any parent jobs.
     const DEDUPLICATED: bool = false;
 
     /// Job queue where this job will be executed.
     const QUEUE: &'static str = DEFAULT_QUEUE;
 
+    /// The maximum number of times a failed job is retried.
+    ///
+    /// Once a job has exhausted its retries, it is moved to the
+    /// `failed_background_jobs` table, together with its last error and all
+    /// jobs that depend on it. If this is `None`, the job is retried forever
+    /// and its dependent jobs keep waiting for it.
+    const MAX_RETRIES: Option<u32> = None;
+
+    /// The maximum time that a single run of the job may take.
+    ///
+    /// Once the timeout has elapsed, the job is asked to stop via its
+    /// [cancellation token](crate::cancellation_token). If it does not stop
+    /// within a short grace period, it is dropped. Either way, the run is
+    /// treated as a failure. If this is `None`, the job may run forever.
+    const TIMEOUT: Option<Duration> = None;
+
+    /// The maximum number of jobs of this type that may run at the same time,
+    /// across all workers and runners.
+    ///
+    /// If this is `None`, the concurrency is only limited by the number of
+    /// workers of the queue.
+    const MAX_CONCURRENCY: Option<u32> = None;
+
     /// The application data provided to this job at runtime.
     type Context: Clone + Send + 'static;
 
@@ -45,33 +73,159 @@ pub trait BackgroundJob: Serialize + DeserializeOwned + Send + Sync + 'static {
         &self,
         conn: &mut AsyncPgConnection,
     ) -> BoxFuture<'_, Result<Option<i64>, EnqueueError>> {
-        let data = match serde_json::to_value(self) {
-            Ok(data) => data,
-            Err(err) => return async move { Err(EnqueueError::SerializationError(err)) }.boxed(),
-        };
-        let priority = Self::PRIORITY;
-
-        if Self::DEDUPLICATED {
-            let future = enqueue_deduplicated(conn, Self::JOB_NAME, data, priority);
-            future.boxed()
-        } else {
-            let future = enqueue_simple(conn, Self::JOB_NAME, data, priority);
-            async move { Ok(Some(future.await?)) }.boxed()
-        }
+        enqueue_job(self, conn, None)
+    }
+
+    /// Enqueue the job, so that it is not run before the given time.
+    #[instrument(name = "swirl.enqueue", skip(self, conn), fields(message = Self::JOB_NAME))]
+    fn enqueue_at(
+        &self,
+        conn: &mut AsyncPgConnection,
+        run_at: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<Option<i64>, EnqueueError>> {
+        enqueue_job(self, conn, Some(run_at))
+    }
+
+    /// Enqueue the job, so that it is not run before the given delay has
+    /// passed.
+    fn enqueue_after(
+        &self,
+        conn: &mut AsyncPgConnection,
+        delay: TimeDelta,
+    ) -> BoxFuture<'_, Result<Option<i64>, EnqueueError>> {
+        self.enqueue_at(conn, Utc::now() + delay)
+    }
+
+    /// Enqueue the job, so that it is only run once all of the given parent
+    /// jobs have succeeded.
+    ///
+    /// Parent jobs that are no longer enqueued are assumed to have succeeded.
+    /// If one of the parent jobs has already failed, an error is returned. If
+    /// a parent job is moved to the `failed_background_jobs` table later on,
+    /// the job is moved there as well without being run.
+    ///
+    /// Jobs with parents are never deduplicated.
+    #[instrument(name = "swirl.enqueue", skip(self, conn), fields(message = Self::JOB_NAME))]
+    fn enqueue_with_parents<'a>(
+        &'a self,
+        conn: &'a mut AsyncPgConnection,
+        parents: &'a [i64],
+    ) -> BoxFuture<'a, Result<i64, EnqueueError>> {
+        enqueue_with_parents(self, conn, parents)
+    }
+}
+
+define_sql_function!(fn coalesce(x: Nullable<Timestamptz>, y: Timestamptz) -> Timestamptz);
+
+/// Enqueue the job to be run at `run_at`, or as soon as possible if
+/// `run_at` is `None`.
+fn enqueue_job<'a, J: BackgroundJob>(
+    job: &J,
+    conn: &mut AsyncPgConnection,
+    run_at: Option<DateTime<Utc>>,
+) -> BoxFuture<'a, Result<Option<i64>, EnqueueError>> {
+    let data = match serde_json::to_value(job) {
+        Ok(data) => data,
+        Err(err) => return async move { Err(EnqueueError::SerializationError(err)) }.boxed(),
+    };
+    let priority = J::PRIORITY;
+    let trace = current_trace_context();
+
+    if J::DEDUPLICATED {
+        let future =
+            enqueue_deduplicated(conn, J::JOB_NAME, data, priority, J::QUEUE, run_at, trace);
+        future.boxed()
+    } else {
+        let future = enqueue_simple(conn, J::JOB_NAME, data, priority, J::QUEUE, run_at, trace);
+        async move { Ok(Some(future.await?)) }.boxed()
     }
 }
 
+/// Enqueue the job together with its dependencies on the given parent jobs.
+fn enqueue_with_parents<'a, J: BackgroundJob>(
+    job: &J,
+    conn: &'a mut AsyncPgConnection,
+    parents: &'a [i64],
+) -> BoxFuture<'a, Result<i64, EnqueueError>> {
+    let data = match serde_json::to_value(job) {
+        Ok(data) => data,
+        Err(err) => return async move { Err(EnqueueError::SerializationError(err)) }.boxed(),
+    };
+    let trace = current_trace_context();
+
+    conn.transaction(move |conn| {
+        async move {
+            // Lock the enqueued parent jobs, so that they can't succeed or
+            // fail before their dependencies are committed.
+            let enqueued_parents = background_jobs::table
+                .select(background_jobs::id)
+                .filter(background_jobs::id.eq_any(parents))
+                .for_key_share()
+                .load::<i64>(conn)
+                .await?;
+
+            let failed_parent = failed_background_jobs::table
+                .select(failed_background_jobs::id)
+                .filter(failed_background_jobs::id.eq_any(parents))
+                .first::<i64>(conn)
+                .await
+                .optional()?;
+
+            if let Some(failed_parent) = failed_parent {
+                return Err(EnqueueError::ParentFailed(failed_parent));
+            }
+
+            let future =
+                enqueue_simple(conn, J::JOB_NAME, data, J::PRIORITY, J::QUEUE, None, trace);
+            let job_id = future.await?;
+
+            let dependencies = enqueued_parents
+                .into_iter()
+                .map(|parent_id| {
+                    (
+                        background_job_dependencies::job_id.eq(job_id),
+                        background_job_dependencies::parent_id.eq(parent_id),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            diesel::insert_into(background_job_dependencies::table)
+                .values(dependencies)
+                .execute(conn)
+                .await?;
+
+            Ok(job_id)
+        }
+        .scope_boxed()
+    })
+}
+
+/// Enqueue the job, unless there is already an unstarted job with the same
+/// data that is run at the same time or earlier and is not waiting for any
+/// parent jobs.
 fn enqueue_deduplicated(
     conn: &mut AsyncPgConnection,
     job_type: &'static str,
     data: Value,
     priority: i16,
+    queue: &'static str,
+    run_at: Option<DateTime<Utc>>,
+    trace_context: Option<String>,
 ) -> impl Future<Output = Result<Option<i64>, EnqueueError>> {
+    // Jobs that are waiting for their parent jobs may never be run, so they
+    // can't replace the new job.
+    let has_parents = exists(
+        background_job_dependencies::table
+            .filter(background_job_dependencies::job_id.eq(background_jobs::id)),
+    );
+
     let similar_jobs = background_jobs::table
         .select(background_jobs::id)
         .filter(background_jobs::job_type.eq(job_type))
         .filter(background_jobs::data.eq(data.clone()))
         .filter(background_jobs::priority.eq(priority))
+        .filter(background_jobs::run_at.le(coalesce(run_at, now)))
+        .filter(not(has_parents))
         .for_update()
         .skip_locked();
 
@@ -79,6 +233,9 @@ fn enqueue_deduplicated(
         job_type.into_sql::<Text>(),
         data.into_sql::<Jsonb>(),
         priority.into_sql::<Int2>(),
+        queue.into_sql::<Text>(),
+        coalesce(run_at, now),
+        trace_context.into_sql::<Nullable<Text>>(),
     ))
     .filter(not(exists(similar_jobs)));
 
@@ -88,6 +245,9 @@ fn enqueue_deduplicated(
             background_jobs::job_type,
             background_jobs::data,
             background_jobs::priority,
+            background_jobs::queue,
+            background_jobs::run_at,
+            background_jobs::trace_context,
         ))
         .returning(background_jobs::id)
         .get_result::<i64>(conn);
@@ -100,12 +260,18 @@ fn enqueue_simple(
     job_type: &'static str,
     data: Value,
     priority: i16,
+    queue: &'static str,
+    run_at: Option<DateTime<Utc>>,
+    trace_context: Option<String>,
 ) -> impl Future<Output = Result<i64, EnqueueError>> {
     let future = diesel::insert_into(background_jobs::table)
         .values((
             background_jobs::job_type.eq(job_type),
             background_jobs::data.eq(data),
             background_jobs::priority.eq(priority),
+            background_jobs::queue.eq(queue),
+            background_jobs::run_at.eq(coalesce(run_at, now)),
+            background_jobs::trace_context.eq(trace_context),
         ))
         .returning(background_jobs::id)
         .get_result(conn);
@@ -0,0 +1,72 @@
+use anyhow::anyhow;
+use std::future::Future;
+use std::pin::pin;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// The time that a job has to stop after it was asked to, before it is
+/// dropped.
+const CANCELLATION_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+tokio::task_local! {
+    static CANCELLATION_TOKEN: CancellationToken;
+}
+
+/// Returns the cancellation token of the currently running job.
+///
+/// The token is cancelled once the job has exceeded its
+/// [`TIMEOUT`](crate::BackgroundJob::TIMEOUT). Long-running jobs should check
+/// it regularly, or race it against their work, and stop as soon as possible
+/// once it is cancelled.
+///
+/// The token is stored in a task-local variable, so it is only available
+/// within the task of the job itself. In `spawn_blocking` closures and tasks
+/// spawned by the job, as well as outside of a running job, this returns a
+/// token that is never cancelled. Jobs therefore have to call this before
+/// spawning and pass the token on.
+///
+/// Once the grace period after the cancellation has elapsed, the job's future
+/// is dropped, but blocking and spawned tasks keep running until they check
+/// the token. Child processes should be spawned with `kill_on_drop(true)`, so
+/// that they are stopped along with the job.
+pub fn cancellation_token() -> CancellationToken {
+    CANCELLATION_TOKEN
+        .try_with(CancellationToken::clone)
+        .unwrap_or_default()
+}
+
+/// Runs a job with its own cancellation token, and cancels it once the
+/// timeout has elapsed.
+///
+/// A job that exceeds its timeout is treated as a failure, even if it stops
+/// successfully within the grace period.
+pub(crate) async fn run_with_timeout<F>(
+    future: F,
+    timeout_after: Option<Duration>,
+) -> anyhow::Result<()>
+where
+    F: Future<Output = anyhow::Result<()>>,
+{
+    let token = CancellationToken::new();
+    let future = CANCELLATION_TOKEN.scope(token.clone(), future);
+
+    let Some(timeout_after) = timeout_after else {
+        return future.await;
+    };
+
+    let mut future = pin!(future);
+    if let Ok(result) = timeout(timeout_after, &mut future).await {
+        return result;
+    }
+
+    warn!("Job timed out after {timeout_after:?}. Cancelling…");
+    token.cancel();
+
+    if timeout(CANCELLATION_GRACE_PERIOD, future).await.is_err() {
+        warn!("Job did not stop within {CANCELLATION_GRACE_PERIOD:?}. Dropping it…");
+    }
+
+    Err(anyhow!("Job timed out after {timeout_after:?}"))
+}
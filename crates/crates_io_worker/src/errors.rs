@@ -6,6 +6,10 @@ pub enum EnqueueError {
     #[error(transparent)]
     SerializationError(#[from] serde_json::error::Error),
 
+    /// One of the parent jobs of the job has already failed
+    #[error("Parent job {0} has failed")]
+    ParentFailed(i64),
+
     /// An error occurred inserting the job into the database
     #[error(transparent)]
     DatabaseError(#[from] diesel::result::Error),
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 type RunTaskFnReturn = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
 type RunTaskFn<Context> = dyn Fn(Context, serde_json::Value) -> RunTaskFnReturn + Send + Sync;
@@ -10,12 +11,18 @@ type RunTaskFn<Context> = dyn Fn(Context, serde_json::Value) -> RunTaskFnReturn
 #[derive(Clone)]
 pub struct JobRegistry<Context> {
     entries: HashMap<String, Arc<RunTaskFn<Context>>>,
+    max_retries: HashMap<String, u32>,
+    timeouts: HashMap<String, Duration>,
+    max_concurrency: HashMap<String, u32>,
 }
 
 impl<Context> Default for JobRegistry<Context> {
     fn default() -> Self {
         Self {
             entries: HashMap::new(),
+            max_retries: HashMap::new(),
+            timeouts: HashMap::new(),
+            max_concurrency: HashMap::new(),
         }
     }
 }
@@ -24,12 +31,44 @@ impl<Context: Clone + Send + Sync + 'static> JobRegistry<Context> {
     pub fn register<J: BackgroundJob<Context = Context>>(&mut self) {
         self.entries
             .insert(J::JOB_NAME.to_string(), Arc::new(runnable::<J>));
+
+        if let Some(max_retries) = J::MAX_RETRIES {
+            self.max_retries
+                .insert(J::JOB_NAME.to_string(), max_retries);
+        }
+
+        if let Some(timeout) = J::TIMEOUT {
+            self.timeouts.insert(J::JOB_NAME.to_string(), timeout);
+        }
+
+        if let Some(max_concurrency) = J::MAX_CONCURRENCY {
+            self.max_concurrency
+                .insert(J::JOB_NAME.to_string(), max_concurrency);
+        }
     }
 
     pub fn get(&self, key: &str) -> Option<&Arc<RunTaskFn<Context>>> {
         self.entries.get(key)
     }
 
+    /// Returns the maximum number of retries of a job type, or `None` if the
+    /// job type is retried forever.
+    pub fn max_retries(&self, key: &str) -> Option<u32> {
+        self.max_retries.get(key).copied()
+    }
+
+    /// Returns the timeout of a job type, or `None` if the job type may run
+    /// forever.
+    pub fn timeout(&self, key: &str) -> Option<Duration> {
+        self.timeouts.get(key).copied()
+    }
+
+    /// Returns the maximum number of concurrently running jobs of a job type,
+    /// or `None` if the job type has no concurrency limit.
+    pub fn max_concurrency(&self, key: &str) -> Option<u32> {
+        self.max_concurrency.get(key).copied()
+    }
+
     /// Returns a list of all registered job types.
     pub fn job_types(&self) -> Vec<String> {
         self.entries.keys().cloned().collect()
@@ -1,14 +1,23 @@
 #![doc = include_str!("../README.md")]
 
+pub mod admin;
 mod background_job;
+mod cancellation;
 mod errors;
 mod job_registry;
+mod listener;
+mod metrics;
 mod runner;
+mod schedule;
 pub mod schema;
 mod storage;
 mod util;
 mod worker;
 
-pub use self::background_job::BackgroundJob;
+pub use self::background_job::{BackgroundJob, DEFAULT_QUEUE};
+pub use self::cancellation::cancellation_token;
 pub use self::errors::EnqueueError;
+pub use self::listener::Listener;
+pub use self::metrics::JobMetrics;
 pub use self::runner::Runner;
+pub use self::schedule::{MissedTicks, Schedule};
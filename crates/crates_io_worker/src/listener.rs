@@ -0,0 +1,118 @@
+use futures_util::future::BoxFuture;
+use futures_util::stream::poll_fn;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, Notify};
+use tokio::time::sleep;
+use tokio_postgres::{AsyncMessage, Client, Connection, Notification};
+use tracing::{debug, info, warn};
+
+/// Returns the name of the `LISTEN`/`NOTIFY` channel that is used to wake up
+/// the workers of a queue when a new job is enqueued.
+///
+/// The notifications are sent by the `notify_background_job_queue()` trigger
+/// of the `background_jobs` table, which uses the same channel names.
+pub(crate) fn notification_channel(queue: &str) -> String {
+    format!("background_jobs.{queue}")
+}
+
+/// A dedicated database connection that receives the notifications that are
+/// sent when new jobs are enqueued.
+///
+/// The connection is created by the application, since it needs to be set up
+/// the same way as the connections of the connection pool, e.g. with TLS.
+pub struct Listener {
+    client: Client,
+    notifications: mpsc::UnboundedReceiver<Notification>,
+}
+
+impl Listener {
+    /// Wrap a `tokio-postgres` connection, and drive it in a background task.
+    pub fn new<S, T>(client: Client, mut connection: Connection<S, T>) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (sender, notifications) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut messages = poll_fn(move |cx| connection.poll_message(cx));
+            while let Some(message) = messages.next().await {
+                match message {
+                    Ok(AsyncMessage::Notification(notification)) => {
+                        if sender.send(notification).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(error) => {
+                        warn!("Notification listener connection failed: {error}");
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            client,
+            notifications,
+        }
+    }
+
+    async fn listen(&self, channels: impl Iterator<Item = String>) -> anyhow::Result<()> {
+        for channel in channels {
+            let channel = channel.replace('"', "\"\"");
+            self.client
+                .batch_execute(&format!("LISTEN \"{channel}\""))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) type ConnectFn = dyn Fn() -> BoxFuture<'static, anyhow::Result<Listener>> + Send + Sync;
+
+pub(crate) struct Dispatcher {
+    pub(crate) connect: Arc<ConnectFn>,
+    /// The notifiers of the workers, by notification channel.
+    pub(crate) notifiers: HashMap<String, Arc<Notify>>,
+    pub(crate) reconnect_interval: Duration,
+}
+
+impl Dispatcher {
+    /// Wake up the workers whenever a notification for their queue arrives,
+    /// and reconnect forever if the listener connection is lost.
+    pub async fn run(&self) {
+        loop {
+            if let Err(error) = self.listen().await {
+                warn!("Failed to listen for notifications: {error}");
+            }
+
+            sleep(self.reconnect_interval).await;
+        }
+    }
+
+    async fn listen(&self) -> anyhow::Result<()> {
+        let mut listener = (self.connect)().await?;
+        listener.listen(self.notifiers.keys().cloned()).await?;
+        info!("Listening for notifications…");
+
+        // Notifications might have been missed while we were not listening.
+        self.notifiers
+            .values()
+            .for_each(|notify| notify.notify_waiters());
+
+        while let Some(notification) = listener.notifications.recv().await {
+            debug!(channel = %notification.channel(), "Received notification");
+            if let Some(notify) = self.notifiers.get(notification.channel()) {
+                notify.notify_waiters();
+            }
+        }
+
+        Err(anyhow::anyhow!("Listener connection closed"))
+    }
+}
@@ -0,0 +1,88 @@
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+use std::time::Duration;
+
+/// The histogram buckets of the job durations and queue wait times, in
+/// seconds, going from 10ms for quick jobs up to an hour for database dumps
+/// and jobs that were delayed by a full queue.
+const HISTOGRAM_BUCKETS: &[f64] = &[
+    0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0, 3600.0,
+];
+
+const LABELS: &[&str] = &["job", "queue"];
+
+/// Prometheus metrics of the jobs run by a [Runner](crate::Runner), labelled
+/// by job type and queue.
+#[derive(Clone)]
+pub struct JobMetrics {
+    job_duration: HistogramVec,
+    queue_wait_time: HistogramVec,
+    jobs_succeeded: IntCounterVec,
+    jobs_failed: IntCounterVec,
+    jobs_retried: IntCounterVec,
+}
+
+impl JobMetrics {
+    /// Create the metrics, and register them with the given registry.
+    pub fn new(registry: &Registry) -> prometheus::Result<Self> {
+        let metrics = Self {
+            job_duration: histogram("job_duration_seconds", "Duration of job runs")?,
+            queue_wait_time: histogram(
+                "job_queue_wait_seconds",
+                "Time between the `run_at` time of a job and its first run",
+            )?,
+            jobs_succeeded: counter("jobs_succeeded_total", "Number of successful job runs")?,
+            jobs_failed: counter("jobs_failed_total", "Number of failed job runs")?,
+            jobs_retried: counter(
+                "jobs_retried_total",
+                "Number of runs of previously failed jobs",
+            )?,
+        };
+
+        registry.register(Box::new(metrics.job_duration.clone()))?;
+        registry.register(Box::new(metrics.queue_wait_time.clone()))?;
+        registry.register(Box::new(metrics.jobs_succeeded.clone()))?;
+        registry.register(Box::new(metrics.jobs_failed.clone()))?;
+        registry.register(Box::new(metrics.jobs_retried.clone()))?;
+
+        Ok(metrics)
+    }
+
+    /// Record that a job was picked up by a worker.
+    ///
+    /// The queue wait time is only recorded for the first run of a job, since
+    /// retries are intentionally delayed.
+    pub(crate) fn job_started(&self, job_type: &str, queue: &str, retries: i32, wait: Duration) {
+        let labels = &[job_type, queue];
+        if retries == 0 {
+            let wait = wait.as_secs_f64();
+            self.queue_wait_time.with_label_values(labels).observe(wait);
+        } else {
+            self.jobs_retried.with_label_values(labels).inc();
+        }
+    }
+
+    /// Record the duration and the result of a job run.
+    pub(crate) fn job_finished(&self, job_type: &str, queue: &str, duration: Duration, ok: bool) {
+        let labels = &[job_type, queue];
+        let duration = duration.as_secs_f64();
+        self.job_duration
+            .with_label_values(labels)
+            .observe(duration);
+
+        let counter = if ok {
+            &self.jobs_succeeded
+        } else {
+            &self.jobs_failed
+        };
+        counter.with_label_values(labels).inc();
+    }
+}
+
+fn histogram(name: &str, help: &str) -> prometheus::Result<HistogramVec> {
+    let opts = HistogramOpts::new(name, help).buckets(HISTOGRAM_BUCKETS.to_vec());
+    HistogramVec::new(opts, LABELS)
+}
+
+fn counter(name: &str, help: &str) -> prometheus::Result<IntCounterVec> {
+    IntCounterVec::new(Opts::new(name, help), LABELS)
+}
@@ -1,14 +1,20 @@
 use crate::background_job::DEFAULT_QUEUE;
 use crate::job_registry::JobRegistry;
+use crate::listener::{notification_channel, ConnectFn, Dispatcher, Listener};
+use crate::metrics::JobMetrics;
+use crate::schedule::{Schedule, ScheduledJob, Scheduler};
 use crate::worker::Worker;
 use crate::{storage, BackgroundJob};
 use anyhow::anyhow;
 use diesel_async::pooled_connection::deadpool::Pool;
 use diesel_async::AsyncPgConnection;
 use futures_util::future::join_all;
+use futures_util::FutureExt;
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 use tracing::{info, info_span, warn, Instrument};
 
@@ -18,6 +24,9 @@ const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
 pub struct Runner<Context> {
     connection_pool: Pool<AsyncPgConnection>,
     queues: HashMap<String, Queue<Context>>,
+    schedules: HashMap<String, ScheduledJob>,
+    listener: Option<Arc<ConnectFn>>,
+    metrics: Option<JobMetrics>,
     context: Context,
     shutdown_when_queue_empty: bool,
 }
@@ -27,6 +36,9 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
         Self {
             connection_pool,
             queues: HashMap::new(),
+            schedules: HashMap::new(),
+            listener: None,
+            metrics: None,
             context,
             shutdown_when_queue_empty: false,
         }
@@ -39,6 +51,25 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
         self
     }
 
+    /// Enqueue the given job according to a cron schedule.
+    ///
+    /// The job type has to be registered via [Self::register_job_type] too.
+    /// Each job type can only have a single schedule, so scheduling the same
+    /// job type again replaces the previous schedule.
+    ///
+    /// The scheduled ticks are tracked in the `background_job_schedules`
+    /// table, which ensures that each tick is only enqueued once, even if
+    /// multiple runners are running at the same time.
+    pub fn schedule_job<J: BackgroundJob<Context = Context>>(
+        mut self,
+        job: J,
+        schedule: Schedule,
+    ) -> Self {
+        let scheduled_job = ScheduledJob::new(job, schedule);
+        self.schedules.insert(J::JOB_NAME.into(), scheduled_job);
+        self
+    }
+
     /// Adjust the configuration of the [DEFAULT_QUEUE] queue.
     pub fn configure_default_queue<F>(self, f: F) -> Self
     where
@@ -57,6 +88,28 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
         self
     }
 
+    /// Wake up idle workers via PostgreSQL `LISTEN`/`NOTIFY` as soon as new
+    /// jobs are enqueued, instead of only when they poll for jobs again.
+    ///
+    /// The `connect` function is used to establish the dedicated [Listener]
+    /// connection, and to reconnect if that connection is lost. The workers
+    /// keep polling, in case notifications are missed.
+    pub fn listen_for_notifications<F, Fut>(mut self, connect: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<Listener>> + Send + 'static,
+    {
+        self.listener = Some(Arc::new(move || connect().boxed()));
+        self
+    }
+
+    /// Record the durations, results and queue wait times of the jobs in the
+    /// given [JobMetrics].
+    pub fn with_metrics(mut self, metrics: JobMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Set the runner to shut down when the background job queue is empty.
     pub fn shutdown_when_queue_empty(mut self) -> Self {
         self.shutdown_when_queue_empty = true;
@@ -68,17 +121,24 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
     /// This returns a `RunningRunner` which can be used to wait for the workers to shutdown.
     pub fn start(&self) -> RunHandle {
         let mut handles = Vec::new();
+        let mut notifiers = HashMap::new();
         for (queue_name, queue) in &self.queues {
+            let notify = Arc::new(Notify::new());
+            notifiers.insert(notification_channel(queue_name), notify.clone());
+
             for i in 1..=queue.num_workers {
                 let name = format!("background-worker-{queue_name}-{i}");
                 info!(worker.name = %name, "Starting worker…");
 
                 let worker = Worker {
                     connection_pool: self.connection_pool.clone(),
+                    queue: queue_name.clone(),
+                    notify: notify.clone(),
                     context: self.context.clone(),
                     job_registry: Arc::new(queue.job_registry.clone()),
                     shutdown_when_queue_empty: self.shutdown_when_queue_empty,
                     poll_interval: queue.poll_interval,
+                    metrics: self.metrics.clone(),
                 };
 
                 let span = info_span!("worker", worker.name = %name);
@@ -88,7 +148,37 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
             }
         }
 
-        RunHandle { handles }
+        let scheduler = (!self.schedules.is_empty()).then(|| {
+            info!("Starting scheduler…");
+
+            let scheduler = Scheduler {
+                connection_pool: self.connection_pool.clone(),
+                schedules: self.schedules.clone(),
+                poll_interval: DEFAULT_POLL_INTERVAL,
+            };
+
+            let span = info_span!("scheduler");
+            tokio::spawn(async move { scheduler.run().instrument(span).await })
+        });
+
+        let listener = self.listener.clone().map(|connect| {
+            info!("Starting notification listener…");
+
+            let dispatcher = Dispatcher {
+                connect,
+                notifiers,
+                reconnect_interval: DEFAULT_POLL_INTERVAL,
+            };
+
+            let span = info_span!("listener");
+            tokio::spawn(async move { dispatcher.run().instrument(span).await })
+        });
+
+        RunHandle {
+            handles,
+            scheduler,
+            listener,
+        }
     }
 
     /// Check if any jobs in the queue have failed.
@@ -109,16 +199,29 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
 
 pub struct RunHandle {
     handles: Vec<JoinHandle<()>>,
+    scheduler: Option<JoinHandle<()>>,
+    listener: Option<JoinHandle<()>>,
 }
 
 impl RunHandle {
     /// Wait for all background workers to shut down.
+    ///
+    /// The scheduler and the notification listener are stopped once all
+    /// workers have shut down.
     pub async fn wait_for_shutdown(self) {
         join_all(self.handles).await.into_iter().for_each(|result| {
             if let Err(error) = result {
                 warn!(%error, "Background worker task panicked");
             }
         });
+
+        if let Some(scheduler) = self.scheduler {
+            scheduler.abort();
+        }
+
+        if let Some(listener) = self.listener {
+            listener.abort();
+        }
     }
 }
 
@@ -0,0 +1,298 @@
+use crate::errors::EnqueueError;
+use crate::{storage, BackgroundJob};
+use anyhow::Context;
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use diesel_async::pooled_connection::deadpool::Pool;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+
+/// Ticks that are older than this are considered missed, e.g. because no
+/// runner was running at the time.
+const MISSED_TICK_THRESHOLD: TimeDelta = TimeDelta::minutes(1);
+
+/// The maximum number of ticks that are replayed for [MissedTicks::RunAll].
+const MAX_REPLAYED_TICKS: usize = 100;
+
+/// What to do with the ticks of a [Schedule] that were missed, e.g. because
+/// no runner was running at the time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissedTicks {
+    /// Don't enqueue the job for missed ticks.
+    Skip,
+    /// Enqueue the job once, no matter how many ticks were missed.
+    #[default]
+    RunOnce,
+    /// Enqueue the job once for every missed tick, but at most 100 times.
+    RunAll,
+}
+
+/// A cron schedule for a background job.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    cron: cron::Schedule,
+    timezone: Tz,
+    missed_ticks: MissedTicks,
+}
+
+impl Schedule {
+    /// Parse a cron expression.
+    ///
+    /// The expression consists of the seconds, minutes, hours, day of month,
+    /// month, day of week and an optional year field, e.g. `0 30 4 * * *` for
+    /// every day at 04:30. By default, the expression is evaluated in UTC.
+    pub fn cron(expression: &str) -> anyhow::Result<Self> {
+        let cron = cron::Schedule::from_str(expression)
+            .with_context(|| format!("Invalid cron expression: {expression}"))?;
+
+        Ok(Self {
+            cron,
+            timezone: Tz::UTC,
+            missed_ticks: MissedTicks::default(),
+        })
+    }
+
+    /// Set the timezone in which the cron expression is evaluated.
+    pub fn timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Set what to do with ticks that were missed.
+    pub fn missed_ticks(mut self, missed_ticks: MissedTicks) -> Self {
+        self.missed_ticks = missed_ticks;
+        self
+    }
+
+    /// Returns how often the job should be enqueued for the ticks after
+    /// `last_tick` up to `now`, and the most recent of these ticks.
+    fn due_ticks(
+        &self,
+        last_tick: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> (usize, Option<DateTime<Utc>>) {
+        // Searching backwards from `now` finds the most recent tick without
+        // iterating over all ticks since `last_tick`. The backwards search
+        // skips a tick at exactly `now` unless it starts within that second.
+        let search_start = (now + TimeDelta::nanoseconds(1)).with_timezone(&self.timezone);
+        let most_recent = self
+            .cron
+            .after(&search_start)
+            .next_back()
+            .map(|tick| tick.with_timezone(&Utc))
+            .filter(|tick| *tick > last_tick);
+
+        let Some(most_recent) = most_recent else {
+            return (0, None);
+        };
+
+        let is_missed = most_recent < now - MISSED_TICK_THRESHOLD;
+        let count = match self.missed_ticks {
+            MissedTicks::Skip if is_missed => 0,
+            MissedTicks::Skip | MissedTicks::RunOnce => 1,
+            MissedTicks::RunAll => {
+                // Only the most recent ticks are replayed, so that a runner
+                // that was down for a long time doesn't flood the queue.
+                let first_tick = most_recent.with_timezone(&self.timezone);
+                let replayed = self.cron.after(&first_tick).rev();
+                let replayed = replayed.take_while(|tick| *tick > last_tick);
+                1 + replayed.take(MAX_REPLAYED_TICKS - 1).count()
+            }
+        };
+
+        (count, Some(most_recent))
+    }
+}
+
+type EnqueueFuture<'a> = BoxFuture<'a, Result<Option<i64>, EnqueueError>>;
+type EnqueueFn = dyn for<'a> Fn(&'a mut AsyncPgConnection) -> EnqueueFuture<'a> + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct ScheduledJob {
+    schedule: Schedule,
+    enqueue: Arc<EnqueueFn>,
+}
+
+impl ScheduledJob {
+    pub(crate) fn new<J: BackgroundJob>(job: J, schedule: Schedule) -> Self {
+        let job = Arc::new(job);
+        let enqueue: Arc<EnqueueFn> = Arc::new(move |conn| {
+            let job = job.clone();
+            async move { job.enqueue(conn).await }.boxed()
+        });
+
+        Self { schedule, enqueue }
+    }
+
+    /// Enqueue the job for the ticks after `last_tick` up to `now`, and
+    /// record the most recent of these ticks as handled.
+    async fn enqueue_due(
+        &self,
+        conn: &mut AsyncPgConnection,
+        job_type: &str,
+        last_tick: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let (count, most_recent) = self.schedule.due_ticks(last_tick, now);
+        let Some(most_recent) = most_recent else {
+            return Ok(());
+        };
+
+        if count == 0 {
+            warn!(job.typ = %job_type, tick = %most_recent, "Skipping missed ticks");
+        }
+
+        for _ in 0..count {
+            info!(job.typ = %job_type, tick = %most_recent, "Enqueueing scheduled job…");
+            (self.enqueue)(conn).await?;
+        }
+
+        debug!(job.typ = %job_type, tick = %most_recent, "Updating schedule…");
+        storage::update_schedule(conn, job_type, most_recent).await?;
+
+        Ok(())
+    }
+}
+
+pub(crate) struct Scheduler {
+    pub(crate) connection_pool: Pool<AsyncPgConnection>,
+    pub(crate) schedules: HashMap<String, ScheduledJob>,
+    pub(crate) poll_interval: Duration,
+}
+
+impl Scheduler {
+    /// Enqueue the scheduled jobs forever.
+    pub async fn run(&self) {
+        loop {
+            if let Err(error) = self.enqueue_due_jobs().await {
+                error!("Failed to enqueue scheduled jobs: {error}");
+            }
+
+            sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Enqueue the scheduled jobs that are due.
+    ///
+    /// The state rows of the schedules are locked while the jobs are
+    /// enqueued, so that concurrently running runners don't enqueue the
+    /// same tick twice.
+    async fn enqueue_due_jobs(&self) -> anyhow::Result<()> {
+        let mut conn = self.connection_pool.get().await?;
+
+        let job_types = self
+            .schedules
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let now = Utc::now();
+
+        storage::insert_schedules(&mut conn, &job_types, now).await?;
+
+        conn.transaction(|conn| {
+            async move {
+                for (job_type, last_tick) in storage::lock_schedules(conn, &job_types).await? {
+                    if let Some(scheduled_job) = self.schedules.get(&job_type) {
+                        scheduled_job
+                            .enqueue_due(conn, &job_type, last_tick, now)
+                            .await?;
+                    }
+                }
+
+                Ok::<_, anyhow::Error>(())
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 4, 7, hour, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_due_ticks() {
+        let schedule = Schedule::cron("0 0 * * * *").unwrap();
+
+        assert_eq!(schedule.due_ticks(utc(10, 0), utc(10, 59)), (0, None));
+        assert_eq!(
+            schedule.due_ticks(utc(10, 0), utc(11, 0)),
+            (1, Some(utc(11, 0)))
+        );
+    }
+
+    #[test]
+    fn test_missed_ticks() {
+        let schedule = Schedule::cron("0 0 * * * *").unwrap();
+
+        let skip = schedule.clone().missed_ticks(MissedTicks::Skip);
+        assert_eq!(
+            skip.due_ticks(utc(10, 0), utc(13, 0)),
+            (1, Some(utc(13, 0)))
+        );
+        assert_eq!(
+            skip.due_ticks(utc(10, 0), utc(13, 5)),
+            (0, Some(utc(13, 0)))
+        );
+
+        let run_once = schedule.clone().missed_ticks(MissedTicks::RunOnce);
+        assert_eq!(
+            run_once.due_ticks(utc(10, 0), utc(13, 5)),
+            (1, Some(utc(13, 0)))
+        );
+
+        let run_all = schedule.missed_ticks(MissedTicks::RunAll);
+        assert_eq!(
+            run_all.due_ticks(utc(10, 0), utc(13, 5)),
+            (3, Some(utc(13, 0)))
+        );
+    }
+
+    #[test]
+    fn test_missed_ticks_are_bounded() {
+        let schedule = Schedule::cron("* * * * * *").unwrap();
+        let last_tick = utc(10, 0) - TimeDelta::days(365);
+
+        let skip = schedule.clone().missed_ticks(MissedTicks::Skip);
+        assert_eq!(skip.due_ticks(last_tick, utc(10, 0)), (1, Some(utc(10, 0))));
+
+        let run_all = schedule.missed_ticks(MissedTicks::RunAll);
+        assert_eq!(
+            run_all.due_ticks(last_tick, utc(10, 0)),
+            (MAX_REPLAYED_TICKS, Some(utc(10, 0)))
+        );
+    }
+
+    #[test]
+    fn test_timezone() {
+        let schedule = Schedule::cron("0 0 9 * * *")
+            .unwrap()
+            .timezone(chrono_tz::Europe::Berlin);
+
+        // 09:00 in Berlin is 07:00 UTC during daylight saving time.
+        assert_eq!(
+            schedule.due_ticks(utc(6, 0), utc(7, 0)),
+            (1, Some(utc(7, 0)))
+        );
+    }
+
+    #[test]
+    fn test_invalid_expression() {
+        let error = Schedule::cron("every day").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid cron expression: every day");
+    }
+}
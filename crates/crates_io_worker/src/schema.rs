@@ -5,7 +5,54 @@ diesel::table! {
         data -> Jsonb,
         retries -> Int4,
         last_retry -> Timestamp,
-        created_at -> Timestamp,
+        created_at -> Timestamptz,
         priority -> Int2,
+        queue -> Text,
+        last_error -> Nullable<Text>,
+        run_at -> Timestamptz,
+        trace_context -> Nullable<Text>,
     }
 }
+
+diesel::table! {
+    background_job_dependencies (job_id, parent_id) {
+        job_id -> Int8,
+        parent_id -> Int8,
+    }
+}
+
+diesel::table! {
+    background_job_schedules (job_type) {
+        job_type -> Text,
+        last_tick -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    failed_background_jobs (id) {
+        id -> Int8,
+        job_type -> Text,
+        data -> Jsonb,
+        retries -> Int4,
+        priority -> Int2,
+        created_at -> Timestamptz,
+        failed_at -> Timestamptz,
+        error -> Text,
+        backtrace -> Nullable<Text>,
+        queue -> Text,
+    }
+}
+
+diesel::table! {
+    paused_background_job_queues (queue) {
+        queue -> Text,
+        paused_at -> Timestamptz,
+    }
+}
+
+diesel::allow_tables_to_appear_in_same_query!(
+    background_job_dependencies,
+    background_jobs,
+    failed_background_jobs,
+    paused_background_job_queues,
+);
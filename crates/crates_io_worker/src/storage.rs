@@ -1,16 +1,25 @@
-use crate::schema::background_jobs;
-use diesel::dsl::now;
+use crate::schema::{
+    background_job_dependencies, background_job_schedules, background_jobs, failed_background_jobs,
+    paused_background_job_queues,
+};
+use chrono::{DateTime, Utc};
+use diesel::dsl::{exists, not, now};
 use diesel::pg::Pg;
 use diesel::prelude::*;
-use diesel::sql_types::{Bool, Integer, Interval};
+use diesel::sql_types::{Bool, Integer, Interval, Nullable, Text};
 use diesel::{delete, update};
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use std::backtrace::BacktraceStatus;
 
 #[derive(Queryable, Selectable, Identifiable, Debug, Clone)]
 pub(super) struct BackgroundJob {
     pub(super) id: i64,
     pub(super) job_type: String,
     pub(super) data: serde_json::Value,
+    pub(super) retries: i32,
+    pub(super) run_at: DateTime<Utc>,
+    /// The `sentry-trace` header of the trace in which the job was enqueued.
+    pub(super) trace_context: Option<String>,
 }
 
 fn retriable() -> Box<dyn BoxableExpression<background_jobs::table, Pg, SqlType = Bool>> {
@@ -26,21 +35,63 @@ fn retriable() -> Box<dyn BoxableExpression<background_jobs::table, Pg, SqlType
 
 /// Finds the next job that is unlocked, and ready to be retried. If a row is
 /// found, it will be locked.
+///
+/// If the queue is paused, no job is returned. Jobs that are still waiting
+/// for their parent jobs are skipped.
+///
+/// The row is locked with `FOR NO KEY UPDATE`, so that dependent jobs can
+/// still be enqueued while the job is running.
 pub(super) async fn find_next_unlocked_job(
     conn: &mut AsyncPgConnection,
+    queue: &str,
     job_types: &[String],
 ) -> QueryResult<BackgroundJob> {
+    let is_paused = exists(paused_background_job_queues::table.find(queue));
+    let has_parents = exists(
+        background_job_dependencies::table
+            .filter(background_job_dependencies::job_id.eq(background_jobs::id)),
+    );
+
     background_jobs::table
         .select(BackgroundJob::as_select())
+        .filter(not(is_paused))
+        .filter(not(has_parents))
         .filter(background_jobs::job_type.eq_any(job_types))
+        .filter(background_jobs::run_at.le(now))
         .filter(retriable())
         .order((background_jobs::priority.desc(), background_jobs::id))
-        .for_update()
+        .for_no_key_update()
         .skip_locked()
         .first::<BackgroundJob>(conn)
         .await
 }
 
+/// Tries to acquire one of the `max_concurrency` slots of a job type.
+///
+/// The slots are transaction-level advisory locks, so an acquired slot is held
+/// until the job's transaction is committed or rolled back.
+pub(super) async fn try_acquire_concurrency_slot(
+    conn: &mut AsyncPgConnection,
+    job_type: &str,
+    max_concurrency: u32,
+) -> QueryResult<bool> {
+    define_sql_function!(fn hashtext(text: Text) -> Integer);
+    define_sql_function!(fn pg_try_advisory_xact_lock(key1: Integer, key2: Integer) -> Bool);
+
+    let key = format!("background_jobs.{job_type}");
+    for slot in 0..i32::try_from(max_concurrency).unwrap_or(i32::MAX) {
+        let acquired = diesel::select(pg_try_advisory_xact_lock(hashtext(&key), slot))
+            .get_result::<bool>(conn)
+            .await?;
+
+        if acquired {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
 /// The number of jobs that have failed at least once
 pub(super) async fn failed_job_count(conn: &mut AsyncPgConnection) -> QueryResult<i64> {
     background_jobs::table
@@ -65,12 +116,167 @@ pub(super) async fn delete_successful_job(
 ///
 /// Ignores any database errors that may have occurred. If the DB has gone away,
 /// we assume that just trying again with a new connection will succeed.
-pub(super) async fn update_failed_job(conn: &mut AsyncPgConnection, job_id: i64) {
+pub(super) async fn update_failed_job(
+    conn: &mut AsyncPgConnection,
+    job_id: i64,
+    error: &anyhow::Error,
+) {
     let _ = update(background_jobs::table.find(job_id))
         .set((
             background_jobs::retries.eq(background_jobs::retries + 1),
             background_jobs::last_retry.eq(now),
+            background_jobs::last_error.eq(format!("{error:#}")),
         ))
         .execute(conn)
         .await;
 }
+
+/// Moves a job that has exhausted its retries to the `failed_background_jobs`
+/// table, together with the error of its last run.
+///
+/// The jobs that depend on the failed job can never be run, so they are moved
+/// to the `failed_background_jobs` table as well by a database trigger. This
+/// is why the job is only deleted from the `background_jobs` table after it
+/// has been inserted into the `failed_background_jobs` table, since its
+/// dependencies are deleted together with it.
+pub(super) async fn move_to_failed_jobs(
+    conn: &mut AsyncPgConnection,
+    job_id: i64,
+    error: &anyhow::Error,
+) -> QueryResult<()> {
+    // Wait for any transactions that are enqueueing dependent jobs, so that
+    // their dependencies are visible to the trigger.
+    background_jobs::table
+        .find(job_id)
+        .select(background_jobs::id)
+        .for_update()
+        .execute(conn)
+        .await?;
+
+    let backtrace = error.backtrace();
+    let backtrace =
+        (backtrace.status() == BacktraceStatus::Captured).then(|| backtrace.to_string());
+
+    let failed_job = background_jobs::table.find(job_id).select((
+        background_jobs::id,
+        background_jobs::job_type,
+        background_jobs::data,
+        background_jobs::retries,
+        background_jobs::priority,
+        background_jobs::queue,
+        background_jobs::created_at,
+        format!("{error:#}").into_sql::<Text>(),
+        backtrace.into_sql::<Nullable<Text>>(),
+    ));
+
+    diesel::insert_into(failed_background_jobs::table)
+        .values(failed_job)
+        .into_columns((
+            failed_background_jobs::id,
+            failed_background_jobs::job_type,
+            failed_background_jobs::data,
+            failed_background_jobs::retries,
+            failed_background_jobs::priority,
+            failed_background_jobs::queue,
+            failed_background_jobs::created_at,
+            failed_background_jobs::error,
+            failed_background_jobs::backtrace,
+        ))
+        .execute(conn)
+        .await?;
+
+    delete(background_jobs::table.find(job_id))
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the IDs of all jobs that directly or indirectly depend on the
+/// given job, in the order in which they were discovered.
+pub(crate) async fn dependent_jobs(
+    conn: &mut AsyncPgConnection,
+    job_id: i64,
+) -> QueryResult<Vec<i64>> {
+    let mut dependent_jobs = Vec::new();
+    let mut parents = vec![job_id];
+
+    while !parents.is_empty() {
+        let children = background_job_dependencies::table
+            .select(background_job_dependencies::job_id)
+            .filter(background_job_dependencies::parent_id.eq_any(&parents))
+            .distinct()
+            .load::<i64>(conn)
+            .await?;
+
+        parents = children
+            .into_iter()
+            .filter(|child| !dependent_jobs.contains(child))
+            .collect();
+
+        dependent_jobs.extend(&parents);
+    }
+
+    Ok(dependent_jobs)
+}
+
+/// Creates the state rows of the given schedules, unless they exist already.
+///
+/// New schedules start at `last_tick`, so that ticks before the schedule was
+/// registered are not considered missed.
+pub(super) async fn insert_schedules(
+    conn: &mut AsyncPgConnection,
+    job_types: &[&str],
+    last_tick: DateTime<Utc>,
+) -> QueryResult<()> {
+    let values = job_types
+        .iter()
+        .map(|job_type| {
+            (
+                background_job_schedules::job_type.eq(job_type),
+                background_job_schedules::last_tick.eq(last_tick),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    diesel::insert_into(background_job_schedules::table)
+        .values(values)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Finds and locks the state rows of the given schedules.
+///
+/// Rows that are currently locked by another runner are skipped, so that
+/// each tick is only handled by a single runner.
+pub(super) async fn lock_schedules(
+    conn: &mut AsyncPgConnection,
+    job_types: &[&str],
+) -> QueryResult<Vec<(String, DateTime<Utc>)>> {
+    background_job_schedules::table
+        .select((
+            background_job_schedules::job_type,
+            background_job_schedules::last_tick,
+        ))
+        .filter(background_job_schedules::job_type.eq_any(job_types))
+        .for_update()
+        .skip_locked()
+        .load(conn)
+        .await
+}
+
+/// Records the most recent tick of a schedule that has been handled.
+pub(super) async fn update_schedule(
+    conn: &mut AsyncPgConnection,
+    job_type: &str,
+    last_tick: DateTime<Utc>,
+) -> QueryResult<()> {
+    update(background_job_schedules::table.find(job_type))
+        .set(background_job_schedules::last_tick.eq(last_tick))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
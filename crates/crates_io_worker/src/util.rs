@@ -4,8 +4,15 @@ use std::any::Any;
 use std::future::Future;
 use std::panic::PanicHookInfo;
 
+const SENTRY_TRACE_HEADER: &str = "sentry-trace";
+
+/// Runs the callback in a new Sentry transaction.
+///
+/// If a `sentry-trace` header was captured when the job was enqueued, the
+/// transaction continues that trace.
 pub async fn with_sentry_transaction<F, R, E, Fut>(
     transaction_name: &str,
+    trace_context: Option<&str>,
     callback: F,
 ) -> Result<R, E>
 where
@@ -15,7 +22,14 @@ where
     let hub = Hub::new_from_top(Hub::current());
     let _scope_guard = hub.push_scope();
 
-    let tx_ctx = sentry_core::TransactionContext::new(transaction_name, "swirl.perform");
+    let op = "swirl.perform";
+    let tx_ctx = match trace_context {
+        Some(header) => {
+            let headers = [(SENTRY_TRACE_HEADER, header)];
+            sentry_core::TransactionContext::continue_from_headers(transaction_name, op, headers)
+        }
+        None => sentry_core::TransactionContext::new(transaction_name, op),
+    };
     let tx = sentry_core::start_transaction(tx_ctx);
 
     hub.configure_scope(|scope| scope.set_span(Some(tx.clone().into())));
@@ -31,6 +45,14 @@ where
     result
 }
 
+/// Returns the `sentry-trace` header of the currently active span, if any,
+/// so that it can be stored with an enqueued job.
+pub fn current_trace_context() -> Option<String> {
+    let span = Hub::current().configure_scope(|scope| scope.get_span())?;
+    let mut headers = span.iter_headers();
+    headers.find_map(|(name, value)| (name == SENTRY_TRACE_HEADER).then_some(value))
+}
+
 /// Try to figure out what's in the box, and print it if we can.
 ///
 /// The actual error type we will get from `panic::catch_unwind` is really poorly documented.
@@ -1,7 +1,10 @@
+use crate::cancellation::run_with_timeout;
 use crate::job_registry::JobRegistry;
+use crate::metrics::JobMetrics;
 use crate::storage;
 use crate::util::{try_to_extract_panic_info, with_sentry_transaction};
 use anyhow::anyhow;
+use chrono::Utc;
 use diesel::prelude::*;
 use diesel_async::pooled_connection::deadpool::Pool;
 use diesel_async::scoped_futures::ScopedFutureExt;
@@ -9,23 +12,35 @@ use diesel_async::{AsyncConnection, AsyncPgConnection};
 use futures_util::FutureExt;
 use sentry_core::{Hub, SentryFutureExt};
 use std::panic::AssertUnwindSafe;
+use std::pin::pin;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::time::sleep;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::time::{sleep, timeout};
 use tracing::{debug, error, info_span, warn};
 
 pub struct Worker<Context> {
     pub(crate) connection_pool: Pool<AsyncPgConnection>,
+    pub(crate) queue: String,
+    pub(crate) notify: Arc<Notify>,
     pub(crate) context: Context,
     pub(crate) job_registry: Arc<JobRegistry<Context>>,
     pub(crate) shutdown_when_queue_empty: bool,
     pub(crate) poll_interval: Duration,
+    pub(crate) metrics: Option<JobMetrics>,
 }
 
 impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
     /// Run background jobs forever, or until the queue is empty if `shutdown_when_queue_empty` is set.
     pub async fn run(&self) {
         loop {
+            // The notifications are registered for before looking for the
+            // next job, so that a job that is enqueued in the meantime is not
+            // missed. `notify_waiters()` does not store a permit for futures
+            // that are created afterwards.
+            let mut notified = pin!(self.notify.notified());
+            notified.as_mut().enable();
+
             match self.run_next_job().await {
                 Ok(Some(_)) => {}
                 Ok(None) if self.shutdown_when_queue_empty => {
@@ -34,10 +49,10 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
                 }
                 Ok(None) => {
                     debug!(
-                        "No pending background worker jobs found. Polling again in {:?}…",
+                        "No pending background worker jobs found. Waiting for notifications or polling again in {:?}…",
                         self.poll_interval
                     );
-                    sleep(self.poll_interval).await;
+                    let _ = timeout(self.poll_interval, notified).await;
                 }
                 Err(error) => {
                     error!("Failed to run job: {error}");
@@ -58,14 +73,12 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
         let job_registry = self.job_registry.clone();
         let mut conn = self.connection_pool.get().await?;
 
-        let job_types = job_registry.job_types();
+        let queue = &self.queue;
+        let metrics = self.metrics.as_ref();
         conn.transaction(|conn| {
             async move {
                 debug!("Looking for next background worker job…");
-                let Some(job) = storage::find_next_unlocked_job(conn, &job_types)
-                    .await
-                    .optional()?
-                else {
+                let Some(job) = find_next_job(conn, queue, &job_registry).await? else {
                     return Ok(None);
                 };
 
@@ -75,12 +88,22 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
                 let job_id = job.id;
                 debug!("Running job…");
 
-                let future = with_sentry_transaction(&job.job_type, async || {
+                if let Some(metrics) = metrics {
+                    let wait = (Utc::now() - job.run_at).to_std().unwrap_or_default();
+                    metrics.job_started(&job.job_type, queue, job.retries, wait);
+                }
+
+                let start = Instant::now();
+                let trace_context = job.trace_context.as_deref();
+                let future = with_sentry_transaction(&job.job_type, trace_context, async || {
                     let run_task_fn = job_registry
                         .get(&job.job_type)
                         .ok_or_else(|| anyhow!("Unknown job type {}", job.job_type))?;
 
-                    AssertUnwindSafe(run_task_fn(context, job.data))
+                    let job_timeout = job_registry.timeout(&job.job_type);
+                    let future = run_with_timeout(run_task_fn(context, job.data), job_timeout);
+
+                    AssertUnwindSafe(future)
                         .catch_unwind()
                         .await
                         .map_err(|e| try_to_extract_panic_info(&e))
@@ -88,14 +111,28 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
                         .and_then(std::convert::identity)
                 });
 
-                match future.bind_hub(Hub::current()).await {
+                let result = future.bind_hub(Hub::current()).await;
+                if let Some(metrics) = metrics {
+                    let duration = start.elapsed();
+                    metrics.job_finished(&job.job_type, queue, duration, result.is_ok());
+                }
+
+                match result {
                     Ok(_) => {
                         debug!("Deleting successful job…");
                         storage::delete_successful_job(conn, job_id).await?
                     }
                     Err(error) => {
                         warn!("Failed to run job: {error}");
-                        storage::update_failed_job(conn, job_id).await;
+
+                        let max_retries = job_registry.max_retries(&job.job_type);
+                        let retries = i64::from(job.retries);
+                        if max_retries.is_some_and(|max| retries >= i64::from(max)) {
+                            warn!("Job has exhausted its retries. Moving it to the failed jobs…");
+                            storage::move_to_failed_jobs(conn, job_id, &error).await?
+                        } else {
+                            storage::update_failed_job(conn, job_id, &error).await;
+                        }
                     }
                 }
 
@@ -106,3 +143,65 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
         .await
     }
 }
+
+enum FindJobError {
+    /// The job type has reached its maximum concurrency.
+    Saturated(String),
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for FindJobError {
+    fn from(error: diesel::result::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+/// Finds and locks the next job of the queue that is ready to be run.
+///
+/// Jobs of types that have reached their maximum concurrency are skipped. For
+/// job types with a concurrency limit, one of their concurrency slots is held
+/// for the rest of the surrounding transaction.
+async fn find_next_job<Context: Clone + Send + Sync + 'static>(
+    conn: &mut AsyncPgConnection,
+    queue: &str,
+    job_registry: &JobRegistry<Context>,
+) -> QueryResult<Option<storage::BackgroundJob>> {
+    let mut job_types = job_registry.job_types();
+
+    loop {
+        // The job is locked in a savepoint, so that its row lock is released
+        // again if no concurrency slot is available for its job type.
+        let types = &job_types;
+        let result = conn
+            .transaction(|conn| {
+                async move {
+                    let job = storage::find_next_unlocked_job(conn, queue, types).await?;
+                    if let Some(max_concurrency) = job_registry.max_concurrency(&job.job_type) {
+                        if !storage::try_acquire_concurrency_slot(
+                            conn,
+                            &job.job_type,
+                            max_concurrency,
+                        )
+                        .await?
+                        {
+                            return Err(FindJobError::Saturated(job.job_type));
+                        }
+                    }
+
+                    Ok(job)
+                }
+                .scope_boxed()
+            })
+            .await;
+
+        match result {
+            Ok(job) => return Ok(Some(job)),
+            Err(FindJobError::Saturated(job_type)) => {
+                debug!("Maximum concurrency of {job_type} jobs reached. Skipping them…");
+                job_types.retain(|t| *t != job_type);
+            }
+            Err(FindJobError::Database(diesel::result::Error::NotFound)) => return Ok(None),
+            Err(FindJobError::Database(error)) => return Err(error),
+        }
+    }
+}
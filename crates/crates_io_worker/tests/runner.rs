@@ -1,17 +1,25 @@
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
 use claims::{assert_none, assert_some};
 use crates_io_test_db::TestDatabase;
-use crates_io_worker::schema::background_jobs;
-use crates_io_worker::{BackgroundJob, Runner};
+use crates_io_worker::schema::{background_job_schedules, background_jobs, failed_background_jobs};
+use crates_io_worker::{
+    admin, cancellation_token, BackgroundJob, EnqueueError, JobMetrics, Listener, MissedTicks,
+    Runner, Schedule,
+};
 use diesel::prelude::*;
 use diesel_async::pooled_connection::deadpool::Pool;
 use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use insta::assert_compact_json_snapshot;
+use prometheus::Registry;
+use sentry_core::Hub;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::atomic::{AtomicU8, Ordering};
-use std::sync::Arc;
-use tokio::sync::Barrier;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Barrier, Notify};
+use tokio_postgres::NoTls;
 
 async fn all_jobs(conn: &mut AsyncPgConnection) -> QueryResult<Vec<(String, Value)>> {
     background_jobs::table
@@ -230,6 +238,208 @@ async fn panicking_in_jobs_updates_retry_counter() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn jobs_are_moved_to_failed_jobs_after_max_retries() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob {
+        value: String,
+    }
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        const MAX_RETRIES: Option<u32> = Some(0);
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Err(anyhow::anyhow!("something went wrong"))
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let runner = runner(pool, ()).register_job_type::<TestJob>();
+
+    let value = "foo".to_string();
+    let job_id = assert_some!(TestJob { value }.enqueue(&mut conn).await?);
+
+    let runner = runner.start();
+    runner.wait_for_shutdown().await;
+
+    assert!(!job_exists(job_id, &mut conn).await?);
+
+    let failed_jobs = failed_background_jobs::table
+        .select((
+            failed_background_jobs::id,
+            failed_background_jobs::job_type,
+            failed_background_jobs::data,
+            failed_background_jobs::retries,
+            failed_background_jobs::error,
+        ))
+        .load::<(i64, String, Value, i32, String)>(&mut conn)
+        .await?;
+
+    assert_eq!(failed_jobs.len(), 1);
+    let (id, job_type, data, retries, error) = &failed_jobs[0];
+    assert_eq!(*id, job_id);
+    assert_eq!(job_type, "test");
+    assert_compact_json_snapshot!(data, @r#"{"value": "foo"}"#);
+    assert_eq!(*retries, 0);
+    assert_eq!(error, "something went wrong");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_jobs_can_be_inspected_and_cancelled() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Err(anyhow::anyhow!("something went wrong"))
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let runner = runner(pool, ()).register_job_type::<TestJob>();
+
+    let job_id = assert_some!(TestJob.enqueue(&mut conn).await?);
+
+    let runner = runner.start();
+    runner.wait_for_shutdown().await;
+
+    let job = assert_some!(admin::find_job(&mut conn, job_id).await?);
+    assert_eq!(job.job_type, "test");
+    assert_eq!(job.queue, "default");
+    assert_eq!(job.retries, 1);
+    assert_eq!(job.last_error.as_deref(), Some("something went wrong"));
+
+    let jobs = admin::list_jobs(&mut conn, None, Some("default"), None, 10).await?;
+    assert_eq!(jobs.len(), 1);
+    let jobs = admin::list_jobs(&mut conn, Some("other"), None, None, 10).await?;
+    assert!(jobs.is_empty());
+
+    admin::retry_now(&mut conn, job_id).await?;
+    let job = assert_some!(admin::find_job(&mut conn, job_id).await?);
+    assert_eq!(job.last_retry, DateTime::UNIX_EPOCH.naive_utc());
+
+    admin::cancel_job(&mut conn, job_id).await?;
+    assert!(!job_exists(job_id, &mut conn).await?);
+
+    let error = admin::cancel_job(&mut conn, job_id).await.unwrap_err();
+    assert!(matches!(error, admin::JobControlError::NotFound));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn jobs_are_listed_in_pages() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct UrgentJob;
+
+    impl BackgroundJob for UrgentJob {
+        const JOB_NAME: &'static str = "urgent";
+        const PRIORITY: i16 = 10;
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let first = assert_some!(TestJob.enqueue(&mut conn).await?);
+    let second = assert_some!(TestJob.enqueue(&mut conn).await?);
+    let urgent = assert_some!(UrgentJob.enqueue(&mut conn).await?);
+
+    let page = admin::list_jobs(&mut conn, None, None, None, 2).await?;
+    let ids = page.iter().map(|job| job.id).collect::<Vec<_>>();
+    assert_eq!(ids, [urgent, first]);
+
+    let after = page.last().map(|job| job.cursor());
+    let page = admin::list_jobs(&mut conn, None, None, after, 2).await?;
+    let ids = page.iter().map(|job| job.id).collect::<Vec<_>>();
+    assert_eq!(ids, [second]);
+
+    let after = page.last().map(|job| job.cursor());
+    let page = admin::list_jobs(&mut conn, None, None, after, 2).await?;
+    assert!(page.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn jobs_of_paused_queues_are_not_run() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let runner = runner(pool, ()).register_job_type::<TestJob>();
+
+    let error = admin::pause_queue(&mut conn, "defualt", &["default"])
+        .await
+        .unwrap_err();
+    assert!(matches!(error, admin::JobControlError::UnknownQueue));
+    assert!(admin::paused_queues(&mut conn).await?.is_empty());
+
+    admin::pause_queue(&mut conn, "default", &["default"]).await?;
+    let paused_queues = admin::paused_queues(&mut conn).await?;
+    assert_eq!(paused_queues.len(), 1);
+    assert_eq!(paused_queues[0].0, "default");
+
+    let job_id = assert_some!(TestJob.enqueue(&mut conn).await?);
+
+    runner.start().wait_for_shutdown().await;
+    assert!(job_exists(job_id, &mut conn).await?);
+
+    assert!(admin::resume_queue(&mut conn, "default").await?);
+    assert!(!admin::resume_queue(&mut conn, "default").await?);
+
+    runner.start().wait_for_shutdown().await;
+    assert!(!job_exists(job_id, &mut conn).await?);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn jobs_can_be_deduplicated() -> anyhow::Result<()> {
     #[derive(Clone)]
@@ -314,6 +524,678 @@ async fn jobs_can_be_deduplicated() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn delayed_jobs_are_not_run_before_run_at() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob {
+        value: String,
+    }
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let runner = runner(pool, ()).register_job_type::<TestJob>();
+
+    let value = "foo".to_string();
+    let delay = TimeDelta::hours(1);
+    assert_some!(TestJob { value }.enqueue_after(&mut conn, delay).await?);
+
+    let value = "bar".to_string();
+    let run_at = Utc::now() - TimeDelta::minutes(1);
+    assert_some!(TestJob { value }.enqueue_at(&mut conn, run_at).await?);
+
+    runner.start().wait_for_shutdown().await;
+    assert_compact_json_snapshot!(all_jobs(&mut conn).await?, @r#"[["test", {"value": "foo"}]]"#);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn delayed_jobs_are_deduplicated_by_earlier_jobs() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        const DEDUPLICATED: bool = true;
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    // Enqueue a delayed job
+    let delay = TimeDelta::hours(2);
+    assert_some!(TestJob.enqueue_after(&mut conn, delay).await?);
+
+    // Enqueue the same job to run earlier, which should NOT be deduplicated,
+    // since the delayed job would run too late
+    assert_some!(TestJob.enqueue(&mut conn).await?);
+
+    // Enqueue the same job again, which should be deduplicated by the job
+    // that runs immediately, no matter when it is supposed to run
+    assert_none!(TestJob.enqueue(&mut conn).await?);
+    let delay = TimeDelta::hours(1);
+    assert_none!(TestJob.enqueue_after(&mut conn, delay).await?);
+
+    assert_eq!(all_jobs(&mut conn).await?.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn timed_out_jobs_are_cancelled_and_treated_as_failures() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        const TIMEOUT: Option<Duration> = Some(Duration::from_millis(100));
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            cancellation_token().cancelled().await;
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let runner = runner(pool, ()).register_job_type::<TestJob>();
+
+    let job_id = assert_some!(TestJob.enqueue(&mut conn).await?);
+
+    let start = Instant::now();
+    runner.start().wait_for_shutdown().await;
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let job = assert_some!(admin::find_job(&mut conn, job_id).await?);
+    assert_eq!(job.retries, 1);
+    assert_eq!(job.last_error.as_deref(), Some("Job timed out after 100ms"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn jobs_do_not_exceed_their_max_concurrency() -> anyhow::Result<()> {
+    #[derive(Clone, Default)]
+    struct TestContext {
+        running: Arc<AtomicU8>,
+        max_running: Arc<AtomicU8>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob {
+        value: u8,
+    }
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        const MAX_CONCURRENCY: Option<u32> = Some(1);
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            let running = ctx.running.fetch_add(1, Ordering::SeqCst) + 1;
+            ctx.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            ctx.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    let test_context = TestContext::default();
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let runner = Runner::new(pool, test_context.clone())
+        .configure_default_queue(|queue| queue.num_workers(4))
+        .register_job_type::<TestJob>()
+        .shutdown_when_queue_empty();
+
+    for value in 0..3 {
+        TestJob { value }.enqueue(&mut conn).await?;
+    }
+
+    runner.start().wait_for_shutdown().await;
+
+    // The workers that could not acquire a concurrency slot shut down early,
+    // so the remaining jobs are run one after another by a single worker.
+    assert!(all_jobs(&mut conn).await?.is_empty());
+    let max_running = AtomicU8::load(&test_context.max_running, Ordering::SeqCst);
+    assert_eq!(max_running, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn jobs_are_run_after_their_parent_jobs() -> anyhow::Result<()> {
+    #[derive(Clone, Default)]
+    struct TestContext {
+        runs: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct ParentJob {
+        name: String,
+    }
+
+    impl BackgroundJob for ParentJob {
+        const JOB_NAME: &'static str = "parent";
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            ctx.runs.lock().unwrap().push(self.name.clone());
+            Ok(())
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct ChildJob;
+
+    impl BackgroundJob for ChildJob {
+        const JOB_NAME: &'static str = "child";
+        const PRIORITY: i16 = 10;
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            ctx.runs.lock().unwrap().push("child".to_string());
+            Ok(())
+        }
+    }
+
+    let test_context = TestContext::default();
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let runner = runner(pool, test_context.clone())
+        .register_job_type::<ParentJob>()
+        .register_job_type::<ChildJob>();
+
+    let name = "a".to_string();
+    let a = assert_some!(ParentJob { name }.enqueue(&mut conn).await?);
+    let name = "b".to_string();
+    let b = assert_some!(ParentJob { name }.enqueue(&mut conn).await?);
+
+    // Parent jobs that are no longer enqueued are assumed to have succeeded
+    let finished = 4242;
+    let child = ChildJob
+        .enqueue_with_parents(&mut conn, &[a, b, finished])
+        .await?;
+
+    let status = admin::job_status(&mut conn, child).await?;
+    assert_eq!(
+        status,
+        admin::JobStatus::Waiting {
+            parents: vec![a, b]
+        }
+    );
+
+    runner.start().wait_for_shutdown().await;
+
+    let runs = test_context.runs.lock().unwrap().clone();
+    assert_eq!(runs.len(), 3);
+    assert_eq!(runs[2], "child");
+
+    let status = admin::job_status(&mut conn, child).await?;
+    assert_eq!(status, admin::JobStatus::Finished);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_parent_jobs_fail_their_dependent_jobs() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct ParentJob;
+
+    impl BackgroundJob for ParentJob {
+        const JOB_NAME: &'static str = "parent";
+        const MAX_RETRIES: Option<u32> = Some(0);
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Err(anyhow::anyhow!("something went wrong"))
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct ChildJob;
+
+    impl BackgroundJob for ChildJob {
+        const JOB_NAME: &'static str = "child";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            panic!("Child jobs of failed jobs must not be run");
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let runner = runner(pool, ())
+        .register_job_type::<ParentJob>()
+        .register_job_type::<ChildJob>();
+
+    let parent = assert_some!(ParentJob.enqueue(&mut conn).await?);
+    let child = ChildJob.enqueue_with_parents(&mut conn, &[parent]).await?;
+    let grandchild = ChildJob.enqueue_with_parents(&mut conn, &[child]).await?;
+
+    runner.start().wait_for_shutdown().await;
+
+    assert!(all_jobs(&mut conn).await?.is_empty());
+
+    let error = format!("Parent job {parent} failed: something went wrong");
+    let status = admin::job_status(&mut conn, child).await?;
+    assert_eq!(status, admin::JobStatus::Failed { error });
+
+    let error =
+        format!("Parent job {child} failed: Parent job {parent} failed: something went wrong");
+    let status = admin::job_status(&mut conn, grandchild).await?;
+    assert_eq!(status, admin::JobStatus::Failed { error });
+
+    let result = ChildJob.enqueue_with_parents(&mut conn, &[parent]).await;
+    assert!(matches!(result, Err(EnqueueError::ParentFailed(id)) if id == parent));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn dependent_jobs_fail_whenever_their_parent_job_is_moved_to_failed_jobs(
+) -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let parent = assert_some!(TestJob.enqueue(&mut conn).await?);
+    let child = TestJob.enqueue_with_parents(&mut conn, &[parent]).await?;
+
+    // Give up on the parent job without going through the worker, e.g. from
+    // a database console
+    let failed_job = background_jobs::table.find(parent).select((
+        background_jobs::id,
+        background_jobs::job_type,
+        background_jobs::data,
+        background_jobs::retries,
+        background_jobs::priority,
+        background_jobs::created_at,
+        "gave up".into_sql::<diesel::sql_types::Text>(),
+    ));
+    diesel::insert_into(failed_background_jobs::table)
+        .values(failed_job)
+        .into_columns((
+            failed_background_jobs::id,
+            failed_background_jobs::job_type,
+            failed_background_jobs::data,
+            failed_background_jobs::retries,
+            failed_background_jobs::priority,
+            failed_background_jobs::created_at,
+            failed_background_jobs::error,
+        ))
+        .execute(&mut conn)
+        .await?;
+
+    assert!(!job_exists(child, &mut conn).await?);
+
+    let error = format!("Parent job {parent} failed: gave up");
+    let status = admin::job_status(&mut conn, child).await?;
+    assert_eq!(status, admin::JobStatus::Failed { error });
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn jobs_waiting_for_parent_jobs_do_not_deduplicate_new_jobs() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        const DEDUPLICATED: bool = true;
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let parent = assert_some!(
+        TestJob
+            .enqueue_after(&mut conn, TimeDelta::hours(1))
+            .await?
+    );
+    TestJob.enqueue_with_parents(&mut conn, &[parent]).await?;
+
+    // The job that is waiting for its parent job may never be run, so the
+    // same job has to be enqueued again
+    assert_some!(TestJob.enqueue(&mut conn).await?);
+    assert_none!(TestJob.enqueue(&mut conn).await?);
+
+    assert_eq!(all_jobs(&mut conn).await?.len(), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancelling_jobs_cancels_their_dependent_jobs() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let parent = assert_some!(TestJob.enqueue(&mut conn).await?);
+    let child = TestJob.enqueue_with_parents(&mut conn, &[parent]).await?;
+    let grandchild = TestJob.enqueue_with_parents(&mut conn, &[child]).await?;
+
+    admin::cancel_job(&mut conn, parent).await?;
+    assert!(all_jobs(&mut conn).await?.is_empty());
+
+    let status = admin::job_status(&mut conn, grandchild).await?;
+    assert_eq!(status, admin::JobStatus::Finished);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn job_runs_are_recorded_in_metrics() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob {
+        fail: bool,
+    }
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            match self.fail {
+                true => Err(anyhow::anyhow!("something went wrong")),
+                false => Ok(()),
+            }
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let registry = Registry::new();
+    let runner = runner(pool, ())
+        .register_job_type::<TestJob>()
+        .with_metrics(JobMetrics::new(&registry)?);
+
+    TestJob { fail: false }.enqueue(&mut conn).await?;
+    let job_id = assert_some!(TestJob { fail: true }.enqueue(&mut conn).await?);
+
+    runner.start().wait_for_shutdown().await;
+
+    admin::retry_now(&mut conn, job_id).await?;
+    runner.start().wait_for_shutdown().await;
+
+    let metrics = registry.gather();
+    let metric = |name: &str| {
+        let family = metrics.iter().find(|family| family.name() == name);
+        let family = family.unwrap_or_else(|| panic!("Metric {name} not found"));
+        let labels = family.get_metric()[0].get_label();
+        assert_eq!(labels[0].value(), "test");
+        assert_eq!(labels[1].value(), "default");
+        family.get_metric()[0].clone()
+    };
+
+    assert_eq!(
+        metric("jobs_succeeded_total").get_counter().get_value(),
+        1.0
+    );
+    assert_eq!(metric("jobs_failed_total").get_counter().get_value(), 2.0);
+    assert_eq!(metric("jobs_retried_total").get_counter().get_value(), 1.0);
+
+    let job_duration = metric("job_duration_seconds");
+    assert_eq!(job_duration.get_histogram().get_sample_count(), 3);
+    let queue_wait = metric("job_queue_wait_seconds");
+    assert_eq!(queue_wait.get_histogram().get_sample_count(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn trace_context_is_captured_when_enqueueing_jobs() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let job_id = assert_some!(TestJob.enqueue(&mut conn).await?);
+    let trace_context = background_jobs::table
+        .find(job_id)
+        .select(background_jobs::trace_context)
+        .get_result::<Option<String>>(&mut conn)
+        .await?;
+    assert_none!(trace_context);
+
+    let hub = Hub::current();
+    let _scope_guard = hub.push_scope();
+    let tx_ctx = sentry_core::TransactionContext::new("test", "test");
+    let tx = sentry_core::start_transaction(tx_ctx);
+    hub.configure_scope(|scope| scope.set_span(Some(tx.clone().into())));
+
+    let job_id = assert_some!(TestJob.enqueue(&mut conn).await?);
+    let trace_context = background_jobs::table
+        .find(job_id)
+        .select(background_jobs::trace_context)
+        .get_result::<Option<String>>(&mut conn)
+        .await?;
+    let trace_context = assert_some!(trace_context);
+
+    let trace_id = tx.get_trace_context().trace_id.to_string();
+    assert!(trace_context.starts_with(&trace_id));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn idle_workers_are_woken_up_by_notifications() -> anyhow::Result<()> {
+    #[derive(Clone)]
+    struct TestContext {
+        job_started: Arc<Notify>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            ctx.job_started.notify_one();
+            Ok(())
+        }
+    }
+
+    let test_context = TestContext {
+        job_started: Arc::new(Notify::new()),
+    };
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    let database_url = test_database.url().to_string();
+    let runner = Runner::new(pool, test_context.clone())
+        .configure_default_queue(|queue| queue.poll_interval(Duration::from_secs(10)))
+        .register_job_type::<TestJob>()
+        .listen_for_notifications(move || {
+            let database_url = database_url.clone();
+            async move {
+                let (client, connection) = tokio_postgres::connect(&database_url, NoTls).await?;
+                Ok(Listener::new(client, connection))
+            }
+        });
+
+    let _handle = runner.start();
+
+    // Give the worker the chance to find the queue empty, and the listener
+    // the chance to connect
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let start = Instant::now();
+    TestJob.enqueue(&mut conn).await?;
+
+    let job_started = test_context.job_started.notified();
+    tokio::time::timeout(Duration::from_secs(5), job_started).await?;
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn scheduled_jobs_are_enqueued_once_per_tick() -> anyhow::Result<()> {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn last_tick(conn: &mut AsyncPgConnection) -> QueryResult<chrono::DateTime<Utc>> {
+        background_job_schedules::table
+            .select(background_job_schedules::last_tick)
+            .get_result(conn)
+            .await
+    }
+
+    let test_database = TestDatabase::new();
+
+    let pool = pool(test_database.url())?;
+    let mut conn = pool.get().await?;
+
+    // Pretend that the last three hourly ticks were missed
+    let current_hour = Utc::now().duration_trunc(TimeDelta::hours(1))?;
+    let previous_tick = current_hour - TimeDelta::hours(3);
+    diesel::insert_into(background_job_schedules::table)
+        .values((
+            background_job_schedules::job_type.eq(TestJob::JOB_NAME),
+            background_job_schedules::last_tick.eq(previous_tick),
+        ))
+        .execute(&mut conn)
+        .await?;
+
+    // The workers are intentionally not started, so that the enqueued jobs
+    // stay in the queue.
+    let schedule = Schedule::cron("0 0 * * * *")?.missed_ticks(MissedTicks::RunAll);
+    let runners = [
+        Runner::new(pool.clone(), ()).schedule_job(TestJob, schedule.clone()),
+        Runner::new(pool.clone(), ()).schedule_job(TestJob, schedule),
+    ];
+    let handles = runners.iter().map(Runner::start).collect::<Vec<_>>();
+
+    let wait_for_tick = async {
+        while last_tick(&mut conn).await? == previous_tick {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        Ok::<_, diesel::result::Error>(())
+    };
+    tokio::time::timeout(Duration::from_secs(10), wait_for_tick).await??;
+
+    // Give both schedulers the chance to check the schedule again
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    // The next hour might have started while the test was running, so the
+    // expected number of jobs is derived from the recorded tick instead of
+    // the current time.
+    let last_tick = last_tick(&mut conn).await?;
+    assert!(last_tick >= current_hour);
+    let num_ticks = (last_tick - previous_tick).num_hours();
+
+    let jobs = all_jobs(&mut conn).await?;
+    assert_eq!(jobs.len() as i64, num_ticks);
+    assert!(jobs.iter().all(|(job_type, _)| job_type == "test"));
+
+    for handle in handles {
+        handle.wait_for_shutdown().await;
+    }
+
+    Ok(())
+}
+
 fn pool(database_url: &str) -> anyhow::Result<Pool<AsyncPgConnection>> {
     let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(database_url);
     Ok(Pool::builder(manager).max_size(4).build()?)
@@ -0,0 +1 @@
+drop table storage_integrity_issues;
@@ -0,0 +1,20 @@
+create table storage_integrity_issues
+(
+    version_id  integer     not null
+        constraint storage_integrity_issues_versions_id_fk
+            references versions
+            on delete cascade,
+    kind        integer     not null,
+    path        varchar     not null,
+    message     varchar,
+    detected_at timestamptz not null default now(),
+    constraint storage_integrity_issues_pk
+        primary key (version_id, kind)
+);
+
+comment on table storage_integrity_issues is 'Missing or corrupted files in the storage backend, as detected by the `audit_storage` background job.';
+comment on column storage_integrity_issues.version_id is 'Reference to the version in the `versions` table.';
+comment on column storage_integrity_issues.kind is 'Kind of the detected issue (0 = missing crate file, 1 = checksum mismatch, 2 = missing readme)';
+comment on column storage_integrity_issues.path is 'Path of the affected file inside the storage bucket';
+comment on column storage_integrity_issues.message is 'Optional details about the issue, e.g. the checksum that was found instead of the expected one';
+comment on column storage_integrity_issues.detected_at is 'Date and time when the issue was last detected';
@@ -0,0 +1 @@
+drop table crate_client_downloads;
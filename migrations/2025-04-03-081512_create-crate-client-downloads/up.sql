@@ -0,0 +1,18 @@
+create table crate_client_downloads
+(
+    crate_id      integer not null
+        constraint crate_client_downloads_crates_id_fk
+            references crates
+            on delete cascade,
+    date          date    not null,
+    cargo_version varchar not null,
+    downloads     bigint  not null default 0,
+    constraint crate_client_downloads_pk
+        primary key (crate_id, date, cargo_version)
+);
+
+comment on table crate_client_downloads is 'Number of downloads per crate, day and client, as derived from the `User-Agent` header of the requests in the CDN logs.';
+comment on column crate_client_downloads.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column crate_client_downloads.date is 'The date on which the downloads happened.';
+comment on column crate_client_downloads.cargo_version is 'The `major.minor` version of cargo that downloaded the crate, or an empty string if the download was not made by cargo.';
+comment on column crate_client_downloads.downloads is 'The number of downloads for this combination of crate, date and client.';
@@ -0,0 +1 @@
+drop table daily_registry_stats;
@@ -0,0 +1,15 @@
+create table daily_registry_stats
+(
+    date         date    not null
+        constraint daily_registry_stats_pk
+            primary key,
+    downloads    bigint  not null default 0,
+    new_crates   integer not null default 0,
+    new_versions integer not null default 0
+);
+
+comment on table daily_registry_stats is 'Registry-wide statistics per day, maintained by the `update_downloads` background job.';
+comment on column daily_registry_stats.date is 'The date that these statistics are for.';
+comment on column daily_registry_stats.downloads is 'The total number of downloads across all crates on this date.';
+comment on column daily_registry_stats.new_crates is 'The number of crates that were created on this date.';
+comment on column daily_registry_stats.new_versions is 'The number of versions that were published on this date.';
@@ -0,0 +1,11 @@
+create table background_job_schedules
+(
+    job_type  text        not null
+        constraint background_job_schedules_pk
+            primary key,
+    last_tick timestamptz not null
+);
+
+comment on table background_job_schedules is 'The state of the cron schedules of the background job runner.';
+comment on column background_job_schedules.job_type is 'The job type that is enqueued by this schedule.';
+comment on column background_job_schedules.last_tick is 'The most recent tick of the schedule that has been handled.';
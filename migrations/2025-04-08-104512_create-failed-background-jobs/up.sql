@@ -0,0 +1,28 @@
+create table failed_background_jobs
+(
+    id         bigint      not null
+        constraint failed_background_jobs_pk
+            primary key,
+    job_type   text        not null,
+    data       jsonb       not null,
+    retries    integer     not null,
+    priority   smallint    not null,
+    created_at timestamptz not null,
+    failed_at  timestamptz not null default now(),
+    error      text        not null,
+    backtrace  text
+);
+
+comment on table failed_background_jobs is 'Background jobs that have exhausted their retries.';
+comment on column failed_background_jobs.id is 'The ID that the job had in the `background_jobs` table.';
+comment on column failed_background_jobs.job_type is 'The type of the job.';
+comment on column failed_background_jobs.data is 'The serialized job data.';
+comment on column failed_background_jobs.retries is 'The number of times the job was retried before it was given up on.';
+comment on column failed_background_jobs.priority is 'The priority of the job.';
+comment on column failed_background_jobs.created_at is 'The time at which the job was originally enqueued.';
+comment on column failed_background_jobs.failed_at is 'The time at which the job was given up on.';
+comment on column failed_background_jobs.error is 'The error message of the last failed run.';
+comment on column failed_background_jobs.backtrace is 'The backtrace of the last failed run, if one was captured.';
+
+create index failed_background_jobs_job_type_index
+    on failed_background_jobs (job_type);
@@ -0,0 +1,8 @@
+drop table paused_background_job_queues;
+
+alter table failed_background_jobs
+    drop column queue;
+
+alter table background_jobs
+    drop column queue,
+    drop column last_error;
@@ -0,0 +1,23 @@
+alter table background_jobs
+    add column queue text not null default 'default',
+    add column last_error text;
+
+comment on column background_jobs.queue is 'The queue that the job was enqueued in.';
+comment on column background_jobs.last_error is 'The error message of the last failed run, if any.';
+
+alter table failed_background_jobs
+    add column queue text not null default 'default';
+
+comment on column failed_background_jobs.queue is 'The queue that the job was enqueued in.';
+
+create table paused_background_job_queues
+(
+    queue     text        not null
+        constraint paused_background_job_queues_pk
+            primary key,
+    paused_at timestamptz not null default now()
+);
+
+comment on table paused_background_job_queues is 'Background job queues that are paused. The job runner does not pick up jobs from paused queues.';
+comment on column paused_background_job_queues.queue is 'The name of the paused queue.';
+comment on column paused_background_job_queues.paused_at is 'The time at which the queue was paused.';
@@ -0,0 +1 @@
+DROP FUNCTION IF EXISTS notify_background_job_queue CASCADE;
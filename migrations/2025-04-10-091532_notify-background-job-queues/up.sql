@@ -0,0 +1,14 @@
+CREATE OR REPLACE FUNCTION notify_background_job_queue() RETURNS TRIGGER AS $$
+BEGIN
+    -- Wake up the idle workers of the queue. The notification is only
+    -- delivered once the surrounding transaction is committed.
+    PERFORM pg_notify('background_jobs.' || NEW.queue, '');
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_notify_background_job_queue ON background_jobs;
+CREATE TRIGGER trigger_notify_background_job_queue
+     AFTER INSERT ON background_jobs
+     FOR EACH ROW
+     EXECUTE PROCEDURE notify_background_job_queue();
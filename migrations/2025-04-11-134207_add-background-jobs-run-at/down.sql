@@ -0,0 +1,8 @@
+DROP TRIGGER IF EXISTS trigger_notify_background_job_queue ON background_jobs;
+CREATE TRIGGER trigger_notify_background_job_queue
+     AFTER INSERT ON background_jobs
+     FOR EACH ROW
+     EXECUTE PROCEDURE notify_background_job_queue();
+
+alter table background_jobs
+    drop column run_at;
@@ -0,0 +1,12 @@
+alter table background_jobs
+    add column run_at timestamptz not null default now();
+
+comment on column background_jobs.run_at is 'The earliest time at which the job may be run.';
+
+-- Delayed jobs can't be run yet, so there is no need to wake up the workers.
+DROP TRIGGER IF EXISTS trigger_notify_background_job_queue ON background_jobs;
+CREATE TRIGGER trigger_notify_background_job_queue
+     AFTER INSERT ON background_jobs
+     FOR EACH ROW
+     WHEN (NEW.run_at <= now())
+     EXECUTE PROCEDURE notify_background_job_queue();
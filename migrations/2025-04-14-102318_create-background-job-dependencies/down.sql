@@ -0,0 +1,6 @@
+drop trigger trigger_fail_background_job_dependents on failed_background_jobs;
+drop function fail_background_job_dependents();
+
+drop table background_job_dependencies;
+
+drop function notify_background_job_dependents();
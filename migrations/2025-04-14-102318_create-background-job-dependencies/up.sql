@@ -0,0 +1,63 @@
+create table background_job_dependencies
+(
+    job_id    bigint not null
+        constraint background_job_dependencies_job_id_fk
+            references background_jobs
+            on delete cascade,
+    parent_id bigint not null
+        constraint background_job_dependencies_parent_id_fk
+            references background_jobs
+            on delete cascade,
+    constraint background_job_dependencies_pk
+        primary key (job_id, parent_id)
+);
+
+comment on table background_job_dependencies is 'Dependencies between background jobs. A job is not run until all of its parent jobs have succeeded and their rows have been deleted.';
+comment on column background_job_dependencies.job_id is 'The job that is waiting for its parent job.';
+comment on column background_job_dependencies.parent_id is 'The parent job that has to succeed before the job is run.';
+
+create index background_job_dependencies_parent_id_index
+    on background_job_dependencies (parent_id);
+
+CREATE OR REPLACE FUNCTION notify_background_job_dependents() RETURNS TRIGGER AS $$
+BEGIN
+    -- Wake up the idle workers of the queue of a job once its last parent
+    -- job has succeeded.
+    PERFORM pg_notify('background_jobs.' || queue, '')
+       FROM background_jobs
+      WHERE id = OLD.job_id
+        AND run_at <= now()
+        AND NOT EXISTS (SELECT 1 FROM background_job_dependencies WHERE job_id = OLD.job_id);
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_notify_background_job_dependents
+     AFTER DELETE ON background_job_dependencies
+     FOR EACH ROW
+     EXECUTE PROCEDURE notify_background_job_dependents();
+
+CREATE OR REPLACE FUNCTION fail_background_job_dependents() RETURNS TRIGGER AS $$
+BEGIN
+    -- Jobs that depend on a failed job can never be run, so they are moved
+    -- to the `failed_background_jobs` table as well. This trigger fires for
+    -- the moved jobs again, which fails their own dependents.
+    INSERT INTO failed_background_jobs (id, job_type, data, retries, priority, queue, created_at, error)
+         SELECT background_jobs.id, job_type, data, retries, priority, queue, created_at,
+                'Parent job ' || NEW.id || ' failed: ' || NEW.error
+           FROM background_jobs
+           JOIN background_job_dependencies ON background_job_dependencies.job_id = background_jobs.id
+          WHERE background_job_dependencies.parent_id = NEW.id
+    ON CONFLICT DO NOTHING;
+
+    DELETE FROM background_jobs
+     WHERE id IN (SELECT job_id FROM background_job_dependencies WHERE parent_id = NEW.id);
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_fail_background_job_dependents
+     AFTER INSERT ON failed_background_jobs
+     FOR EACH ROW
+     EXECUTE PROCEDURE fail_background_job_dependents();
@@ -0,0 +1,4 @@
+alter table background_jobs
+    add column trace_context text;
+
+comment on column background_jobs.trace_context is 'The `sentry-trace` header of the trace in which the job was enqueued, if any. The job is run as part of the same trace.';
@@ -0,0 +1 @@
+drop table publish_rate_override_actions;
@@ -0,0 +1,32 @@
+create table publish_rate_override_actions
+(
+    id         serial
+        constraint publish_rate_override_actions_pk
+            primary key,
+    user_id    integer
+        constraint publish_rate_override_actions_user_id_fk
+            references users
+            on delete set null,
+    action     integer     not null,
+    burst      integer     not null,
+    expires_at timestamptz,
+    admin_id   integer
+        constraint publish_rate_override_actions_admin_id_fk
+            references users
+            on delete set null,
+    reason     text        not null,
+    created_at timestamptz not null default now()
+);
+
+comment on table publish_rate_override_actions is 'Audit log of the changes to the `publish_rate_overrides` table by crates.io admins.';
+comment on column publish_rate_override_actions.id is 'Unique identifier of the change.';
+comment on column publish_rate_override_actions.user_id is 'The user whose rate limit was overridden. `NULL` if the user was deleted, so that the audit log is kept.';
+comment on column publish_rate_override_actions.action is 'The rate limited action, see `LimitedAction`.';
+comment on column publish_rate_override_actions.burst is 'The burst of the override after the change.';
+comment on column publish_rate_override_actions.expires_at is 'The expiry of the override after the change. Expiring an override sets it to the time of the change.';
+comment on column publish_rate_override_actions.admin_id is 'The admin who made the change. `NULL` if the admin was deleted.';
+comment on column publish_rate_override_actions.reason is 'The reason for the change, as given by the admin.';
+comment on column publish_rate_override_actions.created_at is 'Date and time when the change was made.';
+
+create index publish_rate_override_actions_user_id_index
+    on publish_rate_override_actions (user_id);
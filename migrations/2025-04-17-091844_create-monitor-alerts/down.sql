@@ -0,0 +1 @@
+drop table monitor_alerts;
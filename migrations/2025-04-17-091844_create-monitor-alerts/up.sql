@@ -0,0 +1,11 @@
+create table monitor_alerts
+(
+    key          text        not null
+        constraint monitor_alerts_pk
+            primary key,
+    triggered_at timestamptz not null default now()
+);
+
+comment on table monitor_alerts is 'The alerts of the monitor that are currently triggered. Used by alert sinks that can''t deduplicate alerts themselves.';
+comment on column monitor_alerts.key is 'The key of the check that triggered the alert.';
+comment on column monitor_alerts.triggered_at is 'The time at which the alert was first triggered.';
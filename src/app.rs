@@ -2,10 +2,12 @@
 
 use crate::config;
 use crate::db::{ConnectionConfig, connection_url, make_manager_config};
+use crate::downloads_archive::DownloadsArchive;
 use std::sync::Arc;
 
 use crate::email::Emails;
 use crate::metrics::{InstanceMetrics, ServiceMetrics};
+use crate::middleware::read_rate_limit::ReadRateLimiter;
 use crate::rate_limiter::RateLimiter;
 use crate::storage::Storage;
 use axum::extract::{FromRef, FromRequestParts, State};
@@ -48,6 +50,9 @@ pub struct App {
     /// Storage backend for crate files and other large objects.
     pub storage: Arc<Storage>,
 
+    /// Cached read access to the archived `version_downloads` files.
+    pub downloads_archive: DownloadsArchive,
+
     /// Metrics related to the service as a whole
     pub service_metrics: ServiceMetrics,
 
@@ -56,6 +61,9 @@ pub struct App {
 
     /// Rate limit select actions.
     pub rate_limiter: RateLimiter,
+
+    /// Rate limit the read-only API endpoints.
+    pub read_rate_limiter: ReadRateLimiter,
 }
 
 impl App {
@@ -128,16 +136,22 @@ impl App {
             None
         };
 
+        let storage = Arc::new(Storage::from_config(&config.storage));
+        let downloads_archive =
+            DownloadsArchive::new(storage.clone(), config.downloads_archive_cache_max_capacity);
+
         App {
             primary_database,
             replica_database,
             github,
             github_oauth,
             emails,
-            storage: Arc::new(Storage::from_config(&config.storage)),
+            storage,
+            downloads_archive,
             service_metrics: ServiceMetrics::new().expect("could not initialize service metrics"),
             instance_metrics,
             rate_limiter: RateLimiter::new(config.rate_limiter.clone()),
+            read_rate_limiter: ReadRateLimiter::new(config.read_rate_limits.clone()),
             config: Arc::new(config),
         }
     }
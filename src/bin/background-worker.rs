@@ -1,11 +1,21 @@
 //! Runs enqueued background jobs
 //!
 //! This binary will loop until interrupted. It will run all jobs in the
-//! background queue, sleeping for 1 second whenever the queue is empty. If we
-//! are unable to spawn workers to run jobs (either because we couldn't connect
-//! to the DB, an error occurred while loading, or we just never heard back from
-//! the worker thread), we will rebuild the runner and try again up to 5 times.
-//! After the 5th occurrence, we will panic.
+//! background queue. Whenever the queue is empty, the workers wait until they
+//! are notified about new jobs via PostgreSQL `LISTEN`/`NOTIFY`, or until they
+//! poll the queue again after 10 seconds. If we are unable to spawn workers to
+//! run jobs (either because we couldn't connect to the DB, an error occurred
+//! while loading, or we just never heard back from the worker thread), we will
+//! rebuild the runner and try again up to 5 times. After the 5th occurrence,
+//! we will panic.
+//!
+//! If the `SCHEDULE_PERIODIC_JOBS` environment variable is set to `true`, the
+//! periodic jobs (e.g. `update_downloads` and `dump_db`) are enqueued by the
+//! runner itself, instead of by an external scheduler.
+//!
+//! If the `WORKER_METRICS_PORT` environment variable is set, the job metrics
+//! of this process are served on `GET /metrics` on that port, protected by the
+//! `METRICS_AUTHORIZATION_TOKEN`.
 //!
 //! Usage:
 //!      cargo run --bin background-worker
@@ -14,26 +24,32 @@
 extern crate tracing;
 
 use anyhow::Context;
+use crates_io::cdn::PurgeBatcher;
 use crates_io::cloudfront::CloudFront;
 use crates_io::db::make_manager_config;
 use crates_io::fastly::Fastly;
 use crates_io::storage::Storage;
-use crates_io::worker::{Environment, RunnerExt};
+use crates_io::worker::{DOWNLOADS_QUEUE, Environment, REPOSITORY_QUEUE, RunnerExt, serve_metrics};
 use crates_io::{Emails, config};
 use crates_io::{db, ssh};
-use crates_io_env_vars::var;
+use crates_io_env_vars::{var, var_parsed};
 use crates_io_index::RepositoryConfig;
 use crates_io_team_repo::TeamRepoImpl;
-use crates_io_worker::Runner;
+use crates_io_worker::{JobMetrics, Runner};
 use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use diesel_async::pooled_connection::deadpool::Pool;
 use object_store::prefix::PrefixStore;
+use prometheus::Registry;
 use reqwest::Client;
 use secrecy::ExposeSecret;
 use std::sync::Arc;
 use std::thread::sleep;
 use std::time::Duration;
 
+/// The interval after which idle workers poll for new jobs, in case a
+/// notification about new jobs was missed.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
 fn main() -> anyhow::Result<()> {
     let _sentry = crates_io::sentry::init();
 
@@ -69,7 +85,8 @@ fn main() -> anyhow::Result<()> {
 
     let repository_config = RepositoryConfig::from_environment()?;
 
-    let cloudfront = CloudFront::from_environment();
+    let cloudfront =
+        CloudFront::from_environment().map(|cloudfront| PurgeBatcher::new(Arc::new(cloudfront)));
     let storage = Arc::new(Storage::from_config(&config.storage));
 
     let downloads_archive_store = PrefixStore::new(storage.as_inner(), "archive/version-downloads");
@@ -81,11 +98,12 @@ fn main() -> anyhow::Result<()> {
         .expect("Couldn't build client");
 
     let emails = Emails::from_environment(&config);
-    let fastly = Fastly::from_environment(client.clone());
+    let fastly =
+        Fastly::from_environment(client.clone())?.map(|fastly| PurgeBatcher::new(Arc::new(fastly)));
     let team_repo = TeamRepoImpl::default();
 
     let manager_config = make_manager_config(config.db.enforce_tls);
-    let manager = AsyncDieselConnectionManager::new_with_config(db_url, manager_config);
+    let manager = AsyncDieselConnectionManager::new_with_config(&db_url, manager_config);
     let deadpool = Pool::builder(manager).max_size(10).build()?;
 
     let environment = Environment::builder()
@@ -100,6 +118,8 @@ fn main() -> anyhow::Result<()> {
         .team_repo(Box::new(team_repo))
         .build();
 
+    let enforce_tls = environment.config.db.enforce_tls;
+    let metrics_authorization_token = environment.config.metrics_authorization_token.clone();
     let environment = Arc::new(environment);
 
     std::thread::spawn({
@@ -111,13 +131,47 @@ fn main() -> anyhow::Result<()> {
         }
     });
 
+    let metrics_registry = Registry::new_custom(Some("cratesio_worker".into()), None)?;
+    let metrics = JobMetrics::new(&metrics_registry)?;
+
     let runner = Runner::new(deadpool, environment.clone())
-        .configure_default_queue(|queue| queue.num_workers(5))
-        .configure_queue("downloads", |queue| queue.num_workers(1))
-        .configure_queue("repository", |queue| queue.num_workers(1))
-        .register_crates_io_job_types();
+        .configure_default_queue(|queue| queue.num_workers(5).poll_interval(POLL_INTERVAL))
+        .configure_queue(DOWNLOADS_QUEUE, |queue| {
+            queue.num_workers(1).poll_interval(POLL_INTERVAL)
+        })
+        .configure_queue(REPOSITORY_QUEUE, |queue| {
+            queue.num_workers(1).poll_interval(POLL_INTERVAL)
+        })
+        .register_crates_io_job_types()
+        .with_metrics(metrics)
+        .listen_for_notifications(move || {
+            let db_url = db_url.clone();
+            async move { db::establish_listener_connection(&db_url, enforce_tls).await }
+        });
+
+    let runner = if var_parsed("SCHEDULE_PERIODIC_JOBS")?.unwrap_or(false) {
+        runner.schedule_crates_io_jobs()?
+    } else {
+        runner
+    };
+
+    let metrics_port = var_parsed::<u16>("WORKER_METRICS_PORT")?;
 
     runtime.block_on(async {
+        match (metrics_port, metrics_authorization_token) {
+            (Some(port), Some(token)) => {
+                tokio::spawn(async move {
+                    if let Err(error) = serve_metrics(metrics_registry, token, port).await {
+                        warn!("Failed to serve worker metrics: {error}");
+                    }
+                });
+            }
+            (Some(_), None) => {
+                warn!("Not serving worker metrics, since METRICS_AUTHORIZATION_TOKEN is not set");
+            }
+            (None, _) => {}
+        }
+
         let handle = runner.start();
 
         info!("Runner booted, running jobs");
@@ -0,0 +1,70 @@
+use anyhow::Context;
+use crates_io::db;
+use crates_io::storage::Storage;
+use crates_io::worker::jobs::audit_storage::audit;
+
+/// Number of versions that are checked per database round-trip.
+const BATCH_SIZE: i64 = 1000;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "audit-storage",
+    about = "Verify that all crate files and READMEs exist in the storage backend \
+        and that the crate files match their checksums.",
+    long_about = "Verify that all crate files and READMEs exist in the storage backend \
+        and that the crate files match their checksums. Detected issues are recorded \
+        in the `storage_integrity_issues` table. Use `enqueue-job audit_storage` to run \
+        the audit as a background job instead."
+)]
+pub struct Opts {
+    /// Only check versions with an ID greater than this value. This can be
+    /// used to resume an interrupted audit.
+    #[arg(long, default_value_t = 0)]
+    after: i32,
+
+    /// Maximum number of versions to check.
+    #[arg(long)]
+    limit: Option<i64>,
+}
+
+pub async fn run(opts: Opts) -> anyhow::Result<()> {
+    let storage = Storage::from_environment();
+
+    let mut conn = db::oneoff_connection()
+        .await
+        .context("Failed to connect to the database")?;
+
+    let mut after = opts.after;
+    let mut remaining = opts.limit.unwrap_or(i64::MAX);
+    let mut num_checked = 0;
+    let mut num_issues = 0;
+
+    while remaining > 0 {
+        let limit = remaining.min(BATCH_SIZE);
+        let summary = audit(&storage, &mut conn, after, limit).await?;
+
+        for issue in &summary.issues {
+            println!("{issue:?}");
+        }
+
+        num_checked += summary.num_checked;
+        num_issues += summary.issues.len();
+        remaining -= summary.num_checked as i64;
+
+        let Some(last_version_id) = summary.last_version_id else {
+            break;
+        };
+
+        after = last_version_id;
+        println!("checked {num_checked} versions so far (last version ID: {after})");
+
+        if (summary.num_checked as i64) < limit {
+            break;
+        }
+    }
+
+    println!("checked {num_checked} versions and found {num_issues} issues");
+    println!("use `audit-storage --after {after}` to continue from here");
+
+    Ok(())
+}
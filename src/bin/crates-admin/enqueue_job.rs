@@ -20,6 +20,11 @@ pub enum Command {
         /// The date before which to archive version downloads (default: 90 days ago)
         before: Option<NaiveDate>,
     },
+    AuditStorage {
+        /// Only check versions with an ID greater than this value
+        #[arg(long, default_value_t = 0)]
+        after: i32,
+    },
     IndexVersionDownloadsArchive,
     UpdateDownloads,
     CleanProcessedLogFiles,
@@ -63,6 +68,9 @@ pub async fn run(command: Command) -> Result<()> {
                 .enqueue(&mut conn)
                 .await?;
         }
+        Command::AuditStorage { after } => {
+            jobs::AuditStorage::after(after).enqueue(&mut conn).await?;
+        }
         Command::IndexVersionDownloadsArchive => {
             jobs::IndexVersionDownloadsArchive
                 .enqueue(&mut conn)
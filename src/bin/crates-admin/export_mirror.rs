@@ -0,0 +1,661 @@
+use anyhow::{Context, anyhow, bail};
+use crates_io::db;
+use crates_io::index::get_index_data;
+use crates_io::schema::crates;
+use crates_io::storage::Storage;
+use crates_io::tasks::spawn_blocking;
+use crates_io_index::Repository;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use futures_util::{StreamExt, TryStreamExt};
+use hex::ToHex;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Maximum number of crate files that are downloaded concurrently.
+const MAX_CONCURRENCY: usize = 10;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "export-mirror",
+    about = "Export a subset of the registry into a self-contained mirror.",
+    long_about = "Export a subset of the registry into a self-contained mirror. \
+        The output contains the sparse index files, a `config.json` file and \
+        the `.crate` files of all selected versions, and can be used by cargo \
+        either as a `local-registry` or, when served over HTTP, as a sparse \
+        registry."
+)]
+pub struct Opts {
+    /// Names of the crates to export. `*` and `?` can be used as wildcards.
+    #[arg(required_unless_present = "lockfile", conflicts_with = "lockfile")]
+    patterns: Vec<String>,
+
+    /// Export only the crate versions that are referenced by this
+    /// `Cargo.lock` file.
+    #[arg(long)]
+    lockfile: Option<PathBuf>,
+
+    /// Output directory. If the path ends with `.tar.gz` or `.tgz` a
+    /// tarball is created instead.
+    #[arg(long, short)]
+    output: PathBuf,
+
+    /// URL that the mirror will be served from. This is used for the `dl`
+    /// field of the generated `config.json` file and defaults to a `file://`
+    /// URL pointing at the output directory. Required when creating a
+    /// tarball, since the location it will be extracted to is unknown.
+    #[arg(long)]
+    base_url: Option<String>,
+}
+
+pub async fn run(opts: Opts) -> anyhow::Result<()> {
+    let storage = Storage::from_environment();
+
+    let base_url = resolve_base_url(&opts.output, opts.base_url)?;
+
+    let mut conn = db::oneoff_connection()
+        .await
+        .context("Failed to connect to the database")?;
+
+    let selection = match &opts.lockfile {
+        Some(path) => {
+            let contents = tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+
+            Selection::Lockfile(parse_lockfile(&contents)?)
+        }
+        None => Selection::Patterns(opts.patterns.clone()),
+    };
+
+    let tarball_prefix = tarball_prefix(&opts.output);
+    let (_tempdir, export_dir) = match tarball_prefix {
+        Some(_) => {
+            let tempdir = tempfile::tempdir().context("Failed to create temporary directory")?;
+            let path = tempdir.path().to_path_buf();
+            (Some(tempdir), path)
+        }
+        None => (None, opts.output.clone()),
+    };
+
+    let summary = export(&storage, &mut conn, &selection, &export_dir, &base_url).await?;
+
+    if let Some(prefix) = tarball_prefix {
+        println!("creating tarball {}", opts.output.display());
+        let output = opts.output.clone();
+        spawn_blocking(move || create_tarball(&export_dir, &prefix, &output)).await??;
+    }
+
+    println!(
+        "exported {} versions of {} crates to {}",
+        summary.num_versions,
+        summary.num_crates,
+        opts.output.display()
+    );
+
+    Ok(())
+}
+
+/// The crates and versions that should be exported.
+#[derive(Debug)]
+enum Selection {
+    /// All versions of the crates matching any of the patterns.
+    Patterns(Vec<String>),
+    /// Only the listed versions, keyed by crate name.
+    Lockfile(BTreeMap<String, BTreeSet<String>>),
+}
+
+impl Selection {
+    async fn crate_names(&self, conn: &mut AsyncPgConnection) -> anyhow::Result<Vec<String>> {
+        match self {
+            Selection::Patterns(patterns) => {
+                let patterns = patterns.iter().map(|p| pattern_to_like(p));
+
+                let mut query = crates::table.select(crates::name).into_boxed();
+                for pattern in patterns {
+                    query = query.or_filter(crates::name.ilike(pattern));
+                }
+
+                let names = query
+                    .order(crates::name)
+                    .load(conn)
+                    .await
+                    .context("Failed to load crate names")?;
+
+                Ok(names)
+            }
+            Selection::Lockfile(packages) => Ok(packages.keys().cloned().collect()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ExportSummary {
+    num_crates: usize,
+    num_versions: usize,
+}
+
+/// A `.crate` file that should be downloaded into the mirror.
+#[derive(Debug)]
+struct CrateFile {
+    name: String,
+    version: String,
+    checksum: String,
+}
+
+async fn export(
+    storage: &Storage,
+    conn: &mut AsyncPgConnection,
+    selection: &Selection,
+    export_dir: &Path,
+    base_url: &str,
+) -> anyhow::Result<ExportSummary> {
+    let index_dir = export_dir.join("index");
+    tokio::fs::create_dir_all(&index_dir)
+        .await
+        .with_context(|| format!("Failed to create {}", index_dir.display()))?;
+
+    let config = index_config(base_url);
+    tokio::fs::write(index_dir.join("config.json"), config)
+        .await
+        .context("Failed to write config.json")?;
+
+    let crate_names = selection.crate_names(conn).await?;
+    if crate_names.is_empty() {
+        bail!("No crates matched the selection");
+    }
+
+    println!("exporting index files for {} crates", crate_names.len());
+
+    let mut summary = ExportSummary::default();
+    let mut files = Vec::new();
+    for name in &crate_names {
+        let Some(index_data) = get_index_data(name, conn).await? else {
+            bail!("Crate `{name}` does not exist or has no versions");
+        };
+
+        let mut entries = parse_index_data(&index_data)?;
+        if let Selection::Lockfile(packages) = selection {
+            let versions = &packages[name];
+            entries.retain(|entry| versions.contains(&entry.vers));
+
+            for version in versions {
+                if !entries.iter().any(|entry| &entry.vers == version) {
+                    bail!("Version {version} of crate `{name}` does not exist");
+                }
+            }
+        }
+
+        let mut bytes = Vec::new();
+        crates_io_index::write_crates(&entries, &mut bytes)?;
+
+        let path = index_dir.join(Repository::relative_index_file(name));
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, bytes)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        summary.num_crates += 1;
+        summary.num_versions += entries.len();
+
+        files.extend(entries.into_iter().map(|entry| CrateFile {
+            name: entry.name,
+            version: entry.vers,
+            checksum: entry.cksum,
+        }));
+    }
+
+    println!("downloading {} crate files", files.len());
+
+    let pb = ProgressBar::new(files.len() as u64);
+    pb.set_style(ProgressStyle::with_template(
+        "{bar:60} ({pos}/{len}, ETA {eta})",
+    )?);
+
+    futures_util::stream::iter(files)
+        .map(|file| download_crate_file(storage, export_dir, file))
+        .buffer_unordered(MAX_CONCURRENCY)
+        .inspect_ok(|_| pb.inc(1))
+        .try_collect::<()>()
+        .await?;
+
+    pb.finish_and_clear();
+
+    Ok(summary)
+}
+
+/// Download a single crate file into the export directory and verify that
+/// its checksum matches the index.
+///
+/// Files that already exist with the expected checksum are skipped, which
+/// allows interrupted exports to be resumed.
+async fn download_crate_file(
+    storage: &Storage,
+    export_dir: &Path,
+    file: CrateFile,
+) -> anyhow::Result<()> {
+    let CrateFile {
+        ref name,
+        ref version,
+        ref checksum,
+    } = file;
+
+    let path = export_dir.join(format!("{name}-{version}.crate"));
+    if let Ok(existing) = tokio::fs::read(&path).await {
+        if sha256_hex(&existing) == *checksum {
+            return Ok(());
+        }
+    }
+
+    let bytes = storage
+        .download_crate_file(name, version)
+        .await
+        .with_context(|| format!("Failed to download {name}@{version}"))?;
+
+    let actual_checksum = sha256_hex(&bytes);
+    if actual_checksum != *checksum {
+        bail!(
+            "Checksum mismatch for {name}@{version}: expected {checksum}, found {actual_checksum}"
+        );
+    }
+
+    tokio::fs::write(&path, bytes)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).encode_hex()
+}
+
+/// Generate the `config.json` file of the mirror index.
+///
+/// The `.crate` files are stored next to the `index` directory using the
+/// `{crate}-{version}.crate` naming scheme that is also expected by cargo's
+/// `local-registry` sources.
+fn index_config(base_url: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let config = serde_json::json!({
+        "dl": format!("{base_url}/{{crate}}-{{version}}.crate"),
+    });
+
+    serde_json::to_string_pretty(&config).expect("JSON serialization should not fail")
+}
+
+/// Returns the URL that the mirror will be served from.
+///
+/// Directories are served from their own location by default, but tarballs
+/// need an explicit URL, since the `.crate` files are only available after
+/// the tarball was extracted somewhere.
+fn resolve_base_url(output: &Path, base_url: Option<String>) -> anyhow::Result<String> {
+    if let Some(base_url) = base_url {
+        return Ok(base_url);
+    }
+
+    if tarball_prefix(output).is_some() {
+        bail!("`--base-url` is required when exporting a tarball");
+    }
+
+    let output = std::path::absolute(output)?;
+    Ok(format!("file://{}", output.display()))
+}
+
+/// Parse the newline-delimited JSON of an index file.
+fn parse_index_data(index_data: &str) -> anyhow::Result<Vec<crates_io_index::Crate>> {
+    index_data
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_str(line).context("Failed to parse index data"))
+        .collect()
+}
+
+/// Convert a crate name pattern with `*` and `?` wildcards into a SQL `LIKE`
+/// pattern.
+fn pattern_to_like(pattern: &str) -> String {
+    let mut like = String::with_capacity(pattern.len());
+    for c in pattern.chars() {
+        match c {
+            '*' => like.push('%'),
+            '?' => like.push('_'),
+            '%' | '_' | '\\' => {
+                like.push('\\');
+                like.push(c);
+            }
+            c => like.push(c),
+        }
+    }
+    like
+}
+
+#[derive(Debug, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockfilePackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockfilePackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+}
+
+/// Source IDs that refer to the crates.io registry in `Cargo.lock` files.
+const CRATES_IO_SOURCES: &[&str] = &[
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+/// Parse a `Cargo.lock` file and return the crates.io packages it references.
+///
+/// Path dependencies are ignored, since they are part of the project itself.
+/// Git dependencies and packages from other registries are skipped with a
+/// warning, since they are not available from crates.io.
+fn parse_lockfile(contents: &str) -> anyhow::Result<BTreeMap<String, BTreeSet<String>>> {
+    let lockfile: Lockfile = toml::from_str(contents).context("Failed to parse Cargo.lock")?;
+
+    let mut packages: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for package in lockfile.package {
+        let Some(source) = package.source else {
+            continue;
+        };
+
+        if !CRATES_IO_SOURCES.contains(&source.as_str()) {
+            let LockfilePackage { name, version, .. } = package;
+            warn!("Skipping {name}@{version}, which is not from crates.io: {source}");
+            continue;
+        }
+
+        packages
+            .entry(package.name)
+            .or_default()
+            .insert(package.version);
+    }
+
+    if packages.is_empty() {
+        return Err(anyhow!(
+            "Cargo.lock does not contain any crates.io packages"
+        ));
+    }
+
+    Ok(packages)
+}
+
+/// Returns the name of the top-level directory in the tarball, or `None` if
+/// the output path is not a tarball.
+fn tarball_prefix(output: &Path) -> Option<PathBuf> {
+    let file_name = output.file_name()?.to_str()?;
+    let prefix = file_name
+        .strip_suffix(".tar.gz")
+        .or_else(|| file_name.strip_suffix(".tgz"))?;
+
+    Some(PathBuf::from(prefix))
+}
+
+fn create_tarball(export_dir: &Path, prefix: &Path, output: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::create(output)
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+
+    let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut tar = tar::Builder::new(encoder);
+    tar.append_dir_all(prefix, export_dir)?;
+    tar.into_inner()?.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crates_io::models::{NewCrate, NewUser, NewVersion};
+    use crates_io::storage::StorageConfig;
+    use crates_io_tarball::TarballBuilder;
+    use crates_io_test_db::TestDatabase;
+    use insta::assert_snapshot;
+
+    async fn publish(
+        conn: &mut AsyncPgConnection,
+        storage: &Storage,
+        user_id: i32,
+        name: &str,
+        versions: &[&str],
+    ) {
+        let new_crate = NewCrate {
+            name,
+            ..Default::default()
+        };
+        let krate = new_crate.create(conn, user_id).await.unwrap();
+
+        for num in versions {
+            let manifest = format!("[package]\nname = \"{name}\"\nversion = \"{num}\"\n");
+            let bytes = TarballBuilder::new()
+                .add_file(&format!("{name}-{num}/Cargo.toml"), manifest.as_bytes())
+                .build();
+
+            NewVersion::builder(krate.id, num)
+                .published_by(user_id)
+                .checksum(&sha256_hex(&bytes))
+                .build()
+                .save(conn, "foo@example.com")
+                .await
+                .unwrap();
+
+            storage
+                .upload_crate_file(name, num, bytes.into())
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Returns the paths of all files in the directory, relative to it.
+    fn list_files(root: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+        let mut dirs = vec![root.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    let path = path.strip_prefix(root).unwrap();
+                    files.push(path.display().to_string());
+                }
+            }
+        }
+
+        files.sort();
+        files
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+        let storage = Storage::from_config(&StorageConfig::in_memory());
+
+        let user = NewUser::builder()
+            .gh_id(42)
+            .gh_login("foo")
+            .gh_access_token("token")
+            .build()
+            .insert(&mut conn)
+            .await
+            .unwrap();
+
+        publish(&mut conn, &storage, user.id, "foo", &["1.0.0", "1.1.0"]).await;
+        publish(&mut conn, &storage, user.id, "foo-bar", &["0.1.0"]).await;
+        publish(&mut conn, &storage, user.id, "bar", &["2.0.0"]).await;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let export_dir = tempdir.path().join("mirror");
+        let base_url = resolve_base_url(&export_dir, None).unwrap();
+        let selection = Selection::Patterns(vec!["foo*".into()]);
+
+        let summary = export(&storage, &mut conn, &selection, &export_dir, &base_url)
+            .await
+            .unwrap();
+        assert_eq!(summary.num_crates, 2);
+        assert_eq!(summary.num_versions, 3);
+
+        assert_snapshot!(list_files(&export_dir).join("\n"), @r"
+        foo-1.0.0.crate
+        foo-1.1.0.crate
+        foo-bar-0.1.0.crate
+        index/3/f/foo
+        index/config.json
+        index/fo/o-/foo-bar
+        ");
+
+        // The `dl` template of the `config.json` file has to point at the
+        // exported `.crate` files.
+        let config = std::fs::read_to_string(export_dir.join("index/config.json")).unwrap();
+        let config: serde_json::Value = serde_json::from_str(&config).unwrap();
+        let dl = config["dl"].as_str().unwrap();
+        for (name, num) in [("foo", "1.0.0"), ("foo", "1.1.0"), ("foo-bar", "0.1.0")] {
+            let url = dl.replace("{crate}", name).replace("{version}", num);
+            let path = url.strip_prefix("file://").unwrap();
+            let bytes = std::fs::read(path).unwrap();
+            let expected = storage.download_crate_file(name, num).await.unwrap();
+            assert_eq!(bytes, expected);
+        }
+
+        // Tarballs contain the same files in a single top-level directory
+        let output = tempdir.path().join("mirror.tar.gz");
+        create_tarball(&export_dir, Path::new("mirror"), &output).unwrap();
+
+        let extract_dir = tempdir.path().join("extracted");
+        let file = std::fs::File::open(&output).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        archive.unpack(&extract_dir).unwrap();
+
+        let files = list_files(&extract_dir.join("mirror"));
+        assert_eq!(files, list_files(&export_dir));
+    }
+
+    #[test]
+    fn test_pattern_to_like() {
+        assert_eq!(pattern_to_like("serde"), "serde");
+        assert_eq!(pattern_to_like("serde*"), "serde%");
+        assert_eq!(pattern_to_like("tokio-?"), "tokio-_");
+        assert_eq!(pattern_to_like("serde_json"), "serde\\_json");
+        assert_eq!(pattern_to_like("100%"), "100\\%");
+    }
+
+    #[test]
+    fn test_parse_lockfile() {
+        let lockfile = r#"
+version = 4
+
+[[package]]
+name = "foo"
+version = "0.1.0"
+
+[[package]]
+name = "serde"
+version = "1.0.219"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "5f0e2c6ed6606019b4e29e69dbaba95b11854410e5347d525002456dbbb786b6"
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "2.0.100"
+source = "sparse+https://index.crates.io/"
+
+[[package]]
+name = "bar"
+version = "0.2.0"
+source = "git+https://github.com/foo/bar#0123456789abcdef"
+
+[[package]]
+name = "baz"
+version = "0.3.0"
+source = "sparse+https://registry.example.com/index/"
+"#;
+
+        let packages = parse_lockfile(lockfile).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages["serde"], BTreeSet::from(["1.0.219".into()]));
+        assert_eq!(
+            packages["syn"],
+            BTreeSet::from(["1.0.109".into(), "2.0.100".into()])
+        );
+    }
+
+    #[test]
+    fn test_parse_lockfile_without_registry_packages() {
+        let lockfile = r#"
+[[package]]
+name = "foo"
+version = "0.1.0"
+"#;
+
+        assert!(parse_lockfile(lockfile).is_err());
+    }
+
+    #[test]
+    fn test_index_config() {
+        assert_snapshot!(index_config("https://mirror.example.com/"), @r#"
+        {
+          "dl": "https://mirror.example.com/{crate}-{version}.crate"
+        }
+        "#);
+    }
+
+    #[test]
+    fn test_tarball_prefix() {
+        assert_eq!(
+            tarball_prefix(Path::new("/tmp/mirror.tar.gz")),
+            Some(PathBuf::from("mirror"))
+        );
+        assert_eq!(
+            tarball_prefix(Path::new("mirror.tgz")),
+            Some(PathBuf::from("mirror"))
+        );
+        assert_eq!(tarball_prefix(Path::new("/tmp/mirror")), None);
+    }
+
+    #[test]
+    fn test_resolve_base_url() {
+        let base_url = Some("https://mirror.example.com".to_string());
+        assert_eq!(
+            resolve_base_url(Path::new("mirror.tar.gz"), base_url).unwrap(),
+            "https://mirror.example.com"
+        );
+
+        assert_eq!(
+            resolve_base_url(Path::new("/tmp/mirror"), None).unwrap(),
+            "file:///tmp/mirror"
+        );
+
+        let error = resolve_base_url(Path::new("/tmp/mirror.tar.gz"), None).unwrap_err();
+        assert_snapshot!(error, @"`--base-url` is required when exporting a tarball");
+    }
+
+    #[test]
+    fn test_parse_index_data() {
+        let index_data = concat!(
+            r#"{"name":"foo","vers":"1.0.0","deps":[],"cksum":"abc","features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"foo","vers":"1.1.0","deps":[],"cksum":"def","features":{},"yanked":true}"#,
+            "\n",
+        );
+
+        let entries = parse_index_data(index_data).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].vers, "1.0.0");
+        assert_eq!(entries[1].cksum, "def");
+        assert_eq!(entries[1].yanked, Some(true));
+    }
+}
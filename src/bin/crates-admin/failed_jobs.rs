@@ -0,0 +1,359 @@
+use crate::dialoguer;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use crates_io::db;
+use crates_io::schema::{background_jobs, failed_background_jobs};
+use diesel::dsl::count_star;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "failed-jobs",
+    about = "Inspect, requeue or discard background jobs that have exhausted their retries.",
+    rename_all = "snake_case"
+)]
+pub enum Command {
+    /// List the failed jobs
+    List {
+        /// Only list jobs of this type
+        #[arg(long)]
+        job_type: Option<String>,
+    },
+    /// Show the details of a failed job, including its data and backtrace
+    Show { id: i64 },
+    /// Enqueue failed jobs again, with a reset retry counter
+    Requeue(Selection),
+    /// Permanently delete failed jobs
+    Discard {
+        #[command(flatten)]
+        selection: Selection,
+
+        /// Don't ask for confirmation: yes, we are sure. Best for scripting.
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+pub struct Selection {
+    /// IDs of the failed jobs
+    #[arg(value_name = "ID", required_unless_present = "job_type")]
+    ids: Vec<i64>,
+
+    /// Select all failed jobs of this type
+    #[arg(long, conflicts_with = "ids")]
+    job_type: Option<String>,
+}
+
+impl Selection {
+    fn filter(
+        &self,
+    ) -> Box<dyn BoxableExpression<failed_background_jobs::table, Pg, SqlType = Bool>> {
+        match &self.job_type {
+            Some(job_type) => Box::new(failed_background_jobs::job_type.eq(job_type.clone())),
+            None => Box::new(failed_background_jobs::id.eq_any(self.ids.clone())),
+        }
+    }
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = failed_background_jobs, check_for_backend(Pg))]
+struct FailedJob {
+    id: i64,
+    job_type: String,
+    data: serde_json::Value,
+    retries: i32,
+    queue: String,
+    created_at: DateTime<Utc>,
+    failed_at: DateTime<Utc>,
+    error: String,
+    backtrace: Option<String>,
+}
+
+pub async fn run(command: Command) -> anyhow::Result<()> {
+    let mut conn = db::oneoff_connection()
+        .await
+        .context("Failed to connect to the database")?;
+
+    execute(command, &mut conn).await
+}
+
+async fn execute(command: Command, conn: &mut AsyncPgConnection) -> anyhow::Result<()> {
+    match command {
+        Command::List { job_type } => {
+            let mut query = failed_background_jobs::table
+                .select(FailedJob::as_select())
+                .order(failed_background_jobs::id)
+                .into_boxed();
+
+            if let Some(job_type) = job_type {
+                query = query.filter(failed_background_jobs::job_type.eq(job_type));
+            }
+
+            let jobs = query.load(conn).await?;
+            if jobs.is_empty() {
+                println!("No failed jobs found");
+            }
+
+            for job in jobs {
+                let error = job.error.lines().next().unwrap_or_default();
+                println!(
+                    "{} {} (failed at {}, {} retries): {error}",
+                    job.id, job.job_type, job.failed_at, job.retries
+                );
+            }
+        }
+        Command::Show { id } => {
+            let job = failed_background_jobs::table
+                .find(id)
+                .select(FailedJob::as_select())
+                .first(conn)
+                .await
+                .optional()?
+                .with_context(|| format!("Failed job {id} not found"))?;
+
+            println!("ID:         {}", job.id);
+            println!("Job type:   {}", job.job_type);
+            println!("Data:       {}", job.data);
+            println!("Queue:      {}", job.queue);
+            println!("Retries:    {}", job.retries);
+            println!("Created at: {}", job.created_at);
+            println!("Failed at:  {}", job.failed_at);
+            println!();
+            println!("{}", job.error);
+
+            if let Some(backtrace) = job.backtrace {
+                println!();
+                println!("{backtrace}");
+            }
+        }
+        Command::Requeue(selection) => {
+            let requeued = conn
+                .transaction(|conn| {
+                    async move {
+                        // The failed jobs are deleted first, so that only the
+                        // jobs that are actually removed are enqueued again.
+                        let jobs = diesel::delete(
+                            failed_background_jobs::table.filter(selection.filter()),
+                        )
+                        .returning((
+                            failed_background_jobs::job_type,
+                            failed_background_jobs::data,
+                            failed_background_jobs::priority,
+                            failed_background_jobs::queue,
+                        ))
+                        .get_results::<(String, serde_json::Value, i16, String)>(conn)
+                        .await?;
+
+                        let jobs = jobs
+                            .into_iter()
+                            .map(|(job_type, data, priority, queue)| {
+                                (
+                                    background_jobs::job_type.eq(job_type),
+                                    background_jobs::data.eq(data),
+                                    background_jobs::priority.eq(priority),
+                                    background_jobs::queue.eq(queue),
+                                )
+                            })
+                            .collect::<Vec<_>>();
+
+                        let requeued = diesel::insert_into(background_jobs::table)
+                            .values(jobs)
+                            .execute(conn)
+                            .await?;
+
+                        Ok::<_, anyhow::Error>(requeued)
+                    }
+                    .scope_boxed()
+                })
+                .await?;
+
+            println!("Requeued {requeued} failed jobs");
+        }
+        Command::Discard { selection, yes } => {
+            let count: i64 = failed_background_jobs::table
+                .select(count_star())
+                .filter(selection.filter())
+                .get_result(conn)
+                .await?;
+
+            if count == 0 {
+                println!("No failed jobs found");
+                return Ok(());
+            }
+
+            let prompt = format!("Do you want to permanently delete {count} failed jobs?");
+            if !yes && !dialoguer::confirm(prompt).await? {
+                return Ok(());
+            }
+
+            let discarded =
+                diesel::delete(failed_background_jobs::table.filter(selection.filter()))
+                    .execute(conn)
+                    .await?;
+
+            println!("Discarded {discarded} failed jobs");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crates_io_test_db::TestDatabase;
+    use insta::assert_debug_snapshot;
+    use serde_json::json;
+
+    async fn insert_failed_job(conn: &mut AsyncPgConnection, id: i64, job_type: &str, queue: &str) {
+        diesel::insert_into(failed_background_jobs::table)
+            .values((
+                failed_background_jobs::id.eq(id),
+                failed_background_jobs::job_type.eq(job_type),
+                failed_background_jobs::data.eq(json!({ "id": id })),
+                failed_background_jobs::retries.eq(5),
+                failed_background_jobs::priority.eq(10),
+                failed_background_jobs::queue.eq(queue),
+                failed_background_jobs::created_at.eq(Utc::now()),
+                failed_background_jobs::error.eq("something went wrong"),
+            ))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    async fn failed_job_ids(conn: &mut AsyncPgConnection) -> Vec<i64> {
+        failed_background_jobs::table
+            .select(failed_background_jobs::id)
+            .order(failed_background_jobs::id)
+            .load(conn)
+            .await
+            .unwrap()
+    }
+
+    async fn enqueued_jobs(
+        conn: &mut AsyncPgConnection,
+    ) -> Vec<(String, serde_json::Value, i16, String)> {
+        background_jobs::table
+            .select((
+                background_jobs::job_type,
+                background_jobs::data,
+                background_jobs::priority,
+                background_jobs::queue,
+            ))
+            .order(background_jobs::id)
+            .load(conn)
+            .await
+            .unwrap()
+    }
+
+    fn selection(ids: Vec<i64>, job_type: Option<&str>) -> Selection {
+        let job_type = job_type.map(Into::into);
+        Selection { ids, job_type }
+    }
+
+    #[tokio::test]
+    async fn requeue_failed_jobs_by_id() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        insert_failed_job(&mut conn, 1, "foo", "default").await;
+        insert_failed_job(&mut conn, 2, "foo", "downloads").await;
+        insert_failed_job(&mut conn, 3, "bar", "downloads").await;
+
+        let command = Command::Requeue(selection(vec![2, 3], None));
+        execute(command, &mut conn).await.unwrap();
+
+        assert_eq!(failed_job_ids(&mut conn).await, vec![1]);
+        assert_debug_snapshot!(enqueued_jobs(&mut conn).await, @r#"
+        [
+            (
+                "foo",
+                Object {
+                    "id": Number(2),
+                },
+                10,
+                "downloads",
+            ),
+            (
+                "bar",
+                Object {
+                    "id": Number(3),
+                },
+                10,
+                "downloads",
+            ),
+        ]
+        "#);
+    }
+
+    #[tokio::test]
+    async fn requeue_failed_jobs_by_type() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        insert_failed_job(&mut conn, 1, "foo", "default").await;
+        insert_failed_job(&mut conn, 2, "foo", "downloads").await;
+        insert_failed_job(&mut conn, 3, "bar", "downloads").await;
+
+        let command = Command::Requeue(selection(vec![], Some("foo")));
+        execute(command, &mut conn).await.unwrap();
+
+        assert_eq!(failed_job_ids(&mut conn).await, vec![3]);
+        assert_debug_snapshot!(enqueued_jobs(&mut conn).await, @r#"
+        [
+            (
+                "foo",
+                Object {
+                    "id": Number(1),
+                },
+                10,
+                "default",
+            ),
+            (
+                "foo",
+                Object {
+                    "id": Number(2),
+                },
+                10,
+                "downloads",
+            ),
+        ]
+        "#);
+    }
+
+    #[tokio::test]
+    async fn discard_failed_jobs() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        insert_failed_job(&mut conn, 1, "foo", "default").await;
+        insert_failed_job(&mut conn, 2, "bar", "default").await;
+
+        let selection = selection(vec![], Some("bar"));
+        let command = Command::Discard {
+            selection,
+            yes: true,
+        };
+        execute(command, &mut conn).await.unwrap();
+
+        assert_eq!(failed_job_ids(&mut conn).await, vec![1]);
+        assert!(enqueued_jobs(&mut conn).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn show_unknown_failed_job() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        let error = execute(Command::Show { id: 42 }, &mut conn)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Failed job 42 not found");
+    }
+}
@@ -0,0 +1,849 @@
+use crate::render_readmes::find_file_by_path;
+use anyhow::{Context, anyhow, bail};
+use async_compression::tokio::bufread::GzipDecoder;
+use crates_io::controllers::krate::publish::{ManifestMetadata, add_dependencies};
+use crates_io::db;
+use crates_io::models::{
+    self, Category, Crate, Keyword, NewCrate, NewVersion, update_default_version,
+};
+use crates_io::schema::{crates, emails, users, versions};
+use crates_io::storage::Storage;
+use crates_io::tasks::spawn_blocking;
+use crates_io::worker::jobs;
+use crates_io_tarball::{StringOrBool, process_tarball};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use hex::ToHex;
+use indicatif::{ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "import",
+    about = "Import crates from a mirror directory or tarball into the database.",
+    long_about = "Import crates from a mirror directory or tarball into the database. \
+        The source can be a directory or `.tar.gz` file created by `export-mirror`, \
+        or a plain directory of `.crate` files. Versions that already exist in the \
+        database are skipped, so an interrupted import can be resumed by running \
+        the command again."
+)]
+pub struct Opts {
+    /// Path to the mirror directory, mirror tarball, or directory of `.crate`
+    /// files.
+    path: PathBuf,
+
+    /// GitHub login of the user that will own the imported crates and will
+    /// be recorded as the publisher of the imported versions.
+    #[arg(long)]
+    owner: String,
+
+    /// Maximum size of a crate file after decompression.
+    #[arg(long, default_value_t = 512 * 1024 * 1024)]
+    max_unpack_size: u64,
+}
+
+pub async fn run(opts: Opts) -> anyhow::Result<()> {
+    let storage = Storage::from_environment();
+
+    let mut conn = db::oneoff_connection()
+        .await
+        .context("Failed to connect to the database")?;
+
+    let owner = Owner::load(&opts.owner, &mut conn).await?;
+
+    let (_tempdir, root) = if opts.path.is_file() {
+        let tempdir = tempfile::tempdir().context("Failed to create temporary directory")?;
+        let target = tempdir.path().to_path_buf();
+
+        println!("extracting {}", opts.path.display());
+        let path = opts.path.clone();
+        let root = spawn_blocking(move || extract_tarball(&path, &target)).await??;
+        (Some(tempdir), root)
+    } else {
+        (None, opts.path.clone())
+    };
+
+    let crates = spawn_blocking(move || discover(&root)).await??;
+    let summary = import(&storage, &mut conn, &owner, crates, opts.max_unpack_size).await?;
+
+    println!(
+        "imported {} versions, skipped {} existing versions, {} versions failed",
+        summary.num_imported, summary.num_skipped, summary.num_failed
+    );
+
+    if summary.num_failed > 0 {
+        bail!("Failed to import {} versions", summary.num_failed);
+    }
+
+    Ok(())
+}
+
+/// The user that owns the imported crates.
+#[derive(Debug)]
+struct Owner {
+    id: i32,
+    email: String,
+}
+
+impl Owner {
+    async fn load(gh_login: &str, conn: &mut AsyncPgConnection) -> anyhow::Result<Self> {
+        let (id, email): (i32, Option<String>) = users::table
+            .left_join(emails::table)
+            .filter(users::gh_login.eq(gh_login))
+            .select((users::id, emails::email.nullable()))
+            .first(conn)
+            .await
+            .optional()?
+            .ok_or_else(|| anyhow!("User `{gh_login}` does not exist"))?;
+
+        let email = email.ok_or_else(|| anyhow!("User `{gh_login}` has no email address"))?;
+
+        Ok(Self { id, email })
+    }
+
+    async fn owns(&self, krate: &Crate, conn: &mut AsyncPgConnection) -> QueryResult<bool> {
+        let owners = krate.owners(conn).await?;
+        Ok(owners
+            .iter()
+            .any(|owner| matches!(owner, models::Owner::User(user) if user.id == self.id)))
+    }
+}
+
+/// A single crate version that should be imported.
+#[derive(Debug)]
+struct ImportVersion {
+    num: String,
+    /// Path to the `.crate` file.
+    path: PathBuf,
+    /// Expected SHA-256 checksum of the `.crate` file, if known from the
+    /// index metadata.
+    checksum: Option<String>,
+    yanked: bool,
+}
+
+#[derive(Debug, Default)]
+struct ImportSummary {
+    num_imported: usize,
+    num_skipped: usize,
+    num_failed: usize,
+}
+
+/// Extract a mirror tarball into the `target` directory and return the
+/// directory containing the mirror files.
+fn extract_tarball(path: &Path, target: &Path) -> anyhow::Result<PathBuf> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    archive
+        .unpack(target)
+        .with_context(|| format!("Failed to extract {}", path.display()))?;
+
+    // `export-mirror` puts all files into a single top-level directory
+    let entries = std::fs::read_dir(target)?.collect::<Result<Vec<_>, _>>()?;
+    if let [entry] = entries.as_slice() {
+        if entry.file_type()?.is_dir() {
+            return Ok(entry.path());
+        }
+    }
+
+    Ok(target.to_path_buf())
+}
+
+/// Find all crate versions in the given directory, keyed by crate name.
+///
+/// If the directory contains an `index` directory, the index files determine
+/// which versions are imported, in which order, and whether they are yanked.
+/// Otherwise all `.crate` files in the directory are imported in semver
+/// order.
+fn discover(root: &Path) -> anyhow::Result<BTreeMap<String, Vec<ImportVersion>>> {
+    let index_dir = root.join("index");
+    if index_dir.is_dir() {
+        discover_from_index(root, &index_dir)
+    } else {
+        discover_from_crate_files(root)
+    }
+}
+
+fn discover_from_index(
+    root: &Path,
+    index_dir: &Path,
+) -> anyhow::Result<BTreeMap<String, Vec<ImportVersion>>> {
+    let mut crates = BTreeMap::new();
+
+    let mut dirs = vec![index_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+
+            // Index files are always stored in subdirectories, so any files
+            // in the top-level directory (e.g. `config.json`) are skipped.
+            if dir == index_dir {
+                continue;
+            }
+
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+
+            for line in contents.lines().filter(|line| !line.is_empty()) {
+                let entry: crates_io_index::Crate = serde_json::from_str(line)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+                let file_name = format!("{}-{}.crate", entry.name, entry.vers);
+                let version = ImportVersion {
+                    num: entry.vers,
+                    path: root.join(file_name),
+                    checksum: Some(entry.cksum),
+                    yanked: entry.yanked.unwrap_or_default(),
+                };
+
+                crates
+                    .entry(entry.name)
+                    .or_insert_with(Vec::new)
+                    .push(version);
+            }
+        }
+    }
+
+    Ok(crates)
+}
+
+fn discover_from_crate_files(root: &Path) -> anyhow::Result<BTreeMap<String, Vec<ImportVersion>>> {
+    let mut crates = BTreeMap::new();
+
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        let Some(stem) = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(|file_name| file_name.strip_suffix(".crate"))
+        else {
+            continue;
+        };
+
+        let Some((name, num)) = split_crate_file_name(stem) else {
+            warn!(path = %path.display(), "Skipping file with unexpected name");
+            continue;
+        };
+
+        let version = ImportVersion {
+            num: num.to_string(),
+            path: path.clone(),
+            checksum: None,
+            yanked: false,
+        };
+
+        crates
+            .entry(name.to_string())
+            .or_insert_with(Vec::new)
+            .push(version);
+    }
+
+    for versions in crates.values_mut() {
+        versions.sort_by_cached_key(|version| semver::Version::parse(&version.num).ok());
+    }
+
+    Ok(crates)
+}
+
+/// Split a `{name}-{version}` file stem into the crate name and version.
+///
+/// Since both crate names and versions may contain dashes, the first split
+/// position that results in a valid semver version is used.
+fn split_crate_file_name(stem: &str) -> Option<(&str, &str)> {
+    stem.match_indices('-')
+        .map(|(index, _)| (&stem[..index], &stem[index + 1..]))
+        .find(|(name, num)| !name.is_empty() && semver::Version::parse(num).is_ok())
+}
+
+async fn import(
+    storage: &Storage,
+    conn: &mut AsyncPgConnection,
+    owner: &Owner,
+    crates: BTreeMap<String, Vec<ImportVersion>>,
+    max_unpack_size: u64,
+) -> anyhow::Result<ImportSummary> {
+    // Create all crates upfront so that dependencies between the imported
+    // crates can be resolved regardless of the order they are imported in.
+    // Crates that already exist are left alone here and are checked for
+    // their owners below.
+    println!("creating {} crates", crates.len());
+    for name in crates.keys() {
+        let new_crate = NewCrate {
+            name,
+            ..Default::default()
+        };
+
+        new_crate
+            .create(conn, owner.id)
+            .await
+            .optional()
+            .with_context(|| format!("Failed to create crate `{name}`"))?;
+    }
+
+    let num_versions = crates.values().map(Vec::len).sum::<usize>();
+    println!("importing {num_versions} versions");
+
+    let pb = ProgressBar::new(num_versions as u64);
+    pb.set_style(ProgressStyle::with_template(
+        "{bar:60} ({pos}/{len}, ETA {eta})",
+    )?);
+
+    let mut summary = ImportSummary::default();
+    for (name, versions) in crates {
+        let krate: Crate = Crate::by_name(&name).first(conn).await?;
+        if krate.name != name {
+            pb.suspend(|| warn!("Skipping `{name}`, which conflicts with `{}`", krate.name));
+            pb.inc(versions.len() as u64);
+            summary.num_failed += versions.len();
+            continue;
+        }
+
+        // Existing crates are only imported into if they are owned by the
+        // importing user, so that the import can't add versions to crates of
+        // other users.
+        if !owner.owns(&krate, conn).await? {
+            pb.suspend(|| warn!("Skipping `{name}`, which is owned by other users"));
+            pb.inc(versions.len() as u64);
+            summary.num_failed += versions.len();
+            continue;
+        }
+
+        let existing_versions: HashSet<String> = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .select(versions::num)
+            .load::<String>(conn)
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut num_versions = existing_versions.len();
+        for version in versions {
+            pb.inc(1);
+
+            if existing_versions.contains(&version.num) {
+                summary.num_skipped += 1;
+                continue;
+            }
+
+            let result = import_version(storage, conn, owner, &krate, &version, max_unpack_size);
+            match result.await {
+                Ok(()) => {
+                    summary.num_imported += 1;
+                    num_versions += 1;
+                }
+                Err(error) => {
+                    let num = &version.num;
+                    pb.suspend(|| warn!("Failed to import {name}@{num}: {error:#}"));
+                    summary.num_failed += 1;
+                }
+            }
+        }
+
+        // Crates without any versions would break the index sync, so the
+        // crates that were created upfront are removed again if all of their
+        // versions failed to import.
+        if num_versions == 0 {
+            diesel::delete(crates::table.find(krate.id))
+                .execute(conn)
+                .await?;
+
+            continue;
+        }
+
+        // These are also run for crates without any newly imported versions,
+        // in case a previous run was interrupted before reaching this point.
+        update_default_version(krate.id, conn).await?;
+        jobs::SyncToGitIndex::new(&krate.name).enqueue(conn).await?;
+        jobs::SyncToSparseIndex::new(&krate.name)
+            .enqueue(conn)
+            .await?;
+    }
+
+    pb.finish_and_clear();
+
+    Ok(summary)
+}
+
+async fn import_version(
+    storage: &Storage,
+    conn: &mut AsyncPgConnection,
+    owner: &Owner,
+    krate: &Crate,
+    version: &ImportVersion,
+    max_unpack_size: u64,
+) -> anyhow::Result<()> {
+    let path = &version.path;
+    let tarball_bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let hex_cksum: String = Sha256::digest(&tarball_bytes).encode_hex();
+    if let Some(expected) = &version.checksum {
+        if *expected != hex_cksum {
+            bail!("Checksum mismatch: expected {expected}, found {hex_cksum}");
+        }
+    }
+
+    let pkg_name = format!("{}-{}", krate.name, version.num);
+    let tarball_info = process_tarball(&pkg_name, &*tarball_bytes, max_unpack_size).await?;
+
+    let ManifestMetadata {
+        description,
+        mut license,
+        license_file,
+        homepage,
+        documentation,
+        repository,
+        rust_version,
+        edition,
+        readme: readme_path,
+        links,
+        keywords,
+        categories,
+        features,
+        has_lib,
+        bin_names,
+        deps,
+    } = ManifestMetadata::from_manifest(tarball_info.manifest);
+
+    let readme_path = match readme_path {
+        Some(StringOrBool::Bool(false)) => None,
+        Some(StringOrBool::String(path)) => Some(path),
+        _ => Some(String::from("README.md")),
+    };
+
+    if license.is_none() && license_file.is_some() {
+        license = Some(String::from("non-standard"));
+    }
+
+    // Invalid keywords are dropped instead of failing the import, since older
+    // crates were published before some of the validations were introduced.
+    let keywords = keywords
+        .iter()
+        .filter(|keyword| keyword.len() <= 20 && Keyword::valid_name(keyword))
+        .take(5)
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    let categories = categories.iter().map(String::as_str).collect::<Vec<_>>();
+    let bin_names = bin_names.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let readme = match &readme_path {
+        Some(readme_path) => read_readme(&tarball_bytes, &pkg_name, readme_path).await,
+        None => None,
+    };
+
+    let pkg_path_in_vcs = tarball_info.vcs_info.map(|info| info.path_in_vcs);
+    let content_length = tarball_bytes.len();
+    let tarball_bytes = tarball_bytes.into();
+
+    let mut uploaded = false;
+    let uploaded_ref = &mut uploaded;
+
+    let result = conn
+        .transaction(|conn| {
+            async move {
+                NewCrate {
+                    name: &krate.name,
+                    description: description.as_deref(),
+                    homepage: homepage.as_deref(),
+                    documentation: documentation.as_deref(),
+                    readme: readme.as_deref(),
+                    repository: repository.as_deref(),
+                    max_upload_size: None,
+                    max_features: None,
+                }
+                .update(conn)
+                .await?;
+
+                let new_version = NewVersion::builder(krate.id, &version.num)
+                    .features(serde_json::to_value(&features)?)
+                    .maybe_license(license.as_deref())
+                    .size(content_length as i32)
+                    .published_by(owner.id)
+                    .checksum(&hex_cksum)
+                    .maybe_links(links.as_deref())
+                    .maybe_rust_version(rust_version.as_deref())
+                    .has_lib(has_lib)
+                    .bin_names(bin_names.as_slice())
+                    .maybe_edition(edition.map(|edition| edition.as_str()))
+                    .maybe_description(description.as_deref())
+                    .maybe_homepage(homepage.as_deref())
+                    .maybe_documentation(documentation.as_deref())
+                    .maybe_repository(repository.as_deref())
+                    .categories(&categories)
+                    .keywords(&keywords)
+                    .yanked(version.yanked)
+                    .build();
+
+                let saved_version = new_version.save(conn, &owner.email).await?;
+
+                add_dependencies(conn, &deps, saved_version.id)
+                    .await
+                    .map_err(|error| anyhow!("{error}"))?;
+
+                Keyword::update_crate(conn, krate.id, &keywords).await?;
+
+                let unknown_categories =
+                    Category::update_crate(conn, krate.id, &categories).await?;
+                if !unknown_categories.is_empty() {
+                    let unknown_categories = unknown_categories.join(", ");
+                    debug!("Ignoring unknown categories: {unknown_categories}");
+                }
+
+                if let (Some(readme), Some(readme_path)) = (readme, readme_path) {
+                    if !readme.is_empty() {
+                        jobs::RenderAndUploadReadme::new(
+                            saved_version.id,
+                            readme,
+                            readme_path,
+                            repository,
+                            pkg_path_in_vcs,
+                        )
+                        .enqueue(conn)
+                        .await?;
+                    }
+                }
+
+                storage
+                    .upload_crate_file(&krate.name, &version.num, tarball_bytes)
+                    .await
+                    .context("Failed to upload crate file")?;
+
+                *uploaded_ref = true;
+
+                Ok::<_, anyhow::Error>(())
+            }
+            .scope_boxed()
+        })
+        .await;
+
+    // The crate file is uploaded as the last step of the transaction, so the
+    // version never exists without its file. If committing the transaction
+    // fails after the upload, the file is removed again so that it does not
+    // exist without its version either.
+    if result.is_err() && uploaded {
+        if let Err(error) = storage.delete_crate_file(&krate.name, &version.num).await {
+            warn!("Failed to delete crate file of {pkg_name}: {error}");
+        }
+    }
+
+    result
+}
+
+/// Read the README file from a crate tarball, if it exists.
+async fn read_readme(tarball: &[u8], pkg_name: &str, readme_path: &str) -> Option<String> {
+    let mut archive = tokio_tar::Archive::new(GzipDecoder::new(tarball));
+    let mut entries = archive.entries().ok()?;
+
+    let path = Path::new(pkg_name).join(readme_path);
+    find_file_by_path(&mut entries, &path).await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crates_io::models::{NewEmail, NewUser};
+    use crates_io::schema::dependencies;
+    use crates_io::storage::StorageConfig;
+    use crates_io_tarball::TarballBuilder;
+    use crates_io_test_db::TestDatabase;
+    use futures_util::TryStreamExt;
+    use insta::assert_debug_snapshot;
+
+    fn build_crate(name: &str, num: &str, extra_manifest: &str) -> Vec<u8> {
+        let manifest = format!(
+            "[package]\nname = \"{name}\"\nversion = \"{num}\"\n\
+            description = \"the {name} crate\"\nlicense = \"MIT\"\n{extra_manifest}"
+        );
+
+        TarballBuilder::new()
+            .add_file(&format!("{name}-{num}/Cargo.toml"), manifest.as_bytes())
+            .add_file(&format!("{name}-{num}/README.md"), b"# readme")
+            .build()
+    }
+
+    fn write_mirror(root: &Path, crates: &[(&str, &str, bool, Vec<u8>)]) {
+        let mut index = BTreeMap::<&str, String>::new();
+        for (name, num, yanked, bytes) in crates {
+            std::fs::write(root.join(format!("{name}-{num}.crate")), bytes).unwrap();
+
+            let cksum: String = Sha256::digest(bytes).encode_hex();
+            let entry = serde_json::json!({
+                "name": name, "vers": num, "deps": [], "cksum": cksum,
+                "features": {}, "yanked": yanked,
+            });
+
+            let lines = index.entry(name).or_default();
+            lines.push_str(&entry.to_string());
+            lines.push('\n');
+        }
+
+        for (name, lines) in index {
+            let path = root
+                .join("index")
+                .join(crates_io_index::Repository::relative_index_file(name));
+
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, lines).unwrap();
+        }
+
+        std::fs::write(root.join("index/config.json"), "{}").unwrap();
+    }
+
+    async fn create_owner(conn: &mut AsyncPgConnection) -> Owner {
+        let user = NewUser::builder()
+            .gh_id(42)
+            .gh_login("admin")
+            .gh_access_token("token")
+            .build()
+            .insert(conn)
+            .await
+            .unwrap();
+
+        NewEmail::builder()
+            .user_id(user.id)
+            .email("admin@example.com")
+            .verified(true)
+            .build()
+            .insert(conn)
+            .await
+            .unwrap();
+
+        Owner::load("admin", conn).await.unwrap()
+    }
+
+    async fn all_versions(conn: &mut AsyncPgConnection) -> Vec<(String, String, bool)> {
+        versions::table
+            .inner_join(crates::table)
+            .select((crates::name, versions::num, versions::yanked))
+            .order((crates::name, versions::num))
+            .load(conn)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_split_crate_file_name() {
+        assert_eq!(split_crate_file_name("foo-1.0.0"), Some(("foo", "1.0.0")));
+        assert_eq!(
+            split_crate_file_name("foo-bar-1.0.0-beta.1"),
+            Some(("foo-bar", "1.0.0-beta.1"))
+        );
+        assert_eq!(
+            split_crate_file_name("foo-2-0.1.0"),
+            Some(("foo-2", "0.1.0"))
+        );
+        assert_eq!(split_crate_file_name("foo"), None);
+        assert_eq!(split_crate_file_name("-1.0.0"), None);
+    }
+
+    #[tokio::test]
+    async fn test_import() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+        let storage = Storage::from_config(&StorageConfig::in_memory());
+        let owner = create_owner(&mut conn).await;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let bar_manifest = "[dependencies]\nfoo = \"1.0\"\n";
+        write_mirror(
+            tempdir.path(),
+            &[
+                ("foo", "1.0.0", false, build_crate("foo", "1.0.0", "")),
+                ("foo", "1.1.0", true, build_crate("foo", "1.1.0", "")),
+                (
+                    "bar",
+                    "0.1.0",
+                    false,
+                    build_crate("bar", "0.1.0", bar_manifest),
+                ),
+            ],
+        );
+
+        let crates = discover(tempdir.path()).unwrap();
+        let summary = import(&storage, &mut conn, &owner, crates, 1024 * 1024)
+            .await
+            .unwrap();
+        assert_eq!(summary.num_imported, 3);
+        assert_eq!(summary.num_failed, 0);
+
+        assert_debug_snapshot!(all_versions(&mut conn).await, @r#"
+        [
+            (
+                "bar",
+                "0.1.0",
+                false,
+            ),
+            (
+                "foo",
+                "1.0.0",
+                false,
+            ),
+            (
+                "foo",
+                "1.1.0",
+                true,
+            ),
+        ]
+        "#);
+
+        let deps: Vec<(String, String)> = dependencies::table
+            .inner_join(crates::table)
+            .select((crates::name, dependencies::req))
+            .load(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(deps, vec![("foo".to_string(), "^1.0".to_string())]);
+
+        let mut paths: Vec<String> = storage
+            .as_inner()
+            .list(None)
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect()
+            .await
+            .unwrap();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "crates/bar/bar-0.1.0.crate",
+                "crates/foo/foo-1.0.0.crate",
+                "crates/foo/foo-1.1.0.crate",
+            ]
+        );
+
+        // Running the import again skips all existing versions
+        let crates = discover(tempdir.path()).unwrap();
+        let summary = import(&storage, &mut conn, &owner, crates, 1024 * 1024)
+            .await
+            .unwrap();
+        assert_eq!(summary.num_imported, 0);
+        assert_eq!(summary.num_skipped, 3);
+        assert_eq!(all_versions(&mut conn).await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_import_checksum_mismatch() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+        let storage = Storage::from_config(&StorageConfig::in_memory());
+        let owner = create_owner(&mut conn).await;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        write_mirror(
+            tempdir.path(),
+            &[("foo", "1.0.0", false, build_crate("foo", "1.0.0", ""))],
+        );
+
+        let corrupted = build_crate("foo", "1.0.0", "keywords = [\"foo\"]\n");
+        std::fs::write(tempdir.path().join("foo-1.0.0.crate"), corrupted).unwrap();
+
+        let crates = discover(tempdir.path()).unwrap();
+        let summary = import(&storage, &mut conn, &owner, crates, 1024 * 1024)
+            .await
+            .unwrap();
+        assert_eq!(summary.num_imported, 0);
+        assert_eq!(summary.num_failed, 1);
+        assert_eq!(all_versions(&mut conn).await, vec![]);
+
+        let num_crates: i64 = crates::table.count().get_result(&mut conn).await.unwrap();
+        assert_eq!(num_crates, 0);
+    }
+
+    #[tokio::test]
+    async fn test_import_into_crate_of_other_user() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+        let storage = Storage::from_config(&StorageConfig::in_memory());
+        let owner = create_owner(&mut conn).await;
+
+        let other_user = NewUser::builder()
+            .gh_id(43)
+            .gh_login("other")
+            .gh_access_token("token")
+            .build()
+            .insert(&mut conn)
+            .await
+            .unwrap();
+
+        let new_crate = NewCrate {
+            name: "foo",
+            ..Default::default()
+        };
+        new_crate.create(&mut conn, other_user.id).await.unwrap();
+
+        let tempdir = tempfile::tempdir().unwrap();
+        write_mirror(
+            tempdir.path(),
+            &[
+                ("foo", "1.0.0", false, build_crate("foo", "1.0.0", "")),
+                ("bar", "1.0.0", false, build_crate("bar", "1.0.0", "")),
+            ],
+        );
+
+        let crates = discover(tempdir.path()).unwrap();
+        let summary = import(&storage, &mut conn, &owner, crates, 1024 * 1024)
+            .await
+            .unwrap();
+        assert_eq!(summary.num_imported, 1);
+        assert_eq!(summary.num_failed, 1);
+
+        assert_debug_snapshot!(all_versions(&mut conn).await, @r#"
+        [
+            (
+                "bar",
+                "1.0.0",
+                false,
+            ),
+        ]
+        "#);
+
+        // The crate of the other user is left untouched
+        let num_crates: i64 = crates::table.count().get_result(&mut conn).await.unwrap();
+        assert_eq!(num_crates, 2);
+
+        let paths: Vec<String> = storage
+            .as_inner()
+            .list(None)
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(paths, vec!["crates/bar/bar-1.0.0.crate"]);
+    }
+
+    #[tokio::test]
+    async fn test_import_crate_files() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+        let storage = Storage::from_config(&StorageConfig::in_memory());
+        let owner = create_owner(&mut conn).await;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        for num in ["1.10.0", "1.2.0"] {
+            let path = tempdir.path().join(format!("foo-bar-{num}.crate"));
+            std::fs::write(path, build_crate("foo-bar", num, "")).unwrap();
+        }
+
+        let crates = discover(tempdir.path()).unwrap();
+        let versions = crates["foo-bar"].iter().map(|v| v.num.as_str());
+        assert_eq!(versions.collect::<Vec<_>>(), vec!["1.2.0", "1.10.0"]);
+
+        let summary = import(&storage, &mut conn, &owner, crates, 1024 * 1024)
+            .await
+            .unwrap();
+        assert_eq!(summary.num_imported, 2);
+        assert_eq!(all_versions(&mut conn).await.len(), 2);
+    }
+}
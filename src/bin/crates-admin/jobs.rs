@@ -0,0 +1,156 @@
+use anyhow::{Context, anyhow};
+use crates_io::db;
+use crates_io::worker::QUEUES;
+use crates_io_worker::admin::{self, JobCursor, JobStatus};
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "jobs",
+    about = "Inspect and control the enqueued background jobs and their queues."
+)]
+pub enum Command {
+    /// List the enqueued jobs and the paused queues
+    List {
+        /// Only list jobs of this type
+        #[arg(long)]
+        job_type: Option<String>,
+
+        /// Only list jobs of this queue
+        #[arg(long)]
+        queue: Option<String>,
+
+        /// The maximum number of jobs to list
+        #[arg(long, default_value_t = 100)]
+        limit: i64,
+
+        /// Only list the jobs after this position, as printed at the end of
+        /// the previous list (`<priority>:<id>`)
+        #[arg(long, value_parser = parse_cursor)]
+        after: Option<JobCursor>,
+    },
+    /// Show the details of an enqueued job, including its data and last error
+    Show { id: i64 },
+    /// Show whether a job, e.g. the last job of a workflow, is still waiting
+    /// for its parent jobs, enqueued, failed or finished
+    Status { id: i64 },
+    /// Make a job that is waiting for its next retry available immediately
+    RetryNow { id: i64 },
+    /// Delete an enqueued job that is not currently running, and the jobs
+    /// that depend on it
+    Cancel { id: i64 },
+    /// Stop the workers of a queue from picking up new jobs
+    PauseQueue { queue: String },
+    /// Let the workers of a paused queue pick up new jobs again
+    ResumeQueue { queue: String },
+}
+
+fn parse_cursor(value: &str) -> anyhow::Result<JobCursor> {
+    let (priority, id) = value
+        .split_once(':')
+        .ok_or_else(|| anyhow!("expected `<priority>:<id>`"))?;
+
+    Ok(JobCursor {
+        priority: priority.parse()?,
+        id: id.parse()?,
+    })
+}
+
+pub async fn run(command: Command) -> anyhow::Result<()> {
+    let mut conn = db::oneoff_connection()
+        .await
+        .context("Failed to connect to the database")?;
+
+    match command {
+        Command::List {
+            job_type,
+            queue,
+            limit,
+            after,
+        } => {
+            let job_type = job_type.as_deref();
+            let queue = queue.as_deref();
+            let jobs = admin::list_jobs(&mut conn, job_type, queue, after, limit).await?;
+            if jobs.is_empty() {
+                println!("No jobs found");
+            }
+
+            let next = jobs.last().map(|job| job.cursor());
+            let has_more = jobs.len() as i64 == limit;
+
+            for job in jobs {
+                let error = job.last_error.as_deref().unwrap_or_default();
+                let error = error.lines().next().unwrap_or_default();
+                println!(
+                    "{} {} (queue {}, {} retries): {error}",
+                    job.id, job.job_type, job.queue, job.retries
+                );
+            }
+
+            if let Some(JobCursor { priority, id }) = next.filter(|_| has_more) {
+                println!("There may be more jobs, list them with `--after {priority}:{id}`");
+            }
+
+            for (queue, paused_at) in admin::paused_queues(&mut conn).await? {
+                println!("Queue {queue} is paused since {paused_at}");
+            }
+        }
+        Command::Show { id } => {
+            let job = admin::find_job(&mut conn, id)
+                .await?
+                .with_context(|| format!("Job {id} not found"))?;
+
+            println!("ID:         {}", job.id);
+            println!("Job type:   {}", job.job_type);
+            println!("Queue:      {}", job.queue);
+            println!("Priority:   {}", job.priority);
+            println!("Data:       {}", job.data);
+            println!("Retries:    {}", job.retries);
+            println!("Last retry: {}", job.last_retry);
+            println!("Created at: {}", job.created_at);
+            println!("Run at:     {}", job.run_at);
+
+            if let Some(error) = job.last_error {
+                println!();
+                println!("{error}");
+            }
+        }
+        Command::Status { id } => match admin::job_status(&mut conn, id).await? {
+            JobStatus::Waiting { parents } => {
+                let parents = parents.iter().map(ToString::to_string).collect::<Vec<_>>();
+                println!("Job {id} is waiting for jobs {}", parents.join(", "));
+            }
+            JobStatus::Enqueued => println!("Job {id} is enqueued"),
+            JobStatus::Failed { error } => println!("Job {id} has failed: {error}"),
+            JobStatus::Finished => println!("Job {id} has finished or was cancelled"),
+        },
+        Command::RetryNow { id } => {
+            admin::retry_now(&mut conn, id)
+                .await
+                .with_context(|| format!("Failed to retry job {id}"))?;
+
+            println!("Job {id} will be retried immediately");
+        }
+        Command::Cancel { id } => {
+            admin::cancel_job(&mut conn, id)
+                .await
+                .with_context(|| format!("Failed to cancel job {id}"))?;
+
+            println!("Cancelled job {id}");
+        }
+        Command::PauseQueue { queue } => {
+            admin::pause_queue(&mut conn, &queue, QUEUES)
+                .await
+                .with_context(|| format!("Failed to pause queue {queue}"))?;
+            println!("Paused queue {queue}");
+        }
+        Command::ResumeQueue { queue } => {
+            if admin::resume_queue(&mut conn, &queue).await? {
+                println!("Resumed queue {queue}");
+            } else {
+                println!("Queue {queue} was not paused");
+            }
+        }
+    }
+
+    Ok(())
+}
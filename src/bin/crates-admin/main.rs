@@ -1,13 +1,19 @@
 #[macro_use]
 extern crate tracing;
 
+mod audit_storage;
 mod default_versions;
 mod delete_crate;
 mod delete_version;
 mod dialoguer;
 mod enqueue_job;
+mod export_mirror;
+mod failed_jobs;
+mod import;
+mod jobs;
 mod migrate;
 mod populate;
+mod rate_limit;
 mod render_readmes;
 mod transfer_crates;
 mod upload_index;
@@ -17,8 +23,11 @@ mod yank_version;
 #[derive(clap::Parser, Debug)]
 #[command(name = "crates-admin")]
 enum Command {
+    AuditStorage(audit_storage::Opts),
     DeleteCrate(delete_crate::Opts),
     DeleteVersion(delete_version::Opts),
+    ExportMirror(export_mirror::Opts),
+    Import(import::Opts),
     Populate(populate::Opts),
     RenderReadmes(render_readmes::Opts),
     TransferCrates(transfer_crates::Opts),
@@ -30,6 +39,12 @@ enum Command {
     EnqueueJob(enqueue_job::Command),
     #[clap(subcommand)]
     DefaultVersions(default_versions::Command),
+    #[clap(subcommand)]
+    FailedJobs(failed_jobs::Command),
+    #[clap(subcommand)]
+    Jobs(jobs::Command),
+    #[clap(subcommand)]
+    RateLimit(rate_limit::Command),
 }
 
 #[tokio::main]
@@ -46,8 +61,11 @@ async fn main() -> anyhow::Result<()> {
     span.record("command", tracing::field::debug(&command));
 
     match command {
+        Command::AuditStorage(opts) => audit_storage::run(opts).await,
         Command::DeleteCrate(opts) => delete_crate::run(opts).await,
         Command::DeleteVersion(opts) => delete_version::run(opts).await,
+        Command::ExportMirror(opts) => export_mirror::run(opts).await,
+        Command::Import(opts) => import::run(opts).await,
         Command::Populate(opts) => populate::run(opts).await,
         Command::RenderReadmes(opts) => render_readmes::run(opts).await,
         Command::TransferCrates(opts) => transfer_crates::run(opts).await,
@@ -57,6 +75,9 @@ async fn main() -> anyhow::Result<()> {
         Command::YankVersion(opts) => yank_version::run(opts).await,
         Command::EnqueueJob(command) => enqueue_job::run(command).await,
         Command::DefaultVersions(opts) => default_versions::run(opts).await,
+        Command::FailedJobs(command) => failed_jobs::run(command).await,
+        Command::Jobs(command) => jobs::run(command).await,
+        Command::RateLimit(command) => rate_limit::run(command).await,
     }
 }
 
@@ -0,0 +1,356 @@
+use anyhow::{Context, anyhow};
+use chrono::{TimeDelta, Utc};
+use crates_io::db;
+use crates_io::models::User;
+use crates_io::rate_limiter::{LimitedAction, RateLimitOverrideChange, UserRateLimitOverride};
+use diesel::prelude::*;
+use diesel_async::AsyncPgConnection;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "rate-limit",
+    about = "Inspect and change the publish rate limit overrides of users."
+)]
+pub enum Command {
+    /// List the overrides that have not expired yet
+    List {
+        /// Only list the overrides of the user with this GitHub login
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// Grant a new override to a user, or modify their existing override
+    Set {
+        /// GitHub login of the user
+        user: String,
+
+        /// The rate limited action: `publish_new`, `publish_update` or
+        /// `yank_unyank`
+        action: LimitedAction,
+
+        /// The number of actions that the user can perform in a burst
+        #[arg(long)]
+        burst: i32,
+
+        /// Expire the override after this many hours. Without it, the
+        /// override does not expire.
+        #[arg(long)]
+        expires_in_hours: Option<i64>,
+
+        /// GitHub login of the admin making the change
+        #[arg(long)]
+        admin: String,
+
+        /// Why the override is changed, e.g. a link to the support request
+        #[arg(long)]
+        reason: String,
+    },
+    /// Expire the override of a user immediately
+    Expire {
+        /// GitHub login of the user
+        user: String,
+
+        /// The rate limited action: `publish_new`, `publish_update` or
+        /// `yank_unyank`
+        action: LimitedAction,
+
+        /// GitHub login of the admin making the change
+        #[arg(long)]
+        admin: String,
+
+        /// Why the override is expired
+        #[arg(long)]
+        reason: String,
+    },
+}
+
+pub async fn run(command: Command) -> anyhow::Result<()> {
+    let mut conn = db::oneoff_connection()
+        .await
+        .context("Failed to connect to the database")?;
+
+    execute(command, &mut conn).await
+}
+
+async fn execute(command: Command, conn: &mut AsyncPgConnection) -> anyhow::Result<()> {
+    match command {
+        Command::List { user } => {
+            let user_id = match user {
+                Some(login) => Some(find_user(conn, &login).await?.id),
+                None => None,
+            };
+
+            let overrides = UserRateLimitOverride::list_active(user_id, conn).await?;
+            if overrides.is_empty() {
+                println!("No overrides found");
+            }
+
+            for rate_limit_override in overrides {
+                let expiry = match rate_limit_override.expires_at {
+                    Some(expires_at) => format!("expires at {expires_at}"),
+                    None => "does not expire".to_string(),
+                };
+                println!(
+                    "{} {}: burst of {} ({expiry})",
+                    rate_limit_override.gh_login,
+                    rate_limit_override.action.name(),
+                    rate_limit_override.burst,
+                );
+            }
+        }
+        Command::Set {
+            user,
+            action,
+            burst,
+            expires_in_hours,
+            admin,
+            reason,
+        } => {
+            if burst < 1 {
+                return Err(anyhow!("The burst must be at least 1"));
+            }
+            if expires_in_hours.is_some_and(|hours| hours < 1) {
+                return Err(anyhow!("The override must expire in at least 1 hour"));
+            }
+            let reason = validate_reason(&reason)?;
+
+            let expires_at = expires_in_hours
+                .map(|hours| {
+                    TimeDelta::try_hours(hours)
+                        .and_then(|delta| Utc::now().checked_add_signed(delta))
+                        .ok_or_else(|| anyhow!("The expiry of {hours} hours is out of range"))
+                })
+                .transpose()?;
+
+            let admin = find_admin(conn, &admin).await?;
+            let user = find_user(conn, &user).await?;
+
+            let change = RateLimitOverrideChange {
+                user_id: user.id,
+                action,
+                admin_id: admin.id,
+                reason,
+            };
+            change.set(burst, expires_at, conn).await?;
+
+            match expires_at {
+                Some(expires_at) => println!(
+                    "Set the {} override of {} to a burst of {burst} until {expires_at}",
+                    action.name(),
+                    user.gh_login
+                ),
+                None => println!(
+                    "Set the {} override of {} to a burst of {burst}",
+                    action.name(),
+                    user.gh_login
+                ),
+            }
+        }
+        Command::Expire {
+            user,
+            action,
+            admin,
+            reason,
+        } => {
+            let reason = validate_reason(&reason)?;
+            let admin = find_admin(conn, &admin).await?;
+            let user = find_user(conn, &user).await?;
+
+            let change = RateLimitOverrideChange {
+                user_id: user.id,
+                action,
+                admin_id: admin.id,
+                reason,
+            };
+            if change.expire(conn).await? {
+                println!(
+                    "Expired the {} override of {}",
+                    action.name(),
+                    user.gh_login
+                );
+            } else {
+                println!("{} has no active {} override", user.gh_login, action.name());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Requires a reason for the audit log, like the admin API does.
+fn validate_reason(reason: &str) -> anyhow::Result<&str> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(anyhow!("A reason for the change is required"));
+    }
+
+    Ok(reason)
+}
+
+async fn find_user(conn: &mut AsyncPgConnection, login: &str) -> anyhow::Result<User> {
+    User::find_by_login(conn, login)
+        .await
+        .optional()?
+        .ok_or_else(|| anyhow!("Could not find user with login `{login}`"))
+}
+
+/// Finds the acting admin, who is recorded in the audit log of the change.
+async fn find_admin(conn: &mut AsyncPgConnection, login: &str) -> anyhow::Result<User> {
+    let admin = find_user(conn, login).await?;
+    if !admin.is_admin {
+        return Err(anyhow!("User `{login}` is not a crates.io admin"));
+    }
+
+    Ok(admin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crates_io::models::NewUser;
+    use crates_io::schema::{publish_rate_override_actions, publish_rate_overrides, users};
+    use crates_io_test_db::TestDatabase;
+    use diesel_async::RunQueryDsl;
+
+    async fn create_user(conn: &mut AsyncPgConnection, gh_id: i32, login: &str) -> User {
+        NewUser::builder()
+            .gh_id(gh_id)
+            .gh_login(login)
+            .gh_access_token("token")
+            .build()
+            .insert(conn)
+            .await
+            .unwrap()
+    }
+
+    async fn create_admin(conn: &mut AsyncPgConnection) -> User {
+        let admin = create_user(conn, 1, "admin").await;
+        diesel::update(users::table.find(admin.id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .await
+            .unwrap();
+        admin
+    }
+
+    fn set(user: &str, burst: i32, admin: &str) -> Command {
+        Command::Set {
+            user: user.into(),
+            action: LimitedAction::PublishNew,
+            burst,
+            expires_in_hours: Some(24),
+            admin: admin.into(),
+            reason: "Coordinated release".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn set_and_expire_override() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+        let admin = create_admin(&mut conn).await;
+        let user = create_user(&mut conn, 2, "foo").await;
+
+        execute(set("foo", 100, "admin"), &mut conn).await.unwrap();
+
+        let overrides = UserRateLimitOverride::list_active(Some(user.id), &mut conn)
+            .await
+            .unwrap();
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides[0].burst, 100);
+        assert!(overrides[0].expires_at.unwrap() > Utc::now() + TimeDelta::hours(23));
+
+        let command = Command::Expire {
+            user: "foo".into(),
+            action: LimitedAction::PublishNew,
+            admin: "admin".into(),
+            reason: "Release is done".into(),
+        };
+        execute(command, &mut conn).await.unwrap();
+
+        let overrides = UserRateLimitOverride::list_active(None, &mut conn)
+            .await
+            .unwrap();
+        assert!(overrides.is_empty());
+
+        let actions: Vec<(Option<i32>, Option<i32>, i32, String)> =
+            publish_rate_override_actions::table
+                .select((
+                    publish_rate_override_actions::user_id,
+                    publish_rate_override_actions::admin_id,
+                    publish_rate_override_actions::burst,
+                    publish_rate_override_actions::reason,
+                ))
+                .order(publish_rate_override_actions::id)
+                .load(&mut conn)
+                .await
+                .unwrap();
+
+        let ids = (Some(user.id), Some(admin.id));
+        assert_eq!(
+            actions,
+            vec![
+                (ids.0, ids.1, 100, "Coordinated release".into()),
+                (ids.0, ids.1, 100, "Release is done".into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_changes_are_rejected() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+        create_admin(&mut conn).await;
+        create_user(&mut conn, 2, "foo").await;
+
+        let error = execute(set("foo", 100, "foo"), &mut conn)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "User `foo` is not a crates.io admin");
+
+        let error = execute(set("bar", 100, "admin"), &mut conn)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Could not find user with login `bar`");
+
+        let error = execute(set("foo", 0, "admin"), &mut conn)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "The burst must be at least 1");
+
+        let mut command = set("foo", 100, "admin");
+        if let Command::Set { reason, .. } = &mut command {
+            *reason = "  ".into();
+        }
+        let error = execute(command, &mut conn).await.unwrap_err();
+        assert_eq!(error.to_string(), "A reason for the change is required");
+
+        let mut command = set("foo", 100, "admin");
+        if let Command::Set {
+            expires_in_hours, ..
+        } = &mut command
+        {
+            *expires_in_hours = Some(i64::MAX);
+        }
+        let error = execute(command, &mut conn).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("The expiry of {} hours is out of range", i64::MAX)
+        );
+
+        let command = Command::Expire {
+            user: "foo".into(),
+            action: LimitedAction::PublishNew,
+            admin: "admin".into(),
+            reason: "".into(),
+        };
+        let error = execute(command, &mut conn).await.unwrap_err();
+        assert_eq!(error.to_string(), "A reason for the change is required");
+
+        let count: i64 = publish_rate_overrides::table
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -233,7 +233,7 @@ async fn render_pkg_readme<R: AsyncRead + Unpin>(
 }
 
 /// Search an entry by its path in a Tar archive.
-async fn find_file_by_path<R: AsyncRead + Unpin>(
+pub async fn find_file_by_path<R: AsyncRead + Unpin>(
     entries: &mut tokio_tar::Entries<R>,
     path: &Path,
 ) -> anyhow::Result<String> {
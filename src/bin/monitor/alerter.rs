@@ -0,0 +1,269 @@
+//! The sinks that the results of the checks are sent to.
+//!
+//! The sink is selected by the `MONITOR_ALERTER` environment variable:
+//!
+//! - `pagerduty` (default): pages whoever is on call. Requires
+//!   `PAGERDUTY_API_TOKEN` and `PAGERDUTY_INTEGRATION_KEY`.
+//! - `webhook`: sends a JSON payload for every check result to
+//!   `MONITOR_WEBHOOK_URL`.
+//! - `email`: sends an email to `MONITOR_ALERT_EMAIL` when an alert is
+//!   triggered and when it is resolved again. The triggered alerts are
+//!   tracked in the `monitor_alerts` table, so that an alert that is still
+//!   triggered is not sent again on the next run.
+//! - `stdout`: prints a JSON payload for every check result, which is mostly
+//!   useful for local development.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use crates_io::config;
+use crates_io::email::{Email, Emails};
+use crates_io::schema::monitor_alerts;
+use crates_io_env_vars::{required_var, var};
+use crates_io_pagerduty as pagerduty;
+use crates_io_pagerduty::PagerdutyClient;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+
+/// The result of a check.
+#[derive(Debug)]
+pub enum Alert {
+    /// The check failed, and whoever is on call should be alerted.
+    Trigger(String),
+    /// The check passed, and any previously triggered alert can be resolved.
+    Resolve(String),
+}
+
+#[async_trait]
+pub trait Alerter {
+    /// Sends the result of the check with the given key to the sink.
+    async fn send(&self, conn: &mut AsyncPgConnection, key: &str, alert: &Alert) -> Result<()>;
+}
+
+/// Creates the [Alerter] that is selected by the `MONITOR_ALERTER`
+/// environment variable.
+pub fn from_environment() -> Result<Box<dyn Alerter>> {
+    let alerter = var("MONITOR_ALERTER")?;
+    match alerter.as_deref().unwrap_or("pagerduty") {
+        "pagerduty" => {
+            let api_token = required_var("PAGERDUTY_API_TOKEN")?.into();
+            let service_key = required_var("PAGERDUTY_INTEGRATION_KEY")?;
+            let client = PagerdutyClient::new(api_token, service_key);
+            Ok(Box::new(PagerdutyAlerter(client)))
+        }
+        "webhook" => {
+            let url = required_var("MONITOR_WEBHOOK_URL")?;
+            let client = reqwest::Client::new();
+            Ok(Box::new(WebhookAlerter { client, url }))
+        }
+        "email" => {
+            let recipient = required_var("MONITOR_ALERT_EMAIL")?;
+            let config = config::Server::from_environment()?;
+            let emails = Emails::from_environment(&config);
+            Ok(Box::new(EmailAlerter { emails, recipient }))
+        }
+        "stdout" => Ok(Box::new(StdoutAlerter)),
+        alerter => Err(anyhow!("Unknown `MONITOR_ALERTER`: {alerter}")),
+    }
+}
+
+/// The JSON representation of an alert, as sent by the [WebhookAlerter] and
+/// the [StdoutAlerter].
+#[derive(Debug, Serialize)]
+struct AlertPayload<'a> {
+    key: &'a str,
+    status: &'static str,
+    description: &'a str,
+}
+
+impl<'a> AlertPayload<'a> {
+    fn new(key: &'a str, alert: &'a Alert) -> Self {
+        let (status, description) = match alert {
+            Alert::Trigger(description) => ("trigger", description),
+            Alert::Resolve(description) => ("resolve", description),
+        };
+
+        Self {
+            key,
+            status,
+            description,
+        }
+    }
+}
+
+struct PagerdutyAlerter(PagerdutyClient);
+
+#[async_trait]
+impl Alerter for PagerdutyAlerter {
+    async fn send(&self, _conn: &mut AsyncPgConnection, key: &str, alert: &Alert) -> Result<()> {
+        let event = match alert {
+            Alert::Trigger(description) => pagerduty::Event::Trigger {
+                incident_key: Some(key.into()),
+                description: description.clone(),
+            },
+            Alert::Resolve(description) => pagerduty::Event::Resolve {
+                incident_key: key.into(),
+                description: Some(description.clone()),
+            },
+        };
+
+        self.0.send(&event).await
+    }
+}
+
+struct WebhookAlerter {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[async_trait]
+impl Alerter for WebhookAlerter {
+    async fn send(&self, _conn: &mut AsyncPgConnection, key: &str, alert: &Alert) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(&AlertPayload::new(key, alert))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+struct EmailAlerter {
+    emails: Emails,
+    recipient: String,
+}
+
+#[async_trait]
+impl Alerter for EmailAlerter {
+    async fn send(&self, conn: &mut AsyncPgConnection, key: &str, alert: &Alert) -> Result<()> {
+        // Emails are only sent when the state of an alert changes. The row is
+        // only committed once the email has been sent, so that a failed email
+        // is sent again on the next run.
+        conn.transaction(|conn| {
+            async move {
+                let (changed, resolved, description) = match alert {
+                    Alert::Trigger(description) => {
+                        let inserted = diesel::insert_into(monitor_alerts::table)
+                            .values(monitor_alerts::key.eq(key))
+                            .on_conflict_do_nothing()
+                            .execute(conn)
+                            .await?;
+
+                        (inserted > 0, false, description)
+                    }
+                    Alert::Resolve(description) => {
+                        let deleted = diesel::delete(monitor_alerts::table.find(key))
+                            .execute(conn)
+                            .await?;
+
+                        (deleted > 0, true, description)
+                    }
+                };
+
+                if changed {
+                    let email = AlertEmail {
+                        key,
+                        description,
+                        resolved,
+                    };
+                    self.emails.send(&self.recipient, email).await?;
+                }
+
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+}
+
+struct AlertEmail<'a> {
+    key: &'a str,
+    description: &'a str,
+    resolved: bool,
+}
+
+impl Email for AlertEmail<'_> {
+    fn subject(&self) -> String {
+        if self.resolved {
+            format!("crates.io: Resolved monitoring alert `{}`", self.key)
+        } else {
+            format!("crates.io: Monitoring alert `{}`", self.key)
+        }
+    }
+
+    fn body(&self) -> String {
+        let status = if self.resolved {
+            "has resolved its alert"
+        } else {
+            "has triggered an alert"
+        };
+
+        format!(
+            "The `{key}` check of the crates.io monitor {status}:\n\n{description}",
+            key = self.key,
+            description = self.description,
+        )
+    }
+}
+
+struct StdoutAlerter;
+
+#[async_trait]
+impl Alerter for StdoutAlerter {
+    async fn send(&self, _conn: &mut AsyncPgConnection, key: &str, alert: &Alert) -> Result<()> {
+        let payload = serde_json::to_string(&AlertPayload::new(key, alert))?;
+        println!("{payload}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crates_io_test_db::TestDatabase;
+
+    #[tokio::test]
+    async fn email_alerts_are_only_sent_when_their_state_changes() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        let alerter = EmailAlerter {
+            emails: Emails::new_in_memory(),
+            recipient: "oncall@crates.io".into(),
+        };
+
+        let trigger = Alert::Trigger("Something is wrong".into());
+        let resolve = Alert::Resolve("Everything is fine".into());
+
+        alerter.send(&mut conn, "foo", &resolve).await.unwrap();
+        alerter.send(&mut conn, "foo", &trigger).await.unwrap();
+        alerter.send(&mut conn, "foo", &trigger).await.unwrap();
+        alerter.send(&mut conn, "bar", &trigger).await.unwrap();
+        alerter.send(&mut conn, "foo", &resolve).await.unwrap();
+        alerter.send(&mut conn, "foo", &resolve).await.unwrap();
+
+        let subjects = alerter
+            .emails
+            .mails_in_memory()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, content)| {
+                let subject = content.lines().find(|line| line.starts_with("Subject:"));
+                subject.unwrap_or_default().to_string()
+            })
+            .collect::<Vec<_>>();
+
+        insta::assert_debug_snapshot!(subjects, @r#"
+        [
+            "Subject: crates.io: Monitoring alert `foo`",
+            "Subject: crates.io: Monitoring alert `bar`",
+            "Subject: crates.io: Resolved monitoring alert `foo`",
+        ]
+        "#);
+    }
+}
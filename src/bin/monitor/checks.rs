@@ -0,0 +1,65 @@
+//! The checks that are run by the monitor.
+//!
+//! New checks implement the [Check] trait and are registered in
+//! [from_environment], which reads their thresholds from the environment.
+
+mod cdn_log_backlog;
+mod db_dump;
+mod failing_background_jobs;
+mod readme_rendering;
+mod spam_attack;
+mod stalled_update_downloads;
+mod storage_integrity;
+
+use crate::alerter::Alert;
+use anyhow::Result;
+use async_trait::async_trait;
+use crates_io_env_vars::{list, var_parsed};
+use diesel_async::AsyncPgConnection;
+use std::collections::HashSet;
+
+#[async_trait]
+pub trait Check: Send + Sync {
+    /// The unique key of the check, which is also used as the incident key of
+    /// its alerts.
+    fn key(&self) -> &'static str;
+
+    /// A short description of what the check is looking for.
+    fn description(&self) -> &'static str;
+
+    async fn run(&self, conn: &mut AsyncPgConnection) -> Result<Alert>;
+}
+
+/// Creates all checks that are not disabled by the `MONITOR_DISABLED_CHECKS`
+/// environment variable, configured by their respective environment
+/// variables.
+pub fn from_environment() -> Result<Vec<Box<dyn Check>>> {
+    let checks: Vec<Box<dyn Check>> = vec![
+        Box::new(failing_background_jobs::FailingBackgroundJobs {
+            max_job_time: var_parsed("MAX_JOB_TIME")?.unwrap_or(15),
+        }),
+        Box::new(stalled_update_downloads::StalledUpdateDownloads {
+            max_job_time: var_parsed("MONITOR_MAX_UPDATE_DOWNLOADS_TIME")?.unwrap_or(120),
+        }),
+        Box::new(spam_attack::SpamAttack {
+            bad_crate_names: list("SPAM_CRATE_NAMES")?,
+        }),
+        Box::new(storage_integrity::StorageIntegrity),
+        Box::new(cdn_log_backlog::CdnLogBacklog {
+            max_backlog: var_parsed("MONITOR_MAX_CDN_LOG_BACKLOG")?.unwrap_or(1000),
+        }),
+        Box::new(readme_rendering::ReadmeRendering {
+            max_failures: var_parsed("MONITOR_MAX_README_FAILURES")?.unwrap_or(10),
+        }),
+        Box::new(db_dump::DbDump {
+            max_age: var_parsed("MONITOR_MAX_DB_DUMP_AGE")?.unwrap_or(30),
+        }),
+    ];
+
+    let disabled: HashSet<_> = list("MONITOR_DISABLED_CHECKS")?.into_iter().collect();
+
+    Ok(checks
+        .into_iter()
+        .filter(|check| !disabled.contains(check.key()))
+        .collect())
+}
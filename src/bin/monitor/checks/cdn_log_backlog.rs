@@ -0,0 +1,81 @@
+use super::Check;
+use crate::alerter::Alert;
+use anyhow::Result;
+use async_trait::async_trait;
+use crates_io::schema::background_jobs;
+use crates_io::worker::jobs;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// Check for a backlog of CDN log files that are waiting to be processed,
+/// which means that the download counts are falling behind.
+pub struct CdnLogBacklog {
+    /// Max number of enqueued `process_cdn_log` jobs
+    pub max_backlog: i64,
+}
+
+#[async_trait]
+impl Check for CdnLogBacklog {
+    fn key(&self) -> &'static str {
+        "cdn_log_backlog"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checking for a backlog of CDN log files"
+    }
+
+    async fn run(&self, conn: &mut AsyncPgConnection) -> Result<Alert> {
+        let backlog: i64 = background_jobs::table
+            .filter(background_jobs::job_type.eq(jobs::ProcessCdnLog::JOB_NAME))
+            .count()
+            .get_result(conn)
+            .await?;
+
+        Ok(if backlog > self.max_backlog {
+            Alert::Trigger(format!(
+                "{backlog} CDN log files are waiting to be processed"
+            ))
+        } else {
+            Alert::Resolve("No CDN log backlog".into())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crates_io_test_db::TestDatabase;
+
+    const CHECK: CdnLogBacklog = CdnLogBacklog { max_backlog: 2 };
+
+    async fn insert_jobs(conn: &mut AsyncPgConnection, job_type: &str, count: usize) {
+        let job = (
+            background_jobs::job_type.eq(job_type),
+            background_jobs::data.eq(serde_json::Value::Null),
+        );
+
+        diesel::insert_into(background_jobs::table)
+            .values(vec![job; count])
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    async fn is_triggered(conn: &mut AsyncPgConnection) -> bool {
+        matches!(CHECK.run(conn).await.unwrap(), Alert::Trigger(_))
+    }
+
+    #[tokio::test]
+    async fn backlog_triggers() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        insert_jobs(&mut conn, "other", 5).await;
+        insert_jobs(&mut conn, jobs::ProcessCdnLog::JOB_NAME, 2).await;
+        assert!(!is_triggered(&mut conn).await);
+
+        insert_jobs(&mut conn, jobs::ProcessCdnLog::JOB_NAME, 1).await;
+        assert!(is_triggered(&mut conn).await);
+    }
+}
@@ -0,0 +1,86 @@
+use super::Check;
+use crate::alerter::Alert;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crates_io::storage::Storage;
+use diesel_async::AsyncPgConnection;
+
+/// The path of the database dump, as uploaded by the `dump_db` job.
+const DB_DUMP_PATH: &str = "db-dump.tar.gz";
+
+/// Check that the `dump_db` job has recently uploaded a new database dump.
+///
+/// The storage is only configured once the check is run, so that the other
+/// checks don't require the storage environment variables.
+pub struct DbDump {
+    /// Max age of the database dump in hours
+    pub max_age: i64,
+}
+
+#[async_trait]
+impl Check for DbDump {
+    fn key(&self) -> &'static str {
+        "db_dump_stale"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checking for a stale database dump"
+    }
+
+    async fn run(&self, _conn: &mut AsyncPgConnection) -> Result<Alert> {
+        let storage = Storage::try_from_environment()?;
+        self.check(&storage, Utc::now()).await
+    }
+}
+
+impl DbDump {
+    async fn check(&self, storage: &Storage, now: DateTime<Utc>) -> Result<Alert> {
+        let Some(last_modified) = storage.db_dump_last_modified(DB_DUMP_PATH).await? else {
+            return Ok(Alert::Trigger("No database dump found".into()));
+        };
+
+        let hours = now.signed_duration_since(last_modified).num_hours();
+
+        Ok(if hours > self.max_age {
+            Alert::Trigger(format!(
+                "The last database dump was uploaded {hours} hours ago"
+            ))
+        } else {
+            Alert::Resolve("Database dump is up to date".into())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+    use crates_io::storage::StorageConfig;
+    use std::io::Write;
+
+    const CHECK: DbDump = DbDump { max_age: 24 };
+
+    #[tokio::test]
+    async fn stale_or_missing_dump_triggers() {
+        let storage = Storage::from_config(&StorageConfig::in_memory());
+        let now = Utc::now();
+
+        let alert = CHECK.check(&storage, now).await.unwrap();
+        assert!(matches!(alert, Alert::Trigger(_)));
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"dump").unwrap();
+        storage
+            .upload_db_dump(DB_DUMP_PATH, file.path())
+            .await
+            .unwrap();
+
+        let alert = CHECK.check(&storage, now).await.unwrap();
+        assert!(matches!(alert, Alert::Resolve(_)));
+
+        let later = now + TimeDelta::hours(26);
+        let alert = CHECK.check(&storage, later).await.unwrap();
+        assert!(matches!(alert, Alert::Trigger(_)));
+    }
+}
@@ -0,0 +1,164 @@
+use super::Check;
+use crate::alerter::Alert;
+use anyhow::Result;
+use async_trait::async_trait;
+use crates_io::schema::{
+    background_job_dependencies, background_jobs, paused_background_job_queues,
+};
+use diesel::dsl::*;
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Timestamptz};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// Check for old background jobs that are not currently running.
+///
+/// This check includes `skip_locked` in the query and will only trigger on
+/// enqueued jobs that have attempted to run and have failed (and are in the
+/// queue awaiting a retry).
+///
+/// Within the default 15 minute time, a job should have already had several
+/// failed retry attempts. Jobs are only considered once their `run_at` time
+/// has passed, and jobs in paused queues or waiting for their parent jobs are
+/// ignored, since they are not expected to run yet.
+pub struct FailingBackgroundJobs {
+    /// Max job execution time in minutes
+    pub max_job_time: i32,
+}
+
+#[async_trait]
+impl Check for FailingBackgroundJobs {
+    fn key(&self) -> &'static str {
+        "background_jobs"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checking for failed background jobs"
+    }
+
+    async fn run(&self, conn: &mut AsyncPgConnection) -> Result<Alert> {
+        let max_job_time = self.max_job_time;
+
+        let is_paused = exists(
+            paused_background_job_queues::table
+                .filter(paused_background_job_queues::queue.eq(background_jobs::queue)),
+        );
+        let has_parents = exists(
+            background_job_dependencies::table
+                .filter(background_job_dependencies::job_id.eq(background_jobs::id)),
+        );
+
+        let stalled_jobs: Vec<i32> = background_jobs::table
+            .select(1.into_sql::<Integer>())
+            .filter(
+                background_jobs::run_at.lt(now.into_sql::<Timestamptz>() - max_job_time.minutes()),
+            )
+            .filter(background_jobs::priority.ge(0))
+            .filter(not(is_paused))
+            .filter(not(has_parents))
+            .for_update()
+            .skip_locked()
+            .load(conn)
+            .await?;
+
+        let stalled_job_count = stalled_jobs.len();
+
+        Ok(if stalled_job_count > 0 {
+            Alert::Trigger(format!(
+                "{stalled_job_count} jobs have been in the queue for more than {max_job_time} minutes"
+            ))
+        } else {
+            Alert::Resolve("No stalled background jobs".into())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, Utc};
+    use crates_io_test_db::TestDatabase;
+
+    const CHECK: FailingBackgroundJobs = FailingBackgroundJobs { max_job_time: 15 };
+
+    async fn insert_job(conn: &mut AsyncPgConnection, queue: &str, minutes_ago: i64) -> i64 {
+        let run_at = Utc::now() - TimeDelta::minutes(minutes_ago);
+
+        diesel::insert_into(background_jobs::table)
+            .values((
+                background_jobs::job_type.eq("test"),
+                background_jobs::data.eq(serde_json::Value::Null),
+                background_jobs::queue.eq(queue),
+                background_jobs::run_at.eq(run_at),
+            ))
+            .returning(background_jobs::id)
+            .get_result(conn)
+            .await
+            .unwrap()
+    }
+
+    async fn is_triggered(conn: &mut AsyncPgConnection) -> bool {
+        matches!(CHECK.run(conn).await.unwrap(), Alert::Trigger(_))
+    }
+
+    #[tokio::test]
+    async fn old_jobs_trigger() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        insert_job(&mut conn, "default", 5).await;
+        assert!(!is_triggered(&mut conn).await);
+
+        insert_job(&mut conn, "default", 30).await;
+        assert!(is_triggered(&mut conn).await);
+    }
+
+    #[tokio::test]
+    async fn delayed_jobs_are_ignored() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        // Enqueued long ago, but only due in the future
+        let id = insert_job(&mut conn, "default", -60).await;
+        diesel::update(background_jobs::table.find(id))
+            .set(background_jobs::created_at.eq(Utc::now() - TimeDelta::hours(2)))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        assert!(!is_triggered(&mut conn).await);
+    }
+
+    #[tokio::test]
+    async fn jobs_in_paused_queues_are_ignored() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        insert_job(&mut conn, "paused", 30).await;
+        diesel::insert_into(paused_background_job_queues::table)
+            .values(paused_background_job_queues::queue.eq("paused"))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        assert!(!is_triggered(&mut conn).await);
+    }
+
+    #[tokio::test]
+    async fn jobs_waiting_for_parents_are_ignored() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        let parent_id = insert_job(&mut conn, "default", 5).await;
+        let job_id = insert_job(&mut conn, "default", 30).await;
+        diesel::insert_into(background_job_dependencies::table)
+            .values((
+                background_job_dependencies::job_id.eq(job_id),
+                background_job_dependencies::parent_id.eq(parent_id),
+            ))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        assert!(!is_triggered(&mut conn).await);
+    }
+}
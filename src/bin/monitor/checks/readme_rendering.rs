@@ -0,0 +1,132 @@
+use super::Check;
+use crate::alerter::Alert;
+use anyhow::Result;
+use async_trait::async_trait;
+use crates_io::schema::{background_jobs, failed_background_jobs};
+use crates_io::worker::jobs;
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::*;
+use diesel::prelude::*;
+use diesel::sql_types::Timestamptz;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// Check for readmes that could not be rendered, either because their jobs
+/// are currently being retried, or because they have failed within the last
+/// day.
+pub struct ReadmeRendering {
+    /// Max number of failing `render_and_upload_readme` jobs
+    pub max_failures: i64,
+}
+
+#[async_trait]
+impl Check for ReadmeRendering {
+    fn key(&self) -> &'static str {
+        "readme_rendering"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checking for readme rendering failures"
+    }
+
+    async fn run(&self, conn: &mut AsyncPgConnection) -> Result<Alert> {
+        const JOB_NAME: &str = jobs::RenderAndUploadReadme::JOB_NAME;
+
+        let retrying: i64 = background_jobs::table
+            .filter(background_jobs::job_type.eq(JOB_NAME))
+            .filter(background_jobs::retries.gt(0))
+            .count()
+            .get_result(conn)
+            .await?;
+
+        let one_day_ago = now.into_sql::<Timestamptz>() - 1.day();
+        let failed: i64 = failed_background_jobs::table
+            .filter(failed_background_jobs::job_type.eq(JOB_NAME))
+            .filter(failed_background_jobs::failed_at.gt(one_day_ago))
+            .count()
+            .get_result(conn)
+            .await?;
+
+        let failures = retrying + failed;
+
+        Ok(if failures > self.max_failures {
+            Alert::Trigger(format!(
+                "{failures} readmes could not be rendered ({retrying} are being retried, {failed} have failed within the last day)"
+            ))
+        } else {
+            Alert::Resolve("No readme rendering failures".into())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, Utc};
+    use crates_io_test_db::TestDatabase;
+
+    const CHECK: ReadmeRendering = ReadmeRendering { max_failures: 1 };
+    const JOB_NAME: &str = jobs::RenderAndUploadReadme::JOB_NAME;
+
+    async fn insert_job(conn: &mut AsyncPgConnection, job_type: &str, retries: i32) {
+        diesel::insert_into(background_jobs::table)
+            .values((
+                background_jobs::job_type.eq(job_type),
+                background_jobs::data.eq(serde_json::Value::Null),
+                background_jobs::retries.eq(retries),
+            ))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    async fn insert_failed_job(conn: &mut AsyncPgConnection, id: i64, hours_ago: i64) {
+        let failed_at = Utc::now() - TimeDelta::hours(hours_ago);
+
+        diesel::insert_into(failed_background_jobs::table)
+            .values((
+                failed_background_jobs::id.eq(id),
+                failed_background_jobs::job_type.eq(JOB_NAME),
+                failed_background_jobs::data.eq(serde_json::Value::Null),
+                failed_background_jobs::retries.eq(5),
+                failed_background_jobs::priority.eq(0),
+                failed_background_jobs::queue.eq("default"),
+                failed_background_jobs::created_at.eq(failed_at),
+                failed_background_jobs::failed_at.eq(failed_at),
+                failed_background_jobs::error.eq("failed"),
+            ))
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+
+    async fn is_triggered(conn: &mut AsyncPgConnection) -> bool {
+        matches!(CHECK.run(conn).await.unwrap(), Alert::Trigger(_))
+    }
+
+    #[tokio::test]
+    async fn retried_and_failed_jobs_trigger() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        // Jobs that have not been retried yet and other job types don't count
+        insert_job(&mut conn, JOB_NAME, 0).await;
+        insert_job(&mut conn, "other", 3).await;
+        assert!(!is_triggered(&mut conn).await);
+
+        insert_job(&mut conn, JOB_NAME, 1).await;
+        assert!(!is_triggered(&mut conn).await);
+
+        insert_failed_job(&mut conn, 1, 2).await;
+        assert!(is_triggered(&mut conn).await);
+    }
+
+    #[tokio::test]
+    async fn old_failures_are_ignored() {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        insert_failed_job(&mut conn, 1, 30).await;
+        insert_failed_job(&mut conn, 2, 48).await;
+        assert!(!is_triggered(&mut conn).await);
+    }
+}
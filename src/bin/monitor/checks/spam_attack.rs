@@ -0,0 +1,41 @@
+use super::Check;
+use crate::alerter::Alert;
+use anyhow::Result;
+use async_trait::async_trait;
+use crates_io::schema::crates;
+use crates_io_diesel_helpers::canon_crate_name;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// Check for known spam patterns
+pub struct SpamAttack {
+    pub bad_crate_names: Vec<String>,
+}
+
+#[async_trait]
+impl Check for SpamAttack {
+    fn key(&self) -> &'static str {
+        "spam_attack"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checking for crates indicating someone is spamming us"
+    }
+
+    async fn run(&self, conn: &mut AsyncPgConnection) -> Result<Alert> {
+        let bad_crate: Option<String> = crates::table
+            .filter(canon_crate_name(crates::name).eq_any(&self.bad_crate_names))
+            .select(crates::name)
+            .first(conn)
+            .await
+            .optional()?;
+
+        Ok(if let Some(bad_crate) = bad_crate {
+            Alert::Trigger(format!(
+                "Crate named {bad_crate} published, possible spam attack underway"
+            ))
+        } else {
+            Alert::Resolve("No spam crates detected".into())
+        })
+    }
+}
@@ -0,0 +1,48 @@
+use super::Check;
+use crate::alerter::Alert;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crates_io::schema::background_jobs;
+use crates_io::worker::jobs;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// Check for an `update_downloads` job that has run longer than expected
+pub struct StalledUpdateDownloads {
+    /// Max job execution time in minutes
+    pub max_job_time: i64,
+}
+
+#[async_trait]
+impl Check for StalledUpdateDownloads {
+    fn key(&self) -> &'static str {
+        "update_downloads_stalled"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checking for stalled background jobs"
+    }
+
+    async fn run(&self, conn: &mut AsyncPgConnection) -> Result<Alert> {
+        let start_time: Option<DateTime<Utc>> = background_jobs::table
+            .filter(background_jobs::job_type.eq(jobs::UpdateDownloads::JOB_NAME))
+            .select(background_jobs::created_at)
+            .first(conn)
+            .await
+            .optional()?;
+
+        if let Some(start_time) = start_time {
+            let minutes = Utc::now().signed_duration_since(start_time).num_minutes();
+
+            if minutes > self.max_job_time {
+                return Ok(Alert::Trigger(format!(
+                    "update_downloads job running for {minutes} minutes"
+                )));
+            }
+        };
+
+        Ok(Alert::Resolve("No stalled update_downloads job".into()))
+    }
+}
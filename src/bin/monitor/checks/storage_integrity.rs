@@ -0,0 +1,36 @@
+use super::Check;
+use crate::alerter::Alert;
+use anyhow::Result;
+use async_trait::async_trait;
+use crates_io::schema::storage_integrity_issues;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// Check for missing or corrupted files detected by the `audit_storage` job
+pub struct StorageIntegrity;
+
+#[async_trait]
+impl Check for StorageIntegrity {
+    fn key(&self) -> &'static str {
+        "storage_integrity"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checking for missing or corrupted files in the storage backend"
+    }
+
+    async fn run(&self, conn: &mut AsyncPgConnection) -> Result<Alert> {
+        let num_issues: i64 = storage_integrity_issues::table
+            .count()
+            .get_result(conn)
+            .await?;
+
+        Ok(if num_issues > 0 {
+            Alert::Trigger(format!(
+                "{num_issues} missing or corrupted files found in the storage backend, see the `storage_integrity_issues` table"
+            ))
+        } else {
+            Alert::Resolve("No storage integrity issues found".into())
+        })
+    }
+}
@@ -0,0 +1,61 @@
+//! Checks for any invariants we expect to be true, and alerts whoever is on
+//! call if they are not.
+//!
+//! The checks are registered in the [checks] module, and their thresholds can
+//! be configured through environment variables. Checks can be disabled by
+//! listing their keys in the comma-separated `MONITOR_DISABLED_CHECKS`
+//! environment variable.
+//!
+//! The alerts are sent to PagerDuty by default. See the [alerter] module for
+//! the other available alert sinks.
+//!
+//! Usage:
+//!     cargo run --bin monitor
+
+mod alerter;
+mod checks;
+
+use crate::alerter::Alert;
+use anyhow::{Result, anyhow};
+use crates_io::db;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let alerter = alerter::from_environment()?;
+    let checks = checks::from_environment()?;
+
+    let conn = &mut db::oneoff_connection().await?;
+
+    let mut failed_checks = Vec::new();
+    for check in checks {
+        let key = check.key();
+
+        println!("{}", check.description());
+
+        let result = match check.run(conn).await {
+            Ok(alert) => {
+                match &alert {
+                    Alert::Trigger(description) => println!("Alerting on-call: {description}"),
+                    Alert::Resolve(description) => println!("{description}"),
+                }
+
+                alerter.send(conn, key, &alert).await
+            }
+            Err(error) => Err(error),
+        };
+
+        // A single failing check should not prevent the other checks from
+        // running, so the errors are only reported at the end.
+        if let Err(error) = result {
+            eprintln!("Failed to run the `{key}` check: {error:?}");
+            failed_checks.push(key);
+        }
+    }
+
+    if !failed_checks.is_empty() {
+        let failed_checks = failed_checks.join(", ");
+        return Err(anyhow!("Failed to run checks: {failed_checks}"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,329 @@
+//! Cache invalidation for the CDNs in front of our storage buckets.
+//!
+//! The [CdnPurger] trait is implemented by the [CloudFront](crate::cloudfront::CloudFront)
+//! and [Fastly](crate::fastly::Fastly) clients, and by the [InMemoryCdn] fake
+//! that is used in tests. Purge requests are usually sent through a
+//! [PurgeBatcher], which coalesces requests that are issued within a short
+//! time window into a single request per CDN.
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use futures_util::FutureExt;
+use futures_util::future::Shared;
+use parking_lot::Mutex;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// The default time window in which purge requests are coalesced.
+const DEFAULT_WINDOW: Duration = Duration::from_millis(250);
+
+/// A surrogate key (also known as cache tag) that is attached to uploaded
+/// files, allowing all files with the same key to be purged at once.
+///
+/// The key is stored in the `surrogate-key` metadata of the uploaded files,
+/// which S3 returns as the `x-amz-meta-surrogate-key` header. Fastly only
+/// purges by key if the cached response has a `Surrogate-Key` header, so the
+/// Fastly services have to copy the header over, as described in
+/// `docs/CDN.md`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SurrogateKey {
+    /// All crate files, READMEs and the RSS feed of a crate.
+    Crate(String),
+    /// The sparse index file of a crate.
+    Index(String),
+}
+
+impl SurrogateKey {
+    pub fn krate(name: impl Into<String>) -> Self {
+        Self::Crate(name.into())
+    }
+
+    /// The index file names are lowercase, so the key is too.
+    pub fn index(name: &str) -> Self {
+        Self::Index(name.to_lowercase())
+    }
+
+    /// Returns the paths that are covered by this key, for CDNs that do not
+    /// support purging by surrogate key but support wildcard invalidations.
+    pub fn paths(&self) -> Vec<String> {
+        match self {
+            Self::Crate(name) => vec![
+                format!("/crates/{name}/*"),
+                format!("/readmes/{name}/*"),
+                format!("/rss/crates/{name}.xml"),
+            ],
+            Self::Index(name) => {
+                let path = crates_io_index::Repository::relative_index_file_for_url(name);
+                vec![format!("/{path}")]
+            }
+        }
+    }
+}
+
+impl fmt::Display for SurrogateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Crate(name) => write!(f, "crate:{name}"),
+            Self::Index(name) => write!(f, "index:{name}"),
+        }
+    }
+}
+
+/// The [CdnPurger] trait defines a basic interface for invalidating cached
+/// content on a CDN.
+///
+/// The [InMemoryCdn] struct implements this trait by recording all purge
+/// requests, which can be used in tests to check which content would have
+/// been invalidated.
+#[async_trait]
+pub trait CdnPurger: Send + Sync {
+    /// Invalidates the given paths, e.g. `crates/foo/foo-1.0.0.crate`.
+    async fn purge_paths(&self, paths: &[String]) -> anyhow::Result<()>;
+
+    /// Invalidates all content that is tagged with any of the given keys.
+    async fn purge_keys(&self, keys: &[SurrogateKey]) -> anyhow::Result<()>;
+}
+
+/// A single request that was sent to an [InMemoryCdn].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Purge {
+    Paths(Vec<String>),
+    Keys(Vec<String>),
+}
+
+/// A [CdnPurger] implementation that records all purge requests instead of
+/// sending them to a real CDN.
+#[derive(Debug, Default)]
+pub struct InMemoryCdn {
+    purges: Mutex<Vec<Purge>>,
+}
+
+impl InMemoryCdn {
+    /// Returns all purge requests that were received so far.
+    pub fn purges(&self) -> Vec<Purge> {
+        self.purges.lock().clone()
+    }
+}
+
+#[async_trait]
+impl CdnPurger for InMemoryCdn {
+    async fn purge_paths(&self, paths: &[String]) -> anyhow::Result<()> {
+        self.purges.lock().push(Purge::Paths(paths.to_vec()));
+        Ok(())
+    }
+
+    async fn purge_keys(&self, keys: &[SurrogateKey]) -> anyhow::Result<()> {
+        let keys = keys.iter().map(ToString::to_string).collect();
+        self.purges.lock().push(Purge::Keys(keys));
+        Ok(())
+    }
+}
+
+type BatchResult = Result<(), Arc<anyhow::Error>>;
+
+/// Coalesces purge requests for a single CDN.
+///
+/// The first request starts a time window, and all requests that are made
+/// within that window are merged into a single batch. Once the window has
+/// elapsed, the batch is sent to the CDN and all callers receive the result.
+#[derive(Clone)]
+pub struct PurgeBatcher {
+    inner: Arc<BatcherInner>,
+}
+
+struct BatcherInner {
+    purger: Arc<dyn CdnPurger>,
+    window: Duration,
+    pending: Mutex<Option<PendingBatch>>,
+}
+
+struct PendingBatch {
+    paths: BTreeSet<String>,
+    keys: BTreeSet<SurrogateKey>,
+    result: Shared<oneshot::Receiver<BatchResult>>,
+}
+
+impl PurgeBatcher {
+    pub fn new(purger: Arc<dyn CdnPurger>) -> Self {
+        Self::with_window(purger, DEFAULT_WINDOW)
+    }
+
+    /// Creates a new [PurgeBatcher] that coalesces all purge requests within
+    /// the given time window.
+    pub fn with_window(purger: Arc<dyn CdnPurger>, window: Duration) -> Self {
+        let inner = BatcherInner {
+            purger,
+            window,
+            pending: Mutex::new(None),
+        };
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Invalidates the given paths on the CDN.
+    pub async fn purge_paths(&self, paths: Vec<String>) -> anyhow::Result<()> {
+        self.purge(paths, vec![]).await
+    }
+
+    /// Invalidates all content tagged with any of the given keys on the CDN.
+    pub async fn purge_keys(&self, keys: Vec<SurrogateKey>) -> anyhow::Result<()> {
+        self.purge(vec![], keys).await
+    }
+
+    /// Adds the paths and keys to the current batch and waits until the
+    /// batch has been sent to the CDN.
+    pub async fn purge(&self, paths: Vec<String>, keys: Vec<SurrogateKey>) -> anyhow::Result<()> {
+        if paths.is_empty() && keys.is_empty() {
+            return Ok(());
+        }
+
+        let result = {
+            let mut pending = self.inner.pending.lock();
+            let batch = pending.get_or_insert_with(|| self.start_batch());
+            batch.paths.extend(paths);
+            batch.keys.extend(keys);
+            batch.result.clone()
+        };
+
+        match result.await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => Err(anyhow!("{error:#}")),
+            Err(_) => Err(anyhow!("CDN purge task was aborted")),
+        }
+    }
+
+    fn start_batch(&self) -> PendingBatch {
+        let (sender, receiver) = oneshot::channel();
+
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(inner.window).await;
+
+            let batch = inner.pending.lock().take();
+            if let Some(batch) = batch {
+                let result = inner.send(batch).await.map_err(Arc::new);
+                let _ = sender.send(result);
+            }
+        });
+
+        PendingBatch {
+            paths: BTreeSet::new(),
+            keys: BTreeSet::new(),
+            result: receiver.shared(),
+        }
+    }
+}
+
+impl BatcherInner {
+    async fn send(&self, batch: PendingBatch) -> anyhow::Result<()> {
+        if !batch.paths.is_empty() {
+            let paths = batch.paths.into_iter().collect::<Vec<_>>();
+            debug!(num_paths = paths.len(), "Purging paths from CDN");
+            self.purger.purge_paths(&paths).await?;
+        }
+
+        if !batch.keys.is_empty() {
+            let keys = batch.keys.into_iter().collect::<Vec<_>>();
+            debug!(num_keys = keys.len(), "Purging surrogate keys from CDN");
+            self.purger.purge_keys(&keys).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    struct FailingCdn;
+
+    #[async_trait]
+    impl CdnPurger for FailingCdn {
+        async fn purge_paths(&self, _paths: &[String]) -> anyhow::Result<()> {
+            Err(anyhow!("purge failed"))
+        }
+
+        async fn purge_keys(&self, _keys: &[SurrogateKey]) -> anyhow::Result<()> {
+            Err(anyhow!("purge failed"))
+        }
+    }
+
+    #[test]
+    fn test_surrogate_key() {
+        let key = SurrogateKey::krate("foo");
+        assert_eq!(key.to_string(), "crate:foo");
+        assert_debug_snapshot!(key.paths(), @r#"
+        [
+            "/crates/foo/*",
+            "/readmes/foo/*",
+            "/rss/crates/foo.xml",
+        ]
+        "#);
+
+        let key = SurrogateKey::index("Foo-Bar");
+        assert_eq!(key.to_string(), "index:foo-bar");
+        assert_debug_snapshot!(key.paths(), @r#"
+        [
+            "/fo/o-/foo-bar",
+        ]
+        "#);
+    }
+
+    #[tokio::test]
+    async fn test_coalescing() {
+        let cdn = Arc::new(InMemoryCdn::default());
+        let batcher = PurgeBatcher::new(cdn.clone());
+
+        let (first, second, third) = tokio::join!(
+            batcher.purge_paths(vec!["crates/foo/foo-1.0.0.crate".into()]),
+            batcher.purge_keys(vec![SurrogateKey::krate("foo")]),
+            batcher.purge(
+                vec!["index/3/f/foo".into(), "crates/foo/foo-1.0.0.crate".into()],
+                vec![SurrogateKey::krate("bar")],
+            ),
+        );
+        assert_ok!(first);
+        assert_ok!(second);
+        assert_ok!(third);
+
+        assert_debug_snapshot!(cdn.purges(), @r#"
+        [
+            Paths(
+                [
+                    "crates/foo/foo-1.0.0.crate",
+                    "index/3/f/foo",
+                ],
+            ),
+            Keys(
+                [
+                    "crate:bar",
+                    "crate:foo",
+                ],
+            ),
+        ]
+        "#);
+
+        // A new request after the batch was sent starts a new batch
+        assert_ok!(batcher.purge_keys(vec![SurrogateKey::krate("baz")]).await);
+        assert_eq!(cdn.purges().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let batcher = PurgeBatcher::with_window(Arc::new(FailingCdn), Duration::ZERO);
+
+        let (first, second) = tokio::join!(
+            batcher.purge_paths(vec!["crates/foo/foo-1.0.0.crate".into()]),
+            batcher.purge_paths(vec!["crates/bar/bar-1.0.0.crate".into()]),
+        );
+        assert_eq!(assert_err!(first).to_string(), "purge failed");
+        assert_eq!(assert_err!(second).to_string(), "purge failed");
+    }
+}
@@ -1,9 +1,19 @@
+use crate::cdn::{CdnPurger, SurrogateKey};
+use async_trait::async_trait;
 use aws_credential_types::Credentials;
 use aws_sdk_cloudfront::config::retry::RetryConfig;
 use aws_sdk_cloudfront::config::{BehaviorVersion, Region};
 use aws_sdk_cloudfront::types::{InvalidationBatch, Paths};
 use aws_sdk_cloudfront::{Client, Config};
 
+/// The maximum number of paths in a single invalidation request.
+///
+/// See <https://docs.aws.amazon.com/AmazonCloudFront/latest/DeveloperGuide/cloudfront-limits.html#limits-invalidations>
+const MAX_PATHS_PER_INVALIDATION: usize = 3000;
+
+/// The maximum number of wildcard paths in a single invalidation request.
+const MAX_WILDCARD_PATHS_PER_INVALIDATION: usize = 15;
+
 pub struct CloudFront {
     client: Client,
     distribution_id: String,
@@ -40,10 +50,11 @@ impl CloudFront {
     }
 
     /// Invalidate multiple paths on Cloudfront.
+    ///
+    /// The paths are split into multiple invalidation requests if they exceed the limits of
+    /// CloudFront.
     #[instrument(skip(self))]
     pub async fn invalidate_many(&self, mut paths: Vec<String>) -> anyhow::Result<()> {
-        let now = chrono::offset::Utc::now().timestamp_micros();
-
         // We need to ensure that paths have a starting slash.
         for path in paths.iter_mut() {
             if !path.starts_with('/') {
@@ -51,6 +62,23 @@ impl CloudFront {
             }
         }
 
+        let (wildcard_paths, paths): (Vec<_>, Vec<_>) =
+            paths.into_iter().partition(|path| path.contains('*'));
+
+        let chunks = wildcard_paths
+            .chunks(MAX_WILDCARD_PATHS_PER_INVALIDATION)
+            .chain(paths.chunks(MAX_PATHS_PER_INVALIDATION));
+
+        for (index, chunk) in chunks.enumerate() {
+            self.create_invalidation(chunk.to_vec(), index).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn create_invalidation(&self, paths: Vec<String>, index: usize) -> anyhow::Result<()> {
+        let now = chrono::offset::Utc::now().timestamp_micros();
+
         let paths = Paths::builder()
             // It looks like you have to set quantity even if you provide a full blown Vec, because
             // reasons.
@@ -59,7 +87,7 @@ impl CloudFront {
             .build()?;
 
         let invalidation_batch = InvalidationBatch::builder()
-            .caller_reference(format!("{now}"))
+            .caller_reference(format!("{now}-{index}"))
             .paths(paths)
             .build()?;
 
@@ -83,3 +111,17 @@ impl CloudFront {
         }
     }
 }
+
+#[async_trait]
+impl CdnPurger for CloudFront {
+    async fn purge_paths(&self, paths: &[String]) -> anyhow::Result<()> {
+        self.invalidate_many(paths.to_vec()).await
+    }
+
+    /// CloudFront does not support purging by surrogate key, so the keys are
+    /// converted into wildcard invalidations instead.
+    async fn purge_keys(&self, keys: &[SurrogateKey]) -> anyhow::Result<()> {
+        let paths = keys.iter().flat_map(SurrogateKey::paths).collect();
+        self.invalidate_many(paths).await
+    }
+}
@@ -2,12 +2,14 @@ mod base;
 mod cdn_log_queue;
 mod cdn_log_storage;
 mod database_pools;
+mod read_rate_limits;
 mod sentry;
 mod server;
 
 pub use self::base::Base;
 pub use self::cdn_log_queue::CdnLogQueueConfig;
-pub use self::cdn_log_storage::CdnLogStorageConfig;
+pub use self::cdn_log_storage::{CdnLogStorageBackend, CdnLogStorageConfig};
 pub use self::database_pools::{DatabasePools, DbPoolConfig};
+pub use self::read_rate_limits::ReadRateLimitConfig;
 pub use self::sentry::SentryConfig;
 pub use self::server::Server;
@@ -1,10 +1,22 @@
 use anyhow::Context;
-use crates_io_env_vars::{required_var, var};
+use crates_io_cdn_logs::LogFormat;
+use crates_io_env_vars::{required_var, var, var_parsed};
 use secrecy::SecretString;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
-pub enum CdnLogStorageConfig {
+pub struct CdnLogStorageConfig {
+    pub backend: CdnLogStorageBackend,
+    /// The format of the CDN log files, or `None` if the format should be
+    /// detected automatically from the file contents.
+    pub format: Option<LogFormat>,
+    /// Whether the downloads per cargo version should be saved to the
+    /// `crate_client_downloads` table.
+    pub client_stats: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum CdnLogStorageBackend {
     S3 {
         access_key: String,
         secret_key: SecretString,
@@ -16,22 +28,51 @@ pub enum CdnLogStorageConfig {
 }
 
 impl CdnLogStorageConfig {
+    fn new(backend: CdnLogStorageBackend) -> Self {
+        Self {
+            backend,
+            format: None,
+            client_stats: false,
+        }
+    }
+
     pub fn s3(access_key: String, secret_key: SecretString) -> Self {
-        Self::S3 {
+        Self::new(CdnLogStorageBackend::S3 {
             access_key,
             secret_key,
-        }
+        })
     }
 
     pub fn local(path: PathBuf) -> Self {
-        Self::Local { path }
+        Self::new(CdnLogStorageBackend::Local { path })
     }
 
     pub fn memory() -> Self {
-        Self::Memory
+        Self::new(CdnLogStorageBackend::Memory)
+    }
+
+    pub fn with_format(self, format: impl Into<Option<LogFormat>>) -> Self {
+        let format = format.into();
+        Self { format, ..self }
+    }
+
+    pub fn with_client_stats(self, client_stats: bool) -> Self {
+        Self {
+            client_stats,
+            ..self
+        }
     }
 
     pub fn from_env() -> anyhow::Result<Self> {
+        let format = var_parsed("CDN_LOG_FORMAT")?;
+        let client_stats = var_parsed("CDN_LOG_CLIENT_STATS")?.unwrap_or_default();
+
+        Ok(Self::backend_from_env()?
+            .with_format(format)
+            .with_client_stats(client_stats))
+    }
+
+    fn backend_from_env() -> anyhow::Result<Self> {
         if let Some(access_key) = var("AWS_ACCESS_KEY")? {
             let secret_key = required_var("AWS_SECRET_KEY")?.into();
             return Ok(Self::s3(access_key, secret_key));
@@ -0,0 +1,113 @@
+use crate::rate_limiter::RateLimiterConfig;
+use anyhow::{Context, anyhow, bail};
+use crates_io_env_vars::{list, required_var, required_var_parsed};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Rate limits of the read-only API endpoints, which are enforced by the
+/// `read_rate_limit` middleware.
+///
+/// The token buckets are currently kept in memory, so each server instance
+/// enforces the limits on its own, and the effective limit of a client is
+/// multiplied by the number of instances that its requests are spread across.
+#[derive(Debug, Clone, Default)]
+pub struct ReadRateLimitConfig {
+    /// Maps route patterns (e.g. `/api/v1/crates`) to the name of their
+    /// budget.
+    pub routes: HashMap<String, String>,
+
+    /// Maps budget names to the rate and burst of their token buckets.
+    pub budgets: HashMap<String, RateLimiterConfig>,
+}
+
+impl ReadRateLimitConfig {
+    /// Reads the rate limits from the environment.
+    ///
+    /// The `READ_RATE_LIMIT_ROUTES` environment variable contains a
+    /// comma-separated list of route patterns and budget names, separated by
+    /// an equals sign (e.g. `/api/v1/crates=LIST_CRATES`). Routes with the
+    /// same budget name share their token buckets.
+    ///
+    /// Every budget is then configured by the `READ_RATE_LIMIT_{NAME}_BURST`
+    /// and `READ_RATE_LIMIT_{NAME}_RATE` environment variables. The rate is
+    /// the time it takes to refill a single token, either in seconds (`2s`)
+    /// or milliseconds (`250ms`), or the number of tokens that are refilled
+    /// per second (`4/s`).
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut routes = HashMap::new();
+        let mut budgets = HashMap::new();
+
+        for pattern in list("READ_RATE_LIMIT_ROUTES")? {
+            let Some((route, budget)) = pattern.rsplit_once('=') else {
+                return Err(anyhow!(
+                    "READ_RATE_LIMIT_ROUTES must be in the form ROUTE=BUDGET, got invalid pattern {pattern}"
+                ));
+            };
+
+            let budget = budget.to_uppercase();
+            if !budgets.contains_key(&budget) {
+                let rate_key = format!("READ_RATE_LIMIT_{budget}_RATE");
+                let burst_key = format!("READ_RATE_LIMIT_{budget}_BURST");
+
+                let rate = required_var(&rate_key)?;
+                let rate = parse_rate(&rate).with_context(|| format!("Invalid {rate_key}"))?;
+
+                let config = RateLimiterConfig {
+                    rate,
+                    burst: required_var_parsed(&burst_key)?,
+                };
+
+                budgets.insert(budget.clone(), config);
+            }
+
+            routes.insert(route.to_string(), budget);
+        }
+
+        Ok(Self { routes, budgets })
+    }
+}
+
+/// Parses the time it takes to refill a single token from e.g. `2s`, `250ms`
+/// or `4/s`.
+fn parse_rate(value: &str) -> anyhow::Result<Duration> {
+    let rate = if let Some(per_second) = value.strip_suffix("/s") {
+        let per_second: u32 = per_second.trim().parse()?;
+        if per_second == 0 {
+            bail!("the rate must be at least one request per second");
+        }
+        Duration::from_secs(1) / per_second
+    } else if let Some(millis) = value.strip_suffix("ms") {
+        Duration::from_millis(millis.trim().parse()?)
+    } else if let Some(seconds) = value.strip_suffix('s') {
+        Duration::from_secs(seconds.trim().parse()?)
+    } else {
+        bail!("expected a rate like `2s`, `250ms` or `4/s`, got `{value}`");
+    };
+
+    if rate.is_zero() {
+        bail!("the rate must be greater than zero");
+    }
+
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn test_parse_rate() {
+        assert_ok_eq!(parse_rate("2s"), Duration::from_secs(2));
+        assert_ok_eq!(parse_rate("250ms"), Duration::from_millis(250));
+        assert_ok_eq!(parse_rate("4/s"), Duration::from_millis(250));
+        assert_ok_eq!(parse_rate("1000/s"), Duration::from_millis(1));
+
+        assert_err!(parse_rate("2"));
+        assert_err!(parse_rate("0s"));
+        assert_err!(parse_rate("0ms"));
+        assert_err!(parse_rate("0/s"));
+        assert_err!(parse_rate("-1s"));
+        assert_err!(parse_rate("foo"));
+    }
+}
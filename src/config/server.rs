@@ -8,8 +8,8 @@ use crate::rate_limiter::{LimitedAction, RateLimiterConfig};
 
 use super::base::Base;
 use super::database_pools::DatabasePools;
-use crate::config::CdnLogQueueConfig;
 use crate::config::cdn_log_storage::CdnLogStorageConfig;
+use crate::config::{CdnLogQueueConfig, ReadRateLimitConfig};
 use crate::middleware::cargo_compat::StatusCodeConfig;
 use crate::storage::StorageConfig;
 use crates_io_env_vars::{list, list_parsed, required_var, var, var_parsed};
@@ -47,6 +47,7 @@ pub struct Server {
     pub max_dependencies: usize,
     pub max_features: usize,
     pub rate_limiter: HashMap<LimitedAction, RateLimiterConfig>,
+    pub read_rate_limits: ReadRateLimitConfig,
     pub new_version_rate_limit: Option<u32>,
     pub blocked_traffic: Vec<(String, Vec<String>)>,
     pub blocked_ips: HashSet<IpAddr>,
@@ -86,6 +87,20 @@ pub struct Server {
     /// can hold. Defaults to 1024.
     pub html_render_cache_max_capacity: u64,
 
+    /// Maximum number of rows that the cache of the version downloads
+    /// archive in `crate::downloads_archive::DownloadsArchive` can hold.
+    /// Defaults to 5,000,000.
+    pub downloads_archive_cache_max_capacity: u64,
+
+    /// Should the `DumpDb` and `ArchiveVersionDownloads` background jobs
+    /// additionally upload Parquet versions of their CSV files?
+    /// Defaults to `false`.
+    pub parquet_exports: bool,
+
+    /// Should the `DumpDb` background job additionally upload the database
+    /// dump as a SQLite database? Defaults to `true`.
+    pub sqlite_exports: bool,
+
     pub content_security_policy: Option<HeaderValue>,
 }
 
@@ -121,6 +136,12 @@ impl Server {
     ///   endpoint even with a healthy database pool.
     /// - `BLOCKED_ROUTES`: A comma separated list of HTTP route patterns that are manually blocked
     ///   by an operator (e.g. `/crates/{crate_id}/{version}/download`).
+    /// - `READ_RATE_LIMIT_ROUTES`: A comma separated list of HTTP route patterns and their rate
+    ///   limit budgets. See [ReadRateLimitConfig::from_env] for more documentation.
+    /// - `PARQUET_EXPORTS`: Whether the database dumps and version download archives should also
+    ///   be exported as Parquet files.
+    /// - `SQLITE_EXPORTS`: Whether the database dumps should also be exported as a SQLite
+    ///   database. Set to `false` to opt out.
     ///
     /// # Panics
     ///
@@ -203,6 +224,7 @@ impl Server {
             max_dependencies: DEFAULT_MAX_DEPENDENCIES,
             max_features: DEFAULT_MAX_FEATURES,
             rate_limiter,
+            read_rate_limits: ReadRateLimitConfig::from_env()?,
             new_version_rate_limit: var_parsed("MAX_NEW_VERSIONS_DAILY")?,
             blocked_traffic: blocked_traffic(),
             blocked_ips,
@@ -232,6 +254,10 @@ impl Server {
             serve_html: true,
             og_image_base_url: var_parsed("OG_IMAGE_BASE_URL")?,
             html_render_cache_max_capacity: var_parsed("HTML_RENDER_CACHE_CAP")?.unwrap_or(1024),
+            downloads_archive_cache_max_capacity: var_parsed("DOWNLOADS_ARCHIVE_CACHE_CAP")?
+                .unwrap_or(5_000_000),
+            parquet_exports: var_parsed("PARQUET_EXPORTS")?.unwrap_or(false),
+            sqlite_exports: var_parsed("SQLITE_EXPORTS")?.unwrap_or(true),
             content_security_policy: Some(content_security_policy.parse()?),
         })
     }
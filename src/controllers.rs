@@ -1,6 +1,7 @@
 pub mod helpers;
 pub mod util;
 
+pub mod admin;
 pub mod category;
 pub mod crate_owner_invitation;
 pub mod git;
@@ -10,6 +11,7 @@ pub mod krate;
 pub mod metrics;
 pub mod session;
 pub mod site_metadata;
+pub mod stats;
 pub mod summary;
 pub mod team;
 pub mod token;
@@ -0,0 +1,21 @@
+use crate::auth::{AuthCheck, Authentication};
+use crate::util::errors::{AppResult, forbidden};
+use diesel_async::AsyncPgConnection;
+use http::request::Parts;
+
+pub mod jobs;
+pub mod rate_limits;
+
+/// Checks that the request is authenticated via session cookie by a
+/// crates.io administrator.
+pub async fn authenticate_admin(
+    parts: &Parts,
+    conn: &mut AsyncPgConnection,
+) -> AppResult<Authentication> {
+    let auth = AuthCheck::only_cookie().check(parts, conn).await?;
+    if !auth.user().is_admin {
+        return Err(forbidden("this action can only be performed by admins"));
+    }
+
+    Ok(auth)
+}
@@ -0,0 +1,197 @@
+use crate::app::AppState;
+use crate::controllers::admin::authenticate_admin;
+use crate::controllers::helpers::OkResponse;
+use crate::controllers::helpers::pagination::{Page, PaginationOptions, encode_seek};
+use crate::util::RequestUtils;
+use crate::util::errors::{AppResult, not_found};
+use crate::worker::QUEUES;
+use axum::Json;
+use axum::extract::{Path, Query};
+use chrono::{DateTime, Utc};
+use crates_io_worker::admin::{self, JobCursor, JobInfo};
+use http::request::Parts;
+use indexmap::IndexMap;
+
+#[derive(Debug, Serialize)]
+pub struct EncodableBackgroundJob {
+    pub id: i64,
+    pub job_type: String,
+    pub queue: String,
+    pub data: serde_json::Value,
+    pub priority: i16,
+    pub retries: i32,
+    pub last_retry: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub run_at: DateTime<Utc>,
+}
+
+impl From<JobInfo> for EncodableBackgroundJob {
+    fn from(job: JobInfo) -> Self {
+        Self {
+            id: job.id,
+            job_type: job.job_type,
+            queue: job.queue,
+            data: job.data,
+            priority: job.priority,
+            retries: job.retries,
+            last_retry: job.last_retry.and_utc(),
+            last_error: job.last_error,
+            created_at: job.created_at,
+            run_at: job.run_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EncodablePausedQueue {
+    pub queue: String,
+    pub paused_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct ListParams {
+    job_type: Option<String>,
+    queue: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListResponse {
+    pub jobs: Vec<EncodableBackgroundJob>,
+    pub paused_queues: Vec<EncodablePausedQueue>,
+    pub meta: ListMeta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListMeta {
+    pub next_page: Option<String>,
+}
+
+/// Handles the `GET /api/private/admin/jobs` endpoint.
+pub async fn list_jobs(
+    app: AppState,
+    Query(params): Query<ListParams>,
+    req: Parts,
+) -> AppResult<Json<ListResponse>> {
+    let mut conn = app.db_read_prefer_primary().await?;
+    authenticate_admin(&req, &mut conn).await?;
+
+    let pagination = PaginationOptions::builder()
+        .enable_pages(false)
+        .enable_seek(true)
+        .gather(&req)?;
+
+    let after = match &pagination.page {
+        Page::Seek(seek) => Some(seek.decode::<JobCursor>()?),
+        _ => None,
+    };
+
+    let job_type = params.job_type.as_deref();
+    let queue = params.queue.as_deref();
+    let limit = pagination.per_page;
+    let jobs = admin::list_jobs(&mut conn, job_type, queue, after, limit).await?;
+
+    let mut next_page = None;
+    if let Some(last) = jobs.last().filter(|_| jobs.len() as i64 == limit) {
+        let params = IndexMap::from([("seek".into(), encode_seek(last.cursor())?)]);
+        next_page = Some(req.query_with_params(params));
+    }
+
+    let jobs = jobs.into_iter().map(EncodableBackgroundJob::from).collect();
+
+    let paused_queues = admin::paused_queues(&mut conn).await?;
+    let paused_queues = paused_queues
+        .into_iter()
+        .map(|(queue, paused_at)| EncodablePausedQueue { queue, paused_at })
+        .collect();
+
+    Ok(Json(ListResponse {
+        jobs,
+        paused_queues,
+        meta: ListMeta { next_page },
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetResponse {
+    pub job: EncodableBackgroundJob,
+}
+
+/// Handles the `GET /api/private/admin/jobs/{id}` endpoint.
+pub async fn get_job(
+    app: AppState,
+    Path(id): Path<i64>,
+    req: Parts,
+) -> AppResult<Json<GetResponse>> {
+    let mut conn = app.db_read_prefer_primary().await?;
+    authenticate_admin(&req, &mut conn).await?;
+
+    let job = admin::find_job(&mut conn, id)
+        .await?
+        .ok_or_else(not_found)?;
+
+    Ok(Json(GetResponse { job: job.into() }))
+}
+
+/// Handles the `POST /api/private/admin/jobs/{id}/retry` endpoint.
+pub async fn retry_job(app: AppState, Path(id): Path<i64>, req: Parts) -> AppResult<OkResponse> {
+    let mut conn = app.db_write().await?;
+    let auth = authenticate_admin(&req, &mut conn).await?;
+
+    admin::retry_now(&mut conn, id).await?;
+    warn!("Admin {} retried background job {id}", auth.user().gh_login);
+
+    Ok(OkResponse::new())
+}
+
+/// Handles the `DELETE /api/private/admin/jobs/{id}` endpoint.
+pub async fn cancel_job(app: AppState, Path(id): Path<i64>, req: Parts) -> AppResult<OkResponse> {
+    let mut conn = app.db_write().await?;
+    let auth = authenticate_admin(&req, &mut conn).await?;
+
+    admin::cancel_job(&mut conn, id).await?;
+    warn!(
+        "Admin {} cancelled background job {id}",
+        auth.user().gh_login
+    );
+
+    Ok(OkResponse::new())
+}
+
+/// Handles the `POST /api/private/admin/queues/{queue}/pause` endpoint.
+pub async fn pause_queue(
+    app: AppState,
+    Path(queue): Path<String>,
+    req: Parts,
+) -> AppResult<OkResponse> {
+    let mut conn = app.db_write().await?;
+    let auth = authenticate_admin(&req, &mut conn).await?;
+
+    admin::pause_queue(&mut conn, &queue, QUEUES).await?;
+    warn!(
+        "Admin {} paused background job queue {queue}",
+        auth.user().gh_login
+    );
+
+    Ok(OkResponse::new())
+}
+
+/// Handles the `POST /api/private/admin/queues/{queue}/resume` endpoint.
+pub async fn resume_queue(
+    app: AppState,
+    Path(queue): Path<String>,
+    req: Parts,
+) -> AppResult<OkResponse> {
+    let mut conn = app.db_write().await?;
+    let auth = authenticate_admin(&req, &mut conn).await?;
+
+    if !admin::resume_queue(&mut conn, &queue).await? {
+        return Err(not_found());
+    }
+    warn!(
+        "Admin {} resumed background job queue {queue}",
+        auth.user().gh_login
+    );
+
+    Ok(OkResponse::new())
+}
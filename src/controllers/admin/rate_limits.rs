@@ -0,0 +1,179 @@
+use crate::app::AppState;
+use crate::controllers::admin::authenticate_admin;
+use crate::controllers::helpers::OkResponse;
+use crate::models::User;
+use crate::rate_limiter::{LimitedAction, RateLimitOverrideChange, UserRateLimitOverride};
+use crate::util::errors::{AppResult, bad_request, not_found};
+use axum::Json;
+use axum::extract::{Path, Query};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::AsyncPgConnection;
+use http::request::Parts;
+
+#[derive(Debug, Serialize)]
+pub struct EncodableRateLimitOverride {
+    pub user: String,
+    pub action: &'static str,
+    pub burst: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserRateLimitOverride> for EncodableRateLimitOverride {
+    fn from(rate_limit_override: UserRateLimitOverride) -> Self {
+        Self {
+            user: rate_limit_override.gh_login,
+            action: rate_limit_override.action.name(),
+            burst: rate_limit_override.burst,
+            expires_at: rate_limit_override.expires_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListParams {
+    user: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListResponse {
+    pub overrides: Vec<EncodableRateLimitOverride>,
+}
+
+/// Handles the `GET /api/private/admin/rate_limits` endpoint.
+pub async fn list_overrides(
+    app: AppState,
+    Query(params): Query<ListParams>,
+    req: Parts,
+) -> AppResult<Json<ListResponse>> {
+    let mut conn = app.db_read_prefer_primary().await?;
+    authenticate_admin(&req, &mut conn).await?;
+
+    let user_id = match params.user {
+        Some(login) => Some(find_user(&mut conn, &login).await?.id),
+        None => None,
+    };
+
+    let overrides = UserRateLimitOverride::list_active(user_id, &mut conn).await?;
+    let overrides = overrides.into_iter().map(Into::into).collect();
+
+    Ok(Json(ListResponse { overrides }))
+}
+
+#[derive(Deserialize)]
+pub struct SetOverrideRequest {
+    burst: i32,
+    expires_at: Option<DateTime<Utc>>,
+    reason: String,
+}
+
+/// Handles the `PUT /api/private/admin/rate_limits/{user}/{action}` endpoint.
+///
+/// Grants a new override or modifies the existing override of the user.
+pub async fn set_override(
+    app: AppState,
+    Path((login, action)): Path<(String, String)>,
+    req: Parts,
+    Json(body): Json<SetOverrideRequest>,
+) -> AppResult<OkResponse> {
+    let mut conn = app.db_write().await?;
+    let auth = authenticate_admin(&req, &mut conn).await?;
+    let admin = auth.user();
+
+    let action = parse_action(&action)?;
+    let reason = validate_reason(&body.reason)?;
+    if body.burst < 1 {
+        return Err(bad_request("burst must be at least 1"));
+    }
+    if body
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(bad_request("expires_at must be in the future"));
+    }
+
+    let user = find_user(&mut conn, &login).await?;
+
+    let change = RateLimitOverrideChange {
+        user_id: user.id,
+        action,
+        admin_id: admin.id,
+        reason,
+    };
+    change.set(body.burst, body.expires_at, &mut conn).await?;
+
+    let until = match body.expires_at {
+        Some(expires_at) => format!("until {expires_at}"),
+        None => "indefinitely".to_string(),
+    };
+    warn!(
+        "Admin {} set the {} rate limit override of {} to a burst of {} {until}: {reason}",
+        admin.gh_login,
+        action.name(),
+        user.gh_login,
+        body.burst,
+    );
+
+    Ok(OkResponse::new())
+}
+
+#[derive(Deserialize)]
+pub struct ExpireOverrideRequest {
+    reason: String,
+}
+
+/// Handles the `POST /api/private/admin/rate_limits/{user}/{action}/expire`
+/// endpoint.
+pub async fn expire_override(
+    app: AppState,
+    Path((login, action)): Path<(String, String)>,
+    req: Parts,
+    Json(body): Json<ExpireOverrideRequest>,
+) -> AppResult<OkResponse> {
+    let mut conn = app.db_write().await?;
+    let auth = authenticate_admin(&req, &mut conn).await?;
+    let admin = auth.user();
+
+    let action = parse_action(&action)?;
+    let reason = validate_reason(&body.reason)?;
+    let user = find_user(&mut conn, &login).await?;
+
+    let change = RateLimitOverrideChange {
+        user_id: user.id,
+        action,
+        admin_id: admin.id,
+        reason,
+    };
+    if !change.expire(&mut conn).await? {
+        return Err(not_found());
+    }
+
+    warn!(
+        "Admin {} expired the {} rate limit override of {}: {reason}",
+        admin.gh_login,
+        action.name(),
+        user.gh_login
+    );
+
+    Ok(OkResponse::new())
+}
+
+async fn find_user(conn: &mut AsyncPgConnection, login: &str) -> AppResult<User> {
+    User::find_by_login(conn, login)
+        .await
+        .optional()?
+        .ok_or_else(|| bad_request(format_args!("could not find user with login `{login}`")))
+}
+
+fn parse_action(action: &str) -> AppResult<LimitedAction> {
+    action.parse().map_err(bad_request)
+}
+
+fn validate_reason(reason: &str) -> AppResult<&str> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(bad_request("a reason for the change is required"));
+    }
+
+    Ok(reason)
+}
@@ -2,6 +2,7 @@ use axum::Json;
 use axum::response::{IntoResponse, Response};
 
 pub mod authorization;
+pub(crate) mod downloads;
 pub(crate) mod pagination;
 
 pub(crate) use self::pagination::Paginate;
@@ -0,0 +1,318 @@
+//! Shared functionality for the crate and version download count endpoints.
+
+use crate::downloads_archive::DownloadsArchive;
+use crate::schema::version_downloads;
+use crate::util::errors::{AppResult, bad_request, internal};
+use crate::views::EncodableVersionDownload;
+use axum::response::{IntoResponse, Response};
+use chrono::{Datelike, Days, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use http::header;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// The number of days that are returned if no explicit date range is
+/// requested. This matches the number of days that are kept in the
+/// `version_downloads` table before they are moved to the archive.
+const DEFAULT_DAYS: u64 = 90;
+
+/// The maximum number of days that can be requested at once.
+const MAX_DAYS: u64 = 366;
+
+/// The maximum number of archived days that can be requested at once.
+///
+/// Each archived day has to be downloaded from the object store and kept in
+/// memory unless it is cached already, so this is lower than [`MAX_DAYS`].
+const MAX_ARCHIVED_DAYS: usize = 92;
+
+/// The time interval that download counts are aggregated by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadsInterval {
+    /// One entry per day.
+    #[default]
+    Day,
+    /// One entry per week, starting on Monday.
+    Week,
+    /// One entry per calendar month.
+    Month,
+}
+
+impl DownloadsInterval {
+    /// Returns the first day of the interval that contains the given date.
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+            Self::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+}
+
+/// An inclusive range of dates for which download counts are requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl DateRange {
+    /// Creates a new date range from the `from` and `to` query parameters.
+    ///
+    /// `to` defaults to today and `from` defaults to the 90 days up to and
+    /// including `to`. Ranges of more than 366 days are rejected.
+    pub fn new(from: Option<NaiveDate>, to: Option<NaiveDate>) -> AppResult<Self> {
+        let to = to.unwrap_or_else(|| Utc::now().date_naive());
+        let from = from.unwrap_or(to - Days::new(DEFAULT_DAYS - 1));
+
+        if from > to {
+            return Err(bad_request("`from` must not be after `to`"));
+        }
+
+        if from + Days::new(MAX_DAYS) <= to {
+            let detail = format!("the requested range must not exceed {MAX_DAYS} days");
+            return Err(bad_request(detail));
+        }
+
+        Ok(Self { from, to })
+    }
+
+    fn dates(&self) -> impl Iterator<Item = NaiveDate> + use<> {
+        let to = self.to;
+        self.from.iter_days().take_while(move |date| *date <= to)
+    }
+}
+
+/// The number of downloads of a version within a day or interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadCount {
+    pub version_id: i32,
+    pub date: NaiveDate,
+    pub downloads: i64,
+}
+
+impl From<DownloadCount> for EncodableVersionDownload {
+    fn from(count: DownloadCount) -> Self {
+        Self {
+            version: count.version_id,
+            downloads: count.downloads,
+            date: count.date.to_string(),
+        }
+    }
+}
+
+/// Loads the per-day download counts of the given versions within the given
+/// date range.
+///
+/// Download counts that have already been moved out of the `version_downloads`
+/// table by the `ArchiveVersionDownloads` background job are read from the
+/// archived CSV files instead. For any date that has an archive file, the
+/// archive takes precedence over the database.
+///
+/// The results are sorted by date and then by descending version ID.
+pub async fn load_version_downloads(
+    conn: &mut AsyncPgConnection,
+    archive: &DownloadsArchive,
+    version_ids: &[i32],
+    range: DateRange,
+) -> AppResult<Vec<DownloadCount>> {
+    let archive_error =
+        |error: anyhow::Error| internal(format!("failed to load archived downloads: {error}"));
+
+    // Only dates outside of the default range can have been archived already,
+    // and there are no archive files before the earliest archived date.
+    let archive_cutoff = Utc::now().date_naive() - Days::new(DEFAULT_DAYS - 1);
+    let earliest_date = archive.earliest_date().await.map_err(archive_error)?;
+    let archive_dates = range
+        .dates()
+        .filter(|date| *date < archive_cutoff)
+        .filter(|date| earliest_date.is_none_or(|earliest_date| *date >= earliest_date))
+        .collect::<Vec<_>>();
+
+    if archive_dates.len() > MAX_ARCHIVED_DAYS {
+        let detail = format!(
+            "download counts older than {DEFAULT_DAYS} days can only be requested for up to {MAX_ARCHIVED_DAYS} days at once"
+        );
+        return Err(bad_request(detail));
+    }
+
+    let live = version_downloads::table
+        .filter(version_downloads::version_id.eq_any(version_ids))
+        .filter(version_downloads::date.between(range.from, range.to))
+        .select((
+            version_downloads::version_id,
+            version_downloads::date,
+            version_downloads::downloads,
+        ))
+        .load::<(i32, NaiveDate, i32)>(conn)
+        .await?;
+
+    let version_ids = version_ids.iter().copied().collect();
+    let archived = archive
+        .version_downloads(&version_ids, archive_dates.into_iter())
+        .await
+        .map_err(archive_error)?;
+
+    let mut counts = live
+        .into_iter()
+        .filter(|(_, date, _)| !archived.contains_key(date))
+        .map(|(version_id, date, downloads)| DownloadCount {
+            version_id,
+            date,
+            downloads: downloads.into(),
+        })
+        .collect::<Vec<_>>();
+
+    for (date, archived_counts) in archived {
+        let archived_counts =
+            archived_counts
+                .into_iter()
+                .map(|(version_id, downloads)| DownloadCount {
+                    version_id,
+                    date,
+                    downloads,
+                });
+
+        counts.extend(archived_counts);
+    }
+
+    counts.sort_unstable_by(|a, b| a.date.cmp(&b.date).then(b.version_id.cmp(&a.version_id)));
+    Ok(counts)
+}
+
+/// Sums up the download counts per version for each interval.
+///
+/// The `date` of the returned counts is the first day of the interval. The
+/// results are sorted by date and then by descending version ID.
+pub fn aggregate(counts: Vec<DownloadCount>, interval: DownloadsInterval) -> Vec<DownloadCount> {
+    if interval == DownloadsInterval::Day {
+        return counts;
+    }
+
+    let mut sums = BTreeMap::new();
+    for count in counts {
+        let key = (interval.start_of(count.date), Reverse(count.version_id));
+        *sums.entry(key).or_default() += count.downloads;
+    }
+
+    sums.into_iter()
+        .map(|((date, Reverse(version_id)), downloads)| DownloadCount {
+            version_id,
+            date,
+            downloads,
+        })
+        .collect()
+}
+
+/// Serializes the given records into a `text/csv` response with a header row.
+pub fn csv_response<T: Serialize>(records: impl IntoIterator<Item = T>) -> AppResult<Response> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for record in records {
+        writer
+            .serialize(record)
+            .map_err(|error| internal(format!("failed to serialize CSV: {error}")))?;
+    }
+
+    let body = writer
+        .into_inner()
+        .map_err(|error| internal(format!("failed to serialize CSV: {error}")))?;
+
+    Ok(([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], body).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_start_of() {
+        let wednesday = date("2024-06-12");
+        assert_eq!(DownloadsInterval::Day.start_of(wednesday), wednesday);
+        assert_eq!(
+            DownloadsInterval::Week.start_of(wednesday),
+            date("2024-06-10")
+        );
+        assert_eq!(
+            DownloadsInterval::Month.start_of(wednesday),
+            date("2024-06-01")
+        );
+
+        let monday = date("2024-06-10");
+        assert_eq!(DownloadsInterval::Week.start_of(monday), monday);
+    }
+
+    #[test]
+    fn test_date_range() {
+        let range = assert_ok!(DateRange::new(None, Some(date("2024-06-30"))));
+        assert_eq!(range.from, date("2024-04-02"));
+        assert_eq!(range.dates().count(), 90);
+
+        let range = assert_ok!(DateRange::new(
+            Some(date("2024-06-01")),
+            Some(date("2024-06-01"))
+        ));
+        assert_eq!(range.dates().collect::<Vec<_>>(), vec![date("2024-06-01")]);
+
+        assert_err!(DateRange::new(
+            Some(date("2024-06-02")),
+            Some(date("2024-06-01"))
+        ));
+
+        let range = assert_ok!(DateRange::new(
+            Some(date("2024-01-01")),
+            Some(date("2024-12-31"))
+        ));
+        assert_eq!(range.dates().count(), 366);
+
+        assert_err!(DateRange::new(
+            Some(date("2024-01-01")),
+            Some(date("2025-01-01"))
+        ));
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let count = |version_id, date: &str, downloads| DownloadCount {
+            version_id,
+            date: self::date(date),
+            downloads,
+        };
+
+        let counts = vec![
+            count(2, "2024-05-31", 1),
+            count(1, "2024-05-31", 2),
+            count(2, "2024-06-01", 3),
+            count(2, "2024-06-03", 4),
+            count(1, "2024-06-03", 5),
+        ];
+
+        let weekly = aggregate(counts.clone(), DownloadsInterval::Week);
+        assert_eq!(
+            weekly,
+            vec![
+                count(2, "2024-05-27", 4),
+                count(1, "2024-05-27", 2),
+                count(2, "2024-06-03", 4),
+                count(1, "2024-06-03", 5),
+            ]
+        );
+
+        let monthly = aggregate(counts, DownloadsInterval::Month);
+        assert_eq!(
+            monthly,
+            vec![
+                count(2, "2024-05-01", 1),
+                count(1, "2024-05-01", 2),
+                count(2, "2024-06-01", 7),
+                count(1, "2024-06-01", 5),
+            ]
+        );
+    }
+}
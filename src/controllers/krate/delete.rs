@@ -230,7 +230,7 @@ mod tests {
     use crates_io_database::schema::crate_owners;
     use diesel_async::AsyncPgConnection;
     use http::{Request, StatusCode};
-    use insta::assert_snapshot;
+    use insta::{assert_debug_snapshot, assert_snapshot};
     use serde_json::json;
 
     #[tokio::test]
@@ -292,6 +292,23 @@ mod tests {
         rss/updates.xml
         ");
 
+        // Assert that the index file and the crate files were purged from the CDN by surrogate key
+        let purges = app.cdn_purges();
+        assert_debug_snapshot!(&purges[purges.len() - 2..], @r#"
+        [
+            Keys(
+                [
+                    "index:foo",
+                ],
+            ),
+            Keys(
+                [
+                    "crate:foo",
+                ],
+            ),
+        ]
+        "#);
+
         Ok(())
     }
 
@@ -4,22 +4,30 @@
 //! download counts are located in `version::downloads`.
 
 use crate::app::AppState;
+use crate::controllers::helpers::downloads::{
+    DateRange, DownloadsInterval, aggregate, csv_response, load_version_downloads,
+};
 use crate::controllers::krate::CratePath;
 use crate::models::download::Version;
-use crate::models::{User, Version as FullVersion, VersionDownload, VersionOwnerAction};
-use crate::schema::{version_downloads, version_owner_actions, versions};
+use crate::models::{User, Version as FullVersion, VersionOwnerAction};
+use crate::schema::{crate_client_downloads, version_owner_actions, versions};
+use crate::util::RequestUtils;
 use crate::util::errors::{AppResult, BoxedAppError, bad_request};
 use crate::views::{EncodableVersion, EncodableVersionDownload};
 use axum::Json;
 use axum::extract::FromRequestParts;
+use axum::response::{IntoResponse, Response};
 use axum_extra::extract::Query;
+use chrono::NaiveDate;
 use crates_io_database::schema::users;
 use crates_io_diesel_helpers::to_char;
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use futures_util::FutureExt;
 use futures_util::future::BoxFuture;
+use http::request::Parts;
 use std::cmp;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::str::FromStr;
 
 #[derive(Debug, Deserialize, FromRequestParts, utoipa::IntoParams)]
@@ -34,11 +42,29 @@ pub struct DownloadsQueryParams {
     ///
     /// This parameter expects a comma-separated list of values.
     include: Option<String>,
+
+    /// Only return download counts on or after this date.
+    ///
+    /// Defaults to 89 days before `to`.
+    #[param(example = "2024-01-01")]
+    from: Option<NaiveDate>,
+
+    /// Only return download counts on or before this date.
+    ///
+    /// Defaults to today.
+    #[param(example = "2024-06-28")]
+    to: Option<NaiveDate>,
+
+    /// The time interval that the download counts are aggregated by.
+    ///
+    /// Defaults to `day`.
+    #[param(inline)]
+    interval: Option<DownloadsInterval>,
 }
 
 #[derive(Debug, Serialize, utoipa::ToSchema)]
 pub struct DownloadsResponse {
-    /// The per-day download counts for the last 90 days.
+    /// The download counts per day, week or month in the requested date range.
     pub version_downloads: Vec<EncodableVersionDownload>,
 
     /// The versions referenced in the download counts, if `?include=versions`
@@ -56,7 +82,7 @@ pub struct DownloadsMeta {
     pub extra_downloads: Vec<ExtraDownload>,
 }
 
-#[derive(Debug, Serialize, Queryable, utoipa::ToSchema)]
+#[derive(Debug, Serialize, utoipa::ToSchema)]
 pub struct ExtraDownload {
     /// The date this download count is for.
     #[schema(example = "2019-12-13")]
@@ -67,27 +93,46 @@ pub struct ExtraDownload {
     downloads: i64,
 }
 
+/// A row of the `text/csv` representation of the crate download counts.
+#[derive(Debug, Serialize)]
+struct CsvRecord {
+    date: NaiveDate,
+    version: String,
+    downloads: i64,
+}
+
 /// Get the download counts for a crate.
 ///
 /// This includes the per-day downloads for the last 90 days and for the
 /// latest 5 versions plus the sum of the rest.
+///
+/// The `from`, `to` and `interval` query parameters can be used to request
+/// a different date range and to aggregate the counts per week or month.
+/// Download counts that are no longer in the database are read from the
+/// version downloads archive. At most 92 of these archived days can be
+/// requested at once.
+///
+/// If the `Accept` header contains `text/csv`, the download counts of all
+/// versions are returned as CSV with `date`, `version` and `downloads`
+/// columns instead.
 #[utoipa::path(
     get,
     path = "/api/v1/crates/{name}/downloads",
     params(CratePath, DownloadsQueryParams),
     tag = "crates",
-    responses((status = 200, description = "Successful Response", body = inline(DownloadsResponse))),
+    responses((status = 200, description = "Successful Response", content(
+        (inline(DownloadsResponse) = "application/json"),
+        (String = "text/csv"),
+    ))),
 )]
 pub async fn get_crate_downloads(
     state: AppState,
     path: CratePath,
     params: DownloadsQueryParams,
-) -> AppResult<Json<DownloadsResponse>> {
+    req: Parts,
+) -> AppResult<Response> {
     let mut conn = state.db_read().await?;
 
-    use diesel::dsl::*;
-    use diesel::sql_types::BigInt;
-
     let crate_id: i32 = path.load_crate_id(&mut conn).await?;
 
     let mut versions: Vec<Version> = versions::table
@@ -97,7 +142,6 @@ pub async fn get_crate_downloads(
         .await?;
 
     versions.sort_unstable_by(|a, b| b.num.cmp(&a.num));
-    let (latest_five, rest) = versions.split_at(cmp::min(5, versions.len()));
 
     let include = params
         .include
@@ -106,34 +150,64 @@ pub async fn get_crate_downloads(
         .transpose()?
         .unwrap_or_default();
 
-    let sum_downloads = sql::<BigInt>("SUM(version_downloads.downloads)");
-    let (downloads, extra_downloads, versions_and_publishers, actions) = tokio::try_join!(
-        VersionDownload::belonging_to(latest_five)
-            .filter(version_downloads::date.gt(date(now - 90.days())))
-            .order((
-                version_downloads::date.asc(),
-                version_downloads::version_id.desc(),
-            ))
-            .load(&mut conn)
-            .boxed(),
-        VersionDownload::belonging_to(rest)
-            .select((
-                to_char(version_downloads::date, "YYYY-MM-DD"),
-                sum_downloads,
-            ))
-            .filter(version_downloads::date.gt(date(now - 90.days())))
-            .group_by(version_downloads::date)
-            .order(version_downloads::date.asc())
-            .load::<ExtraDownload>(&mut conn)
-            .boxed(),
-        load_versions_and_publishers(&mut conn, latest_five, include.versions),
-        load_actions(&mut conn, latest_five, include.versions),
-    )?;
+    let range = DateRange::new(params.from, params.to)?;
+    let interval = params.interval.unwrap_or_default();
+
+    let version_ids = versions
+        .iter()
+        .map(|version| version.id)
+        .collect::<Vec<_>>();
+    let downloads =
+        load_version_downloads(&mut conn, &state.downloads_archive, &version_ids, range).await?;
+    let downloads = aggregate(downloads, interval);
+
+    if req.wants_csv() {
+        let nums = versions
+            .iter()
+            .map(|version| (version.id, &version.num))
+            .collect::<HashMap<_, _>>();
+
+        let records = downloads.into_iter().map(|count| CsvRecord {
+            date: count.date,
+            version: nums[&count.version_id].to_string(),
+            downloads: count.downloads,
+        });
+
+        return csv_response(records);
+    }
+
+    let (latest_five, _) = versions.split_at(cmp::min(5, versions.len()));
+    let latest_five_ids = latest_five
+        .iter()
+        .map(|version| version.id)
+        .collect::<HashSet<_>>();
+
+    let (downloads, rest): (Vec<_>, Vec<_>) = downloads
+        .into_iter()
+        .partition(|count| latest_five_ids.contains(&count.version_id));
 
     let version_downloads = downloads
         .into_iter()
-        .map(VersionDownload::into)
-        .collect::<Vec<EncodableVersionDownload>>();
+        .map(EncodableVersionDownload::from)
+        .collect();
+
+    let mut extra_downloads = BTreeMap::<NaiveDate, i64>::new();
+    for count in rest {
+        *extra_downloads.entry(count.date).or_default() += count.downloads;
+    }
+
+    let extra_downloads = extra_downloads
+        .into_iter()
+        .map(|(date, downloads)| ExtraDownload {
+            date: date.to_string(),
+            downloads,
+        })
+        .collect();
+
+    let (versions_and_publishers, actions) = tokio::try_join!(
+        load_versions_and_publishers(&mut conn, latest_five, include.versions),
+        load_actions(&mut conn, latest_five, include.versions),
+    )?;
 
     let versions = if include.versions {
         let versions_and_publishers = versions_and_publishers.grouped_by(latest_five);
@@ -157,7 +231,80 @@ pub async fn get_crate_downloads(
         version_downloads,
         versions,
         meta: DownloadsMeta { extra_downloads },
-    }))
+    })
+    .into_response())
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct ClientDownloadsResponse {
+    /// The per-day download counts per client for the last 90 days.
+    #[schema(inline)]
+    pub client_downloads: Vec<ClientDownload>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct ClientDownload {
+    /// The date this download count is for.
+    #[schema(example = "2019-12-13")]
+    date: String,
+
+    /// The `major.minor` version of cargo (and the Rust toolchain) that
+    /// downloaded the crate, or `null` if the download was not made by cargo.
+    #[schema(example = "1.74")]
+    cargo_version: Option<String>,
+
+    /// The number of downloads on the given date by this kind of client.
+    #[schema(example = 123)]
+    downloads: i64,
+}
+
+/// Get the download counts for a crate, broken down by client.
+///
+/// This includes the per-day downloads for the last 90 days, grouped by the
+/// cargo version of the clients, as derived from the `User-Agent` headers of
+/// the requests.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{name}/downloads/clients",
+    params(CratePath),
+    tag = "crates",
+    responses((status = 200, description = "Successful Response", body = inline(ClientDownloadsResponse))),
+)]
+pub async fn get_crate_client_downloads(
+    state: AppState,
+    path: CratePath,
+) -> AppResult<Json<ClientDownloadsResponse>> {
+    use diesel::dsl::*;
+
+    let mut conn = state.db_read().await?;
+
+    let crate_id: i32 = path.load_crate_id(&mut conn).await?;
+
+    let rows: Vec<(String, String, i64)> = crate_client_downloads::table
+        .filter(crate_client_downloads::crate_id.eq(crate_id))
+        .filter(crate_client_downloads::date.gt(date(now - 90.days())))
+        .select((
+            to_char(crate_client_downloads::date, "YYYY-MM-DD"),
+            crate_client_downloads::cargo_version,
+            crate_client_downloads::downloads,
+        ))
+        .order((
+            crate_client_downloads::date.asc(),
+            crate_client_downloads::cargo_version.asc(),
+        ))
+        .load(&mut conn)
+        .await?;
+
+    let client_downloads = rows
+        .into_iter()
+        .map(|(date, cargo_version, downloads)| ClientDownload {
+            date,
+            cargo_version: Some(cargo_version).filter(|version| !version.is_empty()),
+            downloads,
+        })
+        .collect();
+
+    Ok(Json(ClientDownloadsResponse { client_downloads }))
 }
 
 type VersionsAndPublishers = (FullVersion, Option<User>);
@@ -7,7 +7,9 @@ use crate::worker::jobs::{
 };
 use axum::Json;
 use axum::body::{Body, Bytes};
-use cargo_manifest::{Dependency, DepsSet, TargetDepsSet};
+use cargo_manifest::{
+    Dependency, DepsSet, Edition, FeatureSet, Manifest, StringOrBool, TargetDepsSet,
+};
 use chrono::{DateTime, SecondsFormat, Utc};
 use crates_io_tarball::{TarballError, process_tarball};
 use crates_io_worker::{BackgroundJob, EnqueueError};
@@ -36,7 +38,7 @@ use crate::controllers::helpers::authorization::Rights;
 use crate::licenses::parse_license_expr;
 use crate::middleware::log_request::RequestLogExt;
 use crate::models::token::EndpointScope;
-use crate::rate_limiter::LimitedAction;
+use crate::rate_limiter::{LimitedAction, RateLimitStatus};
 use crate::schema::*;
 use crate::util::errors::{AppResult, BoxedAppError, bad_request, custom, internal};
 use crate::views::{
@@ -65,7 +67,11 @@ const MAX_DESCRIPTION_LENGTH: usize = 1000;
     tag = "publish",
     responses((status = 200, description = "Successful Response", body = inline(GoodCrate))),
 )]
-pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<GoodCrate>> {
+pub async fn publish(
+    app: AppState,
+    req: Parts,
+    body: Body,
+) -> AppResult<(RateLimitStatus, Json<GoodCrate>)> {
     let stream = body.into_data_stream();
     let stream = stream.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err));
     let mut reader = StreamReader::new(stream);
@@ -151,7 +157,8 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
         None => LimitedAction::PublishNew,
     };
 
-    app.rate_limiter
+    let rate_limit = app
+        .rate_limiter
         .check_rate_limit(auth.user().id, rate_limit_action, &mut conn)
         .await?;
 
@@ -167,19 +174,24 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
     let max_unpack_size = std::cmp::max(app.config.max_unpack_size, max_upload_size as u64);
     let tarball_info = process_tarball(&pkg_name, &*tarball_bytes, max_unpack_size).await?;
 
-    // `unwrap()` is safe here since `process_tarball()` validates that
-    // we only accept manifests with a `package` section and without
-    // inheritance.
-    let package = tarball_info.manifest.package.unwrap();
-
-    let description = package.description.map(|it| it.as_local().unwrap());
-    let mut license = package.license.map(|it| it.as_local().unwrap());
-    let license_file = package.license_file.map(|it| it.as_local().unwrap());
-    let homepage = package.homepage.map(|it| it.as_local().unwrap());
-    let documentation = package.documentation.map(|it| it.as_local().unwrap());
-    let repository = package.repository.map(|it| it.as_local().unwrap());
-    let rust_version = package.rust_version.map(|rv| rv.as_local().unwrap());
-    let edition = package.edition.map(|rv| rv.as_local().unwrap());
+    let ManifestMetadata {
+        description,
+        mut license,
+        license_file,
+        homepage,
+        documentation,
+        repository,
+        rust_version,
+        edition,
+        links,
+        keywords,
+        categories,
+        features,
+        has_lib,
+        bin_names,
+        deps,
+        ..
+    } = ManifestMetadata::from_manifest(tarball_info.manifest);
 
     // Make sure required fields are provided
     fn empty(s: Option<&String>) -> bool {
@@ -233,11 +245,6 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
         validate_rust_version(rust_version)?;
     }
 
-    let keywords = package
-        .keywords
-        .map(|it| it.as_local().unwrap())
-        .unwrap_or_default();
-
     if keywords.len() > 5 {
         return Err(bad_request("expected at most 5 keywords per crate"));
     }
@@ -252,11 +259,6 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
         }
     }
 
-    let categories = package
-        .categories
-        .map(|it| it.as_local().unwrap())
-        .unwrap_or_default();
-
     if categories.len() > 5 {
         return Err(bad_request("expected at most 5 categories per crate"));
     }
@@ -266,7 +268,6 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
         .and_then(|c| c.max_features.map(|mf| mf as usize))
         .unwrap_or(app.config.max_features);
 
-    let features = tarball_info.manifest.features.unwrap_or_default();
     let num_features = features.len();
     if num_features > max_features {
         return Err(bad_request(format!(
@@ -305,13 +306,6 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
         }
     }
 
-    let deps = convert_dependencies(
-        tarball_info.manifest.dependencies.as_ref(),
-        tarball_info.manifest.dev_dependencies.as_ref(),
-        tarball_info.manifest.build_dependencies.as_ref(),
-        tarball_info.manifest.target.as_ref(),
-    );
-
     let max_dependencies = app.config.max_dependencies;
     if deps.len() > max_dependencies {
         return Err(bad_request(format!(
@@ -331,7 +325,7 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
 
     // Create a transaction on the database, if there are no errors,
     // commit the transactions to record a new or updated crate.
-    conn.transaction(|conn| async move {
+    let result: AppResult<Json<GoodCrate>> = conn.transaction(|conn| async move {
         let name = metadata.name;
         let keywords = keywords.iter().map(|s| s.as_str()).collect::<Vec<_>>();
         let categories = categories.iter().map(|s| s.as_str()).collect::<Vec<_>>();
@@ -381,13 +375,7 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
             }
         }
 
-        // https://doc.rust-lang.org/cargo/reference/cargo-targets.html#the-name-field says that
-        // the `name` field is required for `bin` targets, so we can ignore `None` values via
-        // `filter_map()` here.
-        let bin_names = tarball_info.manifest.bin
-            .iter()
-            .filter_map(|bin| bin.name.as_deref())
-            .collect::<Vec<_>>();
+        let bin_names = bin_names.iter().map(|s| s.as_str()).collect::<Vec<_>>();
 
         let edition = edition.map(|edition| edition.as_str());
 
@@ -403,9 +391,9 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
             .size(content_length as i32)
             .published_by(user.id)
             .checksum(&hex_cksum)
-            .maybe_links(package.links.as_deref())
+            .maybe_links(links.as_deref())
             .maybe_rust_version(rust_version.as_deref())
-            .has_lib(tarball_info.manifest.lib.is_some())
+            .has_lib(has_lib)
             .bin_names(bin_names.as_slice())
             .maybe_edition(edition)
             .maybe_description(description.as_deref())
@@ -576,7 +564,9 @@ pub async fn publish(app: AppState, req: Parts, body: Body) -> AppResult<Json<Go
             ),
             warnings,
         }))
-    }.scope_boxed()).await
+    }.scope_boxed()).await;
+
+    Ok((rate_limit, result?))
 }
 
 /// Counts the number of versions for `crate_id` that were published within
@@ -709,6 +699,83 @@ fn validate_rust_version(value: &str) -> AppResult<()> {
     }
 }
 
+/// The crate metadata that is read from the `Cargo.toml` manifest of a crate
+/// tarball.
+///
+/// This is shared between the publish endpoint and `crates-admin import`, so
+/// that both read the same fields from the manifest. Only the publish
+/// endpoint validates the values though.
+pub struct ManifestMetadata {
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub license_file: Option<String>,
+    pub homepage: Option<String>,
+    pub documentation: Option<String>,
+    pub repository: Option<String>,
+    pub rust_version: Option<String>,
+    pub edition: Option<Edition>,
+    pub readme: Option<StringOrBool>,
+    pub links: Option<String>,
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+    pub features: FeatureSet,
+    pub has_lib: bool,
+    pub bin_names: Vec<String>,
+    pub deps: Vec<EncodableCrateDependency>,
+}
+
+impl ManifestMetadata {
+    /// Reads the metadata from a manifest that was validated by
+    /// `process_tarball()`.
+    pub fn from_manifest(manifest: Manifest) -> Self {
+        // `unwrap()` is safe here since `process_tarball()` validates that
+        // we only accept manifests with a `package` section and without
+        // inheritance.
+        let package = manifest.package.unwrap();
+
+        let deps = convert_dependencies(
+            manifest.dependencies.as_ref(),
+            manifest.dev_dependencies.as_ref(),
+            manifest.build_dependencies.as_ref(),
+            manifest.target.as_ref(),
+        );
+
+        // https://doc.rust-lang.org/cargo/reference/cargo-targets.html#the-name-field says that
+        // the `name` field is required for `bin` targets, so we can ignore `None` values via
+        // `filter_map()` here.
+        let bin_names = manifest
+            .bin
+            .into_iter()
+            .filter_map(|bin| bin.name)
+            .collect();
+
+        Self {
+            description: package.description.map(|it| it.as_local().unwrap()),
+            license: package.license.map(|it| it.as_local().unwrap()),
+            license_file: package.license_file.map(|it| it.as_local().unwrap()),
+            homepage: package.homepage.map(|it| it.as_local().unwrap()),
+            documentation: package.documentation.map(|it| it.as_local().unwrap()),
+            repository: package.repository.map(|it| it.as_local().unwrap()),
+            rust_version: package.rust_version.map(|rv| rv.as_local().unwrap()),
+            edition: package.edition.map(|rv| rv.as_local().unwrap()),
+            readme: package.readme.map(|it| it.as_local().unwrap()),
+            links: package.links,
+            keywords: package
+                .keywords
+                .map(|it| it.as_local().unwrap())
+                .unwrap_or_default(),
+            categories: package
+                .categories
+                .map(|it| it.as_local().unwrap())
+                .unwrap_or_default(),
+            features: manifest.features.unwrap_or_default(),
+            has_lib: manifest.lib.is_some(),
+            bin_names,
+            deps,
+        }
+    }
+}
+
 fn convert_dependencies(
     normal_deps: Option<&DepsSet>,
     dev_deps: Option<&DepsSet>,
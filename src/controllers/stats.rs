@@ -0,0 +1,103 @@
+//! Endpoint for exposing registry-wide statistics
+
+use crate::app::AppState;
+use crate::schema::daily_registry_stats;
+use crate::util::errors::{AppResult, bad_request};
+use axum::Json;
+use axum::extract::FromRequestParts;
+use axum_extra::extract::Query;
+use chrono::{Days, NaiveDate, Utc};
+use crates_io_diesel_helpers::to_char;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+/// The maximum number of days that can be requested at once.
+const MAX_DAYS: u64 = 366;
+
+#[derive(Debug, Deserialize, FromRequestParts, utoipa::IntoParams)]
+#[from_request(via(Query))]
+#[into_params(parameter_in = Query)]
+pub struct DailyStatsQueryParams {
+    /// Only return statistics on or after this date.
+    ///
+    /// Defaults to 89 days before `to`.
+    #[param(example = "2024-06-01")]
+    from: Option<NaiveDate>,
+
+    /// Only return statistics on or before this date.
+    ///
+    /// Defaults to today.
+    #[param(example = "2024-06-28")]
+    to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct DailyStatsResponse {
+    /// The registry-wide statistics per day, in ascending order by date.
+    #[schema(inline)]
+    pub daily_stats: Vec<DailyStats>,
+}
+
+#[derive(Debug, Serialize, Queryable, utoipa::ToSchema)]
+pub struct DailyStats {
+    /// The date these statistics are for.
+    #[schema(example = "2019-12-13")]
+    date: String,
+
+    /// The total number of downloads across all crates on the given date.
+    #[schema(example = 123456789)]
+    downloads: i64,
+
+    /// The number of crates that were created on the given date.
+    #[schema(example = 123)]
+    new_crates: i32,
+
+    /// The number of versions that were published on the given date.
+    #[schema(example = 1234)]
+    new_versions: i32,
+}
+
+/// Get registry-wide statistics per day.
+///
+/// This includes the total number of downloads, new crates and new versions
+/// for each day in the requested range, which defaults to the last 90 days
+/// and may span at most 366 days.
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/daily",
+    params(DailyStatsQueryParams),
+    tag = "other",
+    responses((status = 200, description = "Successful Response", body = inline(DailyStatsResponse))),
+)]
+pub async fn get_daily_stats(
+    state: AppState,
+    params: DailyStatsQueryParams,
+) -> AppResult<Json<DailyStatsResponse>> {
+    let to = params.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = params.from.unwrap_or(to - Days::new(89));
+
+    if from > to {
+        return Err(bad_request("`from` must not be after `to`"));
+    }
+
+    if from + Days::new(MAX_DAYS) <= to {
+        let detail = format!("the requested range must not exceed {MAX_DAYS} days");
+        return Err(bad_request(detail));
+    }
+
+    let mut conn = state.db_read().await?;
+
+    let daily_stats = daily_registry_stats::table
+        .filter(daily_registry_stats::date.between(from, to))
+        .select((
+            to_char(daily_registry_stats::date, "YYYY-MM-DD"),
+            daily_registry_stats::downloads,
+            daily_registry_stats::new_crates,
+            daily_registry_stats::new_versions,
+        ))
+        .order(daily_registry_stats::date.asc())
+        .load(&mut conn)
+        .await?;
+
+    Ok(Json(DailyStatsResponse { daily_stats }))
+}
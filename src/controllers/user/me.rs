@@ -1,5 +1,6 @@
 use crate::auth::AuthCheck;
 use axum::Json;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
 use futures_util::FutureExt;
@@ -10,6 +11,7 @@ use crate::controllers::helpers::Paginate;
 use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
 use crate::models::krate::CrateName;
 use crate::models::{CrateOwner, Follow, OwnerKind, User, Version, VersionOwnerAction};
+use crate::rate_limiter::{LimitedAction, RateLimiter};
 use crate::schema::{crate_owners, crates, emails, follows, users, versions};
 use crate::util::errors::AppResult;
 use crate::views::{EncodableMe, EncodablePrivateUser, EncodableVersion, OwnedCrate};
@@ -130,3 +132,86 @@ pub async fn get_authenticated_user_updates(
         meta: UpdatesResponseMeta { more },
     }))
 }
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct RateLimitsResponse {
+    /// The rate limits of the authenticated user, one per rate-limited action.
+    #[schema(inline)]
+    pub rate_limits: Vec<EncodableRateLimit>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct EncodableRateLimit {
+    /// The rate-limited action.
+    #[schema(example = "publish_new")]
+    pub action: &'static str,
+
+    /// The maximum number of times the action can be performed in a burst.
+    #[schema(example = 5)]
+    pub limit: i32,
+
+    /// The number of times the action can currently be performed.
+    #[schema(example = 4)]
+    pub remaining: i32,
+
+    /// The time at which `remaining` will be back at `limit`.
+    #[schema(example = "2019-12-13T13:46:41Z")]
+    pub reset_at: DateTime<Utc>,
+
+    /// The number of seconds after which the action can be performed once
+    /// more.
+    #[schema(example = 600)]
+    pub refill_seconds: i64,
+
+    /// The burst of an active override of the limit, if any.
+    #[schema(example = 10)]
+    pub override_burst: Option<i32>,
+
+    /// The time at which the active override expires, if it does.
+    #[schema(example = "2020-01-13T13:46:41Z")]
+    pub override_expires_at: Option<DateTime<Utc>>,
+}
+
+/// List the rate limits of the authenticated user.
+///
+/// This allows automated publishers to pace themselves instead of running
+/// into the rate limits in the middle of a release. The same information is
+/// also returned in the `RateLimit-Limit`, `RateLimit-Remaining` and
+/// `RateLimit-Reset` headers of the rate-limited endpoints.
+#[utoipa::path(
+    get,
+    path = "/api/v1/me/rate_limits",
+    security(
+        ("api_token" = []),
+        ("cookie" = []),
+    ),
+    tag = "users",
+    responses((status = 200, description = "Successful Response", body = inline(RateLimitsResponse))),
+)]
+pub async fn get_authenticated_user_rate_limits(
+    app: AppState,
+    req: Parts,
+) -> AppResult<Json<RateLimitsResponse>> {
+    let mut conn = app.db_read_prefer_primary().await?;
+    let user_id = AuthCheck::default().check(&req, &mut conn).await?.user_id();
+
+    let mut overrides = RateLimiter::active_overrides(user_id, &mut conn).await?;
+
+    let mut rate_limits = Vec::with_capacity(LimitedAction::VARIANTS.len());
+    for action in LimitedAction::VARIANTS {
+        let status = app.rate_limiter.status(user_id, *action, &mut conn).await?;
+        let active_override = overrides.remove(action);
+
+        rate_limits.push(EncodableRateLimit {
+            action: action.name(),
+            limit: status.limit,
+            remaining: status.remaining,
+            reset_at: status.reset_at,
+            refill_seconds: app.rate_limiter.refill_rate(*action).num_seconds(),
+            override_burst: active_override.map(|o| o.burst),
+            override_expires_at: active_override.and_then(|o| o.expires_at),
+        });
+    }
+
+    Ok(Json(RateLimitsResponse { rate_limits }))
+}
@@ -4,8 +4,9 @@
 
 use super::CrateVersionPath;
 use crate::app::AppState;
-use crate::models::VersionDownload;
-use crate::schema::*;
+use crate::controllers::helpers::downloads::{
+    DateRange, DownloadsInterval, aggregate, csv_response, load_version_downloads,
+};
 use crate::util::errors::AppResult;
 use crate::util::{RequestUtils, redirect};
 use crate::views::EncodableVersionDownload;
@@ -13,9 +14,7 @@ use axum::Json;
 use axum::extract::{FromRequestParts, Query};
 use axum::response::{IntoResponse, Response};
 use axum_extra::json;
-use chrono::{Duration, NaiveDate, Utc};
-use diesel::prelude::*;
-use diesel_async::RunQueryDsl;
+use chrono::NaiveDate;
 use http::request::Parts;
 
 #[derive(Debug, Serialize, utoipa::ToSchema)]
@@ -57,8 +56,28 @@ pub async fn download_version(
 #[into_params(parameter_in = Query)]
 pub struct DownloadsQueryParams {
     /// Only return download counts before this date.
+    ///
+    /// This is an alias for `to` and is ignored if `to` is set.
     #[param(example = "2024-06-28")]
     before_date: Option<NaiveDate>,
+
+    /// Only return download counts on or after this date.
+    ///
+    /// Defaults to 89 days before `to`.
+    #[param(example = "2024-01-01")]
+    from: Option<NaiveDate>,
+
+    /// Only return download counts on or before this date.
+    ///
+    /// Defaults to today.
+    #[param(example = "2024-06-28")]
+    to: Option<NaiveDate>,
+
+    /// The time interval that the download counts are aggregated by.
+    ///
+    /// Defaults to `day`.
+    #[param(inline)]
+    interval: Option<DownloadsInterval>,
 }
 
 #[derive(Debug, Serialize, utoipa::ToSchema)]
@@ -66,38 +85,64 @@ pub struct DownloadsResponse {
     pub version_downloads: Vec<EncodableVersionDownload>,
 }
 
+/// A row of the `text/csv` representation of the version download counts.
+#[derive(Debug, Serialize)]
+struct CsvRecord {
+    date: NaiveDate,
+    downloads: i64,
+}
+
 /// Get the download counts for a crate version.
 ///
 /// This includes the per-day downloads for the last 90 days.
+///
+/// The `from`, `to` and `interval` query parameters can be used to request
+/// a different date range and to aggregate the counts per week or month.
+/// Download counts that are no longer in the database are read from the
+/// version downloads archive. At most 92 of these archived days can be
+/// requested at once.
+///
+/// If the `Accept` header contains `text/csv`, the download counts are
+/// returned as CSV with `date` and `downloads` columns instead.
 #[utoipa::path(
     get,
     path = "/api/v1/crates/{name}/{version}/downloads",
     params(CrateVersionPath, DownloadsQueryParams),
     tag = "versions",
-    responses((status = 200, description = "Successful Response", body = inline(DownloadsResponse))),
+    responses((status = 200, description = "Successful Response", content(
+        (inline(DownloadsResponse) = "application/json"),
+        (String = "text/csv"),
+    ))),
 )]
 pub async fn get_version_downloads(
     app: AppState,
     path: CrateVersionPath,
     params: DownloadsQueryParams,
-) -> AppResult<Json<DownloadsResponse>> {
+    req: Parts,
+) -> AppResult<Response> {
     let mut conn = app.db_read().await?;
     let version = path.load_version(&mut conn).await?;
 
-    let cutoff_end_date = params
-        .before_date
-        .unwrap_or_else(|| Utc::now().date_naive());
+    let range = DateRange::new(params.from, params.to.or(params.before_date))?;
+    let interval = params.interval.unwrap_or_default();
 
-    let cutoff_start_date = cutoff_end_date - Duration::days(89);
+    let downloads =
+        load_version_downloads(&mut conn, &app.downloads_archive, &[version.id], range).await?;
+    let downloads = aggregate(downloads, interval);
+
+    if req.wants_csv() {
+        let records = downloads.into_iter().map(|count| CsvRecord {
+            date: count.date,
+            downloads: count.downloads,
+        });
+
+        return csv_response(records);
+    }
 
-    let version_downloads = VersionDownload::belonging_to(&version)
-        .filter(version_downloads::date.between(cutoff_start_date, cutoff_end_date))
-        .order(version_downloads::date)
-        .load(&mut conn)
-        .await?
+    let version_downloads = downloads
         .into_iter()
-        .map(VersionDownload::into)
+        .map(EncodableVersionDownload::from)
         .collect();
 
-    Ok(Json(DownloadsResponse { version_downloads }))
+    Ok(Json(DownloadsResponse { version_downloads }).into_response())
 }
@@ -4,7 +4,7 @@ use crate::auth::{AuthCheck, Authentication};
 use crate::controllers::helpers::authorization::Rights;
 use crate::models::token::EndpointScope;
 use crate::models::{Crate, NewVersionOwnerAction, Version, VersionAction, VersionOwnerAction};
-use crate::rate_limiter::LimitedAction;
+use crate::rate_limiter::{LimitedAction, RateLimitStatus};
 use crate::schema::versions;
 use crate::util::errors::{AppResult, bad_request, custom};
 use crate::views::EncodableVersion;
@@ -51,13 +51,13 @@ pub async fn update_version(
     path: CrateVersionPath,
     req: Parts,
     Json(update_request): Json<VersionUpdateRequest>,
-) -> AppResult<Json<UpdateResponse>> {
+) -> AppResult<(RateLimitStatus, Json<UpdateResponse>)> {
     let mut conn = state.db_write().await?;
     let (mut version, krate) = path.load_version_and_crate(&mut conn).await?;
     validate_yank_update(&update_request.version, &version)?;
     let auth = authenticate(&req, &mut conn, &krate.name).await?;
 
-    state
+    let rate_limit = state
         .rate_limiter
         .check_rate_limit(auth.user_id(), LimitedAction::YankUnyank, &mut conn)
         .await?;
@@ -78,7 +78,7 @@ pub async fn update_version(
         version.published_by(&mut conn),
     )?;
     let version = EncodableVersion::from(version, &krate.name, published_by, actions);
-    Ok(Json(UpdateResponse { version }))
+    Ok((rate_limit, Json(UpdateResponse { version })))
 }
 
 fn validate_yank_update(update_data: &VersionUpdate, version: &Version) -> AppResult<()> {
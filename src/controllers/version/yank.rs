@@ -4,7 +4,7 @@ use super::CrateVersionPath;
 use super::update::{authenticate, perform_version_yank_update};
 use crate::app::AppState;
 use crate::controllers::helpers::OkResponse;
-use crate::rate_limiter::LimitedAction;
+use crate::rate_limiter::{LimitedAction, RateLimitStatus};
 use crate::util::errors::AppResult;
 use http::request::Parts;
 
@@ -34,7 +34,7 @@ pub async fn yank_version(
     app: AppState,
     path: CrateVersionPath,
     req: Parts,
-) -> AppResult<OkResponse> {
+) -> AppResult<(RateLimitStatus, OkResponse)> {
     modify_yank(path, app, req, true).await
 }
 
@@ -54,7 +54,7 @@ pub async fn unyank_version(
     app: AppState,
     path: CrateVersionPath,
     req: Parts,
-) -> AppResult<OkResponse> {
+) -> AppResult<(RateLimitStatus, OkResponse)> {
     modify_yank(path, app, req, false).await
 }
 
@@ -64,7 +64,7 @@ async fn modify_yank(
     state: AppState,
     req: Parts,
     yanked: bool,
-) -> AppResult<OkResponse> {
+) -> AppResult<(RateLimitStatus, OkResponse)> {
     // FIXME: Should reject bad requests before authentication, but can't due to
     // lifetime issues with `req`.
 
@@ -72,7 +72,7 @@ async fn modify_yank(
     let (mut version, krate) = path.load_version_and_crate(&mut conn).await?;
     let auth = authenticate(&req, &mut conn, &krate.name).await?;
 
-    state
+    let rate_limit = state
         .rate_limiter
         .check_rate_limit(auth.user_id(), LimitedAction::YankUnyank, &mut conn)
         .await?;
@@ -88,5 +88,5 @@ async fn modify_yank(
     )
     .await?;
 
-    Ok(OkResponse::new())
+    Ok((rate_limit, OkResponse::new()))
 }
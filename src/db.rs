@@ -1,4 +1,5 @@
 use crate::certs::CRUNCHY;
+use crates_io_worker::Listener;
 use diesel::{ConnectionResult, QueryResult};
 use diesel_async::pooled_connection::ManagerConfig;
 use diesel_async::pooled_connection::deadpool::{Hook, HookError};
@@ -65,7 +66,26 @@ async fn establish_async_connection(
 ) -> ConnectionResult<AsyncPgConnection> {
     use diesel::ConnectionError::BadConnection;
 
-    let cert = Certificate::from_pem(CRUNCHY).map_err(|err| BadConnection(err.to_string()))?;
+    let connector =
+        make_tls_connector(enforce_tls).map_err(|err| BadConnection(err.to_string()))?;
+    let result = tokio_postgres::connect(url, connector).await;
+    let (client, conn) = result.map_err(|err| BadConnection(err.to_string()))?;
+    AsyncPgConnection::try_from_client_and_connection(client, conn).await
+}
+
+/// Establish a new database connection with the given URL, which is used by
+/// the background worker to listen for notifications about new jobs.
+pub async fn establish_listener_connection(
+    url: &str,
+    enforce_tls: bool,
+) -> anyhow::Result<Listener> {
+    let connector = make_tls_connector(enforce_tls)?;
+    let (client, conn) = tokio_postgres::connect(url, connector).await?;
+    Ok(Listener::new(client, conn))
+}
+
+fn make_tls_connector(enforce_tls: bool) -> anyhow::Result<MakeTlsConnector> {
+    let cert = Certificate::from_pem(CRUNCHY)?;
 
     let connector = TlsConnector::builder()
         .add_root_certificate(cert)
@@ -78,13 +98,9 @@ async fn establish_async_connection(
         // running database, so we also don't need to enforce the validity of
         // the certificate either.
         .danger_accept_invalid_certs(!enforce_tls)
-        .build()
-        .map_err(|err| BadConnection(err.to_string()))?;
+        .build()?;
 
-    let connector = MakeTlsConnector::new(connector);
-    let result = tokio_postgres::connect(url, connector).await;
-    let (client, conn) = result.map_err(|err| BadConnection(err.to_string()))?;
-    AsyncPgConnection::try_from_client_and_connection(client, conn).await
+    Ok(MakeTlsConnector::new(connector))
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,344 @@
+//! Read access to the `version_downloads` archive.
+//!
+//! The `ArchiveVersionDownloads` background job moves rows that are older
+//! than 90 days out of the `version_downloads` table and into one CSV file
+//! per day in the object store. This module reads these files back, so that
+//! the API can serve the full download history of a crate or version.
+//!
+//! Since the archive files contain the download counts of all versions for
+//! a given day, the parsed files are kept in an in-memory LRU cache.
+
+use crate::storage::Storage;
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use futures_util::{StreamExt, TryStreamExt};
+use moka::Expiry;
+use moka::future::{Cache, CacheBuilder};
+use moka::policy::EvictionPolicy;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The maximum number of archive files that are downloaded concurrently.
+const MAX_CONCURRENCY: usize = 10;
+
+/// How long a missing archive file is remembered, before the object store is
+/// asked for it again.
+const MISSING_DAY_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How long the earliest archived date from the archive index is cached.
+const EARLIEST_DATE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The download counts of all versions on a single day, as read from an
+/// archive file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ArchivedDay {
+    /// `(version_id, downloads)` pairs, sorted by `version_id`.
+    downloads: Vec<(i32, i64)>,
+}
+
+impl ArchivedDay {
+    /// Parses an archive file with `version_id,downloads` columns.
+    fn parse(bytes: &[u8]) -> csv::Result<Self> {
+        let mut downloads = csv::Reader::from_reader(bytes)
+            .deserialize::<(i32, i64)>()
+            .collect::<Result<Vec<_>, _>>()?;
+
+        downloads.sort_unstable();
+        Ok(Self { downloads })
+    }
+
+    /// Returns the number of versions with downloads on this day.
+    pub fn len(&self) -> usize {
+        self.downloads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.downloads.is_empty()
+    }
+
+    /// Returns the number of downloads of the given version on this day.
+    pub fn get(&self, version_id: i32) -> Option<i64> {
+        let index = self
+            .downloads
+            .binary_search_by_key(&version_id, |(id, _)| *id)
+            .ok()?;
+
+        Some(self.downloads[index].1)
+    }
+
+    /// Returns the `(version_id, downloads)` pairs for the given versions.
+    pub fn filter(&self, version_ids: &HashSet<i32>) -> Vec<(i32, i64)> {
+        // For small sets of versions it is faster to look up each of them
+        // than to iterate over all versions of the day.
+        if version_ids.len() < 64 {
+            let mut downloads = version_ids
+                .iter()
+                .filter_map(|id| self.get(*id).map(|downloads| (*id, downloads)))
+                .collect::<Vec<_>>();
+
+            downloads.sort_unstable();
+            return downloads;
+        }
+
+        self.downloads
+            .iter()
+            .filter(|(id, _)| version_ids.contains(id))
+            .copied()
+            .collect()
+    }
+}
+
+/// The file name of the archive index, which lists all archive files.
+#[derive(Debug, Deserialize)]
+struct IndexFile {
+    name: String,
+}
+
+/// Expires missing archive files after [`MISSING_DAY_TTL`], while archived
+/// days are only evicted by the LRU policy.
+struct MissingDayExpiry;
+
+impl Expiry<NaiveDate, Option<Arc<ArchivedDay>>> for MissingDayExpiry {
+    fn expire_after_create(
+        &self,
+        _date: &NaiveDate,
+        day: &Option<Arc<ArchivedDay>>,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        day.is_none().then_some(MISSING_DAY_TTL)
+    }
+}
+
+/// Reads and caches the files of the `version_downloads` archive.
+pub struct DownloadsArchive {
+    storage: Arc<Storage>,
+    cache: Cache<NaiveDate, Option<Arc<ArchivedDay>>>,
+    earliest_date: Cache<(), Option<NaiveDate>>,
+}
+
+impl DownloadsArchive {
+    /// Creates a new archive reader for the given storage.
+    ///
+    /// The cache is weighed by the number of versions in each archive file,
+    /// so `max_capacity` is the maximum total number of rows that are kept
+    /// in memory.
+    pub fn new(storage: Arc<Storage>, max_capacity: u64) -> Self {
+        let cache = CacheBuilder::new(max_capacity)
+            .name("version_downloads_archive")
+            .eviction_policy(EvictionPolicy::lru())
+            .expire_after(MissingDayExpiry)
+            .weigher(|_date, day: &Option<Arc<ArchivedDay>>| {
+                let len = day.as_ref().map_or(1, |day| day.len().max(1));
+                len.try_into().unwrap_or(u32::MAX)
+            })
+            .build();
+
+        let earliest_date = CacheBuilder::new(1)
+            .name("version_downloads_archive_earliest_date")
+            .time_to_live(EARLIEST_DATE_TTL)
+            .build();
+
+        Self {
+            storage,
+            cache,
+            earliest_date,
+        }
+    }
+
+    /// Returns the archived download counts for the given date, or `None` if
+    /// the date has not been archived (yet).
+    ///
+    /// Concurrent calls for the same date share a single download. Missing
+    /// archive files are only cached for a few minutes, since the background
+    /// job might archive the date at any time.
+    #[instrument(skip(self))]
+    pub async fn load_day(&self, date: NaiveDate) -> anyhow::Result<Option<Arc<ArchivedDay>>> {
+        let load = async {
+            let bytes = self
+                .storage
+                .download_version_downloads_archive(date)
+                .await?;
+            let day = bytes.map(|bytes| ArchivedDay::parse(&bytes)).transpose()?;
+            anyhow::Ok(day.map(Arc::new))
+        };
+
+        self.cache
+            .try_get_with(date, load)
+            .await
+            .map_err(|error| anyhow!("{error:#}"))
+    }
+
+    /// Returns the earliest archived date according to the archive index, or
+    /// `None` if there is no index (yet).
+    ///
+    /// No archive files exist for the dates before this one, so they don't
+    /// have to be looked up.
+    #[instrument(skip(self))]
+    pub async fn earliest_date(&self) -> anyhow::Result<Option<NaiveDate>> {
+        let load = async {
+            let Some(bytes) = self
+                .storage
+                .download_version_downloads_archive_index()
+                .await?
+            else {
+                return Ok(None);
+            };
+
+            let files: Vec<IndexFile> = serde_json::from_slice(&bytes)?;
+            let earliest_date = files
+                .iter()
+                .filter_map(|file| file.name.strip_suffix(".csv")?.parse().ok())
+                .min();
+
+            anyhow::Ok(earliest_date)
+        };
+
+        self.earliest_date
+            .try_get_with((), load)
+            .await
+            .map_err(|error| anyhow!("{error:#}"))
+    }
+
+    /// Returns the archived download counts of the given versions for each
+    /// of the given dates.
+    ///
+    /// Dates without an archive file are not included in the returned map,
+    /// while archived dates without downloads for any of the versions are
+    /// included with an empty list.
+    pub async fn version_downloads(
+        &self,
+        version_ids: &HashSet<i32>,
+        dates: impl Iterator<Item = NaiveDate>,
+    ) -> anyhow::Result<BTreeMap<NaiveDate, Vec<(i32, i64)>>> {
+        futures_util::stream::iter(dates)
+            .map(async |date| {
+                let day = self.load_day(date).await?;
+                let downloads = day.map(|day| (date, day.filter(version_ids)));
+                anyhow::Ok(downloads)
+            })
+            .buffer_unordered(MAX_CONCURRENCY)
+            .try_filter_map(async |result| Ok(result))
+            .try_collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageConfig;
+    use claims::{assert_err, assert_none, assert_ok, assert_some};
+    use object_store::path::Path;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    async fn upload(storage: &Storage, date: &str, content: &'static str) {
+        let path = Path::from(format!("archive/version-downloads/{date}.csv"));
+        let store = storage.as_inner();
+        store.put(&path, content.into()).await.unwrap();
+    }
+
+    async fn upload_index(storage: &Storage, content: &'static str) {
+        let path = Path::from("archive/version-downloads/index.json");
+        storage.as_inner().put(&path, content.into()).await.unwrap();
+    }
+
+    async fn delete(storage: &Storage, date: &str) {
+        let path = Path::from(format!("archive/version-downloads/{date}.csv"));
+        storage.as_inner().delete(&path).await.unwrap();
+    }
+
+    #[test]
+    fn test_parse() {
+        let day = assert_ok!(ArchivedDay::parse(
+            b"version_id,downloads\n3,30\n1,10\n2,20\n"
+        ));
+        assert_eq!(day.len(), 3);
+        assert_eq!(day.get(1), Some(10));
+        assert_eq!(day.get(3), Some(30));
+        assert_eq!(day.get(4), None);
+
+        let version_ids = HashSet::from([3, 1, 42]);
+        assert_eq!(day.filter(&version_ids), vec![(1, 10), (3, 30)]);
+
+        let version_ids = (2..100).collect();
+        assert_eq!(day.filter(&version_ids), vec![(2, 20), (3, 30)]);
+
+        assert_err!(ArchivedDay::parse(b"version_id,downloads\n1,foo\n"));
+    }
+
+    #[tokio::test]
+    async fn test_version_downloads() {
+        let storage = Arc::new(Storage::from_config(&StorageConfig::in_memory()));
+        upload(&storage, "2024-06-01", "version_id,downloads\n1,10\n2,20\n").await;
+        upload(&storage, "2024-06-02", "version_id,downloads\n2,5\n").await;
+
+        let archive = DownloadsArchive::new(storage.clone(), 1000);
+
+        let version_ids = HashSet::from([1]);
+        let dates = date("2024-05-31").iter_days().take(3);
+        let downloads = assert_ok!(archive.version_downloads(&version_ids, dates).await);
+        assert_eq!(
+            downloads,
+            BTreeMap::from([
+                (date("2024-06-01"), vec![(1, 10)]),
+                (date("2024-06-02"), vec![])
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let storage = Arc::new(Storage::from_config(&StorageConfig::in_memory()));
+        let archive = DownloadsArchive::new(storage.clone(), 1000);
+
+        // Missing files are cached too, so that dates that have not been
+        // archived don't cause a storage request on every call
+        assert_none!(assert_ok!(archive.load_day(date("2024-05-31")).await));
+        upload(&storage, "2024-05-31", "version_id,downloads\n1,10\n").await;
+        assert_none!(assert_ok!(archive.load_day(date("2024-05-31")).await));
+
+        upload(&storage, "2024-06-01", "version_id,downloads\n1,10\n").await;
+        let day = assert_some!(assert_ok!(archive.load_day(date("2024-06-01")).await));
+        assert_eq!(day.get(1), Some(10));
+
+        // Subsequent calls are served from the cache
+        delete(&storage, "2024-06-01").await;
+        let day = assert_some!(assert_ok!(archive.load_day(date("2024-06-01")).await));
+        assert_eq!(day.get(1), Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_loads() {
+        let storage = Arc::new(Storage::from_config(&StorageConfig::in_memory()));
+        upload(&storage, "2024-06-01", "version_id,downloads\n1,10\n").await;
+
+        let archive = DownloadsArchive::new(storage.clone(), 1000);
+
+        // Concurrent calls for the same date share the same parsed file
+        let date = date("2024-06-01");
+        let (first, second) = tokio::join!(archive.load_day(date), archive.load_day(date));
+        let first = assert_some!(assert_ok!(first));
+        let second = assert_some!(assert_ok!(second));
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+
+    #[tokio::test]
+    async fn test_earliest_date() {
+        let storage = Arc::new(Storage::from_config(&StorageConfig::in_memory()));
+        let archive = DownloadsArchive::new(storage.clone(), 1000);
+        assert_none!(assert_ok!(archive.earliest_date().await));
+
+        let archive = DownloadsArchive::new(storage.clone(), 1000);
+        upload_index(
+            &storage,
+            r#"[{"name":"2024-06-02.csv","size":10},{"name":"2024-06-01.csv","size":10}]"#,
+        )
+        .await;
+        let earliest_date = assert_some!(assert_ok!(archive.earliest_date().await));
+        assert_eq!(earliest_date, date("2024-06-01"));
+    }
+}
@@ -1,4 +1,6 @@
+use crate::cdn::{CdnPurger, SurrogateKey};
 use anyhow::{Context, anyhow};
+use async_trait::async_trait;
 use reqwest::Client;
 use reqwest::header::{HeaderMap, HeaderValue};
 use secrecy::{ExposeSecret, SecretString};
@@ -7,19 +9,34 @@ use secrecy::{ExposeSecret, SecretString};
 pub struct Fastly {
     client: Client,
     api_token: SecretString,
+    service_id: String,
     static_domain_name: String,
 }
 
+/// The maximum number of surrogate keys that can be purged with a single
+/// request.
+///
+/// See <https://www.fastly.com/documentation/reference/api/purging/#bulk-purge-tag>
+const MAX_KEYS_PER_REQUEST: usize = 256;
+
 impl Fastly {
-    pub fn from_environment(client: Client) -> Option<Self> {
-        let api_token = dotenvy::var("FASTLY_API_TOKEN").ok()?.into();
+    /// Returns `None` if `FASTLY_API_TOKEN` is not set, and an error if it is set but
+    /// `FASTLY_SERVICE_ID` is not, since surrogate keys can only be purged per service.
+    pub fn from_environment(client: Client) -> anyhow::Result<Option<Self>> {
+        let Ok(api_token) = dotenvy::var("FASTLY_API_TOKEN") else {
+            return Ok(None);
+        };
+
+        let service_id = dotenvy::var("FASTLY_SERVICE_ID")
+            .context("FASTLY_SERVICE_ID must be set if FASTLY_API_TOKEN is set")?;
         let static_domain_name = dotenvy::var("S3_CDN").expect("missing S3_CDN");
 
-        Some(Self {
+        Ok(Some(Self {
             client,
-            api_token,
+            api_token: api_token.into(),
+            service_id,
             static_domain_name,
-        })
+        }))
     }
 
     /// Invalidate a path on Fastly
@@ -55,14 +72,43 @@ impl Fastly {
         Ok(())
     }
 
+    /// Invalidate all content tagged with the given surrogate keys on Fastly
+    ///
+    /// The keys are sent in batches to the bulk purge API of the Fastly service that is
+    /// configured via the `FASTLY_SERVICE_ID` environment variable. The service has to set the
+    /// `Surrogate-Key` header of the cached responses, as described in `docs/CDN.md`.
+    ///
+    /// More information on Fastly's APIs for cache invalidations can be found here:
+    /// <https://developer.fastly.com/reference/api/purging/>
+    #[instrument(skip(self))]
+    pub async fn invalidate_keys(&self, keys: &[SurrogateKey]) -> anyhow::Result<()> {
+        let url = format!("https://api.fastly.com/service/{}/purge", self.service_id);
+        for chunk in keys.chunks(MAX_KEYS_PER_REQUEST) {
+            let keys = chunk.iter().map(ToString::to_string).collect::<Vec<_>>();
+            let mut headers = HeaderMap::new();
+            headers.append("Surrogate-Key", HeaderValue::try_from(keys.join(" "))?);
+
+            self.purge_url_with_headers(&url, headers).await?;
+        }
+
+        Ok(())
+    }
+
     async fn purge_url(&self, url: &str) -> anyhow::Result<()> {
+        self.purge_url_with_headers(url, HeaderMap::new()).await
+    }
+
+    async fn purge_url_with_headers(
+        &self,
+        url: &str,
+        mut headers: HeaderMap,
+    ) -> anyhow::Result<()> {
         trace!(?url);
 
         let api_token = self.api_token.expose_secret();
         let mut api_token = HeaderValue::try_from(api_token)?;
         api_token.set_sensitive(true);
 
-        let mut headers = HeaderMap::new();
         headers.append("Fastly-Key", api_token);
 
         debug!("sending invalidation request to Fastly");
@@ -96,3 +142,22 @@ impl Fastly {
         }
     }
 }
+
+#[async_trait]
+impl CdnPurger for Fastly {
+    /// Fastly doesn't provide an API to purge multiple paths at once, so the
+    /// paths are purged one by one.
+    async fn purge_paths(&self, paths: &[String]) -> anyhow::Result<()> {
+        for path in paths {
+            self.invalidate(path)
+                .await
+                .with_context(|| format!("Failed to invalidate path on Fastly CDN: {path}"))?;
+        }
+
+        Ok(())
+    }
+
+    async fn purge_keys(&self, keys: &[SurrogateKey]) -> anyhow::Result<()> {
+        self.invalidate_keys(keys).await
+    }
+}
@@ -28,11 +28,13 @@ static ALLOC: Jemalloc = Jemalloc;
 mod app;
 pub mod auth;
 pub mod boot;
+pub mod cdn;
 pub mod certs;
 pub mod cloudfront;
 pub mod config;
 pub mod controllers;
 pub mod db;
+pub mod downloads_archive;
 pub mod email;
 pub mod external_urls;
 pub mod fastly;
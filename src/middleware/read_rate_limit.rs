@@ -0,0 +1,440 @@
+//! Rate limiting of the read-only API endpoints.
+//!
+//! In contrast to the [`RateLimiter`](crate::rate_limiter::RateLimiter) for
+//! publishing and yanking, the token buckets of this middleware are not kept
+//! in the database, but in a [`BucketStore`]. The only implementation so far
+//! is the [`InMemoryBucketStore`], so every server instance enforces the
+//! limits on its own. Requests are limited per API token, per cookie session,
+//! or otherwise per IP address.
+//!
+//! See [`ReadRateLimitConfig`] for how the routes and their budgets are
+//! configured.
+
+use crate::app::AppState;
+use crate::config::ReadRateLimitConfig;
+use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::real_ip::RealIp;
+use crate::rate_limiter::RateLimiterConfig;
+use crate::schema::api_tokens;
+use crate::util::errors::custom;
+use crate::util::token::HashedToken;
+use async_trait::async_trait;
+use axum::extract::{Extension, MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use crates_io_session::SessionExtension;
+use diesel::dsl::{exists, select};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use http::{Method, StatusCode, header};
+use moka::future::{Cache, CacheBuilder};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The maximum number of token buckets that are kept in memory. If this
+/// number is exceeded, the least recently used buckets are dropped, which
+/// effectively refills them.
+const MAX_BUCKETS: u64 = 100_000;
+
+/// The maximum number of API tokens whose validity is cached.
+const MAX_CACHED_TOKENS: u64 = 10_000;
+
+/// How long the validity of an API token is cached.
+const TOKEN_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Who a request is attributed to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    Token(Vec<u8>),
+    User(i32),
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: i32,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn new(config: &RateLimiterConfig, now: Instant) -> Self {
+        Self {
+            tokens: config.burst,
+            last_refill: now,
+        }
+    }
+
+    /// Refills the bucket as needed and takes a token from it.
+    ///
+    /// Returns the time until the next token is available if the bucket is
+    /// empty.
+    fn take_token(&mut self, config: &RateLimiterConfig, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let refills = elapsed.as_nanos() / config.rate.as_nanos().max(1);
+        let refills = u32::try_from(refills).unwrap_or(u32::MAX);
+
+        if refills > 0 {
+            self.tokens = self
+                .tokens
+                .saturating_add_unsigned(refills)
+                .min(config.burst);
+
+            // A full bucket does not accumulate any more time.
+            self.last_refill = if self.tokens == config.burst {
+                now
+            } else {
+                self.last_refill + config.rate * refills
+            };
+        }
+
+        if self.tokens > 0 {
+            self.tokens -= 1;
+            Ok(())
+        } else {
+            Err((self.last_refill + config.rate).saturating_duration_since(now))
+        }
+    }
+}
+
+/// Stores the token buckets of the [`ReadRateLimiter`].
+///
+/// Implementations that are shared between server instances would allow
+/// enforcing the limits across all of them.
+#[async_trait]
+pub trait BucketStore: Send + Sync {
+    /// Takes a token from the bucket of the given budget and key, creating a
+    /// full bucket if it doesn't exist yet.
+    ///
+    /// Returns the time until the next token is available if the bucket is
+    /// empty.
+    async fn take_token(
+        &self,
+        budget: &str,
+        config: &RateLimiterConfig,
+        key: RateLimitKey,
+        now: Instant,
+    ) -> Result<(), Duration>;
+}
+
+/// Keeps the token buckets in the memory of the current process.
+pub struct InMemoryBucketStore {
+    buckets: Cache<(String, RateLimitKey), Arc<Mutex<Bucket>>>,
+}
+
+impl InMemoryBucketStore {
+    pub fn new(config: &ReadRateLimitConfig) -> Self {
+        // Once a bucket has not been used for the time that it takes to
+        // refill completely, it can be dropped without changing the outcome.
+        let time_to_idle = config
+            .budgets
+            .values()
+            .map(|config| config.rate * config.burst.max(1) as u32)
+            .max()
+            .unwrap_or_default()
+            .max(Duration::from_secs(1));
+
+        let buckets = CacheBuilder::new(MAX_BUCKETS)
+            .time_to_idle(time_to_idle)
+            .build();
+
+        Self { buckets }
+    }
+}
+
+#[async_trait]
+impl BucketStore for InMemoryBucketStore {
+    async fn take_token(
+        &self,
+        budget: &str,
+        config: &RateLimiterConfig,
+        key: RateLimitKey,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let bucket = self
+            .buckets
+            .get_with((budget.to_string(), key), async move {
+                Arc::new(Mutex::new(Bucket::new(config, now)))
+            })
+            .await;
+
+        let mut bucket = bucket.lock().unwrap_or_else(|error| error.into_inner());
+        bucket.take_token(config, now)
+    }
+}
+
+pub struct ReadRateLimiter {
+    config: ReadRateLimitConfig,
+    buckets: Box<dyn BucketStore>,
+    valid_tokens: Cache<Vec<u8>, bool>,
+}
+
+impl ReadRateLimiter {
+    /// Creates a rate limiter that keeps its token buckets in memory.
+    pub fn new(config: ReadRateLimitConfig) -> Self {
+        let buckets = InMemoryBucketStore::new(&config);
+        Self::with_store(config, Box::new(buckets))
+    }
+
+    pub fn with_store(config: ReadRateLimitConfig, buckets: Box<dyn BucketStore>) -> Self {
+        let valid_tokens = CacheBuilder::new(MAX_CACHED_TOKENS)
+            .time_to_live(TOKEN_CACHE_TTL)
+            .build();
+
+        Self {
+            config,
+            buckets,
+            valid_tokens,
+        }
+    }
+
+    fn budget_for_route(&self, route: &str) -> Option<(&String, &RateLimiterConfig)> {
+        let budget = self.config.routes.get(route)?;
+        self.config.budgets.get_key_value(budget)
+    }
+
+    async fn take_token(
+        &self,
+        budget: &str,
+        config: &RateLimiterConfig,
+        key: RateLimitKey,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        self.buckets.take_token(budget, config, key, now).await
+    }
+}
+
+pub async fn middleware(
+    Extension(real_ip): Extension<RealIp>,
+    matched_path: Option<MatchedPath>,
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Response {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return next.run(req).await;
+    }
+
+    let limiter = &state.read_rate_limiter;
+    let Some(matched_path) = matched_path else {
+        return next.run(req).await;
+    };
+    let Some((budget, config)) = limiter.budget_for_route(matched_path.as_str()) else {
+        return next.run(req).await;
+    };
+
+    let token_hash = hashed_token(&req);
+    let user_id = session_user_id(&req);
+    let ip = *real_ip;
+
+    let now = Instant::now();
+    let result = match rate_limit_key(limiter, token_hash.as_deref(), user_id, ip).await {
+        Some(key) => limiter.take_token(budget, config, key, now).await,
+        None => {
+            // The validity of the API token is not known yet, so the request
+            // is charged to its IP address before the token is looked up in
+            // the database. Otherwise, made-up tokens could be used to send
+            // an unlimited number of database queries.
+            let ip_key = RateLimitKey::Ip(ip);
+            match limiter.take_token(budget, config, ip_key, now).await {
+                Ok(()) => match token_hash {
+                    Some(hash) if is_valid_token(&state, &hash).await => {
+                        let key = RateLimitKey::Token(hash);
+                        limiter.take_token(budget, config, key, now).await
+                    }
+                    _ => Ok(()),
+                },
+                Err(retry_after) => Err(retry_after),
+            }
+        }
+    };
+
+    if let Err(retry_after) = result {
+        req.request_log()
+            .add("cause", format!("read rate limit {budget} exceeded"));
+        return too_many_requests(retry_after);
+    }
+
+    next.run(req).await
+}
+
+/// Returns the hash of the API token of the request, if it is well-formed.
+fn hashed_token(req: &Request) -> Option<Vec<u8>> {
+    let authorization = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())?;
+
+    // Only tokens with a valid prefix are looked up in the database.
+    HashedToken::parse(authorization).ok()?;
+    Some(HashedToken::hash(authorization))
+}
+
+fn session_user_id(req: &Request) -> Option<i32> {
+    req.extensions()
+        .get::<SessionExtension>()
+        .and_then(|session| session.get("user_id"))
+        .and_then(|user_id| user_id.parse().ok())
+}
+
+/// Attributes the request to its API token if the token is known to be
+/// valid, to its cookie session, or to its IP address otherwise.
+///
+/// Returns `None` if the request has an API token whose validity is not
+/// cached yet. The validity of the API tokens is checked, since otherwise
+/// the limits could be circumvented by sending a different made-up token
+/// with every request.
+async fn rate_limit_key(
+    limiter: &ReadRateLimiter,
+    token_hash: Option<&[u8]>,
+    user_id: Option<i32>,
+    ip: IpAddr,
+) -> Option<RateLimitKey> {
+    if let Some(hash) = token_hash {
+        match limiter.valid_tokens.get(hash).await {
+            Some(true) => return Some(RateLimitKey::Token(hash.to_vec())),
+            Some(false) => {}
+            None => return None,
+        }
+    }
+
+    match user_id {
+        Some(user_id) => Some(RateLimitKey::User(user_id)),
+        None => Some(RateLimitKey::Ip(ip)),
+    }
+}
+
+/// Checks whether the API token with the given hash exists and is neither
+/// revoked nor expired.
+async fn is_valid_token(state: &AppState, hash: &[u8]) -> bool {
+    let limiter = &state.read_rate_limiter;
+    if let Some(valid) = limiter.valid_tokens.get(hash).await {
+        return valid;
+    }
+
+    let query = api_tokens::table
+        .filter(api_tokens::token.eq(hash))
+        .filter(api_tokens::revoked.eq(false))
+        .filter(
+            api_tokens::expired_at
+                .is_null()
+                .or(api_tokens::expired_at.gt(diesel::dsl::now)),
+        );
+
+    let result: anyhow::Result<bool> = async {
+        let mut conn = state.db_read().await?;
+        Ok(select(exists(query)).get_result(&mut conn).await?)
+    }
+    .await;
+
+    // Database errors are not cached, and the request is attributed to its
+    // IP address instead.
+    let valid = match result {
+        Ok(valid) => valid,
+        Err(error) => {
+            warn!("Failed to check the API token for the read rate limit: {error}");
+            return false;
+        }
+    };
+
+    limiter.valid_tokens.insert(hash.to_vec(), valid).await;
+    valid
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    // `Retry-After` only supports whole seconds, so round up to avoid
+    // clients retrying too early.
+    let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+    let detail = format!(
+        "You have sent too many requests to this endpoint in a short period of time. \
+         Please try again in {retry_after} seconds."
+    );
+
+    let mut response = custom(StatusCode::TOO_MANY_REQUESTS, detail).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, retry_after.into());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_none, assert_ok};
+    use std::collections::HashMap;
+
+    fn config() -> RateLimiterConfig {
+        RateLimiterConfig {
+            rate: Duration::from_secs(1),
+            burst: 2,
+        }
+    }
+
+    #[test]
+    fn take_token_from_full_bucket() {
+        let config = config();
+        let now = Instant::now();
+        let mut bucket = Bucket::new(&config, now);
+
+        assert_eq!(bucket.take_token(&config, now), Ok(()));
+        assert_eq!(bucket.take_token(&config, now), Ok(()));
+
+        let millis = Duration::from_millis(250);
+        let retry_after = bucket.take_token(&config, now + millis);
+        assert_eq!(retry_after, Err(Duration::from_millis(750)));
+    }
+
+    #[test]
+    fn bucket_is_refilled_over_time() {
+        let config = config();
+        let now = Instant::now();
+        let mut bucket = Bucket::new(&config, now);
+        bucket.tokens = 0;
+
+        let now = now + Duration::from_millis(1500);
+        assert_eq!(bucket.take_token(&config, now), Ok(()));
+        assert_eq!(
+            bucket.take_token(&config, now),
+            Err(Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn bucket_is_not_refilled_above_burst() {
+        let config = config();
+        let now = Instant::now();
+        let mut bucket = Bucket::new(&config, now);
+
+        let now = now + Duration::from_secs(60);
+        assert_eq!(bucket.take_token(&config, now), Ok(()));
+        assert_eq!(bucket.take_token(&config, now), Ok(()));
+        assert_eq!(bucket.take_token(&config, now), Err(Duration::from_secs(1)));
+    }
+
+    #[tokio::test]
+    async fn buckets_are_shared_between_routes_of_the_same_budget() {
+        let config = ReadRateLimitConfig {
+            routes: HashMap::from([
+                ("/a".into(), "SHARED".into()),
+                ("/b".into(), "SHARED".into()),
+            ]),
+            budgets: HashMap::from([("SHARED".into(), config())]),
+        };
+        let limiter = ReadRateLimiter::new(config);
+
+        let now = Instant::now();
+        let key = RateLimitKey::Ip([127, 0, 0, 1].into());
+        let other_key = RateLimitKey::Ip([127, 0, 0, 2].into());
+
+        let (budget, config) = limiter.budget_for_route("/a").unwrap();
+        assert_ok!(limiter.take_token(budget, config, key.clone(), now).await);
+
+        let (budget, config) = limiter.budget_for_route("/b").unwrap();
+        assert_ok!(limiter.take_token(budget, config, key.clone(), now).await);
+        assert_err!(limiter.take_token(budget, config, key, now).await);
+        assert_ok!(limiter.take_token(budget, config, other_key, now).await);
+
+        assert_none!(limiter.budget_for_route("/c"));
+    }
+}
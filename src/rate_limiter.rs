@@ -1,15 +1,28 @@
-use crate::schema::{publish_limit_buckets, publish_rate_overrides};
+use crate::schema::{
+    publish_limit_buckets, publish_rate_override_actions, publish_rate_overrides, users,
+};
 use crate::util::errors::{AppResult, TooManyRequests};
+use anyhow::anyhow;
+use axum::response::{IntoResponseParts, ResponseParts};
 use chrono::{DateTime, Utc};
 use crates_io_diesel_helpers::{date_part, floor, greatest, interval_part, least, pg_enum};
 use diesel::dsl::IntervalDsl;
 use diesel::prelude::*;
 use diesel::sql_types::Interval;
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use http::HeaderMap;
+use http::header::HeaderName;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::convert::Infallible;
+use std::str::FromStr;
 use std::time::Duration;
 
+static RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+static RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+static RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
 pg_enum! {
     pub enum LimitedAction {
         PublishNew = 0,
@@ -43,6 +56,15 @@ impl LimitedAction {
         }
     }
 
+    /// The name of the action in API responses.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LimitedAction::PublishNew => "publish_new",
+            LimitedAction::PublishUpdate => "publish_update",
+            LimitedAction::YankUnyank => "yank_unyank",
+        }
+    }
+
     pub fn error_message(&self) -> &'static str {
         match self {
             LimitedAction::PublishNew => {
@@ -58,12 +80,71 @@ impl LimitedAction {
     }
 }
 
+impl FromStr for LimitedAction {
+    type Err = anyhow::Error;
+
+    /// Parses an action from its [name](LimitedAction::name).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::VARIANTS
+            .iter()
+            .copied()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| {
+                let names = Self::VARIANTS.iter().map(|action| action.name());
+                let names = names.collect::<Vec<_>>().join(", ");
+                anyhow!("Unknown rate limited action `{s}`, expected one of: {names}")
+            })
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct RateLimiterConfig {
     pub rate: Duration,
     pub burst: i32,
 }
 
+/// The state of the rate limit of a user for a [LimitedAction], as exposed
+/// through the `RateLimit-*` response headers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitStatus {
+    /// The maximum number of actions that can be performed in a burst.
+    pub limit: i32,
+    /// The number of actions that can currently be performed.
+    pub remaining: i32,
+    /// The time at which the user can perform `limit` actions again.
+    pub reset_at: DateTime<Utc>,
+}
+
+impl RateLimitStatus {
+    /// Adds the `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset`
+    /// headers to the given header map.
+    ///
+    /// `RateLimit-Reset` is the number of seconds until `reset_at`.
+    pub fn insert_headers(&self, headers: &mut HeaderMap) {
+        let reset = (self.reset_at - Utc::now()).num_seconds().max(0);
+
+        headers.insert(&RATELIMIT_LIMIT, self.limit.into());
+        headers.insert(&RATELIMIT_REMAINING, self.remaining.into());
+        headers.insert(&RATELIMIT_RESET, reset.into());
+    }
+}
+
+impl IntoResponseParts for RateLimitStatus {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        self.insert_headers(res.headers_mut());
+        Ok(res)
+    }
+}
+
+/// An active override of the burst of a [LimitedAction] for a user.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitOverride {
+    pub burst: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug)]
 pub struct RateLimiter {
     config: HashMap<LimitedAction, RateLimiterConfig>,
@@ -79,53 +160,128 @@ impl RateLimiter {
         uploader: i32,
         performed_action: LimitedAction,
         conn: &mut AsyncPgConnection,
-    ) -> AppResult<()> {
+    ) -> AppResult<RateLimitStatus> {
+        let now = Utc::now();
+        let burst = self.burst(uploader, performed_action, now, conn).await?;
         let bucket = self
-            .take_token(uploader, performed_action, Utc::now(), conn)
+            .take_token_with_burst(uploader, performed_action, burst, now, conn)
             .await?;
+
+        let rate = self.refill_rate(performed_action);
+        let status = bucket.status(burst, rate, now);
         if bucket.tokens >= 1 {
-            Ok(())
+            Ok(status)
         } else {
             Err(Box::new(TooManyRequests {
                 action: performed_action,
-                retry_after: bucket.last_refill
-                    + chrono::Duration::from_std(self.config_for_action(performed_action).rate)
-                        .unwrap(),
+                retry_after: bucket.last_refill + rate,
+                status,
             }))
         }
     }
 
+    /// Returns the current state of the rate limit of a user for an action,
+    /// without taking a token from the bucket.
+    pub async fn status(
+        &self,
+        user_id: i32,
+        action: LimitedAction,
+        conn: &mut AsyncPgConnection,
+    ) -> QueryResult<RateLimitStatus> {
+        let now = Utc::now();
+        let burst = self.burst(user_id, action, now, conn).await?;
+
+        let bucket: Option<Bucket> = publish_limit_buckets::table
+            .find((user_id, action))
+            .first(conn)
+            .await
+            .optional()?;
+
+        Ok(match bucket {
+            Some(bucket) => bucket.status(burst, self.refill_rate(action), now),
+            None => RateLimitStatus {
+                limit: burst,
+                remaining: burst,
+                reset_at: now,
+            },
+        })
+    }
+
+    /// Returns the overrides of a user that have not expired yet.
+    pub async fn active_overrides(
+        user_id: i32,
+        conn: &mut AsyncPgConnection,
+    ) -> QueryResult<HashMap<LimitedAction, RateLimitOverride>> {
+        let overrides: Vec<(LimitedAction, i32, Option<DateTime<Utc>>)> =
+            publish_rate_overrides::table
+                .filter(publish_rate_overrides::user_id.eq(user_id))
+                .filter(
+                    publish_rate_overrides::expires_at
+                        .is_null()
+                        .or(publish_rate_overrides::expires_at.gt(Utc::now())),
+                )
+                .select((
+                    publish_rate_overrides::action,
+                    publish_rate_overrides::burst,
+                    publish_rate_overrides::expires_at,
+                ))
+                .load(conn)
+                .await?;
+
+        Ok(overrides
+            .into_iter()
+            .map(|(action, burst, expires_at)| (action, RateLimitOverride { burst, expires_at }))
+            .collect())
+    }
+
+    /// Returns the burst of an action for a user, taking active overrides
+    /// into account.
+    async fn burst(
+        &self,
+        user_id: i32,
+        action: LimitedAction,
+        now: DateTime<Utc>,
+        conn: &mut AsyncPgConnection,
+    ) -> QueryResult<i32> {
+        Ok(publish_rate_overrides::table
+            .find((user_id, action))
+            .filter(
+                publish_rate_overrides::expires_at
+                    .is_null()
+                    .or(publish_rate_overrides::expires_at.gt(now)),
+            )
+            .select(publish_rate_overrides::burst)
+            .first(conn)
+            .await
+            .optional()?
+            .unwrap_or(self.config_for_action(action).burst))
+    }
+
+    /// Returns the time after which a new token is added to the bucket of an
+    /// action.
+    pub fn refill_rate(&self, action: LimitedAction) -> chrono::Duration {
+        chrono::Duration::from_std(self.config_for_action(action).rate).unwrap()
+    }
+
     /// Refill a user's bucket as needed, take a token from it,
     /// and returns the result.
     ///
-    /// The number of tokens remaining will always be between 0 and self.burst.
+    /// The number of tokens remaining will always be between 0 and `burst`.
     /// If the number is 0, the request should be rejected, as the user doesn't
     /// have a token to take. Technically a "full" bucket would have
-    /// `self.burst + 1` tokens in it, but that value would never be returned
+    /// `burst + 1` tokens in it, but that value would never be returned
     /// since we only refill buckets when trying to take a token from it.
-    async fn take_token(
+    async fn take_token_with_burst(
         &self,
         uploader: i32,
         performed_action: LimitedAction,
+        burst: i32,
         now: DateTime<Utc>,
         conn: &mut AsyncPgConnection,
     ) -> QueryResult<Bucket> {
         let config = self.config_for_action(performed_action);
         let refill_rate = (config.rate.as_millis() as i64).milliseconds();
 
-        let burst: i32 = publish_rate_overrides::table
-            .find((uploader, performed_action))
-            .filter(
-                publish_rate_overrides::expires_at
-                    .is_null()
-                    .or(publish_rate_overrides::expires_at.gt(now)),
-            )
-            .select(publish_rate_overrides::burst)
-            .first(conn)
-            .await
-            .optional()?
-            .unwrap_or(config.burst);
-
         // Interval division is poorly defined in general (what is 1 month / 30 days?)
         // However, for the intervals we're dealing with, it is always well
         // defined, so we convert to an f64 of seconds to represent this.
@@ -170,6 +326,173 @@ impl RateLimiter {
     }
 }
 
+/// An override of a user, as listed for admins.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = publish_rate_overrides, check_for_backend(diesel::pg::Pg))]
+pub struct UserRateLimitOverride {
+    pub user_id: i32,
+    #[diesel(select_expression = users::gh_login)]
+    pub gh_login: String,
+    pub action: LimitedAction,
+    pub burst: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl UserRateLimitOverride {
+    /// Returns the overrides that have not expired yet, optionally only those
+    /// of a single user.
+    pub async fn list_active(
+        user_id: Option<i32>,
+        conn: &mut AsyncPgConnection,
+    ) -> QueryResult<Vec<Self>> {
+        let mut query = publish_rate_overrides::table
+            .inner_join(users::table)
+            .filter(
+                publish_rate_overrides::expires_at
+                    .is_null()
+                    .or(publish_rate_overrides::expires_at.gt(Utc::now())),
+            )
+            .select(Self::as_select())
+            .order((users::gh_login, publish_rate_overrides::action))
+            .into_boxed();
+
+        if let Some(user_id) = user_id {
+            query = query.filter(publish_rate_overrides::user_id.eq(user_id));
+        }
+
+        query.load(conn).await
+    }
+}
+
+/// A change of the override of a [LimitedAction] for a user by an admin.
+///
+/// Every change is recorded in the `publish_rate_override_actions` table,
+/// together with the acting admin and the reason for the change.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitOverrideChange<'a> {
+    pub user_id: i32,
+    pub action: LimitedAction,
+    pub admin_id: i32,
+    pub reason: &'a str,
+}
+
+impl RateLimitOverrideChange<'_> {
+    /// Grants a new override, or modifies the existing override of the user.
+    ///
+    /// An `expires_at` of `None` grants the override indefinitely.
+    pub async fn set(
+        &self,
+        burst: i32,
+        expires_at: Option<DateTime<Utc>>,
+        conn: &mut AsyncPgConnection,
+    ) -> QueryResult<()> {
+        conn.transaction(|conn| {
+            async move {
+                let previous_burst: Option<i32> = publish_rate_overrides::table
+                    .find((self.user_id, self.action))
+                    .filter(
+                        publish_rate_overrides::expires_at
+                            .is_null()
+                            .or(publish_rate_overrides::expires_at.gt(Utc::now())),
+                    )
+                    .select(publish_rate_overrides::burst)
+                    .for_update()
+                    .first(conn)
+                    .await
+                    .optional()?;
+
+                diesel::insert_into(publish_rate_overrides::table)
+                    .values((
+                        publish_rate_overrides::user_id.eq(self.user_id),
+                        publish_rate_overrides::action.eq(self.action),
+                        publish_rate_overrides::burst.eq(burst),
+                        publish_rate_overrides::expires_at.eq(expires_at),
+                    ))
+                    .on_conflict((
+                        publish_rate_overrides::user_id,
+                        publish_rate_overrides::action,
+                    ))
+                    .do_update()
+                    .set((
+                        publish_rate_overrides::burst.eq(burst),
+                        publish_rate_overrides::expires_at.eq(expires_at),
+                    ))
+                    .execute(conn)
+                    .await?;
+
+                // Otherwise, a raised burst would only be usable once the
+                // bucket has been refilled over time.
+                if previous_burst.is_none_or(|previous_burst| burst > previous_burst) {
+                    diesel::update(publish_limit_buckets::table.find((self.user_id, self.action)))
+                        .filter(publish_limit_buckets::tokens.lt(burst))
+                        .set(publish_limit_buckets::tokens.eq(burst))
+                        .execute(conn)
+                        .await?;
+                }
+
+                self.record(burst, expires_at, conn).await
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    /// Expires the override of the user immediately.
+    ///
+    /// Returns `false` if the user has no active override for the action.
+    pub async fn expire(&self, conn: &mut AsyncPgConnection) -> QueryResult<bool> {
+        conn.transaction(|conn| {
+            async move {
+                let now = Utc::now();
+                let burst: Option<i32> = diesel::update(
+                    publish_rate_overrides::table
+                        .find((self.user_id, self.action))
+                        .filter(
+                            publish_rate_overrides::expires_at
+                                .is_null()
+                                .or(publish_rate_overrides::expires_at.gt(now)),
+                        ),
+                )
+                .set(publish_rate_overrides::expires_at.eq(now))
+                .returning(publish_rate_overrides::burst)
+                .get_result(conn)
+                .await
+                .optional()?;
+
+                let Some(burst) = burst else {
+                    return Ok(false);
+                };
+
+                self.record(burst, Some(now), conn).await?;
+                Ok(true)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    async fn record(
+        &self,
+        burst: i32,
+        expires_at: Option<DateTime<Utc>>,
+        conn: &mut AsyncPgConnection,
+    ) -> QueryResult<()> {
+        diesel::insert_into(publish_rate_override_actions::table)
+            .values((
+                publish_rate_override_actions::user_id.eq(self.user_id),
+                publish_rate_override_actions::action.eq(self.action),
+                publish_rate_override_actions::burst.eq(burst),
+                publish_rate_override_actions::expires_at.eq(expires_at),
+                publish_rate_override_actions::admin_id.eq(self.admin_id),
+                publish_rate_override_actions::reason.eq(self.reason),
+            ))
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+}
+
 #[derive(Queryable, Insertable, Debug, PartialEq, Clone, Copy)]
 #[diesel(table_name = publish_limit_buckets, check_for_backend(diesel::pg::Pg))]
 #[allow(dead_code)] // Most fields only read in tests
@@ -180,6 +503,33 @@ struct Bucket {
     action: LimitedAction,
 }
 
+impl Bucket {
+    /// Calculates the state of the bucket at the given time.
+    ///
+    /// The stored number of tokens still includes the token that was taken by
+    /// the last action, since buckets are only updated lazily when the next
+    /// token is taken (see [RateLimiter::take_token]).
+    fn status(&self, burst: i32, rate: chrono::Duration, now: DateTime<Utc>) -> RateLimitStatus {
+        let elapsed = (now - self.last_refill).max(chrono::Duration::zero());
+        let refills = elapsed.num_milliseconds() / rate.num_milliseconds().max(1);
+
+        let remaining = (i64::from((self.tokens - 1).max(0)) + refills).min(i64::from(burst));
+        let missing = i64::from(burst) - remaining;
+
+        let reset_at = if missing > 0 {
+            self.last_refill + rate * (refills + missing) as i32
+        } else {
+            now
+        };
+
+        RateLimitStatus {
+            limit: burst,
+            remaining: remaining as i32,
+            reset_at,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -700,6 +1050,86 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn bucket_status_accounts_for_taken_token_and_refills() {
+        let now = now();
+        let rate = chrono::Duration::minutes(1);
+        let bucket = Bucket {
+            user_id: 1,
+            tokens: 3,
+            last_refill: now,
+            action: LimitedAction::PublishNew,
+        };
+
+        // The stored tokens still include the token of the last action
+        let status = bucket.status(5, rate, now);
+        assert_eq!(status.limit, 5);
+        assert_eq!(status.remaining, 2);
+        assert_eq!(status.reset_at, now + rate * 3);
+
+        let later = now + chrono::Duration::seconds(90);
+        let status = bucket.status(5, rate, later);
+        assert_eq!(status.remaining, 3);
+        assert_eq!(status.reset_at, now + rate * 3);
+
+        let much_later = now + chrono::Duration::hours(1);
+        let status = bucket.status(5, rate, much_later);
+        assert_eq!(status.remaining, 5);
+        assert_eq!(status.reset_at, much_later);
+    }
+
+    #[tokio::test]
+    async fn status_does_not_take_a_token() -> anyhow::Result<()> {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        let rate = SampleRateLimiter {
+            rate: Duration::from_secs(60),
+            burst: 10,
+            action: LimitedAction::PublishNew,
+        }
+        .create();
+        let user_id = new_user(&mut conn, "user").await?;
+
+        let status = rate
+            .status(user_id, LimitedAction::PublishNew, &mut conn)
+            .await?;
+        assert_eq!(status.limit, 10);
+        assert_eq!(status.remaining, 10);
+
+        let taken = rate
+            .check_rate_limit(user_id, LimitedAction::PublishNew, &mut conn)
+            .await
+            .unwrap();
+        assert_eq!(taken.remaining, 9);
+
+        let status = rate
+            .status(user_id, LimitedAction::PublishNew, &mut conn)
+            .await?;
+        assert_eq!(status.remaining, 9);
+
+        let status = rate
+            .status(user_id, LimitedAction::PublishNew, &mut conn)
+            .await?;
+        assert_eq!(status.remaining, 9);
+
+        Ok(())
+    }
+
+    impl RateLimiter {
+        async fn take_token(
+            &self,
+            user_id: i32,
+            action: LimitedAction,
+            now: DateTime<Utc>,
+            conn: &mut AsyncPgConnection,
+        ) -> QueryResult<Bucket> {
+            let burst = self.burst(user_id, action, now, conn).await?;
+            self.take_token_with_burst(user_id, action, burst, now, conn)
+                .await
+        }
+    }
+
     async fn new_user(conn: &mut AsyncPgConnection, gh_login: &str) -> QueryResult<i32> {
         use crate::models::NewUser;
 
@@ -1,5 +1,5 @@
 use axum::response::IntoResponse;
-use axum::routing::{get, post};
+use axum::routing::{get, post, put};
 use axum::{Json, Router};
 use http::{Method, StatusCode};
 use utoipa_axum::routes;
@@ -42,6 +42,7 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
         .routes(routes!(version::downloads::get_version_downloads))
         .routes(routes!(version::authors::get_version_authors))
         .routes(routes!(krate::downloads::get_crate_downloads))
+        .routes(routes!(krate::downloads::get_crate_client_downloads))
         .routes(routes!(krate::versions::list_versions))
         .routes(routes!(
             krate::follow::follow_crate,
@@ -61,6 +62,7 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
         .routes(routes!(team::find_team))
         .routes(routes!(user::me::get_authenticated_user))
         .routes(routes!(user::me::get_authenticated_user_updates))
+        .routes(routes!(user::me::get_authenticated_user_rate_limits))
         .routes(routes!(token::list_api_tokens, token::create_api_token))
         .routes(routes!(token::find_api_token, token::revoke_api_token))
         .routes(routes!(token::revoke_current_api_token))
@@ -80,6 +82,7 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             user::email_notifications::update_email_notifications
         ))
         .routes(routes!(summary::get_summary))
+        .routes(routes!(stats::get_daily_stats))
         .routes(routes!(user::email_verification::confirm_user_email))
         .routes(routes!(user::email_verification::resend_email_verification))
         .routes(routes!(site_metadata::get_site_metadata))
@@ -92,6 +95,37 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
     let mut router = router
         // Metrics
         .route("/api/private/metrics/{kind}", get(metrics::prometheus))
+        // Background job inspection and control for admins
+        .route("/api/private/admin/jobs", get(admin::jobs::list_jobs))
+        .route(
+            "/api/private/admin/jobs/{id}",
+            get(admin::jobs::get_job).delete(admin::jobs::cancel_job),
+        )
+        .route(
+            "/api/private/admin/jobs/{id}/retry",
+            post(admin::jobs::retry_job),
+        )
+        .route(
+            "/api/private/admin/queues/{queue}/pause",
+            post(admin::jobs::pause_queue),
+        )
+        .route(
+            "/api/private/admin/queues/{queue}/resume",
+            post(admin::jobs::resume_queue),
+        )
+        // Publish rate limit overrides for admins
+        .route(
+            "/api/private/admin/rate_limits",
+            get(admin::rate_limits::list_overrides),
+        )
+        .route(
+            "/api/private/admin/rate_limits/{user}/{action}",
+            put(admin::rate_limits::set_override),
+        )
+        .route(
+            "/api/private/admin/rate_limits/{user}/{action}/expire",
+            post(admin::rate_limits::expire_override),
+        )
         // Alerts from GitHub scanning for exposed API tokens
         .route(
             "/api/github/secret-scanning/verify",
@@ -1,4 +1,6 @@
+use crate::cdn::SurrogateKey;
 use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
 use crates_io_env_vars::required_var;
 use futures_util::{StreamExt, TryStreamExt};
 use hyper::body::Bytes;
@@ -7,7 +9,9 @@ use object_store::local::LocalFileSystem;
 use object_store::memory::InMemory;
 use object_store::path::Path;
 use object_store::prefix::PrefixStore;
-use object_store::{Attribute, Attributes, ClientOptions, ObjectStore, PutPayload, Result};
+use object_store::{
+    Attribute, AttributeValue, Attributes, ClientOptions, ObjectStore, PutPayload, Result,
+};
 use secrecy::{ExposeSecret, SecretString};
 use std::fs;
 use std::io::Cursor;
@@ -18,6 +22,7 @@ use tokio::io::AsyncWriteExt;
 
 const PREFIX_CRATES: &str = "crates";
 const PREFIX_READMES: &str = "readmes";
+const PREFIX_VERSION_DOWNLOADS_ARCHIVE: &str = "archive/version-downloads";
 const DEFAULT_REGION: &str = "us-west-1";
 const CONTENT_TYPE_CRATE: &str = "application/gzip";
 const CONTENT_TYPE_GZIP: &str = "application/gzip";
@@ -27,6 +32,7 @@ const CONTENT_TYPE_README: &str = "text/html";
 const CACHE_CONTROL_IMMUTABLE: &str = "public,max-age=31536000,immutable";
 const CACHE_CONTROL_INDEX: &str = "public,max-age=600";
 const CACHE_CONTROL_README: &str = "public,max-age=604800";
+const METADATA_SURROGATE_KEY: &str = "surrogate-key";
 
 type StdPath = std::path::Path;
 
@@ -61,15 +67,21 @@ impl StorageConfig {
     }
 
     pub fn from_environment() -> Self {
+        Self::try_from_environment().unwrap()
+    }
+
+    /// Reads the storage configuration from the environment, and returns an
+    /// error if the S3 configuration is incomplete.
+    pub fn try_from_environment() -> anyhow::Result<Self> {
         if let Ok(bucket) = dotenvy::var("S3_BUCKET") {
             let region = dotenvy::var("S3_REGION").ok();
             let cdn_prefix = dotenvy::var("S3_CDN").ok();
 
-            let index_bucket = required_var("S3_INDEX_BUCKET").unwrap();
+            let index_bucket = required_var("S3_INDEX_BUCKET")?;
             let index_region = dotenvy::var("S3_INDEX_REGION").ok();
 
-            let access_key = required_var("AWS_ACCESS_KEY").unwrap();
-            let secret_key: SecretString = required_var("AWS_SECRET_KEY").unwrap().into();
+            let access_key = required_var("AWS_ACCESS_KEY")?;
+            let secret_key: SecretString = required_var("AWS_SECRET_KEY")?.into();
 
             let default = S3Config {
                 bucket,
@@ -87,24 +99,23 @@ impl StorageConfig {
 
             let backend = StorageBackend::S3 { default, index };
 
-            return Self {
+            return Ok(Self {
                 backend,
                 cdn_prefix,
-            };
+            });
         }
 
-        let current_dir = std::env::current_dir()
-            .context("Failed to read the current directory")
-            .unwrap();
+        let current_dir =
+            std::env::current_dir().context("Failed to read the current directory")?;
 
         let path = current_dir.join("local_uploads");
 
         let backend = StorageBackend::LocalFileSystem { path };
 
-        Self {
+        Ok(Self {
             backend,
             cdn_prefix: None,
-        }
+        })
     }
 }
 
@@ -120,7 +131,17 @@ impl Storage {
         Self::from_config(&StorageConfig::from_environment())
     }
 
+    /// Creates the storage from the environment, and returns an error
+    /// instead of panicking if it is misconfigured.
+    pub fn try_from_environment() -> anyhow::Result<Self> {
+        Self::try_from_config(&StorageConfig::try_from_environment()?)
+    }
+
     pub fn from_config(config: &StorageConfig) -> Self {
+        Self::try_from_config(config).unwrap()
+    }
+
+    pub fn try_from_config(config: &StorageConfig) -> anyhow::Result<Self> {
         let cdn_prefix = config.cdn_prefix.clone();
 
         match &config.backend {
@@ -136,20 +157,20 @@ impl Storage {
                     .with_content_type_for_suffix("gz", CONTENT_TYPE_GZIP)
                     .with_content_type_for_suffix("zip", CONTENT_TYPE_ZIP);
 
-                let store = build_s3(default, options);
+                let store = build_s3(default, options)?;
 
-                let index_store = build_s3(index, Default::default());
+                let index_store = build_s3(index, Default::default())?;
 
                 if cdn_prefix.is_none() {
-                    panic!("Missing S3_CDN environment variable");
+                    anyhow::bail!("Missing S3_CDN environment variable");
                 }
 
-                Self {
+                Ok(Self {
                     cdn_prefix,
                     store: Arc::new(store),
                     index_store: Arc::new(index_store),
                     supports_attributes: true,
-                }
+                })
             }
 
             StorageBackend::LocalFileSystem { path } => {
@@ -158,38 +179,35 @@ impl Storage {
                 let index_path = path.join("index");
 
                 fs::create_dir_all(&index_path)
-                    .context("Failed to create file storage directories")
-                    .unwrap();
+                    .context("Failed to create file storage directories")?;
 
                 let local = LocalFileSystem::new_with_prefix(path)
-                    .context("Failed to initialize local file system storage")
-                    .unwrap();
+                    .context("Failed to initialize local file system storage")?;
 
                 let local_index = LocalFileSystem::new_with_prefix(index_path)
-                    .context("Failed to initialize local file system storage")
-                    .unwrap();
+                    .context("Failed to initialize local file system storage")?;
 
                 let store: Arc<dyn ObjectStore> = Arc::new(local);
                 let index_store: Arc<dyn ObjectStore> = Arc::new(local_index);
 
-                Self {
+                Ok(Self {
                     cdn_prefix,
                     store,
                     index_store,
                     supports_attributes: false,
-                }
+                })
             }
 
             StorageBackend::InMemory => {
                 warn!("Using in-memory file storage");
                 let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
 
-                Self {
+                Ok(Self {
                     cdn_prefix,
                     store: store.clone(),
                     index_store: Arc::new(PrefixStore::new(store, "index")),
                     supports_attributes: true,
-                }
+                })
             }
         }
     }
@@ -245,12 +263,61 @@ impl Storage {
         self.store.delete(&path).await
     }
 
+    /// Downloads the crate file of the given crate version.
+    ///
+    /// Returns an [object_store::Error::NotFound] error if the file does not exist.
+    #[instrument(skip(self))]
+    pub async fn download_crate_file(&self, name: &str, version: &str) -> Result<Bytes> {
+        let path = crate_file_path(name, version);
+        self.store.get(&path).await?.bytes().await
+    }
+
+    /// Downloads the archived `version_downloads` CSV file for the given date.
+    ///
+    /// Returns `None` if no archive file exists for the given date.
+    #[instrument(skip(self))]
+    pub async fn download_version_downloads_archive(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Option<Bytes>> {
+        let path = format!("{PREFIX_VERSION_DOWNLOADS_ARCHIVE}/{date}.csv").into();
+        match self.store.get(&path).await {
+            Ok(result) => result.bytes().await.map(Some),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Downloads the `index.json` file of the version downloads archive,
+    /// which lists all archived dates.
+    #[instrument(skip(self))]
+    pub async fn download_version_downloads_archive_index(&self) -> Result<Option<Bytes>> {
+        let path = format!("{PREFIX_VERSION_DOWNLOADS_ARCHIVE}/index.json").into();
+        match self.store.get(&path).await {
+            Ok(result) => result.bytes().await.map(Some),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Checks whether a rendered README file exists for the given crate version.
+    #[instrument(skip(self))]
+    pub async fn readme_exists(&self, name: &str, version: &str) -> Result<bool> {
+        let path = readme_path(name, version);
+        match self.store.head(&path).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
     #[instrument(skip(self, bytes))]
     pub async fn upload_crate_file(&self, name: &str, version: &str, bytes: Bytes) -> Result<()> {
         let path = crate_file_path(name, version);
         let attributes = self.attrs([
-            (Attribute::ContentType, CONTENT_TYPE_CRATE),
-            (Attribute::CacheControl, CACHE_CONTROL_IMMUTABLE),
+            (Attribute::ContentType, CONTENT_TYPE_CRATE.into()),
+            (Attribute::CacheControl, CACHE_CONTROL_IMMUTABLE.into()),
+            surrogate_key_attr(SurrogateKey::krate(name)),
         ]);
         let opts = attributes.into();
         self.store.put_opts(&path, bytes.into(), opts).await?;
@@ -261,8 +328,9 @@ impl Storage {
     pub async fn upload_readme(&self, name: &str, version: &str, bytes: Bytes) -> Result<()> {
         let path = readme_path(name, version);
         let attributes = self.attrs([
-            (Attribute::ContentType, CONTENT_TYPE_README),
-            (Attribute::CacheControl, CACHE_CONTROL_README),
+            (Attribute::ContentType, CONTENT_TYPE_README.into()),
+            (Attribute::CacheControl, CACHE_CONTROL_README.into()),
+            surrogate_key_attr(SurrogateKey::krate(name)),
         ]);
         let opts = attributes.into();
         self.store.put_opts(&path, bytes.into(), opts).await?;
@@ -282,7 +350,12 @@ impl Storage {
         channel.pretty_write_to(&mut cursor, b' ', 4)?;
         let payload = PutPayload::from_bytes(buffer.into());
 
-        let attributes = self.attrs([(Attribute::ContentType, "text/xml; charset=UTF-8")]);
+        let mut attributes = vec![(Attribute::ContentType, "text/xml; charset=UTF-8".into())];
+        if let FeedId::Crate { name } = feed_id {
+            attributes.push(surrogate_key_attr(SurrogateKey::krate(*name)));
+        }
+
+        let attributes = self.attrs(attributes);
         let opts = attributes.into();
         self.store.put_opts(&path, payload, opts).await?;
         Ok(())
@@ -293,8 +366,9 @@ impl Storage {
         let path = crates_io_index::Repository::relative_index_file_for_url(name).into();
         if let Some(content) = content {
             let attributes = self.attrs([
-                (Attribute::ContentType, CONTENT_TYPE_INDEX),
-                (Attribute::CacheControl, CACHE_CONTROL_INDEX),
+                (Attribute::ContentType, CONTENT_TYPE_INDEX.into()),
+                (Attribute::CacheControl, CACHE_CONTROL_INDEX.into()),
+                surrogate_key_attr(SurrogateKey::index(name)),
             ]);
             let payload = content.into();
             let opts = attributes.into();
@@ -306,6 +380,40 @@ impl Storage {
         Ok(())
     }
 
+    /// Downloads a file that was previously uploaded via [Self::upload_db_dump].
+    ///
+    /// Returns `None` if the file does not exist.
+    #[instrument(skip(self))]
+    pub async fn download_db_dump(&self, target: &str) -> Result<Option<Bytes>> {
+        let path = target.into();
+        match self.store.get(&path).await {
+            Ok(result) => result.bytes().await.map(Some),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Returns the time at which a file that was previously uploaded via
+    /// [Self::upload_db_dump] was last modified.
+    ///
+    /// Returns `None` if the file does not exist.
+    #[instrument(skip(self))]
+    pub async fn db_dump_last_modified(&self, target: &str) -> Result<Option<DateTime<Utc>>> {
+        let path = target.into();
+        match self.store.head(&path).await {
+            Ok(meta) => Ok(Some(meta.last_modified)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Deletes a file that was previously uploaded via [Self::upload_db_dump].
+    #[instrument(skip(self))]
+    pub async fn delete_db_dump(&self, target: &str) -> Result<()> {
+        let path = target.into();
+        self.store.delete(&path).await
+    }
+
     #[instrument(skip(self))]
     pub async fn upload_db_dump(&self, target: &str, local_path: &StdPath) -> anyhow::Result<()> {
         let store = self.store.clone();
@@ -355,7 +463,10 @@ impl Storage {
         Ok(paths)
     }
 
-    fn attrs(&self, slice: impl IntoIterator<Item = (Attribute, &'static str)>) -> Attributes {
+    fn attrs<V>(&self, slice: impl IntoIterator<Item = (Attribute, V)>) -> Attributes
+    where
+        V: Into<AttributeValue>,
+    {
         if self.supports_attributes {
             Attributes::from_iter(slice)
         } else {
@@ -364,7 +475,14 @@ impl Storage {
     }
 }
 
-fn build_s3(config: &S3Config, client_options: ClientOptions) -> AmazonS3 {
+/// Returns the metadata attribute that is used by the CDNs to tag files with
+/// a surrogate key.
+fn surrogate_key_attr(key: SurrogateKey) -> (Attribute, AttributeValue) {
+    let attribute = Attribute::Metadata(METADATA_SURROGATE_KEY.into());
+    (attribute, key.to_string().into())
+}
+
+fn build_s3(config: &S3Config, client_options: ClientOptions) -> anyhow::Result<AmazonS3> {
     AmazonS3Builder::new()
         .with_region(config.region.as_deref().unwrap_or(DEFAULT_REGION))
         .with_bucket_name(&config.bucket)
@@ -373,14 +491,13 @@ fn build_s3(config: &S3Config, client_options: ClientOptions) -> AmazonS3 {
         .with_client_options(client_options)
         .build()
         .context("Failed to initialize S3 code")
-        .unwrap()
 }
 
-fn crate_file_path(name: &str, version: &str) -> Path {
+pub(crate) fn crate_file_path(name: &str, version: &str) -> Path {
     format!("{PREFIX_CRATES}/{name}/{name}-{version}.crate").into()
 }
 
-fn readme_path(name: &str, version: &str) -> Path {
+pub(crate) fn readme_path(name: &str, version: &str) -> Path {
     format!("{PREFIX_READMES}/{name}/{name}-{version}.html").into()
 }
 
@@ -414,6 +531,7 @@ impl From<&FeedId<'_>> for Path {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use claims::assert_none;
     use hyper::body::Bytes;
     use tempfile::NamedTempFile;
 
@@ -587,6 +705,29 @@ mod tests {
         assert_eq!(stored_files(&storage.store).await, expected_files);
     }
 
+    #[tokio::test]
+    async fn download_crate_file() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let bytes = Bytes::from_static(b"hello world");
+        s.upload_crate_file("foo", "1.2.3", bytes.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(s.download_crate_file("foo", "1.2.3").await.unwrap(), bytes);
+
+        let error = s.download_crate_file("foo", "2.0.0").await.unwrap_err();
+        assert!(matches!(error, object_store::Error::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn readme_exists() {
+        let storage = prepare().await;
+
+        assert!(storage.readme_exists("foo", "1.2.3").await.unwrap());
+        assert!(!storage.readme_exists("foo", "2.0.0").await.unwrap());
+    }
+
     #[tokio::test]
     async fn upload_crate_file() {
         let s = Storage::from_config(&StorageConfig::in_memory());
@@ -609,6 +750,35 @@ mod tests {
         assert_eq!(stored_files(&s.store).await, expected_files);
     }
 
+    #[tokio::test]
+    async fn surrogate_keys() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        s.upload_crate_file("foo", "1.2.3", Bytes::new())
+            .await
+            .unwrap();
+        s.upload_readme("foo", "1.2.3", Bytes::new()).await.unwrap();
+
+        let attribute = Attribute::Metadata(METADATA_SURROGATE_KEY.into());
+        for path in ["crates/foo/foo-1.2.3.crate", "readmes/foo/foo-1.2.3.html"] {
+            let result = s.store.get(&path.into()).await.unwrap();
+            let value = result
+                .attributes
+                .get(&attribute)
+                .map(|value| value.as_ref());
+            assert_eq!(value, Some("crate:foo"));
+        }
+
+        s.sync_index("Foo", Some(String::new())).await.unwrap();
+
+        let result = s.index_store.get(&"3/f/foo".into()).await.unwrap();
+        let value = result
+            .attributes
+            .get(&attribute)
+            .map(|value| value.as_ref());
+        assert_eq!(value, Some("index:foo"));
+    }
+
     #[tokio::test]
     async fn upload_readme() {
         let s = Storage::from_config(&StorageConfig::in_memory());
@@ -647,6 +817,38 @@ mod tests {
         assert!(stored_files(&s.store).await.is_empty());
     }
 
+    #[tokio::test]
+    async fn download_db_dump() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        assert_none!(s.download_db_dump("db-dump.tar.gz").await.unwrap());
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "foo").unwrap();
+        s.upload_db_dump("db-dump.tar.gz", file.path())
+            .await
+            .unwrap();
+
+        let bytes = s.download_db_dump("db-dump.tar.gz").await.unwrap();
+        assert_eq!(bytes.unwrap().as_ref(), b"foo");
+    }
+
+    #[tokio::test]
+    async fn db_dump_last_modified() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        assert_none!(s.db_dump_last_modified("db-dump.tar.gz").await.unwrap());
+
+        let before = Utc::now();
+        let file = NamedTempFile::new().unwrap();
+        s.upload_db_dump("db-dump.tar.gz", file.path())
+            .await
+            .unwrap();
+
+        let last_modified = s.db_dump_last_modified("db-dump.tar.gz").await.unwrap();
+        assert!(last_modified.unwrap() >= before);
+    }
+
     #[tokio::test]
     async fn upload_db_dump() {
         let s = Storage::from_config(&StorageConfig::in_memory());
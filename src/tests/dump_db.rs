@@ -13,6 +13,9 @@ use tar::Archive;
 static PATH_DATE_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}-\d{6}").unwrap());
 
+static FILE_DATE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d{4}-\d{2}-\d{2}-\d{6}").unwrap());
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_dump_db_job() -> anyhow::Result<()> {
     let (app, _, _, token) = TestApp::full().with_token().await;
@@ -26,9 +29,15 @@ async fn test_dump_db_job() -> anyhow::Result<()> {
 
     app.run_pending_background_jobs().await;
 
-    assert_snapshot!(app.stored_files().await.join("\n"), @r"
+    let stored_files = app.stored_files().await.join("\n");
+    let stored_files = FILE_DATE_RE.replace_all(&stored_files, "YYYY-MM-DD-HHMMSS");
+    assert_snapshot!(stored_files, @r"
+    db-dump.sqlite.gz
     db-dump.tar.gz
     db-dump.zip
+    db-dump/delta/base-YYYY-MM-DD-HHMMSS.tar.gz
+    db-dump/delta/fingerprints.json.gz
+    db-dump/delta/manifest.json
     ");
 
     let path = object_store::path::Path::parse("db-dump.tar.gz")?;
@@ -51,11 +60,13 @@ async fn test_dump_db_job() -> anyhow::Result<()> {
         "YYYY-MM-DD-HHMMSS/data/categories.csv",
         "YYYY-MM-DD-HHMMSS/data/crate_downloads.csv",
         "YYYY-MM-DD-HHMMSS/data/crates.csv",
+        "YYYY-MM-DD-HHMMSS/data/daily_registry_stats.csv",
         "YYYY-MM-DD-HHMMSS/data/keywords.csv",
         "YYYY-MM-DD-HHMMSS/data/metadata.csv",
         "YYYY-MM-DD-HHMMSS/data/reserved_crate_names.csv",
         "YYYY-MM-DD-HHMMSS/data/teams.csv",
         "YYYY-MM-DD-HHMMSS/data/users.csv",
+        "YYYY-MM-DD-HHMMSS/data/crate_client_downloads.csv",
         "YYYY-MM-DD-HHMMSS/data/crates_categories.csv",
         "YYYY-MM-DD-HHMMSS/data/crates_keywords.csv",
         "YYYY-MM-DD-HHMMSS/data/crate_owners.csv",
@@ -83,11 +94,13 @@ async fn test_dump_db_job() -> anyhow::Result<()> {
         "data/categories.csv",
         "data/crate_downloads.csv",
         "data/crates.csv",
+        "data/daily_registry_stats.csv",
         "data/keywords.csv",
         "data/metadata.csv",
         "data/reserved_crate_names.csv",
         "data/teams.csv",
         "data/users.csv",
+        "data/crate_client_downloads.csv",
         "data/crates_categories.csv",
         "data/crates_keywords.csv",
         "data/crate_owners.csv",
@@ -101,6 +114,89 @@ async fn test_dump_db_job() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dump_db_job_without_sqlite() -> anyhow::Result<()> {
+    let (app, _) = TestApp::full()
+        .with_config(|config| config.sqlite_exports = false)
+        .empty()
+        .await;
+    let mut conn = app.db_conn().await;
+
+    DumpDb.enqueue(&mut conn).await?;
+
+    app.run_pending_background_jobs().await;
+
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&"db-dump.tar.gz".to_string()));
+    assert!(!stored_files.contains(&"db-dump.sqlite.gz".to_string()));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dump_db_job_deletes_superseded_deltas() -> anyhow::Result<()> {
+    let (app, _) = TestApp::full().empty().await;
+    let mut conn = app.db_conn().await;
+    let store = app.as_inner().storage.as_inner();
+
+    // Without matching fingerprints, the chain can't be continued and a new
+    // base snapshot is started.
+    let manifest = serde_json::json!({
+        "base": {
+            "timestamp": "2017-01-06T12:00:00Z",
+            "path": "db-dump/delta/base-2017-01-06-120000.tar.gz",
+        },
+        "deltas": [{
+            "from": "2017-01-06T12:00:00Z",
+            "to": "2017-01-07T12:00:00Z",
+            "path": "db-dump/delta/2017-01-07-120000.tar.gz",
+        }],
+    });
+    let files = [
+        ("db-dump/delta/manifest.json", manifest.to_string()),
+        ("db-dump/delta/base-2017-01-06-120000.tar.gz", String::new()),
+        ("db-dump/delta/2017-01-07-120000.tar.gz", String::new()),
+    ];
+    for (path, content) in files {
+        let path = object_store::path::Path::parse(path)?;
+        store.put(&path, content.into()).await?;
+    }
+
+    DumpDb.enqueue(&mut conn).await?;
+
+    app.run_pending_background_jobs().await;
+
+    let stored_files = app.stored_files().await.join("\n");
+    let stored_files = FILE_DATE_RE.replace_all(&stored_files, "YYYY-MM-DD-HHMMSS");
+    assert_snapshot!(stored_files, @r"
+    db-dump.sqlite.gz
+    db-dump.tar.gz
+    db-dump.zip
+    db-dump/delta/base-YYYY-MM-DD-HHMMSS.tar.gz
+    db-dump/delta/fingerprints.json.gz
+    db-dump/delta/manifest.json
+    ");
+
+    // The next dump continues the new chain with a delta.
+    DumpDb.enqueue(&mut conn).await?;
+
+    app.run_pending_background_jobs().await;
+
+    let stored_files = app.stored_files().await.join("\n");
+    let stored_files = FILE_DATE_RE.replace_all(&stored_files, "YYYY-MM-DD-HHMMSS");
+    assert_snapshot!(stored_files, @r"
+    db-dump.sqlite.gz
+    db-dump.tar.gz
+    db-dump.zip
+    db-dump/delta/YYYY-MM-DD-HHMMSS.tar.gz
+    db-dump/delta/base-YYYY-MM-DD-HHMMSS.tar.gz
+    db-dump/delta/fingerprints.json.gz
+    db-dump/delta/manifest.json
+    ");
+
+    Ok(())
+}
+
 fn tar_paths<R: Read>(archive: &mut Archive<R>) -> Vec<String> {
     archive
         .entries()
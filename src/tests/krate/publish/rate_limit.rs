@@ -332,3 +332,26 @@ async fn publish_existing_crate_rate_limit_doesnt_affect_new_crates() {
     let crate_to_publish = PublishBuilder::new("rate_limited2", "1.0.0");
     token.publish_crate(crate_to_publish).await.good();
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_returns_rate_limit_headers() {
+    let (_app, _, _, token) = TestApp::full()
+        .with_rate_limit(LimitedAction::PublishNew, Duration::from_secs(60 * 60), 3)
+        .with_token()
+        .await;
+
+    let crate_to_publish = PublishBuilder::new("rate_limited", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let headers = response.headers();
+    assert_eq!(headers["ratelimit-limit"], "3");
+    assert_eq!(headers["ratelimit-remaining"], "2");
+
+    let reset: i64 = headers["ratelimit-reset"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((3590..=3600).contains(&reset), "unexpected reset: {reset}");
+}
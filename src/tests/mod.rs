@@ -24,6 +24,7 @@ mod not_found_error;
 mod owners;
 mod pagination;
 mod read_only_mode;
+mod read_rate_limit;
 mod routes;
 mod server;
 mod team;
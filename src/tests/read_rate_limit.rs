@@ -0,0 +1,88 @@
+use crate::rate_limiter::RateLimiterConfig;
+use crate::tests::util::{MockRequestExt, RequestHelper, TestApp, TestAppBuilder};
+use http::{StatusCode, header};
+use insta::assert_snapshot;
+use std::time::Duration;
+
+const URL: &str = "/api/v1/crates";
+
+fn with_read_rate_limit(burst: i32) -> TestAppBuilder {
+    TestApp::init().with_config(|config| {
+        let limits = &mut config.read_rate_limits;
+        limits.routes.insert(URL.into(), "LIST_CRATES".into());
+        limits.budgets.insert(
+            "LIST_CRATES".into(),
+            RateLimiterConfig {
+                rate: Duration::from_secs(60 * 60),
+                burst,
+            },
+        );
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_requests_are_rate_limited_per_ip() {
+    let (_app, anon) = with_read_rate_limit(2).empty().await;
+
+    for _ in 0..2 {
+        let response = anon.get::<()>(URL).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after = response.headers().get(header::RETRY_AFTER).unwrap();
+    assert_eq!(retry_after, "3600");
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"You have sent too many requests to this endpoint in a short period of time. Please try again in 3600 seconds."}]}"#);
+
+    // Other routes are not limited
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn valid_tokens_have_their_own_budget() {
+    let (_app, anon, _user, token) = with_read_rate_limit(3).with_token().await;
+
+    // The first request with an unknown token is charged to both the IP
+    // address and the token, until the token is known to be valid.
+    let response = token.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for _ in 0..2 {
+        let response = anon.get::<()>(URL).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    for _ in 0..2 {
+        let response = token.get::<()>(URL).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = token.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_tokens_are_charged_to_the_ip() {
+    let (_app, anon) = with_read_rate_limit(2).empty().await;
+
+    // Made-up tokens must not get their own budget, whether they are already
+    // known to be invalid or not.
+    for i in 0..2 {
+        let mut request = anon.get_request(URL);
+        let token = format!("cio{i:0>32}");
+        request.header(header::AUTHORIZATION, &token);
+        let response = anon.run::<()>(request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let mut request = anon.get_request(URL);
+    request.header(header::AUTHORIZATION, &format!("cio{:0>32}", 0));
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
@@ -1,11 +1,11 @@
-use crate::schema::{crates, version_downloads, versions};
+use crate::schema::{crate_client_downloads, crates, version_downloads, versions};
 use crate::tests::builders::{CrateBuilder, VersionBuilder};
-use crate::tests::util::{MockAnonymousUser, RequestHelper, TestApp};
+use crate::tests::util::{MockAnonymousUser, MockRequestExt, RequestHelper, TestApp};
 use crate::views::EncodableVersionDownload;
-use chrono::{Duration, Utc};
+use chrono::{Duration, NaiveDate, Utc};
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
-use http::StatusCode;
+use http::{StatusCode, header};
 use insta::{assert_json_snapshot, assert_snapshot};
 
 #[derive(Deserialize)]
@@ -42,7 +42,7 @@ pub async fn assert_dl_count(
     anon: &MockAnonymousUser,
     name_and_version: &str,
     query: Option<&str>,
-    count: i32,
+    count: i64,
 ) {
     let url = format!("/api/v1/crates/{name_and_version}/downloads");
     let downloads: Downloads = if let Some(query) = query {
@@ -54,7 +54,7 @@ pub async fn assert_dl_count(
         .version_downloads
         .iter()
         .map(|vd| vd.downloads)
-        .sum::<i32>();
+        .sum::<i64>();
     assert_eq!(total_downloads, count);
 }
 
@@ -215,3 +215,311 @@ async fn test_version_downloads() {
         @r#"{"errors":[{"detail":"Invalid URL: unexpected character 'i' while parsing major version number"}]}"#
     );
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_crate_client_downloads() {
+    let (app, anon, cookie) = TestApp::init().with_user().await;
+    let mut conn = app.db_conn().await;
+
+    let user_id = cookie.as_model().id;
+    let krate = CrateBuilder::new("foo", user_id)
+        .version("1.0.0")
+        .expect_build(&mut conn)
+        .await;
+
+    let today = Utc::now().date_naive();
+    let rows = [
+        (today, "1.74", 10),
+        (today, "1.75", 3),
+        (today, "", 1),
+        (today - Duration::days(1), "1.74", 7),
+        // Downloads older than 90 days are not included
+        (today - Duration::days(100), "1.60", 42),
+    ];
+    for (date, cargo_version, downloads) in rows {
+        diesel::insert_into(crate_client_downloads::table)
+            .values((
+                crate_client_downloads::crate_id.eq(krate.id),
+                crate_client_downloads::date.eq(date),
+                crate_client_downloads::cargo_version.eq(cargo_version),
+                crate_client_downloads::downloads.eq(downloads),
+            ))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+    }
+
+    let response = anon.get::<()>("/api/v1/crates/foo/downloads/clients").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".client_downloads[].date" => "[date]",
+    });
+
+    let response = anon.get::<()>("/api/v1/crates/bar/downloads/clients").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(
+        response.text(),
+        @r#"{"errors":[{"detail":"crate `bar` does not exist"}]}"#
+    );
+}
+
+async fn save_old_version_downloads(
+    conn: &mut AsyncPgConnection,
+    version_id: i32,
+    date: &str,
+    num_downloads: i32,
+) {
+    diesel::insert_into(version_downloads::table)
+        .values((
+            version_downloads::version_id.eq(version_id),
+            version_downloads::date.eq(date.parse::<NaiveDate>().unwrap()),
+            version_downloads::downloads.eq(num_downloads),
+            version_downloads::counted.eq(num_downloads),
+            version_downloads::processed.eq(true),
+        ))
+        .execute(conn)
+        .await
+        .unwrap();
+}
+
+async fn upload_archive(app: &TestApp, date: &str, content: String) {
+    let path = format!("archive/version-downloads/{date}.csv").into();
+    let store = app.as_inner().storage.as_inner();
+    store.put(&path, content.into()).await.unwrap();
+}
+
+/// Prepares a crate with two versions that have downloads in the
+/// `version_downloads` table and in the downloads archive.
+async fn prepare_old_downloads(app: &TestApp, user_id: i32) {
+    let mut conn = app.db_conn().await;
+
+    CrateBuilder::new("foo", user_id)
+        .version("1.0.0")
+        .version("1.1.0")
+        .expect_build(&mut conn)
+        .await;
+
+    let version_ids: Vec<i32> = versions::table
+        .select(versions::id)
+        .order(versions::num)
+        .load(&mut conn)
+        .await
+        .unwrap();
+    let (v1, v2) = (version_ids[0], version_ids[1]);
+
+    save_old_version_downloads(&mut conn, v1, "2024-06-03", 1).await;
+    save_old_version_downloads(&mut conn, v2, "2024-06-04", 2).await;
+    save_old_version_downloads(&mut conn, v1, "2024-06-10", 4).await;
+    save_old_version_downloads(&mut conn, v2, "2024-06-10", 8).await;
+
+    // The archive takes precedence over the database for the same date
+    let content = format!("version_id,downloads\n{v1},16\n{v2},32\n424242,64\n");
+    upload_archive(app, "2024-06-04", content).await;
+    let content = format!("version_id,downloads\n{v1},128\n");
+    upload_archive(app, "2024-05-31", content).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_crate_downloads_date_range() {
+    let (app, anon, cookie) = TestApp::init().with_user().await;
+    prepare_old_downloads(&app, cookie.as_model().id).await;
+
+    let url = "/api/v1/crates/foo/downloads?from=2024-05-31&to=2024-06-10";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), @r#"
+    {
+      "meta": {
+        "extra_downloads": []
+      },
+      "version_downloads": [
+        {
+          "date": "2024-05-31",
+          "downloads": 128,
+          "version": 1
+        },
+        {
+          "date": "2024-06-03",
+          "downloads": 1,
+          "version": 1
+        },
+        {
+          "date": "2024-06-04",
+          "downloads": 32,
+          "version": 2
+        },
+        {
+          "date": "2024-06-04",
+          "downloads": 16,
+          "version": 1
+        },
+        {
+          "date": "2024-06-10",
+          "downloads": 8,
+          "version": 2
+        },
+        {
+          "date": "2024-06-10",
+          "downloads": 4,
+          "version": 1
+        }
+      ]
+    }
+    "#);
+
+    let url = "/api/v1/crates/foo/downloads?from=2024-05-31&to=2024-06-10&interval=week";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), @r#"
+    {
+      "meta": {
+        "extra_downloads": []
+      },
+      "version_downloads": [
+        {
+          "date": "2024-05-27",
+          "downloads": 128,
+          "version": 1
+        },
+        {
+          "date": "2024-06-03",
+          "downloads": 32,
+          "version": 2
+        },
+        {
+          "date": "2024-06-03",
+          "downloads": 17,
+          "version": 1
+        },
+        {
+          "date": "2024-06-10",
+          "downloads": 8,
+          "version": 2
+        },
+        {
+          "date": "2024-06-10",
+          "downloads": 4,
+          "version": 1
+        }
+      ]
+    }
+    "#);
+
+    let url = "/api/v1/crates/foo/downloads?from=2024-05-01&to=2024-06-30&interval=month";
+    let mut request = anon.get_request(url);
+    request.header(header::ACCEPT, "text/csv");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.headers()[header::CONTENT_TYPE].to_str().unwrap(), @"text/csv; charset=utf-8");
+    assert_snapshot!(response.text(), @r"
+    date,version,downloads
+    2024-05-01,1.0.0,128
+    2024-06-01,1.1.0,40
+    2024-06-01,1.0.0,21
+    ");
+
+    let url = "/api/v1/crates/foo/downloads?from=2024-06-10&to=2024-06-01";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"`from` must not be after `to`"}]}"#);
+
+    let url = "/api/v1/crates/foo/downloads?from=2023-06-01&to=2024-06-01";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"the requested range must not exceed 366 days"}]}"#);
+
+    let url = "/api/v1/crates/foo/downloads?interval=year";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"Failed to deserialize query string: interval: unknown variant `year`, expected one of `day`, `week`, `month`"}]}"#);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_version_downloads_date_range() {
+    let (app, anon, cookie) = TestApp::init().with_user().await;
+    prepare_old_downloads(&app, cookie.as_model().id).await;
+
+    let url = "/api/v1/crates/foo/1.0.0/downloads?from=2024-05-31&to=2024-06-10";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), @r#"
+    {
+      "version_downloads": [
+        {
+          "date": "2024-05-31",
+          "downloads": 128,
+          "version": 1
+        },
+        {
+          "date": "2024-06-03",
+          "downloads": 1,
+          "version": 1
+        },
+        {
+          "date": "2024-06-04",
+          "downloads": 16,
+          "version": 1
+        },
+        {
+          "date": "2024-06-10",
+          "downloads": 4,
+          "version": 1
+        }
+      ]
+    }
+    "#);
+
+    let url = "/api/v1/crates/foo/1.0.0/downloads?from=2024-05-01&to=2024-06-30&interval=month";
+    let mut request = anon.get_request(url);
+    request.header(header::ACCEPT, "text/csv");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r"
+    date,downloads
+    2024-05-01,128
+    2024-06-01,21
+    ");
+
+    let url = "/api/v1/crates/foo/1.0.0/downloads?from=2023-06-01&to=2024-06-01";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"the requested range must not exceed 366 days"}]}"#);
+
+    let url = "/api/v1/crates/foo/1.0.0/downloads?from=2024-06-10&to=2024-06-01";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"`from` must not be after `to`"}]}"#);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_downloads_archive_limits() {
+    let (app, anon, cookie) = TestApp::init().with_user().await;
+    prepare_old_downloads(&app, cookie.as_model().id).await;
+
+    let index = r#"[{"name":"2024-06-04.csv","size":10},{"name":"2024-05-31.csv","size":10}]"#;
+    let path = "archive/version-downloads/index.json".into();
+    let store = app.as_inner().storage.as_inner();
+    store.put(&path, index.into()).await.unwrap();
+
+    // Only a limited number of archived days can be requested at once
+    let url = "/api/v1/crates/foo/downloads?from=2024-05-31&to=2025-01-01";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"download counts older than 90 days can only be requested for up to 92 days at once"}]}"#);
+
+    // Dates before the earliest archive file don't count towards the limit
+    let url = "/api/v1/crates/foo/downloads?from=2024-01-01&to=2024-06-10";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let expected = response.text();
+    assert!(expected.contains("2024-05-31"));
+
+    // Neither the dates before the earliest archive file, nor the archive
+    // files that were missing a moment ago are downloaded again
+    upload_archive(&app, "2024-05-01", "version_id,downloads\n1,256\n".into()).await;
+    upload_archive(&app, "2024-06-05", "version_id,downloads\n1,512\n".into()).await;
+
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text(), expected);
+}
@@ -1,4 +1,5 @@
 mod email_notifications;
 pub mod get;
+mod rate_limits;
 pub mod tokens;
 mod updates;
@@ -0,0 +1,77 @@
+use crate::rate_limiter::LimitedAction;
+use crate::schema::publish_rate_overrides;
+use crate::tests::builders::PublishBuilder;
+use crate::tests::util::{RequestHelper, TestApp};
+use chrono::{DateTime, Utc};
+use diesel::ExpressionMethods;
+use diesel_async::RunQueryDsl;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+use std::time::Duration;
+
+const URL: &str = "/api/v1/me/rate_limits";
+
+fn find_action<'a>(json: &'a Value, action: &str) -> &'a Value {
+    json["rate_limits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|rate_limit| rate_limit["action"] == action)
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn anonymous_users_are_forbidden() {
+    let (_app, anon) = TestApp::init().empty().await;
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"this action requires authentication"}]}"#);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rate_limits_reflect_published_crates_and_overrides() {
+    let (app, _, user, token) = TestApp::full()
+        .with_rate_limit(LimitedAction::PublishNew, Duration::from_secs(60), 5)
+        .with_token()
+        .await;
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["rate_limits"].as_array().unwrap().len(), 3);
+
+    let publish_new = find_action(&json, "publish_new");
+    assert_eq!(publish_new["limit"], 5);
+    assert_eq!(publish_new["remaining"], 5);
+    assert_eq!(publish_new["refill_seconds"], 60);
+    assert_eq!(publish_new["override_burst"], Value::Null);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let mut conn = app.db_conn().await;
+    diesel::insert_into(publish_rate_overrides::table)
+        .values((
+            publish_rate_overrides::user_id.eq(user.as_model().id),
+            publish_rate_overrides::burst.eq(10),
+            publish_rate_overrides::expires_at.eq(None::<DateTime<Utc>>),
+            publish_rate_overrides::action.eq(LimitedAction::YankUnyank),
+        ))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let json = token.get::<()>(URL).await.json();
+
+    let publish_new = find_action(&json, "publish_new");
+    assert_eq!(publish_new["limit"], 5);
+    assert_eq!(publish_new["remaining"], 4);
+
+    let yank_unyank = find_action(&json, "yank_unyank");
+    assert_eq!(yank_unyank["limit"], 10);
+    assert_eq!(yank_unyank["remaining"], 10);
+    assert_eq!(yank_unyank["override_burst"], 10);
+    assert_eq!(yank_unyank["override_expires_at"], Value::Null);
+}
@@ -19,5 +19,6 @@ pub mod me;
 pub mod metrics;
 mod private;
 pub mod session;
+pub mod stats;
 pub mod summary;
 pub mod users;
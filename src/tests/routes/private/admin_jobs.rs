@@ -0,0 +1,175 @@
+//! Tests for the `/api/private/admin/jobs` and `/api/private/admin/queues` endpoints
+
+use crate::schema::{background_jobs, users};
+use crate::tests::util::{MockCookieUser, RequestHelper, Response, TestApp};
+use crate::worker::jobs;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::json;
+
+async fn post(user: &MockCookieUser, path: &str) -> Response<()> {
+    user.run(user.post_request(path)).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_access_jobs() {
+    let (app, anon, user) = TestApp::init().with_user().await;
+    let mut conn = app.db_conn().await;
+
+    let job_id = jobs::SyncAdmins.enqueue(&mut conn).await.unwrap().unwrap();
+
+    let response = anon.get::<()>("/api/private/admin/jobs").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"this action requires authentication"}]}"#);
+
+    let response = user.get::<()>("/api/private/admin/jobs").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"this action can only be performed by admins"}]}"#);
+
+    let response = user
+        .delete::<()>(&format!("/api/private/admin/jobs/{job_id}"))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = post(&user, "/api/private/admin/queues/default/pause").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let count: i64 = background_jobs::table
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    // The jobs are not meant to run in this test
+    diesel::delete(background_jobs::table)
+        .execute(&mut conn)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn admins_can_inspect_and_control_jobs() {
+    let (app, _, admin) = TestApp::init().with_user().await;
+    let mut conn = app.db_conn().await;
+
+    diesel::update(admin.as_model())
+        .set(users::is_admin.eq(true))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let job_id = jobs::SyncAdmins.enqueue(&mut conn).await.unwrap().unwrap();
+
+    let response = admin.get::<()>("/api/private/admin/jobs").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["jobs"][0]["id"], job_id);
+    assert_eq!(json["jobs"][0]["job_type"], "sync_admins");
+    assert_eq!(json["jobs"][0]["queue"], "default");
+    assert_eq!(json["jobs"][0]["retries"], 0);
+    assert_eq!(json["jobs"][0]["last_error"], json!(null));
+    assert_eq!(json["paused_queues"], json!([]));
+
+    let response = admin
+        .get_with_query::<()>("/api/private/admin/jobs", "job_type=other")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["jobs"], json!([]));
+
+    let response = admin
+        .get::<()>(&format!("/api/private/admin/jobs/{job_id}"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["job"]["job_type"], "sync_admins");
+
+    let response = post(&admin, &format!("/api/private/admin/jobs/{job_id}/retry")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "ok": true }));
+
+    let response = post(&admin, "/api/private/admin/queues/defualt/pause").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"unknown queue"}]}"#);
+
+    let response = post(&admin, "/api/private/admin/queues/default/pause").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = admin.get::<()>("/api/private/admin/jobs").await;
+    assert_eq!(response.json()["paused_queues"][0]["queue"], "default");
+
+    let response = post(&admin, "/api/private/admin/queues/default/resume").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = post(&admin, "/api/private/admin/queues/default/resume").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = admin
+        .delete::<()>(&format!("/api/private/admin/jobs/{job_id}"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "ok": true }));
+
+    let response = admin
+        .delete::<()>(&format!("/api/private/admin/jobs/{job_id}"))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = admin
+        .get::<()>(&format!("/api/private/admin/jobs/{job_id}"))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn jobs_are_listed_in_pages() {
+    let (app, _, admin) = TestApp::init().with_user().await;
+    let mut conn = app.db_conn().await;
+
+    diesel::update(admin.as_model())
+        .set(users::is_admin.eq(true))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let first = jobs::SyncAdmins.enqueue(&mut conn).await.unwrap().unwrap();
+    let second = jobs::DailyDbMaintenance
+        .enqueue(&mut conn)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let response = admin
+        .get_with_query::<()>("/api/private/admin/jobs", "per_page=1")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["jobs"].as_array().unwrap().len(), 1);
+    assert_eq!(json["jobs"][0]["id"], first);
+
+    let next_page = json["meta"]["next_page"].as_str().unwrap();
+    let query = next_page.trim_start_matches('?');
+    let response = admin
+        .get_with_query::<()>("/api/private/admin/jobs", query)
+        .await;
+    let json = response.json();
+    assert_eq!(json["jobs"].as_array().unwrap().len(), 1);
+    assert_eq!(json["jobs"][0]["id"], second);
+
+    let next_page = json["meta"]["next_page"].as_str().unwrap();
+    let query = next_page.trim_start_matches('?');
+    let response = admin
+        .get_with_query::<()>("/api/private/admin/jobs", query)
+        .await;
+    let json = response.json();
+    assert_eq!(json["jobs"], json!([]));
+    assert_eq!(json["meta"]["next_page"], json!(null));
+
+    // The jobs are not meant to run in this test
+    diesel::delete(background_jobs::table)
+        .execute(&mut conn)
+        .await
+        .unwrap();
+}
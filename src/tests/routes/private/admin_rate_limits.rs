@@ -0,0 +1,298 @@
+//! Tests for the `/api/private/admin/rate_limits` endpoints
+
+use crate::rate_limiter::LimitedAction;
+use crate::schema::{
+    emails, publish_limit_buckets, publish_rate_override_actions, publish_rate_overrides, users,
+};
+use crate::tests::util::{MockCookieUser, MockRequestExt, RequestHelper, Response, TestApp};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{Value, json};
+
+const URL: &str = "/api/private/admin/rate_limits";
+
+async fn post(user: &MockCookieUser, path: &str, body: Value) -> Response<()> {
+    let request = user.post_request(path).with_body(body.to_string().into());
+    user.run(request).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_change_overrides() {
+    let (app, anon, user) = TestApp::init().with_user().await;
+    let mut conn = app.db_conn().await;
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"this action requires authentication"}]}"#);
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"this action can only be performed by admins"}]}"#);
+
+    let body = json!({ "burst": 100, "reason": "please" });
+    let response = user
+        .put::<()>(&format!("{URL}/foo/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let count: i64 = publish_rate_overrides::table
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn admins_can_grant_modify_and_expire_overrides() {
+    let (app, _, admin) = TestApp::init().with_user().await;
+    let user = app.db_new_user("bar").await;
+    let mut conn = app.db_conn().await;
+
+    diesel::update(admin.as_model())
+        .set(users::is_admin.eq(true))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let response = admin.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "overrides": [] }));
+
+    let body = json!({ "burst": 100, "reason": "Coordinated release" });
+    let response = admin
+        .put::<()>(&format!("{URL}/bar/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "ok": true }));
+
+    let response = admin.get_with_query::<()>(URL, "user=bar").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "overrides": [{
+            "user": "bar",
+            "action": "publish_new",
+            "burst": 100,
+            "expires_at": null,
+        }] })
+    );
+
+    let response = user.get::<()>("/api/v1/me/rate_limits").await;
+    let rate_limits = response.json()["rate_limits"].clone();
+    let publish_new = rate_limits
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|rate_limit| rate_limit["action"] == "publish_new")
+        .unwrap();
+    assert_eq!(publish_new["limit"], 100);
+
+    let expires_at = Utc::now() + chrono::Duration::days(2);
+    let body = json!({ "burst": 50, "expires_at": expires_at, "reason": "Smaller burst" });
+    let response = admin
+        .put::<()>(&format!("{URL}/bar/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = admin.get::<()>(URL).await;
+    assert_eq!(response.json()["overrides"][0]["burst"], 50);
+
+    let body = json!({ "reason": "Release is done" });
+    let response = post(
+        &admin,
+        &format!("{URL}/bar/publish_new/expire"),
+        body.clone(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = admin.get::<()>(URL).await;
+    assert_eq!(response.json(), json!({ "overrides": [] }));
+
+    let response = post(&admin, &format!("{URL}/bar/publish_new/expire"), body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    type Action = (Option<i32>, i32, Option<DateTime<Utc>>, String);
+    let actions: Vec<Action> = publish_rate_override_actions::table
+        .select((
+            publish_rate_override_actions::admin_id,
+            publish_rate_override_actions::burst,
+            publish_rate_override_actions::expires_at,
+            publish_rate_override_actions::reason,
+        ))
+        .filter(publish_rate_override_actions::user_id.eq(user.as_model().id))
+        .order(publish_rate_override_actions::id)
+        .load(&mut conn)
+        .await
+        .unwrap();
+
+    let admin_id = Some(admin.as_model().id);
+    assert_eq!(actions.len(), 3);
+    assert_eq!(
+        actions[0],
+        (admin_id, 100, None, "Coordinated release".into())
+    );
+    assert_eq!(actions[1].1, 50);
+    assert_eq!(actions[1].3, "Smaller burst");
+    assert_eq!(actions[2].1, 50);
+    assert!(actions[2].2.unwrap() <= Utc::now());
+    assert_eq!(actions[2].3, "Release is done");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_changes_are_rejected() {
+    let (app, _, admin) = TestApp::init().with_user().await;
+    app.db_new_user("bar").await;
+    let mut conn = app.db_conn().await;
+
+    diesel::update(admin.as_model())
+        .set(users::is_admin.eq(true))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let body = json!({ "burst": 100, "reason": "Coordinated release" });
+    let response = admin
+        .put::<()>(&format!("{URL}/bar/publish_everything"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"Unknown rate limited action `publish_everything`, expected one of: publish_new, publish_update, yank_unyank"}]}"#);
+
+    let response = admin
+        .put::<()>(&format!("{URL}/unknown/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"could not find user with login `unknown`"}]}"#);
+
+    let body = json!({ "burst": 100, "reason": " " });
+    let response = admin
+        .put::<()>(&format!("{URL}/bar/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"a reason for the change is required"}]}"#);
+
+    let body = json!({ "burst": 0, "reason": "Coordinated release" });
+    let response = admin
+        .put::<()>(&format!("{URL}/bar/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"burst must be at least 1"}]}"#);
+
+    let expires_at = Utc::now() - chrono::Duration::days(1);
+    let body = json!({ "burst": 100, "expires_at": expires_at, "reason": "Coordinated release" });
+    let response = admin
+        .put::<()>(&format!("{URL}/bar/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"expires_at must be in the future"}]}"#);
+
+    let count: i64 = publish_rate_override_actions::table
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn raising_the_burst_refills_the_bucket() {
+    let (app, _, admin) = TestApp::init().with_user().await;
+    let user = app.db_new_user("bar").await;
+    let user_id = user.as_model().id;
+    let mut conn = app.db_conn().await;
+
+    diesel::update(admin.as_model())
+        .set(users::is_admin.eq(true))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    diesel::insert_into(publish_limit_buckets::table)
+        .values((
+            publish_limit_buckets::user_id.eq(user_id),
+            publish_limit_buckets::action.eq(LimitedAction::PublishNew),
+            publish_limit_buckets::tokens.eq(0),
+            publish_limit_buckets::last_refill.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let tokens = async |conn: &mut _| -> i32 {
+        publish_limit_buckets::table
+            .find((user_id, LimitedAction::PublishNew))
+            .select(publish_limit_buckets::tokens)
+            .first(conn)
+            .await
+            .unwrap()
+    };
+
+    let body = json!({ "burst": 100, "reason": "Coordinated release" });
+    let response = admin
+        .put::<()>(&format!("{URL}/bar/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(tokens(&mut conn).await, 100);
+
+    diesel::update(publish_limit_buckets::table)
+        .set(publish_limit_buckets::tokens.eq(10))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    // Lowering the burst does not refill the bucket
+    let body = json!({ "burst": 50, "reason": "Smaller burst" });
+    let response = admin
+        .put::<()>(&format!("{URL}/bar/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(tokens(&mut conn).await, 10);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn audit_log_is_kept_when_users_are_deleted() {
+    let (app, _, admin) = TestApp::init().with_user().await;
+    let user = app.db_new_user("bar").await;
+    let mut conn = app.db_conn().await;
+
+    diesel::update(admin.as_model())
+        .set(users::is_admin.eq(true))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let body = json!({ "burst": 100, "reason": "Coordinated release" });
+    let response = admin
+        .put::<()>(&format!("{URL}/bar/publish_new"), body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let user_ids = [user.as_model().id, admin.as_model().id];
+    diesel::delete(publish_rate_overrides::table)
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    diesel::delete(emails::table.filter(emails::user_id.eq_any(user_ids)))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    diesel::delete(users::table.filter(users::id.eq_any(user_ids)))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let actions: Vec<(Option<i32>, Option<i32>, String)> = publish_rate_override_actions::table
+        .select((
+            publish_rate_override_actions::user_id,
+            publish_rate_override_actions::admin_id,
+            publish_rate_override_actions::reason,
+        ))
+        .load(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(actions, vec![(None, None, "Coordinated release".into())]);
+}
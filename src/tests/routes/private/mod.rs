@@ -1 +1,3 @@
+mod admin_jobs;
+mod admin_rate_limits;
 mod crate_owner_invitations;
@@ -0,0 +1,112 @@
+use crate::schema::daily_registry_stats;
+use crate::tests::util::{RequestHelper, TestApp};
+use chrono::{Days, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+
+async fn insert_stats(conn: &mut AsyncPgConnection, date: NaiveDate, downloads: i64) {
+    diesel::insert_into(daily_registry_stats::table)
+        .values((
+            daily_registry_stats::date.eq(date),
+            daily_registry_stats::downloads.eq(downloads),
+            daily_registry_stats::new_crates.eq(2),
+            daily_registry_stats::new_versions.eq(5),
+        ))
+        .execute(conn)
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daily_stats() {
+    let (app, anon) = TestApp::init().empty().await;
+    let mut conn = app.db_conn().await;
+
+    for (date, downloads) in [
+        ("2024-06-01", 100),
+        ("2024-06-02", 200),
+        ("2024-06-03", 300),
+        ("2024-06-04", 400),
+    ] {
+        insert_stats(&mut conn, date.parse().unwrap(), downloads).await;
+    }
+
+    let url = "/api/v1/stats/daily?from=2024-06-02&to=2024-06-03";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), @r#"
+    {
+      "daily_stats": [
+        {
+          "date": "2024-06-02",
+          "downloads": 200,
+          "new_crates": 2,
+          "new_versions": 5
+        },
+        {
+          "date": "2024-06-03",
+          "downloads": 300,
+          "new_crates": 2,
+          "new_versions": 5
+        }
+      ]
+    }
+    "#);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daily_stats_default_range() {
+    let (app, anon) = TestApp::init().empty().await;
+    let mut conn = app.db_conn().await;
+
+    let today = Utc::now().date_naive();
+    insert_stats(&mut conn, today, 10).await;
+    insert_stats(&mut conn, today - Days::new(1), 20).await;
+    // Statistics older than 90 days are not included by default
+    insert_stats(&mut conn, today - Days::new(100), 30).await;
+
+    let response = anon.get::<()>("/api/v1/stats/daily").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".daily_stats[].date" => "[date]",
+    }, @r#"
+    {
+      "daily_stats": [
+        {
+          "date": "[date]",
+          "downloads": 20,
+          "new_crates": 2,
+          "new_versions": 5
+        },
+        {
+          "date": "[date]",
+          "downloads": 10,
+          "new_crates": 2,
+          "new_versions": 5
+        }
+      ]
+    }
+    "#);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daily_stats_invalid_range() {
+    let (_, anon) = TestApp::init().empty().await;
+
+    let url = "/api/v1/stats/daily?from=2024-06-03&to=2024-06-02";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"`from` must not be after `to`"}]}"#);
+
+    let url = "/api/v1/stats/daily?from=2020-01-01&to=2024-06-02";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"the requested range must not exceed 366 days"}]}"#);
+
+    let url = "/api/v1/stats/daily?from=foo";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r#"{"errors":[{"detail":"Failed to deserialize query string: from: input contains invalid characters"}]}"#);
+}
@@ -53,7 +53,7 @@ mod test_app;
 use mock_request::MockRequest;
 pub use mock_request::MockRequestExt;
 pub use response::Response;
-pub use test_app::TestApp;
+pub use test_app::{TestApp, TestAppBuilder};
 
 /// This function can be used to create a `Cookie` header for mock requests that
 /// include cookie-based authentication.
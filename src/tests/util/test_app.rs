@@ -1,4 +1,5 @@
 use super::{MockAnonymousUser, MockCookieUser, MockTokenUser};
+use crate::cdn::{InMemoryCdn, Purge, PurgeBatcher};
 use crate::config::{
     self, Base, CdnLogQueueConfig, CdnLogStorageConfig, DatabasePools, DbPoolConfig,
 };
@@ -32,6 +33,7 @@ struct TestAppInner {
     router: axum::Router,
     index: Option<UpstreamIndex>,
     runner: Option<Runner<Arc<Environment>>>,
+    cdn: Arc<InMemoryCdn>,
 
     primary_db_chaosproxy: Option<Arc<ChaosProxy>>,
     replica_db_chaosproxy: Option<Arc<ChaosProxy>>,
@@ -165,6 +167,11 @@ impl TestApp {
             .collect()
     }
 
+    /// Returns the CDN purge requests that were sent by the background jobs.
+    pub fn cdn_purges(&self) -> Vec<Purge> {
+        self.0.cdn.purges()
+    }
+
     pub async fn emails(&self) -> Vec<String> {
         let emails = self.as_inner().emails.mails_in_memory().await.unwrap();
         emails.into_iter().map(|(_, email)| email).collect()
@@ -282,6 +289,8 @@ impl TestAppBuilder {
 
         let (app, router) = build_app(self.config, self.github);
 
+        let cdn = Arc::new(InMemoryCdn::default());
+
         let runner = if self.build_job_runner {
             let index = self
                 .index
@@ -300,6 +309,7 @@ impl TestAppBuilder {
                 .deadpool(app.primary_database.clone())
                 .emails(app.emails.clone())
                 .team_repo(Box::new(self.team_repo))
+                .fastly(PurgeBatcher::with_window(cdn.clone(), Duration::ZERO))
                 .build();
 
             let runner = Runner::new(app.primary_database.clone(), Arc::new(environment))
@@ -317,6 +327,7 @@ impl TestAppBuilder {
             router,
             index: self.index,
             runner,
+            cdn,
             primary_db_chaosproxy,
             replica_db_chaosproxy,
         };
@@ -448,6 +459,7 @@ fn simple_config() -> config::Server {
         max_features: 10,
         max_dependencies: 10,
         rate_limiter: Default::default(),
+        read_rate_limits: Default::default(),
         new_version_rate_limit: Some(10),
         blocked_traffic: Default::default(),
         blocked_ips: Default::default(),
@@ -476,6 +488,9 @@ fn simple_config() -> config::Server {
         serve_html: false,
         og_image_base_url: None,
         html_render_cache_max_capacity: 1024,
+        downloads_archive_cache_max_capacity: 1000,
+        parquet_exports: false,
+        sqlite_exports: true,
         content_security_policy: None,
     }
 }
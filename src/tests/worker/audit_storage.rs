@@ -0,0 +1,98 @@
+use crate::models::{StorageIntegrityIssue, StorageIssueKind};
+use crate::schema::storage_integrity_issues;
+use crate::tests::builders::PublishBuilder;
+use crate::tests::util::{RequestHelper, TestApp};
+use crate::worker::jobs::AuditStorage;
+use bytes::Bytes;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use insta::assert_debug_snapshot;
+use object_store::path::Path;
+
+async fn all_issues(conn: &mut AsyncPgConnection) -> QueryResult<Vec<(String, StorageIssueKind)>> {
+    let issues = storage_integrity_issues::table
+        .select(StorageIntegrityIssue::as_select())
+        .order((
+            storage_integrity_issues::version_id,
+            storage_integrity_issues::kind,
+        ))
+        .load(conn)
+        .await?;
+
+    Ok(issues
+        .into_iter()
+        .map(|issue| (issue.path, issue.kind))
+        .collect())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_audit_storage_job() -> anyhow::Result<()> {
+    let (app, _, _, token) = TestApp::full().with_token().await;
+    let mut conn = app.db_conn().await;
+
+    for version in ["1.0.0", "1.1.0", "2.0.0"] {
+        let pb = PublishBuilder::new("foo", version).readme("# foo");
+        token.publish_crate(pb).await.good();
+    }
+    app.run_pending_background_jobs().await;
+
+    AuditStorage::default().enqueue(&mut conn).await?;
+    app.run_pending_background_jobs().await;
+    assert_eq!(all_issues(&mut conn).await?, vec![]);
+
+    // Simulate a few broken files in the storage backend
+    let store = app.as_inner().storage.as_inner();
+    store
+        .delete(&Path::from("crates/foo/foo-1.0.0.crate"))
+        .await?;
+    store
+        .delete(&Path::from("readmes/foo/foo-1.1.0.html"))
+        .await?;
+    let payload = Bytes::from_static(b"corrupted").into();
+    store
+        .put(&Path::from("crates/foo/foo-2.0.0.crate"), payload)
+        .await?;
+
+    AuditStorage::default().enqueue(&mut conn).await?;
+    app.run_pending_background_jobs().await;
+    assert_debug_snapshot!(all_issues(&mut conn).await?, @r#"
+    [
+        (
+            "crates/foo/foo-1.0.0.crate",
+            MissingCrateFile,
+        ),
+        (
+            "readmes/foo/foo-1.1.0.html",
+            MissingReadme,
+        ),
+        (
+            "crates/foo/foo-2.0.0.crate",
+            ChecksumMismatch,
+        ),
+    ]
+    "#);
+
+    // Restore one of the files and check that the issue is resolved
+    let payload = Bytes::from_static(b"<h1>foo</h1>").into();
+    store
+        .put(&Path::from("readmes/foo/foo-1.1.0.html"), payload)
+        .await?;
+
+    AuditStorage::default().enqueue(&mut conn).await?;
+    app.run_pending_background_jobs().await;
+    assert_debug_snapshot!(all_issues(&mut conn).await?, @r#"
+    [
+        (
+            "crates/foo/foo-1.0.0.crate",
+            MissingCrateFile,
+        ),
+        (
+            "crates/foo/foo-2.0.0.crate",
+            ChecksumMismatch,
+        ),
+    ]
+    "#);
+
+    Ok(())
+}
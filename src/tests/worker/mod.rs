@@ -1,3 +1,4 @@
+mod audit_storage;
 mod git;
 mod rss;
 mod sync_admins;
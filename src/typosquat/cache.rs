@@ -4,7 +4,11 @@ use thiserror::Error;
 use typomania::Harness;
 use typomania::checks::{Bitflips, Omitted, SwappedWords, Typos};
 
-use super::{checks::Affixes, config, database::TopCrates};
+use super::{
+    checks::{Affixes, Confusables},
+    config,
+    database::TopCrates,
+};
 
 static NOTIFICATION_EMAILS_ENV: &str = "TYPOSQUAT_NOTIFICATION_EMAILS";
 
@@ -75,6 +79,12 @@ impl Cache {
                         config::SUFFIXES.iter(),
                         config::SUFFIX_SEPARATORS.iter(),
                     ))
+                    .with_check(Confusables::new(
+                        config::CONFUSABLES
+                            .iter()
+                            .map(|(c, r)| (c.to_string(), r.to_string())),
+                        top.crates.keys().map(String::as_str),
+                    ))
                     .build(top),
             ),
         })
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use typomania::checks::{Check, Squat};
 use typomania::{Corpus, Package};
 
@@ -84,14 +86,96 @@ impl Check for Affixes {
     }
 }
 
+/// A typomania check that checks if a package name only differs from a popular package name by
+/// characters that look alike, such as `rn` and `m`, or `0` and `o`.
+///
+/// Names are normalised into a "skeleton" by replacing every confusable sequence with its
+/// replacement, in order, and then lowercasing the result. Since the replacements are applied
+/// before lowercasing, they can tell apart an uppercase `I` from a lowercase `i`.
+pub struct Confusables {
+    confusables: Vec<(String, String)>,
+    skeletons: HashMap<String, Vec<String>>,
+}
+
+impl Confusables {
+    pub fn new<Conf, Pop>(confusables: Conf, popular: Pop) -> Self
+    where
+        Conf: Iterator<Item = (String, String)>,
+        Pop: Iterator,
+        Pop::Item: ToString,
+    {
+        let mut check = Self {
+            confusables: confusables.collect(),
+            skeletons: HashMap::new(),
+        };
+
+        for name in popular {
+            let name = name.to_string();
+            check
+                .skeletons
+                .entry(check.skeleton(&name))
+                .or_default()
+                .push(name);
+        }
+
+        check
+    }
+
+    fn skeleton(&self, name: &str) -> String {
+        let skeleton = self
+            .confusables
+            .iter()
+            .fold(name.to_string(), |skeleton, (confusable, replacement)| {
+                skeleton.replace(confusable, replacement)
+            });
+
+        skeleton.to_lowercase()
+    }
+}
+
+impl Check for Confusables {
+    fn check(
+        &self,
+        corpus: &dyn Corpus,
+        name: &str,
+        package: &dyn Package,
+    ) -> typomania::Result<Vec<Squat>> {
+        let mut squats = Vec::new();
+
+        let Some(popular) = self.skeletons.get(&self.skeleton(name)) else {
+            return Ok(squats);
+        };
+
+        for popular in popular {
+            // Names that only differ in case refer to the same crate.
+            if popular.eq_ignore_ascii_case(name) {
+                continue;
+            }
+
+            if corpus.possible_squat(popular, name, package)? {
+                squats.push(Squat::Custom {
+                    message: "uses confusable characters".to_string(),
+                    package: popular.clone(),
+                });
+            }
+        }
+
+        Ok(squats)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::{HashMap, HashSet};
 
+    use crates_io_test_db::TestDatabase;
     use googletest::prelude::*;
     use typomania::{AuthorSet, Harness};
 
     use super::*;
+    use crate::typosquat::config;
+    use crate::typosquat::database::{Crate, TopCrates};
+    use crate::typosquat::test_util::faker;
 
     #[test]
     fn test_affixes() -> anyhow::Result<()> {
@@ -139,6 +223,62 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_confusables() -> anyhow::Result<()> {
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        let alice = faker::user(&mut conn, "alice").await?;
+        let bob = faker::user(&mut conn, "bob").await?;
+
+        faker::crate_and_version(&mut conn, "modular", "Modules", &alice, 100).await?;
+        faker::crate_and_version(&mut conn, "log", "Logging", &alice, 100).await?;
+        faker::crate_and_version(&mut conn, "tokio", "Async runtime", &alice, 100).await?;
+
+        let top = TopCrates::new(&mut conn, 3).await?;
+        let harness = Harness::empty_builder()
+            .with_check(Confusables::new(
+                config::CONFUSABLES
+                    .iter()
+                    .map(|(c, r)| (c.to_string(), r.to_string())),
+                top.crates.keys().map(String::as_str),
+            ))
+            .build(top);
+
+        // Packages that shouldn't be squatting anything, either because they don't look like a
+        // popular crate, or because they share an owner with it.
+        faker::crate_and_version(&mut conn, "modulus", "Unrelated", &bob, 0).await?;
+        faker::crate_and_version(&mut conn, "t0ki0", "Shared owner", &alice, 0).await?;
+
+        for name in ["modulus", "t0ki0"] {
+            let package = Crate::from_name(&mut conn, name).await?;
+            let squats = harness.check_package(name, Box::new(package))?;
+            assert_that!(squats, empty());
+        }
+
+        // Now try some packages that should be.
+        faker::crate_and_version(&mut conn, "rnodular", "Modules?", &bob, 0).await?;
+        faker::crate_and_version(&mut conn, "Iog", "Logging?", &bob, 0).await?;
+        faker::crate_and_version(&mut conn, "l0g", "Logging??", &bob, 0).await?;
+        faker::crate_and_version(&mut conn, "modu1ar", "Modules??", &bob, 0).await?;
+        faker::crate_and_version(&mut conn, "t0kio", "Async runtime?", &bob, 0).await?;
+
+        for (name, popular) in [
+            ("rnodular", "modular"),
+            ("Iog", "log"),
+            ("l0g", "log"),
+            ("modu1ar", "modular"),
+            ("t0kio", "tokio"),
+        ] {
+            let package = Crate::from_name(&mut conn, name).await?;
+            let squats = harness.check_package(name, Box::new(package))?;
+            assert_that!(squats, len(eq(1)));
+            assert_eq!(squats[0].package(), popular);
+        }
+
+        Ok(())
+    }
+
     struct TestPackage {
         name: String,
         description: String,
@@ -11,6 +11,12 @@ pub(super) static SUFFIX_SEPARATORS: &[&str] = &["-", "_"];
 /// Commonly used suffixes when building crate names.
 pub(super) static SUFFIXES: &[&str] = &["api", "cargo", "cli", "core", "lib", "rs", "rust", "sys"];
 
+/// Sequences of characters that look alike in most fonts, and the sequence they're normalised to
+/// before comparing crate names. The replacements are applied in order, and before the names are
+/// lowercased, so an uppercase `I` can be told apart from a lowercase `i`.
+pub(super) static CONFUSABLES: &[(&str, &str)] =
+    &[("rn", "m"), ("vv", "w"), ("I", "l"), ("1", "l"), ("0", "o")];
+
 /// The number of crates to consider in the "top crates" corpus.
 pub(super) static TOP_CRATES: i64 = 3000;
 
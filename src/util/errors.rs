@@ -208,6 +208,20 @@ impl From<crates_io_worker::EnqueueError> for BoxedAppError {
     }
 }
 
+impl From<crates_io_worker::admin::JobControlError> for BoxedAppError {
+    fn from(err: crates_io_worker::admin::JobControlError) -> BoxedAppError {
+        use crates_io_worker::admin::JobControlError;
+
+        match err {
+            JobControlError::NotFound => not_found(),
+            JobControlError::Running => custom(StatusCode::CONFLICT, "job is currently running"),
+            JobControlError::UnknownQueue => bad_request("unknown queue"),
+            JobControlError::DatabaseError(err) => err.into(),
+            _ => Box::new(err),
+        }
+    }
+}
+
 impl From<JoinError> for BoxedAppError {
     fn from(err: JoinError) -> BoxedAppError {
         Box::new(err)
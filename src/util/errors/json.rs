@@ -7,7 +7,7 @@ use std::fmt;
 use super::{AppError, BoxedAppError};
 
 use crate::middleware::log_request::CauseField;
-use crate::rate_limiter::LimitedAction;
+use crate::rate_limiter::{LimitedAction, RateLimitStatus};
 use chrono::{DateTime, Utc};
 use http::{StatusCode, header};
 
@@ -48,6 +48,7 @@ impl AppError for CustomApiError {
 pub(crate) struct TooManyRequests {
     pub action: LimitedAction,
     pub retry_after: DateTime<Utc>,
+    pub status: RateLimitStatus,
 }
 
 impl AppError for TooManyRequests {
@@ -68,6 +69,7 @@ impl AppError for TooManyRequests {
                 .try_into()
                 .expect("HTTP_DATE_FORMAT contains invalid char"),
         );
+        self.status.insert_headers(response.headers_mut());
         response
     }
 }
@@ -28,6 +28,7 @@ pub fn redirect(url: String) -> Response {
 
 pub trait RequestUtils {
     fn wants_json(&self) -> bool;
+    fn wants_csv(&self) -> bool;
     fn query_with_params(&self, params: IndexMap<String, String>) -> String;
 }
 
@@ -39,6 +40,13 @@ impl<T: RequestPartsExt> RequestUtils for T {
             .any(|val| val.to_str().unwrap_or_default().contains("json"))
     }
 
+    fn wants_csv(&self) -> bool {
+        self.headers()
+            .get_all(header::ACCEPT)
+            .iter()
+            .any(|val| val.to_str().unwrap_or_default().contains("text/csv"))
+    }
+
     fn query_with_params(&self, new_params: IndexMap<String, String>) -> String {
         let query_bytes = self.uri().query().unwrap_or("").as_bytes();
 
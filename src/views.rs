@@ -213,9 +213,10 @@ pub struct EncodableVersionDownload {
     #[schema(example = 42)]
     pub version: i32,
 
-    /// The number of downloads for this version on the given date.
+    /// The number of downloads for this version on the given date, or
+    /// within the week or month starting on the given date.
     #[schema(example = 123)]
-    pub downloads: i32,
+    pub downloads: i64,
 
     /// The date this download count is for.
     #[schema(example = "2019-12-13")]
@@ -226,7 +227,7 @@ impl From<VersionDownload> for EncodableVersionDownload {
     fn from(download: VersionDownload) -> Self {
         Self {
             version: download.version_id,
-            downloads: download.downloads,
+            downloads: download.downloads.into(),
             date: download.date.to_string(),
         }
     }
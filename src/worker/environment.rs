@@ -1,6 +1,5 @@
 use crate::Emails;
-use crate::cloudfront::CloudFront;
-use crate::fastly::Fastly;
+use crate::cdn::PurgeBatcher;
 use crate::storage::Storage;
 use crate::typosquat;
 use anyhow::Context;
@@ -23,8 +22,8 @@ pub struct Environment {
     repository_config: RepositoryConfig,
     #[builder(skip)]
     repository: Mutex<Option<Repository>>,
-    cloudfront: Option<CloudFront>,
-    fastly: Option<Fastly>,
+    cloudfront: Option<PurgeBatcher>,
+    fastly: Option<PurgeBatcher>,
     pub storage: Arc<Storage>,
     pub downloads_archive_store: Option<Box<dyn ObjectStore>>,
     pub deadpool: Pool<AsyncPgConnection>,
@@ -56,22 +55,24 @@ impl Environment {
         Ok(repo_lock)
     }
 
-    pub(crate) fn cloudfront(&self) -> Option<&CloudFront> {
+    pub(crate) fn cloudfront(&self) -> Option<&PurgeBatcher> {
         self.cloudfront.as_ref()
     }
 
-    pub(crate) fn fastly(&self) -> Option<&Fastly> {
+    pub(crate) fn fastly(&self) -> Option<&PurgeBatcher> {
         self.fastly.as_ref()
     }
 
     /// Invalidate a file in all registered CDNs.
     pub(crate) async fn invalidate_cdns(&self, path: &str) -> anyhow::Result<()> {
         if let Some(cloudfront) = self.cloudfront() {
-            cloudfront.invalidate(path).await.context("CloudFront")?;
+            let paths = vec![path.to_string()];
+            cloudfront.purge_paths(paths).await.context("CloudFront")?;
         }
 
         if let Some(fastly) = self.fastly() {
-            fastly.invalidate(path).await.context("Fastly")?;
+            let paths = vec![path.to_string()];
+            fastly.purge_paths(paths).await.context("Fastly")?;
         }
 
         Ok(())
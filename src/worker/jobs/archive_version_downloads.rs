@@ -2,9 +2,10 @@ use super::IndexVersionDownloadsArchive;
 use crate::schema::version_downloads;
 use crate::tasks::spawn_blocking;
 use crate::worker::Environment;
-use anyhow::{Context, anyhow};
+use anyhow::{Context, anyhow, ensure};
 use chrono::{NaiveDate, Utc};
-use crates_io_worker::BackgroundJob;
+use crates_io_database_dump::ColumnTypes;
+use crates_io_worker::{BackgroundJob, cancellation_token};
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use futures_util::StreamExt;
@@ -14,10 +15,16 @@ use std::collections::BTreeMap;
 use std::collections::btree_map::Entry;
 use std::path::Path;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tempfile::tempdir;
+use tokio_util::sync::CancellationToken;
 
 const FILE_NAME: &str = "version_downloads.csv";
+const COLUMN_TYPES_FILE_NAME: &str = "column_types.csv";
+
+/// Prefix of the date-partitioned Parquet files within the archive store,
+/// e.g. `parquet/date=2021-01-01/version_downloads.parquet`.
+pub const PARQUET_PREFIX: &str = "parquet";
 
 /// Archive data from the `version_downloads` table older than the given
 /// date to S3.
@@ -26,6 +33,10 @@ const FILE_NAME: &str = "version_downloads.csv";
 /// and a `COPY` command. The CSV file is then split into multiple files based
 /// on the date column and those are uploaded to the object store. Finally, the
 /// successfully uploaded dates are deleted from the database.
+///
+/// If Parquet exports are enabled, the per-date CSV files are additionally
+/// converted to Parquet files and uploaded with a `date=YYYY-MM-DD` partition
+/// prefix.
 #[derive(Serialize, Deserialize)]
 pub struct ArchiveVersionDownloads {
     before: NaiveDate,
@@ -46,6 +57,7 @@ impl Default for ArchiveVersionDownloads {
 impl BackgroundJob for ArchiveVersionDownloads {
     const JOB_NAME: &'static str = "archive_version_downloads";
     const DEDUPLICATED: bool = true;
+    const TIMEOUT: Option<Duration> = Some(Duration::from_secs(60 * 60));
 
     type Context = Arc<Environment>;
 
@@ -57,13 +69,31 @@ impl BackgroundJob for ArchiveVersionDownloads {
             return Ok(());
         };
 
+        // The token is only available within the task of the job, so it has
+        // to be passed to the blocking tasks explicitly.
+        let token = cancellation_token();
+
         let tempdir = tempdir().context("Failed to create temporary directory")?;
         let csv_path = tempdir.path().join(FILE_NAME);
 
         export(&env.config.db.primary.url, &csv_path, &self.before).await?;
-        let dates = spawn_blocking(move || split(csv_path)).await??;
+        ensure!(!token.is_cancelled(), "Job was cancelled");
+
+        let split_token = token.clone();
+        let dates = spawn_blocking(move || split(csv_path, &split_token)).await??;
         let uploaded_dates = upload(downloads_archive_store, tempdir.path(), dates).await?;
 
+        if env.config.parquet_exports {
+            let column_types_path = tempdir.path().join(COLUMN_TYPES_FILE_NAME);
+            export_column_types(&env.config.db.primary.url, &column_types_path).await?;
+
+            let directory = tempdir.path().to_path_buf();
+            let dates = uploaded_dates.clone();
+            let token = token.clone();
+            spawn_blocking(move || convert_to_parquet(directory, &dates, &token)).await??;
+            upload_parquet(downloads_archive_store, tempdir.path(), &uploaded_dates).await;
+        }
+
         let mut conn = env.deadpool.get().await?;
         delete(&mut conn, uploaded_dates).await?;
 
@@ -104,8 +134,25 @@ async fn export(
     Ok(())
 }
 
+/// Export the column types of the database to a CSV file, which is used to
+/// convert the CSV files to Parquet.
+async fn export_column_types(
+    database_url: &SecretString,
+    filename: impl AsRef<Path>,
+) -> anyhow::Result<()> {
+    let filename = filename.as_ref().as_os_str();
+    let filename = filename
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid filename"))?;
+
+    psql(database_url, &ColumnTypes::export_command(filename)).await
+}
+
 /// Run a psql command on the given database.
 ///
+/// The process is killed if the returned future is dropped, e.g. because the
+/// job did not stop within the grace period after it was cancelled.
+///
 /// Returns an error with the stderr output if the command fails.
 async fn psql(database_url: &SecretString, command: &str) -> anyhow::Result<()> {
     debug!(?command, "Running psql script…");
@@ -113,6 +160,7 @@ async fn psql(database_url: &SecretString, command: &str) -> anyhow::Result<()>
         .arg(database_url.expose_secret())
         .arg("-c")
         .arg(command)
+        .kill_on_drop(true)
         .output()
         .await
         .context("Failed to run psql command")?;
@@ -131,7 +179,9 @@ async fn psql(database_url: &SecretString, command: &str) -> anyhow::Result<()>
 /// to be the `date` and dropped from the resulting files. The date is used as
 /// the filename for the new CSV files, which are created in the same directory
 /// as the original file.
-fn split(path: impl AsRef<Path>) -> anyhow::Result<Vec<NaiveDate>> {
+///
+/// Returns an error once the given token is cancelled.
+fn split(path: impl AsRef<Path>, token: &CancellationToken) -> anyhow::Result<Vec<NaiveDate>> {
     let path = path.as_ref();
 
     info!(path = %path.display(), "Splitting CSV file into multiple files…");
@@ -145,6 +195,8 @@ fn split(path: impl AsRef<Path>) -> anyhow::Result<Vec<NaiveDate>> {
     let headers = reader.byte_headers()?.clone();
     let mut row = csv::ByteRecord::new();
     while reader.read_byte_record(&mut row)? {
+        ensure!(!token.is_cancelled(), "Job was cancelled");
+
         let mut row_iter = row.iter();
         let date = row_iter.next();
         let date = date.ok_or_else(|| anyhow!("Missing first column"))?;
@@ -209,14 +261,24 @@ async fn upload(
 /// Upload a single file to the object store.
 async fn upload_file(store: &impl ObjectStore, path: impl AsRef<Path>) -> anyhow::Result<()> {
     let path = path.as_ref();
-    let content = tokio::fs::read(path).await?;
 
     let filename = path
         .file_name()
         .and_then(|filename| filename.to_str())
         .ok_or_else(|| anyhow!("Invalid path"))?;
 
-    let path = object_store::path::Path::parse(filename)?;
+    upload_file_to(store, path, filename).await
+}
+
+/// Upload a single file to the given path in the object store.
+async fn upload_file_to(
+    store: &impl ObjectStore,
+    path: impl AsRef<Path>,
+    target: &str,
+) -> anyhow::Result<()> {
+    let content = tokio::fs::read(path).await?;
+
+    let path = object_store::path::Path::parse(target)?;
 
     debug!(%path, "Uploading file to S3…");
     store.put(&path, content.into()).await?;
@@ -224,6 +286,62 @@ async fn upload_file(store: &impl ObjectStore, path: impl AsRef<Path>) -> anyhow
     Ok(())
 }
 
+/// Convert the per-date CSV files in the given directory to Parquet files.
+///
+/// The column types are taken from the `version_downloads` table schema, as
+/// exported by [export_column_types].
+///
+/// Returns an error once the given token is cancelled.
+fn convert_to_parquet(
+    directory: impl AsRef<Path>,
+    dates: &[NaiveDate],
+    token: &CancellationToken,
+) -> anyhow::Result<()> {
+    let directory = directory.as_ref();
+
+    info!("Converting {} CSV files to Parquet…", dates.len());
+    let instant = Instant::now();
+
+    let column_types = ColumnTypes::load(&directory.join(COLUMN_TYPES_FILE_NAME))?;
+    for date in dates {
+        ensure!(!token.is_cancelled(), "Job was cancelled");
+
+        let csv_path = directory.join(format!("{date}.csv"));
+        let parquet_path = directory.join(format!("{date}.parquet"));
+        column_types.csv_to_parquet("version_downloads", &csv_path, &parquet_path)?;
+    }
+
+    let elapsed = instant.elapsed();
+    info!("Finished converting CSV files to Parquet ({elapsed:?})");
+
+    Ok(())
+}
+
+/// Upload per-date Parquet files from the given directory to the object
+/// store, partitioned by date.
+///
+/// The CSV files are the canonical archive, so failed uploads are only logged
+/// and do not prevent the rows from being deleted from the database.
+async fn upload_parquet(
+    store: &impl ObjectStore,
+    directory: impl AsRef<Path>,
+    dates: &[NaiveDate],
+) {
+    // Upload at most 10 files concurrently.
+    const MAX_CONCURRENCY: usize = 10;
+
+    let directory = directory.as_ref();
+    futures_util::stream::iter(dates)
+        .for_each_concurrent(MAX_CONCURRENCY, async |date| {
+            let path = directory.join(format!("{date}.parquet"));
+            let target = format!("{PARQUET_PREFIX}/date={date}/version_downloads.parquet");
+            if let Err(error) = upload_file_to(store, &path, &target).await {
+                warn!(path = %path.display(), "Failed to upload file to S3: {error}");
+            }
+        })
+        .await;
+}
+
 /// Delete version downloads for the given dates from the database.
 async fn delete(conn: &mut AsyncPgConnection, dates: Vec<NaiveDate>) -> anyhow::Result<()> {
     // Delete version downloads for the given dates in chunks to avoid running
@@ -295,7 +413,8 @@ mod tests {
         )
         .unwrap();
 
-        let dates = split(&csv_path).unwrap();
+        let token = CancellationToken::new();
+        let dates = split(&csv_path, &token).unwrap();
         let dates = dates
             .into_iter()
             .map(|date| date.to_string())
@@ -310,6 +429,10 @@ mod tests {
         1,200
         2,500
         ");
+
+        token.cancel();
+        let error = split(tempdir.path().join(FILE_NAME), &token).unwrap_err();
+        assert_eq!(error.to_string(), "Job was cancelled");
     }
 
     #[tokio::test]
@@ -355,6 +478,36 @@ mod tests {
         assert_err!(store.get(&store_path).await);
     }
 
+    #[tokio::test]
+    async fn test_upload_parquet() {
+        let test_db = TestDatabase::new();
+
+        let tempdir = tempdir().unwrap();
+        let dir_path = tempdir.path();
+
+        let database_url = SecretString::from(test_db.url().to_string());
+        let column_types_path = dir_path.join(COLUMN_TYPES_FILE_NAME);
+        export_column_types(&database_url, &column_types_path)
+            .await
+            .unwrap();
+
+        let csv_path = dir_path.join("2021-01-01.csv");
+        let content = "version_id,downloads\n1,100\n2,400";
+        std::fs::write(&csv_path, content).unwrap();
+
+        let dates = vec![NaiveDate::from_ymd_opt(2021, 1, 1).unwrap()];
+        convert_to_parquet(dir_path, &dates, &CancellationToken::new()).unwrap();
+
+        let store = object_store::memory::InMemory::new();
+        upload_parquet(&store, &dir_path, &dates).await;
+
+        let store_path = "parquet/date=2021-01-01/version_downloads.parquet";
+        let store_path = object_store::path::Path::from(store_path);
+        let result = store.get(&store_path).await.unwrap();
+        let bytes = result.bytes().await.unwrap();
+        assert!(bytes.starts_with(b"PAR1"));
+    }
+
     #[tokio::test]
     async fn test_delete() {
         let test_db = TestDatabase::new();
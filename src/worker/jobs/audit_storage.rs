@@ -0,0 +1,230 @@
+use crate::models::{NewStorageIntegrityIssue, StorageIntegrityIssue, StorageIssueKind};
+use crate::schema::{crates, readme_renderings, versions};
+use crate::storage::{Storage, crate_file_path, readme_path};
+use crate::worker::Environment;
+use anyhow::Context;
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use futures_util::{StreamExt, TryStreamExt};
+use hex::ToHex;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+/// Number of versions that are checked by a single job run.
+const BATCH_SIZE: i64 = 1000;
+
+/// Maximum number of files that are fetched from the storage backend
+/// concurrently.
+const MAX_CONCURRENCY: usize = 10;
+
+/// Verify that the crate files and READMEs of all versions exist in the
+/// storage backend, and that the crate files match the checksums in the
+/// `versions` table.
+///
+/// The versions are processed in batches, ordered by their ID. After each
+/// batch the job enqueues itself again with the last processed version ID,
+/// which allows the audit to resume where it left off if a job fails or the
+/// worker is restarted.
+///
+/// Any detected issues are recorded in the `storage_integrity_issues` table,
+/// and issues that no longer occur are removed from it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AuditStorage {
+    /// Only versions with an ID greater than this value are checked.
+    after: i32,
+}
+
+impl AuditStorage {
+    pub fn after(after: i32) -> Self {
+        Self { after }
+    }
+}
+
+impl BackgroundJob for AuditStorage {
+    const JOB_NAME: &'static str = "audit_storage";
+    const DEDUPLICATED: bool = true;
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let mut conn = env.deadpool.get().await?;
+
+        info!(after = %self.after, "Auditing storage integrity…");
+        let summary = audit(&env.storage, &mut conn, self.after, BATCH_SIZE).await?;
+
+        let num_issues = summary.issues.len();
+        info!(
+            "Checked {} versions and found {num_issues} issues",
+            summary.num_checked
+        );
+
+        match summary.last_version_id {
+            Some(last_version_id) if summary.num_checked as i64 == BATCH_SIZE => {
+                AuditStorage::after(last_version_id)
+                    .enqueue(&mut conn)
+                    .await
+                    .context("Failed to enqueue the next `audit_storage` job")?;
+            }
+            _ => info!("Finished auditing storage integrity"),
+        }
+
+        Ok(())
+    }
+}
+
+/// The result of a single [audit] run.
+#[derive(Debug)]
+pub struct AuditSummary {
+    /// Number of versions that were checked.
+    pub num_checked: usize,
+    /// ID of the last version that was checked, or `None` if there were no
+    /// versions left to check.
+    pub last_version_id: Option<i32>,
+    /// Issues that were detected and recorded in the database.
+    pub issues: Vec<NewStorageIntegrityIssue>,
+}
+
+#[derive(Debug, Queryable)]
+struct AuditedVersion {
+    id: i32,
+    crate_name: String,
+    num: String,
+    checksum: String,
+    has_readme: bool,
+}
+
+/// Check the files of up to `limit` versions with an ID greater than `after`
+/// and record the results in the `storage_integrity_issues` table.
+pub async fn audit(
+    storage: &Storage,
+    conn: &mut AsyncPgConnection,
+    after: i32,
+    limit: i64,
+) -> anyhow::Result<AuditSummary> {
+    let has_readme =
+        exists(readme_renderings::table.filter(readme_renderings::version_id.eq(versions::id)));
+
+    let versions: Vec<AuditedVersion> = versions::table
+        .inner_join(crates::table)
+        .filter(versions::id.gt(after))
+        .order(versions::id)
+        .limit(limit)
+        .select((
+            versions::id,
+            crates::name,
+            versions::num,
+            versions::checksum,
+            has_readme,
+        ))
+        .load(conn)
+        .await
+        .context("Failed to load versions")?;
+
+    let version_ids = versions.iter().map(|v| v.id).collect::<Vec<_>>();
+
+    let issues = futures_util::stream::iter(versions)
+        .map(|version| check_version(storage, version))
+        .buffer_unordered(MAX_CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    conn.transaction(|conn| {
+        async {
+            StorageIntegrityIssue::delete_for_versions(conn, &version_ids).await?;
+            for issue in &issues {
+                issue.insert(conn).await?;
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .scope_boxed()
+    })
+    .await
+    .context("Failed to record storage integrity issues")?;
+
+    Ok(AuditSummary {
+        num_checked: version_ids.len(),
+        last_version_id: version_ids.last().copied(),
+        issues,
+    })
+}
+
+/// Check the crate file and, if the version has one, the README of a single
+/// version.
+///
+/// Errors are only returned if the storage backend could not be queried.
+/// Missing or corrupted files are returned as issues instead.
+async fn check_version(
+    storage: &Storage,
+    version: AuditedVersion,
+) -> anyhow::Result<Vec<NewStorageIntegrityIssue>> {
+    let AuditedVersion {
+        id,
+        crate_name: ref name,
+        ref num,
+        ref checksum,
+        has_readme,
+    } = version;
+
+    let mut issues = Vec::new();
+
+    let crate_path = crate_file_path(name, num).to_string();
+    match storage.download_crate_file(name, num).await {
+        Ok(bytes) => {
+            let actual_checksum: String = Sha256::digest(&bytes).encode_hex();
+            if actual_checksum != *checksum {
+                warn!(%name, %num, "Crate file does not match the expected checksum");
+                let message = format!("expected {checksum}, found {actual_checksum}");
+                let issue = NewStorageIntegrityIssue::builder()
+                    .version_id(id)
+                    .kind(StorageIssueKind::ChecksumMismatch)
+                    .path(crate_path)
+                    .message(message)
+                    .build();
+
+                issues.push(issue);
+            }
+        }
+        Err(object_store::Error::NotFound { .. }) => {
+            warn!(%name, %num, "Crate file is missing");
+            let issue = NewStorageIntegrityIssue::builder()
+                .version_id(id)
+                .kind(StorageIssueKind::MissingCrateFile)
+                .path(crate_path)
+                .build();
+
+            issues.push(issue);
+        }
+        Err(error) => {
+            let context = format!("Failed to download crate file: {crate_path}");
+            return Err(anyhow::Error::from(error).context(context));
+        }
+    }
+
+    if has_readme {
+        let readme_path = readme_path(name, num).to_string();
+        let exists = storage
+            .readme_exists(name, num)
+            .await
+            .with_context(|| format!("Failed to check README file: {readme_path}"))?;
+
+        if !exists {
+            warn!(%name, %num, "README file is missing");
+            let issue = NewStorageIntegrityIssue::builder()
+                .version_id(id)
+                .kind(StorageIssueKind::MissingReadme)
+                .path(readme_path)
+                .build();
+
+            issues.push(issue);
+        }
+    }
+
+    Ok(issues)
+}
@@ -1,3 +1,4 @@
+use crate::cdn::SurrogateKey;
 use crate::storage::FeedId;
 use crate::worker::Environment;
 use crate::worker::jobs::InvalidateCdns;
@@ -28,7 +29,7 @@ impl BackgroundJob for DeleteCrateFromStorage {
         let name = &self.name;
         let feed_id = FeedId::Crate { name };
 
-        let (crate_file_paths, readme_paths, _) = try_join!(
+        try_join!(
             async {
                 info!("{name}: Deleting crate files from S3…");
                 let result = ctx.storage.delete_all_crate_files(name).await;
@@ -51,14 +52,9 @@ impl BackgroundJob for DeleteCrateFromStorage {
         info!("{name}: Enqueuing CDN invalidations");
 
         let mut conn = ctx.deadpool.get().await?;
-        InvalidateCdns::new(
-            crate_file_paths
-                .into_iter()
-                .chain(readme_paths.into_iter())
-                .chain(std::iter::once(object_store::path::Path::from(&feed_id))),
-        )
-        .enqueue(&mut conn)
-        .await?;
+        InvalidateCdns::surrogate_keys([SurrogateKey::krate(name)])
+            .enqueue(&mut conn)
+            .await?;
 
         info!("{name}: Successfully enqueued CDN invalidations.");
 
@@ -1,11 +1,15 @@
-use crate::config::CdnLogStorageConfig;
-use crate::worker::Environment;
+use crate::config::{CdnLogStorageBackend, CdnLogStorageConfig};
+use crate::schema::{crate_client_downloads, crates};
+use crate::worker::{DOWNLOADS_QUEUE, Environment};
 use anyhow::Context;
 use chrono::NaiveDate;
-use crates_io_cdn_logs::{Decompressor, DownloadsMap, count_downloads};
+use crates_io_cdn_logs::{
+    Decompressor, DownloadsMap, LogFormat, count_downloads, count_downloads_as,
+};
 use crates_io_worker::BackgroundJob;
 use diesel::dsl::exists;
 use diesel::prelude::*;
+use diesel::upsert::excluded;
 use diesel::{QueryResult, select};
 use diesel_async::pooled_connection::deadpool::Pool;
 use diesel_async::scoped_futures::ScopedFutureExt;
@@ -16,6 +20,7 @@ use object_store::local::LocalFileSystem;
 use object_store::memory::InMemory;
 use object_store::path::Path;
 use semver::Version;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::sync::Arc;
 use tokio::io::BufReader;
@@ -43,7 +48,7 @@ impl ProcessCdnLog {
 impl BackgroundJob for ProcessCdnLog {
     const JOB_NAME: &'static str = "process_cdn_log";
     const DEDUPLICATED: bool = true;
-    const QUEUE: &'static str = "downloads";
+    const QUEUE: &'static str = DOWNLOADS_QUEUE;
 
     type Context = Arc<Environment>;
 
@@ -54,8 +59,16 @@ impl BackgroundJob for ProcessCdnLog {
         let store = build_store(&ctx.config.cdn_log_storage, &self.region, &self.bucket)
             .context("Failed to build object store")?;
 
+        let config = &ctx.config.cdn_log_storage;
         let db_pool = ctx.deadpool.clone();
-        run(store, &self.path, db_pool).await
+        run(
+            store,
+            &self.path,
+            config.format,
+            config.client_stats,
+            db_pool,
+        )
+        .await
     }
 }
 
@@ -69,8 +82,8 @@ fn build_store(
     region: impl Into<String>,
     bucket: impl Into<String>,
 ) -> anyhow::Result<Arc<dyn ObjectStore>> {
-    match config {
-        CdnLogStorageConfig::S3 {
+    match &config.backend {
+        CdnLogStorageBackend::S3 {
             access_key,
             secret_key,
         } => {
@@ -85,16 +98,20 @@ fn build_store(
 
             Ok(Arc::new(store))
         }
-        CdnLogStorageConfig::Local { path } => {
+        CdnLogStorageBackend::Local { path } => {
             Ok(Arc::new(LocalFileSystem::new_with_prefix(path)?))
         }
-        CdnLogStorageConfig::Memory => Ok(Arc::new(InMemory::new())),
+        CdnLogStorageBackend::Memory => Ok(Arc::new(InMemory::new())),
     }
 }
 
 /// Loads the given log file from the object store and counts the number of
 /// downloads for each crate and version. The results are printed to the log.
 ///
+/// If no `format` is passed in, the log file format is detected automatically.
+/// If `client_stats` is `true`, the downloads per client are saved to the
+/// `crate_client_downloads` table too.
+///
 /// This function is separate from the [`BackgroundJob`] trait method so that
 /// it can be tested without having to construct a full [`Environment`]
 /// struct.
@@ -102,6 +119,8 @@ fn build_store(
 async fn run(
     store: Arc<dyn ObjectStore>,
     path: &str,
+    format: Option<LogFormat>,
+    client_stats: bool,
     db_pool: Pool<AsyncPgConnection>,
 ) -> anyhow::Result<()> {
     if already_processed(path, db_pool.clone()).await? {
@@ -112,7 +131,7 @@ async fn run(
     let parsed_path =
         Path::parse(path).with_context(|| format!("Failed to parse path: {path:?}"))?;
 
-    let downloads = load_and_count(&parsed_path, store).await?;
+    let downloads = load_and_count(&parsed_path, store, format).await?;
     if downloads.is_empty() {
         info!("No downloads found in log file");
         return Ok(());
@@ -136,6 +155,10 @@ async fn run(
             // file again.
             save_as_processed(path, conn).await?;
 
+            if client_stats {
+                save_client_downloads(&downloads, conn).await?;
+            }
+
             save_downloads(downloads, conn).await
         }
         .scope_boxed()
@@ -147,7 +170,11 @@ async fn run(
 
 /// Loads the given log file from the object store and counts the number of
 /// downloads for each crate and version.
-async fn load_and_count(path: &Path, store: Arc<dyn ObjectStore>) -> anyhow::Result<DownloadsMap> {
+async fn load_and_count(
+    path: &Path,
+    store: Arc<dyn ObjectStore>,
+    format: Option<LogFormat>,
+) -> anyhow::Result<DownloadsMap> {
     let meta = store.head(path).await;
     let meta = meta.with_context(|| format!("Failed to request metadata for {path:?}"))?;
 
@@ -155,7 +182,10 @@ async fn load_and_count(path: &Path, store: Arc<dyn ObjectStore>) -> anyhow::Res
     let decompressor = Decompressor::from_extension(reader, path.extension())?;
     let reader = BufReader::new(decompressor);
 
-    count_downloads(reader).await
+    match format {
+        Some(format) => count_downloads_as(reader, format).await,
+        None => count_downloads(reader).await,
+    }
 }
 
 /// Prints the total number of downloads, the number of crates, and the number
@@ -360,6 +390,90 @@ impl Debug for NameAndVersion {
     }
 }
 
+/// Helper struct for inserting rows into the `crate_client_downloads` table.
+#[derive(Insertable)]
+#[diesel(table_name = crate_client_downloads)]
+struct NewClientDownload<'a> {
+    crate_id: i32,
+    date: NaiveDate,
+    cargo_version: &'a str,
+    downloads: i64,
+}
+
+/// Saves the per-client downloads from the given [`DownloadsMap`] to the
+/// `crate_client_downloads` table.
+///
+/// Downloads of crates that don't exist in the database are ignored.
+#[instrument(
+    "db.query",
+    skip_all,
+    fields(message = "INSERT INTO crate_client_downloads ...")
+)]
+async fn save_client_downloads(
+    downloads: &DownloadsMap,
+    conn: &mut AsyncPgConnection,
+) -> anyhow::Result<()> {
+    // `tokio-postgres` has a limit on the size of values it can send to the
+    // database. To avoid hitting this limit, we insert the downloads in
+    // batches.
+    const MAX_BATCH_SIZE: usize = 5_000;
+
+    let clients = downloads.clients();
+    if clients.is_empty() {
+        return Ok(());
+    }
+
+    let names = clients
+        .keys()
+        .map(|(name, _, _)| name)
+        .collect::<HashSet<_>>();
+    let crate_ids: HashMap<String, i32> = crates::table
+        .filter(crates::name.eq_any(names))
+        .select((crates::name, crates::id))
+        .load::<(String, i32)>(conn)
+        .await
+        .context("Failed to load crate IDs")?
+        .into_iter()
+        .collect();
+
+    let mut rows = clients
+        .iter()
+        .filter_map(|((name, date, client), downloads)| {
+            Some(NewClientDownload {
+                crate_id: *crate_ids.get(name)?,
+                date: *date,
+                cargo_version: client.cargo_version.as_deref().unwrap_or_default(),
+                downloads: *downloads as i64,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Sort the rows to insert them in a consistent order, which avoids
+    // deadlocks between concurrent jobs.
+    rows.sort_by_key(|row| (row.crate_id, row.date, row.cargo_version));
+
+    for chunk in rows.chunks(MAX_BATCH_SIZE) {
+        diesel::insert_into(crate_client_downloads::table)
+            .values(chunk)
+            .on_conflict((
+                crate_client_downloads::crate_id,
+                crate_client_downloads::date,
+                crate_client_downloads::cargo_version,
+            ))
+            .do_update()
+            .set(
+                crate_client_downloads::downloads
+                    .eq(crate_client_downloads::downloads
+                        + excluded(crate_client_downloads::downloads)),
+            )
+            .execute(conn)
+            .await
+            .context("Failed to save client downloads")?;
+    }
+
+    Ok(())
+}
+
 /// Checks if the given log file has already been processed.
 ///
 /// Acquires a connection from the pool before passing it to the
@@ -414,7 +528,10 @@ mod tests {
     use crate::schema::{crates, version_downloads, versions};
     use crates_io_test_db::TestDatabase;
     use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-    use insta::assert_debug_snapshot;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use insta::{assert_debug_snapshot, assert_snapshot};
+    use std::io::Write;
 
     const CLOUDFRONT_PATH: &str =
         "cloudfront/static.crates.io/E35K556QRQDZXW.2024-01-16-16.d01d5f13.gz";
@@ -431,7 +548,7 @@ mod tests {
 
         assert_ok!({
             let store = store.clone();
-            run(store, CLOUDFRONT_PATH, db_pool.clone()).await
+            run(store, CLOUDFRONT_PATH, None, false, db_pool.clone()).await
         });
         assert_debug_snapshot!(all_version_downloads(db_pool.clone()).await, @r#"
         [
@@ -444,7 +561,7 @@ mod tests {
 
         // Check that processing the same log file again does not insert
         // duplicate data.
-        assert_ok!(run(store, CLOUDFRONT_PATH, db_pool.clone()).await);
+        assert_ok!(run(store, CLOUDFRONT_PATH, None, false, db_pool.clone()).await);
         assert_debug_snapshot!(all_version_downloads(db_pool).await, @r#"
         [
             "bindgen | 0.65.1 | 1 | 0 | 2024-01-16 | false",
@@ -455,6 +572,72 @@ mod tests {
         "#);
     }
 
+    #[tokio::test]
+    async fn test_process_cdn_log_client_stats() {
+        crate::util::tracing::init_for_test();
+
+        let test_database = TestDatabase::new();
+        let db_pool = build_connection_pool(test_database.url());
+        create_dummy_crates_and_versions(db_pool.clone()).await;
+
+        let store = build_dummy_store().await;
+
+        assert_ok!({
+            let store = store.clone();
+            run(store, CLOUDFRONT_PATH, None, true, db_pool.clone()).await
+        });
+        assert_debug_snapshot!(all_client_downloads(db_pool.clone()).await, @r#"
+        [
+            "bindgen | 2024-01-16 | 1.74 | 1",
+            "quick-error | 2024-01-16 | 1.74 | 2",
+            "quick-error | 2024-01-17 | 1.74 | 1",
+            "tracing-core | 2024-01-16 | 1.74 | 1",
+        ]
+        "#);
+
+        // Check that the client downloads are only saved once
+        assert_ok!(run(store, CLOUDFRONT_PATH, None, true, db_pool.clone()).await);
+        assert_debug_snapshot!(all_client_downloads(db_pool).await, @r#"
+        [
+            "bindgen | 2024-01-16 | 1.74 | 1",
+            "quick-error | 2024-01-16 | 1.74 | 2",
+            "quick-error | 2024-01-17 | 1.74 | 1",
+            "tracing-core | 2024-01-16 | 1.74 | 1",
+        ]
+        "#);
+    }
+
+    #[tokio::test]
+    async fn test_process_cdn_log_with_format() {
+        crate::util::tracing::init_for_test();
+
+        let test_database = TestDatabase::new();
+        let db_pool = build_connection_pool(test_database.url());
+        create_dummy_crates_and_versions(db_pool.clone()).await;
+
+        // Log lines starting with an IPv6 address can't be detected
+        // automatically, so the format has to be configured explicitly.
+        let line = r#"::1 - - [16/Jan/2024:23:53:20 +0000] "GET /crates/bindgen/bindgen-0.65.1.crate HTTP/1.1" 200 1 "-" "cargo""#;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(line.as_bytes()).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let path = "access.log.1.gz";
+        store.put(&path.into(), bytes.into()).await.unwrap();
+
+        let error = assert_err!(run(store.clone(), path, None, false, db_pool.clone()).await);
+        assert_snapshot!(error, @"Failed to determine log file format. Unrecognized first byte: 58.");
+
+        let format = Some(LogFormat::Combined);
+        assert_ok!(run(store, path, format, false, db_pool.clone()).await);
+        assert_debug_snapshot!(all_version_downloads(db_pool).await, @r#"
+        [
+            "bindgen | 0.65.1 | 1 | 0 | 2024-01-16 | false",
+        ]
+        "#);
+    }
+
     #[test]
     fn test_build_store_s3() {
         let access_key = "access_key".into();
@@ -542,6 +725,30 @@ mod tests {
             .collect()
     }
 
+    /// Queries all client downloads from the database and returns them as a
+    /// [`Vec`] of strings for use with [`assert_debug_snapshot!()`].
+    async fn all_client_downloads(db_pool: Pool<AsyncPgConnection>) -> Vec<String> {
+        let mut conn = db_pool.get().await.unwrap();
+
+        crate_client_downloads::table
+            .inner_join(crates::table)
+            .select((
+                crates::name,
+                crate_client_downloads::date,
+                crate_client_downloads::cargo_version,
+                crate_client_downloads::downloads,
+            ))
+            .order((crates::name, crate_client_downloads::date))
+            .load::<(String, NaiveDate, String, i64)>(&mut conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(name, date, cargo_version, downloads)| {
+                format!("{name} | {date} | {cargo_version} | {downloads}")
+            })
+            .collect()
+    }
+
     /// Queries all version downloads from the database and returns them as a
     /// [`Vec`] of tuples.
     async fn query_all_version_downloads(
@@ -0,0 +1,39 @@
+WITH start_date AS (
+    -- Recompute the last few days that are already in the table, since
+    -- download counts for these days might still change. If the table is
+    -- empty, the stats start at the earliest day that still has download
+    -- counts, since older days would be recorded without any downloads.
+    SELECT COALESCE(
+        MAX(date) - 2,
+        (SELECT MIN(date) FROM version_downloads)
+    ) AS date
+    FROM daily_registry_stats
+), downloads AS (
+    SELECT date, SUM(downloads) AS downloads
+    FROM version_downloads
+    WHERE date >= (SELECT date FROM start_date)
+    GROUP BY date
+), new_crates AS (
+    SELECT created_at::date AS date, COUNT(*) AS new_crates
+    FROM crates
+    WHERE created_at >= (SELECT date FROM start_date)
+    GROUP BY 1
+), new_versions AS (
+    SELECT created_at::date AS date, COUNT(*) AS new_versions
+    FROM versions
+    WHERE created_at >= (SELECT date FROM start_date)
+    GROUP BY 1
+)
+INSERT INTO daily_registry_stats (date, downloads, new_crates, new_versions)
+SELECT
+    date,
+    COALESCE(downloads, 0),
+    COALESCE(new_crates, 0),
+    COALESCE(new_versions, 0)
+FROM downloads
+FULL OUTER JOIN new_crates USING (date)
+FULL OUTER JOIN new_versions USING (date)
+ON CONFLICT (date) DO UPDATE
+SET downloads = excluded.downloads,
+    new_crates = excluded.new_crates,
+    new_versions = excluded.new_versions
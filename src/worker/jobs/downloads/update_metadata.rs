@@ -73,6 +73,15 @@ async fn update(conn: &mut AsyncPgConnection) -> QueryResult<()> {
 
     info!("Finished running refresh_recent_crate_downloads");
 
+    // The `daily_registry_stats` table is derived from `version_downloads`,
+    // which only contains the last 90 days, so only the most recent days are
+    // recomputed here and older days are kept as they are.
+    diesel::sql_query(include_str!("update_daily_stats.sql"))
+        .execute(conn)
+        .await?;
+
+    info!("Finished updating daily_registry_stats");
+
     Ok(())
 }
 
@@ -386,4 +395,79 @@ mod tests {
         assert_eq!(versions_changed, Ok(false));
         assert_eq!(crates_changed, Ok(false));
     }
+
+    #[tokio::test]
+    async fn daily_registry_stats() {
+        use crate::schema::daily_registry_stats;
+        use chrono::{Days, NaiveDate, Utc};
+        use diesel::{insert_into, update};
+
+        let test_db = TestDatabase::new();
+        let mut conn = test_db.async_connect().await;
+
+        let user = user(&mut conn).await;
+        let (_, version) = crate_and_version(&mut conn, user.id).await;
+
+        // Crates from before the earliest download counts are not counted,
+        // since their days would be recorded without any downloads.
+        let old_crate = NewCrate {
+            name: "bar",
+            ..Default::default()
+        }
+        .create(&mut conn, user.id)
+        .await
+        .unwrap();
+
+        let created_at = NaiveDate::from_ymd_opt(2017, 1, 1).unwrap();
+        update(crates::table.find(old_crate.id))
+            .set(crates::created_at.eq(created_at.and_hms_opt(0, 0, 0).unwrap().and_utc()))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        let today = Utc::now().date_naive();
+        let yesterday = today - Days::new(1);
+
+        insert_into(version_downloads::table)
+            .values(vec![
+                (
+                    version_downloads::version_id.eq(version.id),
+                    version_downloads::downloads.eq(3),
+                    version_downloads::date.eq(yesterday),
+                ),
+                (
+                    version_downloads::version_id.eq(version.id),
+                    version_downloads::downloads.eq(2),
+                    version_downloads::date.eq(today),
+                ),
+            ])
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        super::update(&mut conn).await.unwrap();
+
+        async fn load_stats(conn: &mut AsyncPgConnection) -> Vec<(NaiveDate, i64, i32, i32)> {
+            daily_registry_stats::table
+                .order(daily_registry_stats::date)
+                .load(conn)
+                .await
+                .unwrap()
+        }
+
+        let stats = load_stats(&mut conn).await;
+        assert_eq!(stats, vec![(yesterday, 3, 0, 0), (today, 2, 1, 1)]);
+
+        update(version_downloads::table)
+            .filter(version_downloads::date.eq(today))
+            .set(version_downloads::downloads.eq(5))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        super::update(&mut conn).await.unwrap();
+
+        let stats = load_stats(&mut conn).await;
+        assert_eq!(stats, vec![(yesterday, 3, 0, 0), (today, 5, 1, 1)]);
+    }
 }
@@ -1,10 +1,26 @@
 use crate::tasks::spawn_blocking;
 use crate::worker::Environment;
-use crates_io_database_dump::{DumpDirectory, create_archives};
-use crates_io_worker::BackgroundJob;
+use anyhow::{Context, anyhow};
+use crates_io_database_dump::{
+    BaseSnapshot, CancellationFlag, Delta, DeltaManifest, DumpDirectory, FingerprintsReader,
+    create_archives, create_delta, create_delta_archive, create_sqlite,
+};
+use crates_io_worker::{BackgroundJob, cancellation_token};
 use secrecy::ExposeSecret;
+use std::fs::File;
+use std::io::{Seek, Write};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+
+const DELTA_PREFIX: &str = "db-dump/delta";
+const MANIFEST_PATH: &str = "db-dump/delta/manifest.json";
+const FINGERPRINTS_PATH: &str = "db-dump/delta/fingerprints.json.gz";
+
+/// The maximum number of deltas in a chain before a new base snapshot is
+/// started, so that consumers don't have to apply an unbounded number of
+/// deltas when catching up.
+const MAX_DELTAS: usize = 30;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct DumpDb;
@@ -12,31 +28,106 @@ pub struct DumpDb;
 impl BackgroundJob for DumpDb {
     const JOB_NAME: &'static str = "dump_db";
     const DEDUPLICATED: bool = true;
+    const TIMEOUT: Option<Duration> = Some(Duration::from_secs(3 * 60 * 60));
 
     type Context = Arc<Environment>;
 
     /// Create CSV dumps of the public information in the database, wrap them in a
-    /// tarball and upload to S3.
+    /// tarball and upload to S3. Unless SQLite exports are disabled, the CSV
+    /// dumps are also imported into a SQLite database, which is uploaded
+    /// gzip-compressed.
+    ///
+    /// If Parquet exports are enabled, the CSV files are additionally converted
+    /// to Parquet files, which are uploaded individually to `db-dump/parquet/`.
+    ///
+    /// Finally, the rows that changed since the previous dump are uploaded as
+    /// a delta tarball to `db-dump/delta/`, and `db-dump/delta/manifest.json`
+    /// is updated to describe the chain of deltas since the last full base
+    /// snapshot.
     async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
         const TAR_PATH: &str = "db-dump.tar.gz";
         const ZIP_PATH: &str = "db-dump.zip";
+        const SQLITE_PATH: &str = "db-dump.sqlite.gz";
+        const PARQUET_PREFIX: &str = "db-dump/parquet";
 
         let db_config = &env.config.db;
         let db_pool_config = db_config.replica.as_ref().unwrap_or(&db_config.primary);
         let database_url = db_pool_config.url.clone();
+        let parquet_exports = env.config.parquet_exports;
+        let sqlite_exports = env.config.sqlite_exports;
+
+        let manifest = load_manifest(&env).await?;
+        let previous = match &manifest {
+            Some(manifest) if manifest.deltas.len() < MAX_DELTAS => {
+                load_fingerprints(&env).await?.filter(|previous| {
+                    // Only continue the chain if the fingerprints belong to its head.
+                    previous.timestamp == manifest.head()
+                })
+            }
+            _ => None,
+        };
 
-        let archives = spawn_blocking(move || {
+        // If a new base snapshot is started, the files of the previous chain
+        // are deleted once the new manifest has been uploaded.
+        let (manifest, superseded) = match manifest {
+            Some(manifest) if previous.is_some() => (Some(manifest), None),
+            manifest => (None, manifest),
+        };
+
+        // The export runs on a blocking thread, which is not stopped when the
+        // job times out, so the export functions check this flag before each
+        // table instead.
+        let cancellation = CancellationFlag::default();
+        let flag = cancellation.clone();
+
+        let mut export = spawn_blocking(move || {
             let directory = DumpDirectory::create()?;
 
             info!("Exporting database…");
-            directory.populate(database_url.expose_secret())?;
+            directory.populate(database_url.expose_secret(), &flag)?;
 
             let export_dir = directory.path();
             info!(path = ?export_dir, "Creating tarball…");
             let tarball_prefix = PathBuf::from(directory.timestamp.format("%F-%H%M%S").to_string());
-            create_archives(export_dir, &tarball_prefix)
-        })
-        .await??;
+            let archives = create_archives(export_dir, &tarball_prefix, &flag)?;
+
+            let sqlite = if sqlite_exports {
+                info!("Creating SQLite database…");
+                Some(create_sqlite(export_dir, &flag)?)
+            } else {
+                None
+            };
+
+            let parquet_paths = if parquet_exports {
+                info!("Converting database dump to Parquet…");
+                directory.dump_parquet(&flag)?
+            } else {
+                vec![]
+            };
+
+            info!("Computing delta to the previous database dump…");
+            let has_previous = previous.is_some();
+            let fingerprints_file = tempfile::NamedTempFile::new()?;
+            let timestamp = directory.timestamp;
+            let fingerprints = fingerprints_file.as_file();
+            create_delta(export_dir, timestamp, previous, fingerprints, &flag)?;
+            let delta_archive = has_previous
+                .then(|| create_delta_archive(export_dir, &tarball_prefix))
+                .transpose()?;
+
+            let delta = (delta_archive, fingerprints_file);
+            Ok::<_, anyhow::Error>((directory, archives, sqlite, parquet_paths, delta))
+        });
+
+        let token = cancellation_token();
+        let result = tokio::select! {
+            result = &mut export => result,
+            _ = token.cancelled() => {
+                cancellation.cancel();
+                export.await
+            }
+        };
+        let (directory, archives, sqlite, parquet_paths, delta) = result??;
 
         info!("Uploading tarball…");
         env.storage
@@ -60,6 +151,123 @@ impl BackgroundJob for DumpDb {
             warn!("Failed to invalidate CDN caches: {error}");
         }
 
+        if let Some(sqlite) = sqlite {
+            info!("Uploading SQLite database…");
+            env.storage
+                .upload_db_dump(SQLITE_PATH, sqlite.path())
+                .await?;
+            info!("Database dump SQLite database uploaded");
+
+            info!("Invalidating CDN caches…");
+            if let Err(error) = env.invalidate_cdns(SQLITE_PATH).await {
+                warn!("Failed to invalidate CDN caches: {error}");
+            }
+        }
+
+        for path in parquet_paths {
+            let file_name = path.file_name().and_then(|name| name.to_str());
+            let file_name = file_name.ok_or_else(|| anyhow!("Invalid path: {path:?}"))?;
+            let target = format!("{PARQUET_PREFIX}/{file_name}");
+
+            info!(%target, "Uploading Parquet file…");
+            env.storage.upload_db_dump(&target, &path).await?;
+
+            if let Err(error) = env.invalidate_cdns(&target).await {
+                warn!("Failed to invalidate CDN caches: {error}");
+            }
+        }
+
+        let (delta_archive, fingerprints_file) = delta;
+        let timestamp = directory.timestamp;
+        let manifest = match (manifest, delta_archive) {
+            (Some(mut manifest), Some(delta_archive)) => {
+                let path = format!("{DELTA_PREFIX}/{}.tar.gz", timestamp.format("%F-%H%M%S"));
+
+                info!(%path, "Uploading delta tarball…");
+                env.storage
+                    .upload_db_dump(&path, delta_archive.path())
+                    .await?;
+
+                let from = manifest.head();
+                manifest.deltas.push(Delta {
+                    from,
+                    to: timestamp,
+                    path,
+                });
+                manifest
+            }
+            _ => {
+                let path = format!(
+                    "{DELTA_PREFIX}/base-{}.tar.gz",
+                    timestamp.format("%F-%H%M%S")
+                );
+
+                info!(%path, "Uploading delta base snapshot…");
+                env.storage
+                    .upload_db_dump(&path, archives.tar.path())
+                    .await?;
+
+                DeltaManifest::new(BaseSnapshot { timestamp, path })
+            }
+        };
+
+        info!("Uploading delta fingerprints…");
+        env.storage
+            .upload_db_dump(FINGERPRINTS_PATH, fingerprints_file.path())
+            .await?;
+
+        // The manifest is uploaded last, so that it never references files
+        // that don't exist yet.
+        info!("Uploading delta manifest…");
+        let manifest_file = tempfile::NamedTempFile::new()?;
+        serde_json::to_writer_pretty(manifest_file.as_file(), &manifest)?;
+        env.storage
+            .upload_db_dump(MANIFEST_PATH, manifest_file.path())
+            .await?;
+
+        if let Err(error) = env.invalidate_cdns(MANIFEST_PATH).await {
+            warn!("Failed to invalidate CDN caches: {error}");
+        }
+
+        if let Some(superseded) = superseded {
+            for path in superseded.paths() {
+                info!(%path, "Deleting superseded delta file…");
+                if let Err(error) = env.storage.delete_db_dump(path).await {
+                    warn!(%path, "Failed to delete superseded delta file: {error}");
+                }
+            }
+        }
+
+        // The export directory is only cleaned up once the Parquet files are uploaded.
+        drop(directory);
+
         Ok(())
     }
 }
+
+async fn load_manifest(env: &Environment) -> anyhow::Result<Option<DeltaManifest>> {
+    let Some(bytes) = env.storage.download_db_dump(MANIFEST_PATH).await? else {
+        return Ok(None);
+    };
+
+    let manifest = serde_json::from_slice(&bytes).context("Failed to parse delta manifest")?;
+    Ok(Some(manifest))
+}
+
+/// Downloads the fingerprints of the previous dump into a temporary file,
+/// from which they are read one table at a time.
+async fn load_fingerprints(env: &Environment) -> anyhow::Result<Option<FingerprintsReader<File>>> {
+    let Some(bytes) = env.storage.download_db_dump(FINGERPRINTS_PATH).await? else {
+        return Ok(None);
+    };
+
+    let reader = spawn_blocking(move || {
+        let mut file = tempfile::tempfile()?;
+        file.write_all(&bytes)?;
+        file.rewind()?;
+        FingerprintsReader::new(file).context("Failed to read fingerprints")
+    })
+    .await??;
+
+    Ok(Some(reader))
+}
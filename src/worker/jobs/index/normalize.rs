@@ -1,5 +1,5 @@
 use crate::tasks::spawn_blocking;
-use crate::worker::Environment;
+use crate::worker::{Environment, REPOSITORY_QUEUE};
 use crates_io_index::Crate;
 use crates_io_worker::BackgroundJob;
 use std::fs;
@@ -20,7 +20,7 @@ impl NormalizeIndex {
 
 impl BackgroundJob for NormalizeIndex {
     const JOB_NAME: &'static str = "normalize_index";
-    const QUEUE: &'static str = "repository";
+    const QUEUE: &'static str = REPOSITORY_QUEUE;
 
     type Context = Arc<Environment>;
 
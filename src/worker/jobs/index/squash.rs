@@ -1,5 +1,5 @@
 use crate::tasks::spawn_blocking;
-use crate::worker::Environment;
+use crate::worker::{Environment, REPOSITORY_QUEUE};
 use chrono::Utc;
 use crates_io_env_vars::var_parsed;
 use crates_io_worker::BackgroundJob;
@@ -13,7 +13,7 @@ pub struct SquashIndex;
 impl BackgroundJob for SquashIndex {
     const JOB_NAME: &'static str = "squash_index";
     const DEDUPLICATED: bool = true;
-    const QUEUE: &'static str = "repository";
+    const QUEUE: &'static str = REPOSITORY_QUEUE;
 
     type Context = Arc<Environment>;
 
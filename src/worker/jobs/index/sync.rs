@@ -1,8 +1,8 @@
+use crate::cdn::SurrogateKey;
 use crate::index::get_index_data;
 use crate::tasks::spawn_blocking;
-use crate::worker::Environment;
+use crate::worker::{Environment, REPOSITORY_QUEUE};
 use anyhow::Context;
-use crates_io_index::Repository;
 use crates_io_worker::BackgroundJob;
 use std::fs;
 use std::fs::File;
@@ -25,7 +25,7 @@ impl BackgroundJob for SyncToGitIndex {
     const JOB_NAME: &'static str = "sync_to_git_index";
     const PRIORITY: i16 = 100;
     const DEDUPLICATED: bool = true;
-    const QUEUE: &'static str = "repository";
+    const QUEUE: &'static str = REPOSITORY_QUEUE;
 
     type Context = Arc<Environment>;
 
@@ -111,13 +111,20 @@ impl BackgroundJob for SyncToSparseIndex {
         let future = env.storage.sync_index(&self.krate, content);
         future.await.context("Failed to sync index data")?;
 
-        if let Some(cloudfront) = env.cloudfront() {
-            let path = Repository::relative_index_file_for_url(&self.krate);
+        let key = SurrogateKey::index(&self.krate);
 
-            info!(%path, "Invalidating index file on CloudFront");
-            let future = cloudfront.invalidate(&path);
+        if let Some(cloudfront) = env.cloudfront() {
+            info!(%key, "Invalidating index file on CloudFront");
+            let future = cloudfront.purge_keys(vec![key.clone()]);
             future.await.context("Failed to invalidate CloudFront")?;
         }
+
+        if let Some(fastly) = env.fastly() {
+            info!(%key, "Invalidating index file on Fastly");
+            let future = fastly.purge_keys(vec![key]);
+            future.await.context("Failed to invalidate Fastly")?;
+        }
+
         Ok(())
     }
 }
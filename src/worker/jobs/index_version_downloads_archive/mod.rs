@@ -6,6 +6,7 @@ use futures_util::TryStreamExt;
 use object_store::{ObjectMeta, ObjectStore};
 
 use crate::worker::Environment;
+use crate::worker::jobs::archive_version_downloads::PARQUET_PREFIX;
 
 const INDEX_PATH: &str = "archive/version-downloads/index.html";
 const INDEX_JSON_PATH: &str = "archive/version-downloads/index.json";
@@ -95,9 +96,16 @@ struct FileSet(BTreeSet<File>);
 
 impl FileSet {
     async fn new_from_store(store: &impl ObjectStore) -> anyhow::Result<Self> {
+        let parquet_prefix = object_store::path::Path::from(PARQUET_PREFIX);
+
         let mut set = BTreeSet::new();
         let mut contents = store.list(None);
         while let Some(object) = contents.try_next().await? {
+            // The Parquet files are not part of the CSV index.
+            if object.location.prefix_matches(&parquet_prefix) {
+                continue;
+            }
+
             match File::try_from(object) {
                 Ok(file) => {
                     set.insert(file);
@@ -262,6 +270,8 @@ mod tests {
             ("index.html", 40),
             // And a nested file that isn't CSV at all.
             ("foo/bar", 50),
+            // And a Parquet partition.
+            ("parquet/date=2024-07-31/version_downloads.parquet", 60),
         ] {
             store.put(&name.into(), vec![0u8; size].into()).await?;
         }
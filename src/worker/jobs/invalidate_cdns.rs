@@ -3,12 +3,17 @@ use std::sync::Arc;
 use anyhow::Context;
 use crates_io_worker::BackgroundJob;
 
+use crate::cdn::SurrogateKey;
 use crate::worker::Environment;
 
-/// A background job that invalidates the given paths on all CDNs in use on crates.io.
+/// A background job that invalidates the given paths or surrogate keys on all CDNs in use on
+/// crates.io.
 #[derive(Deserialize, Serialize)]
 pub struct InvalidateCdns {
+    #[serde(default)]
     paths: Vec<String>,
+    #[serde(default)]
+    surrogate_keys: Vec<SurrogateKey>,
 }
 
 impl InvalidateCdns {
@@ -19,6 +24,18 @@ impl InvalidateCdns {
     {
         Self {
             paths: paths.map(|path| path.to_string()).collect(),
+            surrogate_keys: vec![],
+        }
+    }
+
+    /// Invalidates all content tagged with the given surrogate keys, instead of individual paths.
+    pub fn surrogate_keys<I>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = SurrogateKey>,
+    {
+        Self {
+            paths: vec![],
+            surrogate_keys: keys.into_iter().collect(),
         }
     }
 }
@@ -29,27 +46,23 @@ impl BackgroundJob for InvalidateCdns {
     type Context = Arc<Environment>;
 
     async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
-        // Fastly doesn't provide an API to purge multiple paths at once, except through the use of
-        // surrogate keys. We can't use surrogate keys right now because they require a
-        // Fastly-specific header, and not all of our traffic goes through Fastly.
-        //
-        // For now, we won't parallelise: most crate deletions are for new crates with one (or very
-        // few) versions, so the actual number of paths being invalidated is likely to be small, and
-        // this is all happening from either a background job or admin command anyway.
+        // Purges are coalesced with other purges that happen within a short time window, so
+        // concurrently running jobs result in a single request per CDN.
+        let paths = &self.paths;
+        let keys = &self.surrogate_keys;
+
         if let Some(fastly) = ctx.fastly() {
-            for path in self.paths.iter() {
-                fastly
-                    .invalidate(path)
-                    .await
-                    .with_context(|| format!("Failed to invalidate path on Fastly CDN: {path}"))?;
-            }
+            fastly
+                .purge(paths.clone(), keys.clone())
+                .await
+                .context("Failed to invalidate Fastly CDN")?;
         }
 
         if let Some(cloudfront) = ctx.cloudfront() {
             cloudfront
-                .invalidate_many(self.paths.clone())
+                .purge(paths.clone(), keys.clone())
                 .await
-                .context("Failed to invalidate paths on CloudFront CDN")?;
+                .context("Failed to invalidate CloudFront CDN")?;
         }
 
         Ok(())
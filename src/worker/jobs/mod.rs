@@ -1,4 +1,5 @@
 mod archive_version_downloads;
+pub mod audit_storage;
 mod daily_db_maintenance;
 mod delete_crate;
 mod downloads;
@@ -15,6 +16,7 @@ mod typosquat;
 mod update_default_version;
 
 pub use self::archive_version_downloads::ArchiveVersionDownloads;
+pub use self::audit_storage::AuditStorage;
 pub use self::daily_db_maintenance::DailyDbMaintenance;
 pub use self::delete_crate::DeleteCrateFromStorage;
 pub use self::downloads::{
@@ -39,6 +39,7 @@ impl RenderAndUploadReadme {
 impl BackgroundJob for RenderAndUploadReadme {
     const JOB_NAME: &'static str = "render_and_upload_readme";
     const PRIORITY: i16 = 50;
+    const MAX_CONCURRENCY: Option<u32> = Some(2);
 
     type Context = Arc<Environment>;
 
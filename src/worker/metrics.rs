@@ -0,0 +1,59 @@
+//! A minimal HTTP server that exposes the metrics of the background worker
+//! process, since they are not visible to the `/api/private/metrics/{kind}`
+//! endpoint of the web servers.
+
+use axum::Router;
+use axum::extract::State;
+use axum::routing::get;
+use http::{HeaderMap, StatusCode, header};
+use prometheus::{Registry, TextEncoder};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+struct MetricsState {
+    registry: Registry,
+    authorization_token: String,
+}
+
+/// Serves the metrics of the given registry on `GET /metrics`, protected by
+/// the same bearer token as the metrics endpoint of the web servers.
+pub async fn serve_metrics(
+    registry: Registry,
+    authorization_token: String,
+    port: u16,
+) -> anyhow::Result<()> {
+    let state = Arc::new(MetricsState {
+        registry,
+        authorization_token,
+    });
+
+    let router = Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(state);
+
+    let listener = TcpListener::bind((IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)).await?;
+    info!("Serving worker metrics on port {port}");
+    axum::serve(listener, router).await?;
+
+    Ok(())
+}
+
+async fn metrics(
+    State(state): State<Arc<MetricsState>>,
+    headers: HeaderMap,
+) -> Result<String, StatusCode> {
+    let provided_token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if provided_token != Some(state.authorization_token.as_str()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let metrics = state.registry.gather();
+    TextEncoder::new()
+        .encode_to_string(&metrics)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
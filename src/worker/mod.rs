@@ -5,21 +5,39 @@
 //! runner, and the `jobs` submodule contains the application-specific
 //! background job definitions.
 
-use crates_io_worker::Runner;
+use crates_io_worker::{DEFAULT_QUEUE, Runner, Schedule};
 use std::sync::Arc;
 
 mod environment;
 pub mod jobs;
+mod metrics;
 
 pub use self::environment::Environment;
+pub use self::metrics::serve_metrics;
+
+/// The queue of the jobs that process the CDN download logs.
+pub const DOWNLOADS_QUEUE: &str = "downloads";
+
+/// The queue of the jobs that write to the index repository.
+pub const REPOSITORY_QUEUE: &str = "repository";
+
+/// All queues that the background worker runs jobs from.
+pub const QUEUES: &[&str] = &[DEFAULT_QUEUE, DOWNLOADS_QUEUE, REPOSITORY_QUEUE];
 
 pub trait RunnerExt {
     fn register_crates_io_job_types(self) -> Self;
+
+    /// Schedule the periodic jobs that would otherwise have to be enqueued
+    /// by an external scheduler via `crates-admin enqueue-job`.
+    fn schedule_crates_io_jobs(self) -> anyhow::Result<Self>
+    where
+        Self: Sized;
 }
 
 impl RunnerExt for Runner<Arc<Environment>> {
     fn register_crates_io_job_types(self) -> Self {
         self.register_job_type::<jobs::ArchiveVersionDownloads>()
+            .register_job_type::<jobs::AuditStorage>()
             .register_job_type::<jobs::CheckTyposquat>()
             .register_job_type::<jobs::CleanProcessedLogFiles>()
             .register_job_type::<jobs::DailyDbMaintenance>()
@@ -43,4 +61,21 @@ impl RunnerExt for Runner<Arc<Environment>> {
             .register_job_type::<jobs::rss::SyncCratesFeed>()
             .register_job_type::<jobs::rss::SyncUpdatesFeed>()
     }
+
+    fn schedule_crates_io_jobs(self) -> anyhow::Result<Self> {
+        Ok(self
+            .schedule_job(jobs::UpdateDownloads, Schedule::cron("0 */10 * * * *")?)
+            .schedule_job(jobs::SyncAdmins, Schedule::cron("0 0 * * * *")?)
+            .schedule_job(jobs::DumpDb, Schedule::cron("0 0 0 * * *")?)
+            .schedule_job(jobs::CleanProcessedLogFiles, Schedule::cron("0 0 2 * * *")?)
+            .schedule_job(jobs::DailyDbMaintenance, Schedule::cron("0 0 3 * * *")?)
+            .schedule_job(
+                jobs::IndexVersionDownloadsArchive,
+                Schedule::cron("0 0 4 * * *")?,
+            )
+            .schedule_job(
+                jobs::SendTokenExpiryNotifications,
+                Schedule::cron("0 0 5 * * *")?,
+            ))
+    }
 }